@@ -0,0 +1,80 @@
+//! Measures one population evaluation and reports the heap allocations it
+//! makes.
+//!
+//! Run with `cargo bench --bench evaluate`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use svgnest_cli::config::NestingConfig;
+use svgnest_cli::ga::GeneticAlgorithm;
+use svgnest_cli::part::Part;
+use svgnest_cli::svg_parser::{Point, Polygon};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn polygon(points: Vec<Point>) -> Polygon {
+    Polygon {
+        points,
+        closed: true,
+        ..Default::default()
+    }
+}
+
+fn gear(teeth: usize, size: f64) -> Part {
+    let n = teeth * 2;
+    let points = (0..n)
+        .map(|i| {
+            let a = i as f64 / n as f64 * std::f64::consts::TAU;
+            let r = if i % 2 == 0 { size } else { size * 0.8 };
+            Point {
+                x: r * a.cos(),
+                y: r * a.sin(),
+            }
+        })
+        .collect();
+    Part::new(vec![polygon(points)])
+}
+
+fn evaluate(c: &mut Criterion) {
+    let parts: Vec<Part> = (0..12).map(|i| gear(8 + i % 4, 10.0 + i as f64)).collect();
+    let bin = polygon(vec![
+        Point { x: 0.0, y: 0.0 },
+        Point { x: 200.0, y: 0.0 },
+        Point { x: 200.0, y: 150.0 },
+        Point { x: 0.0, y: 150.0 },
+    ]);
+    let config = NestingConfig::default();
+    let mut ga = GeneticAlgorithm::new(&parts, &bin, config).unwrap();
+    // the first pass fills the NFP cache and the scratch buffers
+    ga.evaluate_population();
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    ga.evaluate_population();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    println!(
+        "allocations per evaluation: {}",
+        allocations / ga.population.len().max(1)
+    );
+
+    c.bench_function("evaluate_population", |b| b.iter(|| ga.evaluate_population()));
+}
+
+criterion_group!(benches, evaluate);
+criterion_main!(benches);
@@ -0,0 +1,82 @@
+//! Measures Minkowski NFPs of concave pairs with many vertices, where the
+//! union of the edge quads dominates, and the first generation of a job of
+//! such parts, which generates every NFP its layouts need on all threads.
+//!
+//! Run with `cargo bench --bench nfp`. To compare two versions, run it with
+//! `-- --save-baseline old` on the first and `-- --baseline old` on the
+//! second.
+
+use criterion::{BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
+use svgnest_cli::config::NestingConfig;
+use svgnest_cli::ga::GeneticAlgorithm;
+use svgnest_cli::geometry::minkowski_difference_clip;
+use svgnest_cli::part::Part;
+use svgnest_cli::svg_parser::{Point, Polygon};
+
+/// Concave outline with `teeth` teeth, `2 * teeth` vertices.
+fn gear(teeth: usize, size: f64) -> Vec<Point> {
+    let n = teeth * 2;
+    (0..n)
+        .map(|i| {
+            let a = i as f64 / n as f64 * std::f64::consts::TAU;
+            let r = if i % 2 == 0 { size } else { size * 0.7 };
+            Point {
+                x: r * a.cos(),
+                y: r * a.sin(),
+            }
+        })
+        .collect()
+}
+
+fn minkowski(c: &mut Criterion) {
+    let mut group = c.benchmark_group("minkowski_difference_clip");
+    group.sample_size(10);
+    for teeth in [8, 16, 32] {
+        let a = gear(teeth, 20.0);
+        let b = gear(teeth + 3, 12.0);
+        group.bench_with_input(BenchmarkId::from_parameter(teeth * 2), &(a, b), |bench, (a, b)| {
+            bench.iter(|| minkowski_difference_clip(black_box(a), black_box(b)))
+        });
+    }
+    group.finish();
+}
+
+fn first_generation(c: &mut Criterion) {
+    let parts: Vec<Part> = (0..12)
+        .map(|i| {
+            Part::new(vec![Polygon {
+                points: gear(8 + i % 4, 10.0),
+                ..Default::default()
+            }])
+        })
+        .collect();
+    let bin = Polygon {
+        points: vec![
+            Point { x: 0.0, y: 0.0 },
+            Point { x: 100.0, y: 0.0 },
+            Point { x: 100.0, y: 100.0 },
+            Point { x: 0.0, y: 100.0 },
+        ],
+        ..Default::default()
+    };
+    let config = NestingConfig::builder()
+        .population_size(16)
+        .explore_concave(true)
+        .seed(Some(1))
+        .build()
+        .unwrap();
+    let mut group = c.benchmark_group("first_generation");
+    group.sample_size(10);
+    group.bench_function("gears", |bench| {
+        bench.iter(|| {
+            // a fresh cache every time, as at the start of a run
+            let mut ga = GeneticAlgorithm::new(&parts, &bin, config.clone()).unwrap();
+            ga.evaluate_population();
+            black_box(ga.best().map(|best| best.fitness))
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, minkowski, first_generation);
+criterion_main!(benches);
@@ -0,0 +1,66 @@
+//! Compares the batched point transforms against rotating through geo.
+//!
+//! Run with `cargo bench --bench transform`.
+
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+use geo::{LineString, Rotate, point};
+use svgnest_cli::geometry::batch::{self, Affine};
+use svgnest_cli::svg_parser::Point;
+
+fn outline(n: usize) -> Vec<Point> {
+    (0..n)
+        .map(|i| {
+            let a = i as f64 / n as f64 * std::f64::consts::TAU;
+            Point {
+                x: 50.0 * a.cos(),
+                y: 30.0 * a.sin(),
+            }
+        })
+        .collect()
+}
+
+fn rotate(c: &mut Criterion) {
+    let pts = outline(1000);
+    let mut group = c.benchmark_group("rotate_1000_points");
+    group.bench_function("geo", |b| {
+        b.iter(|| {
+            let ls: LineString<f64> = pts.iter().map(|p| (p.x, p.y)).collect::<Vec<_>>().into();
+            let rotated = ls.rotate_around_point(black_box(37.0), point!(x: 0.0, y: 0.0));
+            rotated.points().map(|c| Point { x: c.x(), y: c.y() }).collect::<Vec<_>>()
+        })
+    });
+    let mut out = Vec::with_capacity(pts.len());
+    group.bench_function("batch", |b| {
+        b.iter(|| {
+            out.clear();
+            batch::transform_into(&pts, &Affine::rotation(black_box(37.0)), &mut out);
+        })
+    });
+    group.finish();
+}
+
+fn translate(c: &mut Criterion) {
+    let pts = outline(1000);
+    let mut group = c.benchmark_group("translate_1000_points");
+    group.bench_function("scalar", |b| {
+        b.iter(|| {
+            pts.iter()
+                .map(|p| Point {
+                    x: p.x + black_box(3.0),
+                    y: p.y + 4.0,
+                })
+                .collect::<Vec<_>>()
+        })
+    });
+    let mut out = Vec::with_capacity(pts.len());
+    group.bench_function("batch", |b| {
+        b.iter(|| {
+            out.clear();
+            batch::translate_into(&pts, black_box(3.0), 4.0, &mut out);
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, rotate, translate);
+criterion_main!(benches);
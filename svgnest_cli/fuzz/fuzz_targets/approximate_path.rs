@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use svgnest_cli::geometry::CURVE_TOLERANCE;
+use svgnest_cli::svg_parser::approximate_path;
+
+fuzz_target!(|d: &str| {
+    let _ = approximate_path(d, CURVE_TOLERANCE);
+});
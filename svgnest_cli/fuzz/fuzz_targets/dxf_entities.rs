@@ -0,0 +1,13 @@
+#![no_main]
+
+use dxf::Drawing;
+use libfuzzer_sys::fuzz_target;
+use svgnest_cli::dxf_parser::part_from_drawing;
+use svgnest_cli::geometry::Tolerances;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(drawing) = Drawing::load(&mut &data[..]) else {
+        return;
+    };
+    let _ = part_from_drawing(&drawing, None, &Tolerances::default(), false, &mut Vec::new());
+});
@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use svgnest_cli::geometry::CURVE_TOLERANCE;
+use svgnest_cli::svg_parser::polygons_from_str;
+
+fuzz_target!(|data: &str| {
+    let _ = polygons_from_str(data, false, CURVE_TOLERANCE);
+    let _ = polygons_from_str(data, true, CURVE_TOLERANCE);
+});
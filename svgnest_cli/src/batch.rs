@@ -0,0 +1,452 @@
+//! Batch jobs: a directory whose subdirectories are independent nesting jobs.
+//!
+//! A job directory holds a bin file named `bin.svg` or `bin.dxf`, any number
+//! of part files (`.svg` or `.dxf`) and optionally a `config.json` overriding
+//! options of the command line for that job.
+//!
+//! Jobs of the same material cut from the same stock can be merged into one
+//! nesting run to share sheets, with the sheet area they use attributed back
+//! to every job.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{AngleFallback, FitnessWeights, PackDirection, PositionScore};
+use crate::crossover::Crossover;
+use crate::part::PartId;
+use crate::report::Report;
+use crate::schema::{self, Format};
+
+/// Name of the optional per-job configuration file.
+pub const CONFIG_FILE: &str = "config.json";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Job {
+    /// Name of the job directory
+    pub name: String,
+    pub dir: PathBuf,
+    pub bin: PathBuf,
+    /// Part files in name order
+    pub parts: Vec<PathBuf>,
+    pub overrides: JobOverrides,
+}
+
+impl Job {
+    /// Bin followed by the parts, in the order the nester expects its inputs.
+    pub fn inputs(&self) -> Vec<PathBuf> {
+        std::iter::once(self.bin.clone()).chain(self.parts.iter().cloned()).collect()
+    }
+
+    /// Read the job in `dir`.
+    pub fn from_dir(dir: &Path) -> Result<Self> {
+        let name = dir
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let mut bin = None;
+        let mut parts = Vec::new();
+        let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+            .with_context(|| format!("failed to read {}", dir.display()))?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.is_file())
+            .collect();
+        entries.sort();
+        for path in entries {
+            let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+            if !ext.eq_ignore_ascii_case("svg") && !ext.eq_ignore_ascii_case("dxf") {
+                continue;
+            }
+            if path.file_stem().is_some_and(|s| s == "bin") {
+                if bin.is_some() {
+                    bail!("{} has more than one bin file", dir.display());
+                }
+                bin = Some(path);
+            } else {
+                parts.push(path);
+            }
+        }
+        let Some(bin) = bin else {
+            bail!("{} has no bin.svg or bin.dxf", dir.display());
+        };
+        if parts.is_empty() {
+            bail!("{} has no part files", dir.display());
+        }
+        let config = dir.join(CONFIG_FILE);
+        let overrides = if config.is_file() {
+            JobOverrides::from_file(&config)?
+        } else {
+            JobOverrides::default()
+        };
+        Ok(Self {
+            name,
+            dir: dir.to_path_buf(),
+            bin,
+            parts,
+            overrides,
+        })
+    }
+}
+
+/// Options a job may set in its `config.json`. Options left out keep the
+/// value given on the command line.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JobOverrides {
+    pub spacing: Option<f64>,
+    pub rotations: Option<usize>,
+    pub rotation_weights: Option<Vec<f64>>,
+    pub population_size: Option<usize>,
+    pub elitism: Option<usize>,
+    pub mutation_rate: Option<usize>,
+    pub crossover: Option<Crossover>,
+    pub local_search_iters: Option<usize>,
+    pub seed: Option<u64>,
+    pub use_holes: Option<bool>,
+    pub explore_concave: Option<bool>,
+    pub angle_fallback: Option<AngleFallback>,
+    pub split_joint: Option<f64>,
+    pub merge_lines: Option<bool>,
+    pub wrap_x: Option<bool>,
+    pub rotate_sheets: Option<bool>,
+    pub pack_direction: Option<PackDirection>,
+    pub position_score: Option<PositionScore>,
+    pub start_offset: Option<f64>,
+    pub target_utilization: Option<f64>,
+    pub fitness_weights: Option<FitnessWeights>,
+    pub max_parts_per_sheet: Option<usize>,
+    pub max_sheet_weight: Option<f64>,
+    pub max_sheet_area: Option<f64>,
+    pub export_remnants: Option<bool>,
+    pub align_parts: Option<bool>,
+    /// Material the job is cut from. Only jobs naming the same material may
+    /// share sheets.
+    pub material: Option<String>,
+}
+
+impl JobOverrides {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let data = fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+        schema::from_str(Format::JobConfig, &data).with_context(|| format!("invalid {}", path.display()))
+    }
+}
+
+/// Subdirectories of `root` in name order. Directories that are not valid
+/// jobs are returned as errors so the batch can report them and go on.
+pub fn discover_jobs(root: &Path) -> Result<Vec<(PathBuf, Result<Job>)>> {
+    let mut dirs: Vec<PathBuf> = fs::read_dir(root)
+        .with_context(|| format!("failed to read {}", root.display()))?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.is_dir())
+        .collect();
+    dirs.sort();
+    Ok(dirs
+        .into_iter()
+        .map(|dir| {
+            let job = Job::from_dir(&dir);
+            (dir, job)
+        })
+        .collect())
+}
+
+/// What jobs sharing sheets have in common: their material and the contents
+/// of their bin file
+type SheetKey<'a> = (&'a str, Vec<u8>);
+
+/// Groups of jobs that may share sheets: those of the same material whose bin
+/// files have the same contents. Jobs without a material stay on their own.
+/// Every group holds indices into `jobs` in order, the groups are ordered by
+/// their first job.
+pub fn material_groups(jobs: &[Job]) -> Result<Vec<Vec<usize>>> {
+    let mut groups: Vec<(Option<SheetKey>, Vec<usize>)> = Vec::new();
+    for (i, job) in jobs.iter().enumerate() {
+        let Some(material) = job.overrides.material.as_deref() else {
+            groups.push((None, vec![i]));
+            continue;
+        };
+        let bin = fs::read(&job.bin).with_context(|| format!("failed to read {}", job.bin.display()))?;
+        let key = Some((material, bin));
+        match groups.iter_mut().find(|(k, _)| k.is_some() && *k == key) {
+            Some((_, group)) => group.push(i),
+            None => groups.push((key, vec![i])),
+        }
+    }
+    Ok(groups.into_iter().map(|(_, group)| group).collect())
+}
+
+/// One job nesting the parts of all `jobs` on the bin and with the options of
+/// the first, named after all of them. `jobs` must not be empty.
+pub fn merge_jobs(jobs: &[&Job]) -> Job {
+    let names: Vec<&str> = jobs.iter().map(|j| j.name.as_str()).collect();
+    Job {
+        name: names.join("+"),
+        parts: jobs.iter().flat_map(|j| j.parts.iter().cloned()).collect(),
+        ..jobs[0].clone()
+    }
+}
+
+/// What one of several merged jobs takes of their shared layout.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobShare {
+    pub name: String,
+    pub placed: usize,
+    pub unplaced: usize,
+    /// Area of the placed parts of the job
+    pub part_area: f64,
+    /// Area of the used sheets charged to the job. Every sheet is split
+    /// between the jobs by the area of their parts on it.
+    pub sheet_area: f64,
+}
+
+/// Share of each of `jobs` in `report`, the layout of the job merged from
+/// them. Parts are told apart by the file they were read from.
+pub fn job_shares(report: &Report, jobs: &[&Job]) -> Vec<JobShare> {
+    let owner = |id: &PartId| {
+        id.file
+            .as_ref()
+            .and_then(|file| jobs.iter().position(|j| j.parts.contains(file)))
+    };
+    let sheet_area = report.bin_width * report.bin_height;
+    let mut shares: Vec<JobShare> = jobs
+        .iter()
+        .map(|j| JobShare {
+            name: j.name.clone(),
+            placed: 0,
+            unplaced: 0,
+            part_area: 0.0,
+            sheet_area: 0.0,
+        })
+        .collect();
+    // area of the parts of every job on every sheet
+    let mut used = vec![vec![0.0; jobs.len()]; report.sheets];
+    for p in &report.placements {
+        let Some(job) = p.id.as_ref().and_then(|id| owner(&id.part)) else {
+            continue;
+        };
+        shares[job].placed += 1;
+        shares[job].part_area += p.area();
+        if let Some(sheet) = used.get_mut(p.sheet) {
+            sheet[job] += p.area();
+        }
+    }
+    for id in &report.unplaced_ids {
+        if let Some(job) = owner(&id.part) {
+            shares[job].unplaced += 1;
+        }
+    }
+    for sheet in &used {
+        let total: f64 = sheet.iter().sum();
+        if total > 0.0 {
+            for (share, area) in shares.iter_mut().zip(sheet) {
+                share.sheet_area += sheet_area * area / total;
+            }
+        }
+    }
+    shares
+}
+
+/// Outcome of one job of a batch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobSummary {
+    pub name: String,
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub sheets: usize,
+    pub utilization: f64,
+    pub placed: usize,
+    pub unplaced: usize,
+    /// Input elements skipped while parsing
+    #[serde(default)]
+    pub warnings: usize,
+    /// Wall time of the job in seconds
+    pub seconds: f64,
+    /// Shares of the jobs merged into this one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub shares: Vec<JobShare>,
+}
+
+impl JobSummary {
+    pub fn from_report(name: &str, report: &Report, seconds: f64) -> Self {
+        Self {
+            name: name.to_string(),
+            ok: true,
+            error: None,
+            sheets: report.sheets,
+            utilization: report.utilization,
+            placed: report.placements.len(),
+            unplaced: report.unplaced.len(),
+            warnings: report.warnings.len(),
+            seconds,
+            shares: Vec::new(),
+        }
+    }
+
+    pub fn failed(name: &str, error: String, seconds: f64) -> Self {
+        Self {
+            name: name.to_string(),
+            ok: false,
+            error: Some(error),
+            sheets: 0,
+            utilization: 0.0,
+            placed: 0,
+            unplaced: 0,
+            warnings: 0,
+            seconds,
+            shares: Vec::new(),
+        }
+    }
+}
+
+/// Combined report of a batch run.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatchSummary {
+    pub jobs: Vec<JobSummary>,
+}
+
+impl BatchSummary {
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Human readable table, one line per job.
+    pub fn table(&self) -> String {
+        let mut out = String::new();
+        for job in &self.jobs {
+            if job.ok {
+                let warnings = if job.warnings > 0 {
+                    format!(", {} warning(s)", job.warnings)
+                } else {
+                    String::new()
+                };
+                out.push_str(&format!(
+                    "{}: {} sheet(s), {:.1}% utilization, {} placed, {} unplaced{} ({:.1}s)\n",
+                    job.name,
+                    job.sheets,
+                    job.utilization * 100.0,
+                    job.placed,
+                    job.unplaced,
+                    warnings,
+                    job.seconds
+                ));
+                for share in &job.shares {
+                    out.push_str(&format!(
+                        "  {}: {} placed, {} unplaced, {:.2} of sheet area\n",
+                        share.name, share.placed, share.unplaced, share.sheet_area
+                    ));
+                }
+            } else {
+                out.push_str(&format!(
+                    "{}: failed: {}\n",
+                    job.name,
+                    job.error.as_deref().unwrap_or("")
+                ));
+            }
+        }
+        let failed = self.jobs.iter().filter(|j| !j.ok).count();
+        out.push_str(&format!("{} job(s), {} failed\n", self.jobs.len(), failed));
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn job_reads_bin_parts_and_config() {
+        let dir = std::env::temp_dir().join(format!("svgnest_batch_{}", std::process::id()));
+        let job = dir.join("a");
+        fs::create_dir_all(&job).unwrap();
+        for name in ["bin.svg", "2.svg", "1.dxf", "notes.txt"] {
+            fs::write(job.join(name), "").unwrap();
+        }
+        fs::write(job.join(CONFIG_FILE), r#"{"spacing": 2.5, "rotations": 8}"#).unwrap();
+        fs::create_dir_all(dir.join("empty")).unwrap();
+
+        let jobs = discover_jobs(&dir).unwrap();
+        assert_eq!(jobs.len(), 2);
+        let a = jobs[0].1.as_ref().unwrap();
+        assert_eq!(a.name, "a");
+        assert_eq!(a.bin, job.join("bin.svg"));
+        assert_eq!(a.parts, vec![job.join("1.dxf"), job.join("2.svg")]);
+        assert_eq!(a.overrides.spacing, Some(2.5));
+        assert_eq!(a.overrides.rotations, Some(8));
+        assert!(jobs[1].1.is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn merged_jobs_share_sheets_by_part_area() {
+        let dir = std::env::temp_dir().join(format!("svgnest_mix_{}", std::process::id()));
+        let mut jobs = Vec::new();
+        let setups = [
+            ("a", Some("steel"), "s"),
+            ("b", None, "s"),
+            ("c", Some("steel"), "s"),
+            ("d", Some("steel"), "t"),
+        ];
+        for (name, material, bin) in setups {
+            let job = dir.join(name);
+            fs::create_dir_all(&job).unwrap();
+            fs::write(job.join("bin.svg"), bin).unwrap();
+            fs::write(job.join("part.svg"), "").unwrap();
+            let mut job = Job::from_dir(&job).unwrap();
+            job.overrides.material = material.map(str::to_string);
+            jobs.push(job);
+        }
+        assert_eq!(material_groups(&jobs).unwrap(), vec![vec![0, 2], vec![1], vec![3]]);
+
+        let merged = merge_jobs(&[&jobs[0], &jobs[2]]);
+        assert_eq!(merged.name, "a+c");
+        assert_eq!(merged.bin, jobs[0].bin);
+        assert_eq!(merged.parts.len(), 2);
+
+        let placed = |job: &Job, sheet: usize, side: f64| {
+            let y = sheet as f64 * 10.0;
+            crate::report::PlacedPart {
+                part: 0,
+                id: Some(crate::part::InstanceId {
+                    part: PartId {
+                        file: Some(job.parts[0].clone()),
+                        ..Default::default()
+                    },
+                    copy: 0,
+                }),
+                sheet,
+                x: 0.0,
+                y,
+                angle: 0.0,
+                alignment: 0.0,
+                contours: vec![vec![
+                    crate::svg_parser::Point { x: 0.0, y },
+                    crate::svg_parser::Point { x: side, y },
+                    crate::svg_parser::Point { x: side, y: y + side },
+                    crate::svg_parser::Point { x: 0.0, y: y + side },
+                ]],
+                frames: None,
+                metadata: Default::default(),
+            }
+        };
+        let report = Report {
+            bin_width: 10.0,
+            bin_height: 10.0,
+            turned_sheets: Vec::new(),
+            sheets: 2,
+            utilization: 0.0,
+            placements: vec![placed(&jobs[0], 0, 3.0), placed(&jobs[2], 0, 3.0), placed(&jobs[2], 1, 2.0)],
+            unplaced: Vec::new(),
+            unplaced_ids: Vec::new(),
+            sheet_totals: Vec::new(),
+            warnings: Vec::new(),
+        };
+        let shares = job_shares(&report, &[&jobs[0], &jobs[2]]);
+        assert_eq!(shares[0].placed, 1);
+        assert!((shares[0].sheet_area - 50.0).abs() < 1e-9);
+        assert!((shares[1].sheet_area - 150.0).abs() < 1e-9);
+        assert!((shares[1].part_area - 13.0).abs() < 1e-9);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
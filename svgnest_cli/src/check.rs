@@ -0,0 +1,311 @@
+//! Verification of nested layouts, independent of the nester.
+//!
+//! A nested SVG is read back the way it is written: parts are `polygon`,
+//! `polyline` and `path` elements, while the sheet outlines, which are `rect`
+//! elements or carry a `data-sheet` attribute, are ignored. Rings lying inside an odd number of other rings are
+//! holes of the smallest part around them. Every part is then checked to lie
+//! on a sheet of the bin, not to overlap other parts and to keep the spacing
+//! to them.
+
+use std::fmt;
+
+use anyhow::Result;
+use roxmltree::Document;
+use serde::{Deserialize, Serialize};
+
+use crate::geometry::{
+    Bounds, CURVE_TOLERANCE, get_polygon_bounds,
+    ops::{self, Shape},
+};
+use crate::svg_parser::{Point, Polygon, approximate_path, document_transform};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ViolationKind {
+    Overlap,
+    Spacing,
+    OutsideBin,
+}
+
+/// A problem found in a layout. Parts are numbered in document order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Violation {
+    pub kind: ViolationKind,
+    pub parts: Vec<usize>,
+    /// Where the problem is, in layout coordinates
+    pub at: Point,
+    /// Overlapping or protruding area, or the distance between the parts
+    pub amount: f64,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (x, y) = (self.at.x, self.at.y);
+        match (self.kind, self.parts.as_slice()) {
+            (ViolationKind::Overlap, [a, b]) => {
+                write!(f, "parts {} and {} overlap by an area of {} at ({}, {})", a, b, self.amount, x, y)
+            }
+            (ViolationKind::Spacing, [a, b]) => {
+                write!(f, "parts {} and {} are only {} apart at ({}, {})", a, b, self.amount, x, y)
+            }
+            (ViolationKind::OutsideBin, [a]) => {
+                write!(f, "part {} extends outside the bin by an area of {} at ({}, {})", a, self.amount, x, y)
+            }
+            (kind, parts) => write!(f, "{:?} of parts {:?} at ({}, {})", kind, parts, x, y),
+        }
+    }
+}
+
+fn numbers(s: &str) -> Vec<f64> {
+    s.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|t| !t.is_empty())
+        .filter_map(|t| t.parse().ok())
+        .collect()
+}
+
+/// Closed rings of the parts drawn in a nested SVG.
+fn rings_from_svg(data: &str) -> Result<Vec<Vec<Point>>> {
+    let doc = Document::parse(data)?;
+    let mut rings = Vec::new();
+    for node in doc.descendants().filter(|n| n.is_element()) {
+        // parts left unplaced may be drawn beside the sheets and overlays
+        // on top of them
+        let aside = |a: roxmltree::Node| {
+            a.has_tag_name("clipPath") || a.has_attribute("data-unplaced") || a.has_attribute("data-overlay")
+        };
+        if node.ancestors().any(aside) || node.has_attribute("data-sheet") {
+            continue;
+        }
+        // parts may be drawn as their own geometry moved by a transform
+        let transform = document_transform(node);
+        let point = |x, y| {
+            let (x, y) = transform(x, y);
+            Point { x, y }
+        };
+        match node.tag_name().name() {
+            "polygon" | "polyline" => {
+                let values = numbers(node.attribute("points").unwrap_or_default());
+                rings.push(values.chunks_exact(2).map(|c| point(c[0], c[1])).collect());
+            }
+            "path" => {
+                for (_, pts) in approximate_path(node.attribute("d").unwrap_or_default(), CURVE_TOLERANCE) {
+                    rings.push(pts.into_iter().map(|(x, y)| point(x, y)).collect());
+                }
+            }
+            _ => {}
+        }
+    }
+    rings.retain(|r: &Vec<Point>| r.len() >= 3);
+    Ok(rings)
+}
+
+/// Parts drawn in a nested SVG.
+pub fn shapes_from_svg(data: &str) -> Result<Vec<Shape>> {
+    Ok(ops::shapes_from_rings(&rings_from_svg(data)?))
+}
+
+fn shape_bounds(shape: &Shape) -> Option<Bounds> {
+    get_polygon_bounds(&shape.outline)
+}
+
+fn center(bounds: Option<Bounds>) -> Point {
+    bounds.map_or_else(Point::default, |b| Point {
+        x: b.x + b.width / 2.0,
+        y: b.y + b.height / 2.0,
+    })
+}
+
+/// Total area of `pieces` and the center of the largest one, ignoring
+/// slivers thinner than `tol`.
+fn significant_area(pieces: &[Shape], tol: f64) -> Option<(f64, Point)> {
+    let mut total = 0.0;
+    let mut largest: Option<&Shape> = None;
+    for piece in pieces {
+        let area = piece.area();
+        let perimeter: f64 = piece
+            .outline
+            .iter()
+            .zip(piece.outline.iter().cycle().skip(1))
+            .map(|(a, b)| (b.x - a.x).hypot(b.y - a.y))
+            .sum();
+        // twice the area over the perimeter is the width of a thin strip
+        if perimeter <= 0.0 || 2.0 * area / perimeter <= tol {
+            continue;
+        }
+        total += area;
+        if largest.is_none_or(|l| l.area() < area) {
+            largest = Some(piece);
+        }
+    }
+    largest.map(|l| (total, center(shape_bounds(l))))
+}
+
+/// Closest points of the segments `a0`-`a1` and `b0`-`b1`, which do not
+/// cross, and their distance.
+fn segment_distance(a0: Point, a1: Point, b0: Point, b1: Point) -> (f64, Point, Point) {
+    let project = |p: Point, s0: Point, s1: Point| {
+        let (dx, dy) = (s1.x - s0.x, s1.y - s0.y);
+        let len = dx * dx + dy * dy;
+        let t = if len > 0.0 {
+            (((p.x - s0.x) * dx + (p.y - s0.y) * dy) / len).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let q = Point {
+            x: s0.x + t * dx,
+            y: s0.y + t * dy,
+        };
+        ((p.x - q.x).hypot(p.y - q.y), q)
+    };
+    let candidates = [
+        (project(a0, b0, b1), a0, true),
+        (project(a1, b0, b1), a1, true),
+        (project(b0, a0, a1), b0, false),
+        (project(b1, a0, a1), b1, false),
+    ];
+    candidates
+        .into_iter()
+        .map(|((d, q), p, on_a)| if on_a { (d, p, q) } else { (d, q, p) })
+        .min_by(|x, y| x.0.total_cmp(&y.0))
+        .unwrap_or((f64::INFINITY, a0, b0))
+}
+
+fn edges(shape: &Shape) -> impl Iterator<Item = (Point, Point)> + '_ {
+    std::iter::once(&shape.outline)
+        .chain(&shape.holes)
+        .flat_map(|ring| ring.iter().copied().zip(ring.iter().copied().cycle().skip(1)))
+}
+
+/// Shortest distance between the boundaries of two shapes and the point
+/// halfway between the closest points.
+fn boundary_distance(a: &Shape, b: &Shape) -> (f64, Point) {
+    let mut best = (f64::INFINITY, Point::default());
+    for (a0, a1) in edges(a) {
+        for (b0, b1) in edges(b) {
+            let (d, p, q) = segment_distance(a0, a1, b0, b1);
+            if d < best.0 {
+                best = (
+                    d,
+                    Point {
+                        x: (p.x + q.x) / 2.0,
+                        y: (p.y + q.y) / 2.0,
+                    },
+                );
+            }
+        }
+    }
+    best
+}
+
+/// Check that every part of `shapes` lies on a sheet shaped like `bin`, with
+/// the sheets stacked downwards from the origin, and that parts neither
+/// overlap nor come closer than `spacing`. Overlaps and protrusions thinner
+/// than `tol` are not reported.
+pub fn check_layout(shapes: &[Shape], bin: &Polygon, spacing: f64, tol: f64) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    let bounds: Vec<Option<Bounds>> = shapes.iter().map(shape_bounds).collect();
+
+    if let Some(bin_bounds) = get_polygon_bounds(&bin.points) {
+        for (i, shape) in shapes.iter().enumerate() {
+            let Some(b) = bounds[i] else {
+                continue;
+            };
+            let sheet = ((b.y + tol - bin_bounds.y) / bin_bounds.height).floor().max(0.0);
+            let dy = sheet * bin_bounds.height;
+            let sheet_shape = Shape::new(bin.points.iter().map(|p| Point { x: p.x, y: p.y + dy }).collect());
+            let outside = ops::difference(std::slice::from_ref(shape), &[sheet_shape]);
+            if let Some((area, at)) = significant_area(&outside, tol) {
+                violations.push(Violation {
+                    kind: ViolationKind::OutsideBin,
+                    parts: vec![i],
+                    at,
+                    amount: area,
+                });
+            }
+        }
+    }
+
+    for (i, bi) in bounds.iter().enumerate() {
+        for (j, bj) in bounds.iter().enumerate().skip(i + 1) {
+            let (Some(bi), Some(bj)) = (bi, bj) else {
+                continue;
+            };
+            if !bi.overlaps(bj, spacing) {
+                continue;
+            }
+            let common = ops::intersection(&shapes[i..=i], &shapes[j..=j]);
+            if let Some((area, at)) = significant_area(&common, tol) {
+                violations.push(Violation {
+                    kind: ViolationKind::Overlap,
+                    parts: vec![i, j],
+                    at,
+                    amount: area,
+                });
+                continue;
+            }
+            if spacing > 0.0 {
+                let (distance, at) = boundary_distance(&shapes[i], &shapes[j]);
+                if distance < spacing - tol {
+                    violations.push(Violation {
+                        kind: ViolationKind::Spacing,
+                        parts: vec![i, j],
+                        at,
+                        amount: distance,
+                    });
+                }
+            }
+        }
+    }
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BIN: &str = "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"10\" height=\"10\">";
+
+    fn bin() -> Polygon {
+        Polygon {
+            points: vec![
+                Point { x: 0.0, y: 0.0 },
+                Point { x: 10.0, y: 0.0 },
+                Point { x: 10.0, y: 10.0 },
+                Point { x: 0.0, y: 10.0 },
+            ],
+            closed: true,
+            ..Default::default()
+        }
+    }
+
+    fn check(body: &str, spacing: f64) -> Vec<Violation> {
+        let svg = format!("{}{}<rect x=\"0\" y=\"0\" width=\"10\" height=\"20\"/></svg>", BIN, body);
+        check_layout(&shapes_from_svg(&svg).unwrap(), &bin(), spacing, 1e-6)
+    }
+
+    #[test]
+    fn finds_overlaps_spacing_and_protrusions() {
+        let frame = "<polygon points=\"0,0 5,0 5,5 0,5\"/><polygon points=\"4,1 1,1 1,4 4,4\"/>";
+        // a part in the hole of another is fine
+        assert!(check(&format!("{}<polygon points=\"2,2 3,2 3,3 2,3\"/>", frame), 0.0).is_empty());
+        // parts on the second sheet are checked against it
+        assert!(check("<polygon points=\"0,10 5,10 5,15 0,15\"/>", 0.0).is_empty());
+
+        let overlap = check(&format!("{}<polygon points=\"4,4 6,4 6,6 4,6\"/>", frame), 0.0);
+        assert_eq!(overlap.len(), 1);
+        assert_eq!(overlap[0].kind, ViolationKind::Overlap);
+        assert_eq!(overlap[0].parts, vec![0, 1]);
+        assert!((overlap[0].amount - 1.0).abs() < 1e-6);
+
+        let close = check("<polygon points=\"0,0 5,0 5,5 0,5\"/><polygon points=\"6,0 9,0 9,3 6,3\"/>", 2.0);
+        assert_eq!(close.len(), 1);
+        assert_eq!(close[0].kind, ViolationKind::Spacing);
+        assert!((close[0].amount - 1.0).abs() < 1e-9);
+        assert!((close[0].at.x - 5.5).abs() < 1e-9);
+
+        let outside = check("<polygon points=\"8,0 12,0 12,2 8,2\"/>", 0.0);
+        assert_eq!(outside.len(), 1);
+        assert_eq!(outside[0].kind, ViolationKind::OutsideBin);
+        assert!((outside[0].amount - 4.0).abs() < 1e-6);
+    }
+}
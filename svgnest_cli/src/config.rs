@@ -0,0 +1,764 @@
+//! Options of a nesting run, validated before any work starts.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use svgnest_cli::config::NestingConfig;
+//! let config = NestingConfig::builder().spacing(2.0).rotations(&[0.0, 90.0]).build()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::crossover::Crossover;
+use crate::geometry::Tolerances;
+use crate::leads::Lead;
+use crate::palette::ColorBy;
+use crate::part::Part;
+use crate::report::SheetTotals;
+use crate::svg_parser::Point;
+use crate::tabs::Tabs;
+
+/// Edge of the sheet the parts are gathered at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum PackDirection {
+    #[default]
+    Left,
+    Right,
+    Top,
+    Bottom,
+    /// The middle of the sheet, leaving free space all around
+    CenterOut,
+}
+
+/// What happens to a part that fits the bin at none of the allowed rotations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum AngleFallback {
+    /// Leave the part unplaced, saying why before the run starts
+    #[default]
+    Unplace,
+    /// Refuse to nest the job
+    Error,
+    /// Try every whole degree and use the angles at which the part fits
+    Continuous,
+    /// Cut the part along straight lines into pieces the size of the bin,
+    /// overlapping by [`split_joint`](NestingConfig::split_joint), and nest
+    /// the pieces instead
+    Split,
+}
+
+/// How the placer chooses among the free positions a part fits at, which
+/// matters for density as much as the order of the parts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum PositionScore {
+    /// The first position found, in the order the free space opened up
+    #[default]
+    First,
+    /// The smallest y, then the smallest x
+    LowestY,
+    /// The position keeping the convex hull of the parts on the sheet
+    /// smallest
+    Hull,
+    /// The position whose bounds touch the neighbours and the sheet edges
+    /// along the most length
+    Contact,
+    /// The position closest to the
+    /// [`gravity_point`](NestingConfig::gravity_point)
+    Gravity,
+}
+
+/// Annotations drawn on every sheet of the SVG output in a layer of their
+/// own, which is not meant to be cut.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SheetMarks {
+    /// Fiducials in three corners of the sheet, for camera registration.
+    /// The fourth corner is left out so the orientation is unambiguous.
+    pub registration: bool,
+    /// Crosshair at the origin of the sheet
+    pub origin: bool,
+    /// Name of the sheet and the date of the run
+    pub label: bool,
+    /// Size of the marks in drawing units
+    pub size: f64,
+}
+
+impl Default for SheetMarks {
+    fn default() -> Self {
+        Self {
+            registration: false,
+            origin: false,
+            label: false,
+            size: 10.0,
+        }
+    }
+}
+
+impl SheetMarks {
+    /// Returns true if any mark is drawn.
+    pub fn any(&self) -> bool {
+        self.registration || self.origin || self.label
+    }
+}
+
+/// Most a single sheet may carry, for handling and the load limit of the
+/// machine bed. Limits left unset do not apply.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct SheetLimits {
+    /// Number of parts
+    pub parts: Option<usize>,
+    /// Total weight of the parts, as given by their `data-weight` attribute
+    pub weight: Option<f64>,
+    /// Total area of the parts
+    pub area: Option<f64>,
+}
+
+impl SheetLimits {
+    /// Whether a sheet already carrying `totals` may take `part` as well.
+    pub fn admit(&self, totals: &SheetTotals, part: &Part) -> bool {
+        self.parts.is_none_or(|max| totals.parts < max)
+            && self.weight.is_none_or(|max| totals.weight + part.weight() <= max + 1e-9)
+            && self.area.is_none_or(|max| totals.area + part.area() <= max + 1e-9)
+    }
+}
+
+/// How the terms of the fitness of a layout are weighted. Lower fitness is
+/// better: every used sheet costs `sheet`, the width used on every sheet over
+/// the sheet area costs `width` and every part that fits no sheet costs
+/// `unplaced`. Raising `width` against `sheet` makes the optimizer open
+/// another sheet sooner rather than spread parts across a sheet. The share
+/// of the boundary of the placed parts not running along a neighbour costs
+/// `contact`, which favours dense layouts with common cuts.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FitnessWeights {
+    pub sheet: f64,
+    pub width: f64,
+    pub unplaced: f64,
+    pub contact: f64,
+}
+
+impl Default for FitnessWeights {
+    fn default() -> Self {
+        Self {
+            sheet: 1.0,
+            width: 1.0,
+            unplaced: 2.0,
+            contact: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NestingConfig {
+    /// Minimum space between parts
+    pub spacing: f64,
+    /// Angles in degrees the parts may be rotated by. Empty keeps every part
+    /// at its original orientation.
+    pub rotations: Vec<f64>,
+    /// How likely each of `rotations` is picked for a part, relative to the
+    /// others. Empty picks them all alike. Parts may override the weight of
+    /// single angles with their `data-rotation-weights` attribute.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rotation_weights: Vec<f64>,
+    /// What happens to parts that fit at none of `rotations`
+    #[serde(default)]
+    pub angle_fallback: AngleFallback,
+    /// Overlap of neighbouring pieces of a part split with
+    /// [`AngleFallback::Split`], left for joining them
+    #[serde(default)]
+    pub split_joint: f64,
+    pub population_size: usize,
+    /// Best individuals carried over unchanged into the next generation
+    #[serde(default = "default_elitism")]
+    pub elitism: usize,
+    /// Chance in percent that a gene mutates
+    pub mutation_rate: usize,
+    /// How the placement orders of two parents are recombined
+    pub crossover: Crossover,
+    /// Moves tried on every offspring by the local search, 0 to disable it
+    pub local_search_iters: usize,
+    /// Seed of the random generator. Runs with the same seed, parts and
+    /// options find the same layouts; without one every run differs.
+    pub seed: Option<u64>,
+    /// Place parts inside the holes of other parts. With `explore_concave`
+    /// every hole of a placed part is free space of its own that the parts
+    /// after it in the placement order can go into.
+    pub use_holes: bool,
+    /// Explore concave areas for more robust placement
+    pub explore_concave: bool,
+    /// Numerical tolerances, of which the NFP cache uses the angle precision
+    pub tolerances: Tolerances,
+    /// Treat the bin as wrapping around in X (rotary attachments, tubes)
+    pub wrap_x: bool,
+    /// Let every sheet be the bin turned by 90°, where that takes parts the
+    /// bin is too narrow for or fills the sheet better
+    pub rotate_sheets: bool,
+    /// Where on each sheet the placed parts end up
+    pub pack_direction: PackDirection,
+    /// Which of the free positions a part fits at the concave explorer
+    /// takes. Earlier sheets always come first.
+    #[serde(default)]
+    pub position_score: PositionScore,
+    /// Point on the sheet that [`PositionScore::Gravity`] pulls parts to
+    #[serde(default)]
+    pub gravity_point: Point,
+    /// Width of a strip along the left edge of every sheet that is kept
+    /// clear, e.g. where a clamp holds the sheet. Packing starts past it.
+    #[serde(default)]
+    pub start_offset: f64,
+    /// Re-fit arcs to the outlines in the SVG output with this tolerance
+    pub fit_arcs: Option<f64>,
+    /// Draw edges that parts nested flush against each other share as a
+    /// single cut in the SVG output
+    pub merge_common_cuts: bool,
+    /// Uncut tabs left in the outline of every part in the output
+    pub tabs: Option<Tabs>,
+    /// Move onto every closed contour from the waste side in the output
+    pub lead_in: Option<Lead>,
+    /// Move off every closed contour to the waste side in the output
+    pub lead_out: Option<Lead>,
+    /// Non-cut marks drawn on every sheet of the SVG output
+    pub marks: SheetMarks,
+    /// Draw the parts left unplaced in a marked area beside the sheets of
+    /// the SVG output
+    #[serde(default)]
+    pub show_unplaced: bool,
+    /// Draw every part in the SVG output as its own geometry in a group
+    /// moving it into place, instead of with moved coordinates
+    #[serde(default)]
+    pub part_transforms: bool,
+    /// Color the parts in the SVG output and add a legend of the colors
+    #[serde(default)]
+    pub color_by: Option<ColorBy>,
+    /// Shade the free space of every sheet in an overlay layer of the SVG
+    /// output
+    #[serde(default)]
+    pub free_space_overlay: bool,
+    /// What every sheet may carry at most. Parts exceeding a limit on their
+    /// own are left unplaced.
+    pub sheet_limits: SheetLimits,
+    /// Regions of the bin no part may overlap, like pre-punched holes or the
+    /// cut-outs of a skeleton sheet, in the coordinates of the bin. Every
+    /// sheet has them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bin_holes: Vec<Vec<Point>>,
+    /// Stop optimizing as soon as a layout uses at least this share of its
+    /// sheets
+    pub target_utilization: Option<f64>,
+    /// Weights of the terms of the fitness
+    #[serde(default)]
+    pub fitness_weights: FitnessWeights,
+    /// Generate NFPs on the GPU when the `gpu` feature is enabled and an
+    /// adapter is available
+    pub gpu: bool,
+    /// Approximate the NFPs of contours with thousands of vertices on a grid
+    /// with this many cells along its longer side
+    pub raster_nfp: Option<u32>,
+}
+
+impl Default for NestingConfig {
+    fn default() -> Self {
+        Self {
+            spacing: 0.0,
+            rotations: evenly_spaced(4),
+            rotation_weights: Vec::new(),
+            angle_fallback: AngleFallback::Unplace,
+            split_joint: 0.0,
+            population_size: 10,
+            elitism: default_elitism(),
+            mutation_rate: 10,
+            crossover: Crossover::OnePoint,
+            local_search_iters: 0,
+            seed: None,
+            use_holes: false,
+            explore_concave: false,
+            tolerances: Tolerances::default(),
+            wrap_x: false,
+            rotate_sheets: false,
+            pack_direction: PackDirection::Left,
+            position_score: PositionScore::First,
+            gravity_point: Point::default(),
+            start_offset: 0.0,
+            fit_arcs: None,
+            merge_common_cuts: false,
+            show_unplaced: false,
+            part_transforms: false,
+            color_by: None,
+            free_space_overlay: false,
+            tabs: None,
+            lead_in: None,
+            lead_out: None,
+            marks: SheetMarks::default(),
+            sheet_limits: SheetLimits::default(),
+            bin_holes: Vec::new(),
+            target_utilization: None,
+            fitness_weights: FitnessWeights::default(),
+            gpu: false,
+            raster_nfp: None,
+        }
+    }
+}
+
+fn default_elitism() -> usize {
+    1
+}
+
+/// Population size suiting `parts` parts at `rotations` rotations each,
+/// growing with the square root of the number of placements.
+pub fn scaled_population_size(parts: usize, rotations: usize) -> usize {
+    let placements = (parts * rotations.max(1)) as f64;
+    ((2.0 * placements.sqrt()).round() as usize).clamp(4, 200)
+}
+
+/// Elitism suiting a population of `size`: a tenth of it, at least one.
+pub fn scaled_elitism(size: usize) -> usize {
+    (size / 10).max(1)
+}
+
+/// Mutation rate suiting `parts` parts, falling with their number so an
+/// offspring sees about two swaps.
+pub fn scaled_mutation_rate(parts: usize) -> usize {
+    (200 / parts.max(1)).clamp(1, 25)
+}
+
+/// `steps` angles evenly spaced around the full circle, starting at 0.
+pub fn evenly_spaced(steps: usize) -> Vec<f64> {
+    (0..steps).map(|i| i as f64 * 360.0 / steps as f64).collect()
+}
+
+impl NestingConfig {
+    pub fn builder() -> NestingConfigBuilder {
+        NestingConfigBuilder::default()
+    }
+
+    /// Reject settings the nester cannot work with.
+    pub fn validate(&self) -> Result<()> {
+        if !(self.spacing >= 0.0 && self.spacing.is_finite()) {
+            bail!("spacing must be a non-negative number, got {}", self.spacing);
+        }
+        if self.population_size == 0 {
+            bail!("population size must be at least 1");
+        }
+        if self.elitism > self.population_size {
+            bail!("elitism {} exceeds the population size {}", self.elitism, self.population_size);
+        }
+        if self.mutation_rate > 100 {
+            bail!("mutation rate is a percentage, got {}", self.mutation_rate);
+        }
+        if let Some(angle) = self.rotations.iter().find(|a| !a.is_finite()) {
+            bail!("rotation angles must be finite, got {}", angle);
+        }
+        if !self.rotation_weights.is_empty() {
+            if self.rotation_weights.len() != self.rotations.len() {
+                bail!(
+                    "{} rotation weight(s) given for {} rotation(s)",
+                    self.rotation_weights.len(),
+                    self.rotations.len()
+                );
+            }
+            if let Some(w) = self.rotation_weights.iter().find(|w| !(**w >= 0.0 && w.is_finite())) {
+                bail!("rotation weights must be non-negative numbers, got {}", w);
+            }
+            if !self.rotation_weights.iter().any(|&w| w > 0.0) {
+                bail!("at least one rotation weight must be positive");
+            }
+        }
+        let t = &self.tolerances;
+        for (name, value) in [
+            ("curve", t.curve),
+            ("connect", t.connect),
+            ("merge", t.merge),
+            ("angle", t.angle),
+            ("contact", t.contact),
+        ] {
+            if !(value > 0.0 && value.is_finite()) {
+                bail!("{} tolerance must be a positive number, got {}", name, value);
+            }
+        }
+        if !(self.gravity_point.x.is_finite() && self.gravity_point.y.is_finite()) {
+            bail!("gravity point must be finite, got {:?}", self.gravity_point);
+        }
+        if self.raster_nfp.is_some_and(|cells| cells < 8) {
+            bail!("raster NFPs need at least 8 cells, got {}", self.raster_nfp.unwrap_or(0));
+        }
+        if self.rotate_sheets && self.wrap_x {
+            bail!("sheets cannot be rotated when the bin wraps around in X");
+        }
+        if let Some(tol) = self.fit_arcs {
+            if !(tol > 0.0 && tol.is_finite()) {
+                bail!("arc fitting tolerance must be a positive number, got {}", tol);
+            }
+        }
+        if let Some(tabs) = self.tabs {
+            if tabs.count == 0 {
+                bail!("tab count must be at least 1");
+            }
+            if !(tabs.width > 0.0 && tabs.width.is_finite()) {
+                bail!("tab width must be a positive number, got {}", tabs.width);
+            }
+        }
+        if self.lead_in.is_some() || self.lead_out.is_some() {
+            // both split contours apart, leaving no single start point
+            if self.tabs.is_some() {
+                bail!("lead-ins and lead-outs cannot be combined with tabs");
+            }
+            if self.merge_common_cuts {
+                bail!("lead-ins and lead-outs cannot be combined with merged common cuts");
+            }
+        }
+        // these change the outlines where they lie on the sheet
+        if self.part_transforms {
+            if self.tabs.is_some() || self.lead_in.is_some() || self.lead_out.is_some() {
+                bail!("part transforms cannot be combined with tabs, lead-ins or lead-outs");
+            }
+            if self.merge_common_cuts {
+                bail!("part transforms cannot be combined with merged common cuts");
+            }
+        }
+        // a merged cut belongs to no single part
+        if self.color_by.is_some() && self.merge_common_cuts {
+            bail!("colored parts cannot be combined with merged common cuts");
+        }
+        if !(self.marks.size > 0.0 && self.marks.size.is_finite()) {
+            bail!("mark size must be a positive number, got {}", self.marks.size);
+        }
+        if self.sheet_limits.parts == Some(0) {
+            bail!("the part limit per sheet must be at least 1");
+        }
+        for (name, limit) in [("weight", self.sheet_limits.weight), ("area", self.sheet_limits.area)] {
+            if let Some(max) = limit {
+                if !(max > 0.0 && max.is_finite()) {
+                    bail!("{} limit per sheet must be a positive number, got {}", name, max);
+                }
+            }
+        }
+        if self.bin_holes.iter().any(|hole| hole.len() < 3) {
+            bail!("bin holes need at least 3 points");
+        }
+        if self.bin_holes.iter().flatten().any(|p| !(p.x.is_finite() && p.y.is_finite())) {
+            bail!("bin holes must have finite coordinates");
+        }
+        if self.rotate_sheets && !self.bin_holes.is_empty() {
+            bail!("sheets with holes cannot be rotated");
+        }
+        if !(self.split_joint >= 0.0 && self.split_joint.is_finite()) {
+            bail!("split joint must be a non-negative number, got {}", self.split_joint);
+        }
+        if !(self.start_offset >= 0.0 && self.start_offset.is_finite()) {
+            bail!("start offset must be a non-negative number, got {}", self.start_offset);
+        }
+        if self.rotate_sheets && self.start_offset > 0.0 {
+            bail!("sheets with a start offset cannot be rotated");
+        }
+        let w = &self.fitness_weights;
+        for (name, value) in [
+            ("sheet", w.sheet),
+            ("width", w.width),
+            ("unplaced", w.unplaced),
+            ("contact", w.contact),
+        ] {
+            if !(value >= 0.0 && value.is_finite()) {
+                bail!("{} fitness weight must be a non-negative number, got {}", name, value);
+            }
+        }
+        if let Some(target) = self.target_utilization {
+            if !(target > 0.0 && target <= 1.0) {
+                bail!("target utilization must be above 0 and at most 1, got {}", target);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Builder for [`NestingConfig`], starting from its defaults.
+#[derive(Debug, Clone, Default)]
+pub struct NestingConfigBuilder {
+    config: NestingConfig,
+}
+
+impl NestingConfigBuilder {
+    pub fn spacing(mut self, spacing: f64) -> Self {
+        self.config.spacing = spacing;
+        self
+    }
+
+    pub fn rotations(mut self, angles: &[f64]) -> Self {
+        self.config.rotations = angles.to_vec();
+        self
+    }
+
+    /// Weight of each of the rotations, in their order.
+    pub fn rotation_weights(mut self, weights: &[f64]) -> Self {
+        self.config.rotation_weights = weights.to_vec();
+        self
+    }
+
+    pub fn angle_fallback(mut self, fallback: AngleFallback) -> Self {
+        self.config.angle_fallback = fallback;
+        self
+    }
+
+    pub fn split_joint(mut self, joint: f64) -> Self {
+        self.config.split_joint = joint;
+        self
+    }
+
+    /// Allow `steps` rotations evenly spaced around the full circle.
+    pub fn rotation_steps(mut self, steps: usize) -> Self {
+        self.config.rotations = evenly_spaced(steps);
+        self
+    }
+
+    pub fn population_size(mut self, size: usize) -> Self {
+        self.config.population_size = size;
+        self
+    }
+
+    pub fn elitism(mut self, elite: usize) -> Self {
+        self.config.elitism = elite;
+        self
+    }
+
+    pub fn mutation_rate(mut self, rate: usize) -> Self {
+        self.config.mutation_rate = rate;
+        self
+    }
+
+    pub fn crossover(mut self, crossover: Crossover) -> Self {
+        self.config.crossover = crossover;
+        self
+    }
+
+    pub fn local_search_iters(mut self, iters: usize) -> Self {
+        self.config.local_search_iters = iters;
+        self
+    }
+
+    pub fn seed(mut self, seed: Option<u64>) -> Self {
+        self.config.seed = seed;
+        self
+    }
+
+    pub fn use_holes(mut self, on: bool) -> Self {
+        self.config.use_holes = on;
+        self
+    }
+
+    pub fn explore_concave(mut self, on: bool) -> Self {
+        self.config.explore_concave = on;
+        self
+    }
+
+    pub fn tolerances(mut self, tolerances: Tolerances) -> Self {
+        self.config.tolerances = tolerances;
+        self
+    }
+
+    pub fn wrap_x(mut self, on: bool) -> Self {
+        self.config.wrap_x = on;
+        self
+    }
+
+    pub fn rotate_sheets(mut self, on: bool) -> Self {
+        self.config.rotate_sheets = on;
+        self
+    }
+
+    pub fn pack_direction(mut self, direction: PackDirection) -> Self {
+        self.config.pack_direction = direction;
+        self
+    }
+
+    pub fn position_score(mut self, score: PositionScore) -> Self {
+        self.config.position_score = score;
+        self
+    }
+
+    pub fn gravity_point(mut self, point: Point) -> Self {
+        self.config.gravity_point = point;
+        self
+    }
+
+    pub fn start_offset(mut self, offset: f64) -> Self {
+        self.config.start_offset = offset;
+        self
+    }
+
+    pub fn fit_arcs(mut self, tol: Option<f64>) -> Self {
+        self.config.fit_arcs = tol;
+        self
+    }
+
+    pub fn merge_common_cuts(mut self, merge: bool) -> Self {
+        self.config.merge_common_cuts = merge;
+        self
+    }
+
+    pub fn show_unplaced(mut self, show: bool) -> Self {
+        self.config.show_unplaced = show;
+        self
+    }
+
+    pub fn part_transforms(mut self, transforms: bool) -> Self {
+        self.config.part_transforms = transforms;
+        self
+    }
+
+    pub fn color_by(mut self, by: Option<ColorBy>) -> Self {
+        self.config.color_by = by;
+        self
+    }
+
+    pub fn free_space_overlay(mut self, overlay: bool) -> Self {
+        self.config.free_space_overlay = overlay;
+        self
+    }
+
+    pub fn tabs(mut self, tabs: Option<Tabs>) -> Self {
+        self.config.tabs = tabs;
+        self
+    }
+
+    pub fn lead_in(mut self, lead: Option<Lead>) -> Self {
+        self.config.lead_in = lead;
+        self
+    }
+
+    pub fn lead_out(mut self, lead: Option<Lead>) -> Self {
+        self.config.lead_out = lead;
+        self
+    }
+
+    pub fn marks(mut self, marks: SheetMarks) -> Self {
+        self.config.marks = marks;
+        self
+    }
+
+    pub fn sheet_limits(mut self, limits: SheetLimits) -> Self {
+        self.config.sheet_limits = limits;
+        self
+    }
+
+    pub fn bin_holes(mut self, holes: Vec<Vec<Point>>) -> Self {
+        self.config.bin_holes = holes;
+        self
+    }
+
+    pub fn target_utilization(mut self, target: Option<f64>) -> Self {
+        self.config.target_utilization = target;
+        self
+    }
+
+    pub fn fitness_weights(mut self, weights: FitnessWeights) -> Self {
+        self.config.fitness_weights = weights;
+        self
+    }
+
+    pub fn gpu(mut self, on: bool) -> Self {
+        self.config.gpu = on;
+        self
+    }
+
+    pub fn raster_nfp(mut self, cells: Option<u32>) -> Self {
+        self.config.raster_nfp = cells;
+        self
+    }
+
+    pub fn build(self) -> Result<NestingConfig> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder_sets_fields() {
+        let config = NestingConfig::builder()
+            .spacing(2.0)
+            .rotations(&[0.0, 90.0])
+            .population_size(4)
+            .build()
+            .unwrap();
+        assert_eq!(config.spacing, 2.0);
+        assert_eq!(config.rotations, vec![0.0, 90.0]);
+        assert_eq!(config.population_size, 4);
+        assert_eq!(NestingConfig::default().rotations, vec![0.0, 90.0, 180.0, 270.0]);
+    }
+
+    #[test]
+    fn scaled_settings_follow_the_job_size() {
+        assert_eq!(scaled_population_size(5, 4), 9);
+        assert_eq!(scaled_population_size(200, 4), 57);
+        assert_eq!(scaled_population_size(0, 0), 4);
+        assert_eq!(scaled_population_size(10_000, 8), 200);
+        assert_eq!(scaled_elitism(9), 1);
+        assert_eq!(scaled_elitism(57), 5);
+        assert_eq!(scaled_mutation_rate(5), 25);
+        assert_eq!(scaled_mutation_rate(20), 10);
+        assert_eq!(scaled_mutation_rate(200), 1);
+    }
+
+    #[test]
+    fn builder_rejects_nonsense() {
+        assert!(NestingConfig::builder().spacing(-1.0).build().is_err());
+        assert!(NestingConfig::builder().spacing(f64::NAN).build().is_err());
+        assert!(NestingConfig::builder().population_size(0).build().is_err());
+        assert!(NestingConfig::builder().mutation_rate(101).build().is_err());
+        assert!(NestingConfig::builder().population_size(4).elitism(5).build().is_err());
+        assert!(NestingConfig::builder().rotations(&[f64::INFINITY]).build().is_err());
+        assert!(NestingConfig::builder().fit_arcs(Some(0.0)).build().is_err());
+        assert!(NestingConfig::builder().wrap_x(true).rotate_sheets(true).build().is_err());
+        assert!(NestingConfig::builder().target_utilization(Some(1.5)).build().is_err());
+        let weights = FitnessWeights {
+            width: -1.0,
+            ..Default::default()
+        };
+        assert!(NestingConfig::builder().fitness_weights(weights).build().is_err());
+        let weights = FitnessWeights {
+            contact: f64::NAN,
+            ..Default::default()
+        };
+        assert!(NestingConfig::builder().fitness_weights(weights).build().is_err());
+        assert!(NestingConfig::builder().tabs(Some(Tabs { count: 0, width: 1.0 })).build().is_err());
+        let lead = "line:1".parse().ok();
+        assert!(NestingConfig::builder().lead_in(lead).merge_common_cuts(true).build().is_err());
+        assert!(NestingConfig::builder().part_transforms(true).merge_common_cuts(true).build().is_err());
+        assert!(NestingConfig::builder().color_by(Some(ColorBy::Sheet)).merge_common_cuts(true).build().is_err());
+        let marks = SheetMarks {
+            size: 0.0,
+            ..Default::default()
+        };
+        assert!(NestingConfig::builder().marks(marks).build().is_err());
+        let limits = SheetLimits {
+            weight: Some(-1.0),
+            ..Default::default()
+        };
+        assert!(NestingConfig::builder().sheet_limits(limits).build().is_err());
+        let hole = vec![Point { x: 0.0, y: 0.0 }, Point { x: 1.0, y: 0.0 }, Point { x: 0.0, y: 1.0 }];
+        assert!(NestingConfig::builder().bin_holes(vec![hole[..2].to_vec()]).build().is_err());
+        assert!(NestingConfig::builder().bin_holes(vec![hole]).rotate_sheets(true).build().is_err());
+        assert!(NestingConfig::builder().start_offset(-1.0).build().is_err());
+        assert!(NestingConfig::builder().split_joint(f64::NAN).build().is_err());
+        assert!(NestingConfig::builder().raster_nfp(Some(4)).build().is_err());
+        let point = Point { x: f64::NAN, y: 0.0 };
+        assert!(NestingConfig::builder().gravity_point(point).build().is_err());
+        assert!(NestingConfig::builder().start_offset(5.0).rotate_sheets(true).build().is_err());
+        let tolerances = Tolerances {
+            angle: 0.0,
+            ..Default::default()
+        };
+        assert!(NestingConfig::builder().tolerances(tolerances).build().is_err());
+        let rotations = NestingConfig::builder().rotations(&[0.0, 90.0]);
+        assert!(rotations.clone().rotation_weights(&[3.0, 1.0]).build().is_ok());
+        assert!(rotations.clone().rotation_weights(&[1.0]).build().is_err());
+        assert!(rotations.clone().rotation_weights(&[0.0, 0.0]).build().is_err());
+        assert!(rotations.rotation_weights(&[-1.0, 1.0]).build().is_err());
+    }
+}
@@ -0,0 +1,663 @@
+//! Curve-preserving contours kept alongside the flattened polygons.
+//!
+//! Nesting only ever looks at the flattened points of a [`Polygon`]; the
+//! contour records the lines, circular arcs and Béziers the shape was built
+//! from so writers can emit exact curves.
+//!
+//! [`Polygon`]: crate::svg_parser::Polygon
+
+use std::f64::consts::{FRAC_PI_2, PI};
+
+use crate::svg_parser::Point;
+
+/// A single segment of a [`Contour`]. Each segment starts where the previous
+/// one ended.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Segment {
+    Line { to: Point },
+    /// Circular arc around `center`. `sweep` is the signed angle in radians
+    /// swept from the start point, positive in the direction of increasing
+    /// angle.
+    Arc { center: Point, radius: f64, sweep: f64, to: Point },
+    Quadratic { ctrl: Point, to: Point },
+    Cubic { ctrl1: Point, ctrl2: Point, to: Point },
+}
+
+impl Segment {
+    /// End point of the segment.
+    pub fn end(&self) -> Point {
+        match *self {
+            Segment::Line { to }
+            | Segment::Arc { to, .. }
+            | Segment::Quadratic { to, .. }
+            | Segment::Cubic { to, .. } => to,
+        }
+    }
+}
+
+/// Sequence of segments starting at `start`. Closed contours return to
+/// `start` with an implicit straight line.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Contour {
+    pub start: Point,
+    pub segments: Vec<Segment>,
+    pub closed: bool,
+}
+
+impl Contour {
+    pub fn new(start: Point) -> Self {
+        Self {
+            start,
+            segments: Vec::new(),
+            closed: false,
+        }
+    }
+
+    /// Current end point of the contour.
+    pub fn end(&self) -> Point {
+        self.segments.last().map_or(self.start, Segment::end)
+    }
+
+    /// True if the contour contains anything besides straight lines.
+    pub fn has_curves(&self) -> bool {
+        self.segments
+            .iter()
+            .any(|s| !matches!(s, Segment::Line { .. }))
+    }
+
+    /// Approximate the contour by points with at most `tol` deviation. The
+    /// closing point of closed contours is not repeated.
+    pub fn flatten(&self, tol: f64) -> Vec<Point> {
+        let mut pts = vec![self.start];
+        let mut from = self.start;
+        for seg in &self.segments {
+            match *seg {
+                Segment::Line { to } => pts.push(to),
+                Segment::Arc {
+                    center,
+                    radius,
+                    sweep,
+                    to,
+                } => {
+                    let n = arc_steps(radius, sweep, tol);
+                    let a0 = (from.y - center.y).atan2(from.x - center.x);
+                    for i in 1..n {
+                        let a = a0 + sweep * i as f64 / n as f64;
+                        pts.push(Point {
+                            x: center.x + radius * a.cos(),
+                            y: center.y + radius * a.sin(),
+                        });
+                    }
+                    pts.push(to);
+                }
+                Segment::Quadratic { ctrl, to } => {
+                    let dd = hypot(from.x - 2.0 * ctrl.x + to.x, from.y - 2.0 * ctrl.y + to.y);
+                    let n = bezier_steps(0.25 * dd, tol);
+                    for i in 1..=n {
+                        let t = i as f64 / n as f64;
+                        let mt = 1.0 - t;
+                        pts.push(Point {
+                            x: mt * mt * from.x + 2.0 * mt * t * ctrl.x + t * t * to.x,
+                            y: mt * mt * from.y + 2.0 * mt * t * ctrl.y + t * t * to.y,
+                        });
+                    }
+                }
+                Segment::Cubic { ctrl1, ctrl2, to } => {
+                    let dd = hypot(from.x - 2.0 * ctrl1.x + ctrl2.x, from.y - 2.0 * ctrl1.y + ctrl2.y)
+                        .max(hypot(ctrl1.x - 2.0 * ctrl2.x + to.x, ctrl1.y - 2.0 * ctrl2.y + to.y));
+                    let n = bezier_steps(0.75 * dd, tol);
+                    for i in 1..=n {
+                        let t = i as f64 / n as f64;
+                        let mt = 1.0 - t;
+                        let (a, b, c, d) = (mt * mt * mt, 3.0 * mt * mt * t, 3.0 * mt * t * t, t * t * t);
+                        pts.push(Point {
+                            x: a * from.x + b * ctrl1.x + c * ctrl2.x + d * to.x,
+                            y: a * from.y + b * ctrl1.y + c * ctrl2.y + d * to.y,
+                        });
+                    }
+                }
+            }
+            from = seg.end();
+        }
+        if self.closed && pts.len() > 1 && pts[0] == pts[pts.len() - 1] {
+            pts.pop();
+        }
+        pts
+    }
+
+    /// Apply the affine matrix `[a, b, c, d, e, f]`. Arcs stay arcs under
+    /// rotations, reflections, translations and uniform scaling; any other
+    /// transform turns them into cubic Béziers.
+    pub fn transform(&self, m: [f64; 6]) -> Contour {
+        let apply = |p: Point| Point {
+            x: p.x * m[0] + p.y * m[2] + m[4],
+            y: p.x * m[1] + p.y * m[3] + m[5],
+        };
+        let det = m[0] * m[3] - m[1] * m[2];
+        let conformal = (m[0] - m[3] * det.signum()).abs() < 1e-12
+            && (m[1] + m[2] * det.signum()).abs() < 1e-12
+            && det != 0.0;
+        let mut out = Contour {
+            start: apply(self.start),
+            segments: Vec::with_capacity(self.segments.len()),
+            closed: self.closed,
+        };
+        let mut from = self.start;
+        for seg in &self.segments {
+            match *seg {
+                Segment::Line { to } => out.segments.push(Segment::Line { to: apply(to) }),
+                Segment::Arc {
+                    center,
+                    radius,
+                    sweep,
+                    to,
+                } if conformal => out.segments.push(Segment::Arc {
+                    center: apply(center),
+                    radius: radius * det.abs().sqrt(),
+                    sweep: sweep * det.signum(),
+                    to: apply(to),
+                }),
+                Segment::Arc {
+                    center,
+                    radius,
+                    sweep,
+                    to,
+                } => {
+                    let a0 = (from.y - center.y).atan2(from.x - center.x);
+                    let mut cubics = ellipse_cubics(center, radius, radius, 0.0, a0, sweep);
+                    if let Some(Segment::Cubic { to: end, .. }) = cubics.last_mut() {
+                        *end = to;
+                    }
+                    for cubic in cubics {
+                        out.segments.push(match cubic {
+                            Segment::Cubic { ctrl1, ctrl2, to } => Segment::Cubic {
+                                ctrl1: apply(ctrl1),
+                                ctrl2: apply(ctrl2),
+                                to: apply(to),
+                            },
+                            other => other,
+                        });
+                    }
+                }
+                Segment::Quadratic { ctrl, to } => out.segments.push(Segment::Quadratic {
+                    ctrl: apply(ctrl),
+                    to: apply(to),
+                }),
+                Segment::Cubic { ctrl1, ctrl2, to } => out.segments.push(Segment::Cubic {
+                    ctrl1: apply(ctrl1),
+                    ctrl2: apply(ctrl2),
+                    to: apply(to),
+                }),
+            }
+            from = seg.end();
+        }
+        out
+    }
+
+    /// Rotate around the origin, counter-clockwise for positive angles like
+    /// [`rotate_polygon`](crate::geometry::rotate_polygon).
+    pub fn rotate(&self, angle_deg: f64) -> Contour {
+        let (sin, cos) = angle_deg.to_radians().sin_cos();
+        self.transform([cos, sin, -sin, cos, 0.0, 0.0])
+    }
+
+    pub fn translate(&self, dx: f64, dy: f64) -> Contour {
+        self.transform([1.0, 0.0, 0.0, 1.0, dx, dy])
+    }
+
+    /// The same contour traversed in the opposite direction.
+    pub fn reversed(&self) -> Contour {
+        let mut segments = Vec::with_capacity(self.segments.len());
+        let mut from = self.start;
+        for seg in &self.segments {
+            segments.push(match *seg {
+                Segment::Line { .. } => Segment::Line { to: from },
+                Segment::Arc {
+                    center,
+                    radius,
+                    sweep,
+                    ..
+                } => Segment::Arc {
+                    center,
+                    radius,
+                    sweep: -sweep,
+                    to: from,
+                },
+                Segment::Quadratic { ctrl, .. } => Segment::Quadratic { ctrl, to: from },
+                Segment::Cubic { ctrl1, ctrl2, .. } => Segment::Cubic {
+                    ctrl1: ctrl2,
+                    ctrl2: ctrl1,
+                    to: from,
+                },
+            });
+            from = seg.end();
+        }
+        segments.reverse();
+        Contour {
+            start: self.end(),
+            segments,
+            closed: self.closed,
+        }
+    }
+
+    /// SVG path data describing the contour exactly.
+    pub fn to_path_data(&self) -> String {
+        let mut d = format!("M {} {}", self.start.x, self.start.y);
+        let mut from = self.start;
+        for seg in &self.segments {
+            match *seg {
+                Segment::Line { to } => d.push_str(&format!(" L {} {}", to.x, to.y)),
+                Segment::Arc {
+                    center,
+                    radius,
+                    sweep,
+                    to,
+                } => {
+                    // SVG cannot express a full circle in one arc command
+                    let pieces = if sweep.abs() > PI * 1.999 { 2 } else { 1 };
+                    let a0 = (from.y - center.y).atan2(from.x - center.x);
+                    for i in 1..=pieces {
+                        let end = if i == pieces {
+                            to
+                        } else {
+                            let a = a0 + sweep * i as f64 / pieces as f64;
+                            Point {
+                                x: center.x + radius * a.cos(),
+                                y: center.y + radius * a.sin(),
+                            }
+                        };
+                        let part = sweep / pieces as f64;
+                        let large = if part.abs() > PI { 1 } else { 0 };
+                        let positive = if part > 0.0 { 1 } else { 0 };
+                        d.push_str(&format!(
+                            " A {} {} 0 {} {} {} {}",
+                            radius, radius, large, positive, end.x, end.y
+                        ));
+                    }
+                }
+                Segment::Quadratic { ctrl, to } => {
+                    d.push_str(&format!(" Q {} {} {} {}", ctrl.x, ctrl.y, to.x, to.y))
+                }
+                Segment::Cubic { ctrl1, ctrl2, to } => d.push_str(&format!(
+                    " C {} {} {} {} {} {}",
+                    ctrl1.x, ctrl1.y, ctrl2.x, ctrl2.y, to.x, to.y
+                )),
+            }
+            from = seg.end();
+        }
+        if self.closed {
+            d.push_str(" Z");
+        }
+        d
+    }
+}
+
+fn hypot(x: f64, y: f64) -> f64 {
+    (x * x + y * y).sqrt()
+}
+
+/// Number of chords needed for an arc to stay within `tol`.
+fn arc_steps(radius: f64, sweep: f64, tol: f64) -> usize {
+    if radius <= tol || tol <= 0.0 {
+        return ((sweep.abs() / FRAC_PI_2).ceil() as usize).clamp(1, 1024);
+    }
+    let step = 2.0 * (1.0 - tol / radius).acos();
+    ((sweep.abs() / step).ceil() as usize).clamp(1, 1024)
+}
+
+/// Number of uniform steps for a Bézier whose second derivative is bounded
+/// by `8 * bound`.
+fn bezier_steps(bound: f64, tol: f64) -> usize {
+    if tol <= 0.0 {
+        return 16;
+    }
+    ((bound / tol).sqrt().ceil() as usize).clamp(1, 1024)
+}
+
+/// Cubic Béziers approximating the elliptical arc around `center` with radii
+/// `rx`, `ry` rotated by `phi` radians, from parameter `theta` over `sweep`.
+pub fn ellipse_cubics(center: Point, rx: f64, ry: f64, phi: f64, theta: f64, sweep: f64) -> Vec<Segment> {
+    let (sin_phi, cos_phi) = phi.sin_cos();
+    let point = |t: f64| {
+        let (x, y) = (rx * t.cos(), ry * t.sin());
+        Point {
+            x: center.x + x * cos_phi - y * sin_phi,
+            y: center.y + x * sin_phi + y * cos_phi,
+        }
+    };
+    let tangent = |t: f64| {
+        let (x, y) = (-rx * t.sin(), ry * t.cos());
+        Point {
+            x: x * cos_phi - y * sin_phi,
+            y: x * sin_phi + y * cos_phi,
+        }
+    };
+    let pieces = ((sweep.abs() / FRAC_PI_2).ceil() as usize).clamp(1, 1024);
+    let delta = sweep / pieces as f64;
+    let k = 4.0 / 3.0 * (delta / 4.0).tan();
+    (0..pieces)
+        .map(|i| {
+            let t0 = theta + delta * i as f64;
+            let t1 = t0 + delta;
+            let (p0, p1) = (point(t0), point(t1));
+            let (d0, d1) = (tangent(t0), tangent(t1));
+            Segment::Cubic {
+                ctrl1: Point {
+                    x: p0.x + k * d0.x,
+                    y: p0.y + k * d0.y,
+                },
+                ctrl2: Point {
+                    x: p1.x - k * d1.x,
+                    y: p1.y - k * d1.y,
+                },
+                to: p1,
+            }
+        })
+        .collect()
+}
+
+/// Segments for an SVG elliptical arc command from `from` to `to`, following
+/// the endpoint-to-center conversion of the SVG specification. Circular arcs
+/// become a single [`Segment::Arc`], elliptical ones cubic Béziers.
+pub fn svg_arc(
+    from: Point,
+    radii: (f64, f64),
+    x_axis_rotation_deg: f64,
+    large_arc: bool,
+    sweep_flag: bool,
+    to: Point,
+) -> Vec<Segment> {
+    let (mut rx, mut ry) = (radii.0.abs(), radii.1.abs());
+    if rx == 0.0 || ry == 0.0 || from == to {
+        return vec![Segment::Line { to }];
+    }
+    let phi = x_axis_rotation_deg.to_radians();
+    let (sin_phi, cos_phi) = phi.sin_cos();
+    let dx = (from.x - to.x) / 2.0;
+    let dy = (from.y - to.y) / 2.0;
+    let x1 = cos_phi * dx + sin_phi * dy;
+    let y1 = -sin_phi * dx + cos_phi * dy;
+
+    let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+    if lambda > 1.0 {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+    let num = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+    let den = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+    let mut coef = (num / den).max(0.0).sqrt();
+    if large_arc == sweep_flag {
+        coef = -coef;
+    }
+    let cx1 = coef * rx * y1 / ry;
+    let cy1 = -coef * ry * x1 / rx;
+    let center = Point {
+        x: cos_phi * cx1 - sin_phi * cy1 + (from.x + to.x) / 2.0,
+        y: sin_phi * cx1 + cos_phi * cy1 + (from.y + to.y) / 2.0,
+    };
+
+    let angle = |ux: f64, uy: f64, vx: f64, vy: f64| (ux * vy - uy * vx).atan2(ux * vx + uy * vy);
+    let ux = (x1 - cx1) / rx;
+    let uy = (y1 - cy1) / ry;
+    let vx = (-x1 - cx1) / rx;
+    let vy = (-y1 - cy1) / ry;
+    let theta = angle(1.0, 0.0, ux, uy);
+    let mut sweep = angle(ux, uy, vx, vy);
+    if !sweep_flag && sweep > 0.0 {
+        sweep -= 2.0 * PI;
+    } else if sweep_flag && sweep < 0.0 {
+        sweep += 2.0 * PI;
+    }
+
+    if (rx - ry).abs() <= 1e-9 * rx.max(ry) {
+        vec![Segment::Arc {
+            center,
+            radius: rx,
+            sweep,
+            to,
+        }]
+    } else {
+        let mut cubics = ellipse_cubics(center, rx, ry, phi, theta, sweep);
+        if let Some(Segment::Cubic { to: end, .. }) = cubics.last_mut() {
+            *end = to;
+        }
+        cubics
+    }
+}
+
+/// Closed contour of a full circle starting at its rightmost point.
+pub fn circle(center: Point, radius: f64) -> Contour {
+    let start = Point {
+        x: center.x + radius,
+        y: center.y,
+    };
+    Contour {
+        start,
+        segments: vec![Segment::Arc {
+            center,
+            radius,
+            sweep: 2.0 * PI,
+            to: start,
+        }],
+        closed: true,
+    }
+}
+
+/// Closed contour of a full axis-aligned ellipse.
+pub fn ellipse(center: Point, rx: f64, ry: f64) -> Contour {
+    if rx == ry {
+        return circle(center, rx);
+    }
+    let start = Point {
+        x: center.x + rx,
+        y: center.y,
+    };
+    let mut segments = ellipse_cubics(center, rx, ry, 0.0, 0.0, 2.0 * PI);
+    if let Some(Segment::Cubic { to, .. }) = segments.last_mut() {
+        *to = start;
+    }
+    Contour {
+        start,
+        segments,
+        closed: true,
+    }
+}
+
+/// Closed or open contour through `points` using straight lines only.
+pub fn polyline(points: &[Point], closed: bool) -> Option<Contour> {
+    let (first, rest) = points.split_first()?;
+    Some(Contour {
+        start: *first,
+        segments: rest.iter().map(|&to| Segment::Line { to }).collect(),
+        closed,
+    })
+}
+
+/// Center of the circle through three points, `None` if they are collinear.
+fn circumcenter(a: Point, b: Point, c: Point) -> Option<Point> {
+    let d = 2.0 * (a.x * (b.y - c.y) + b.x * (c.y - a.y) + c.x * (a.y - b.y));
+    if d.abs() < 1e-12 {
+        return None;
+    }
+    let (a2, b2, c2) = (a.x * a.x + a.y * a.y, b.x * b.x + b.y * b.y, c.x * c.x + c.y * c.y);
+    Some(Point {
+        x: (a2 * (b.y - c.y) + b2 * (c.y - a.y) + c2 * (a.y - b.y)) / d,
+        y: (a2 * (c.x - b.x) + b2 * (a.x - c.x) + c2 * (b.x - a.x)) / d,
+    })
+}
+
+/// Arc through the run of points if all of them lie within `tol` of a single
+/// circle traversed in one direction, and the run is not nearly straight.
+fn fit_run(run: &[Point], tol: f64) -> Option<Segment> {
+    let (first, last) = (run[0], run[run.len() - 1]);
+    let center = circumcenter(first, run[run.len() / 2], last)?;
+    let radius = hypot(first.x - center.x, first.y - center.y);
+
+    let mut sweep = 0.0;
+    let mut direction = 0.0;
+    let mut prev = (first.y - center.y).atan2(first.x - center.x);
+    for p in &run[1..] {
+        if (hypot(p.x - center.x, p.y - center.y) - radius).abs() > tol {
+            return None;
+        }
+        let angle = (p.y - center.y).atan2(p.x - center.x);
+        let mut step = angle - prev;
+        if step > PI {
+            step -= 2.0 * PI;
+        } else if step < -PI {
+            step += 2.0 * PI;
+        }
+        // consecutive points must advance in one direction and stay close
+        if step == 0.0 || step.signum() == -direction || step.abs() > FRAC_PI_2 {
+            return None;
+        }
+        direction = step.signum();
+        sweep += step;
+        prev = angle;
+    }
+    if sweep.abs() >= 2.0 * PI - 1e-9 {
+        return None;
+    }
+
+    // nearly straight runs are better left as lines
+    let (dx, dy) = (last.x - first.x, last.y - first.y);
+    let chord = hypot(dx, dy);
+    let bulge = run
+        .iter()
+        .map(|p| ((p.x - first.x) * dy - (p.y - first.y) * dx).abs() / chord.max(f64::EPSILON))
+        .fold(0.0, f64::max);
+    if bulge <= tol {
+        return None;
+    }
+    Some(Segment::Arc {
+        center,
+        radius,
+        sweep,
+        to: last,
+    })
+}
+
+/// Contour through `points` where runs of at least four nearly cocircular
+/// points are replaced by arcs. Every point stays within `tol` of the result.
+pub fn fit_arcs(points: &[Point], closed: bool, tol: f64) -> Option<Contour> {
+    let mut pts = points.to_vec();
+    let mut contour = Contour::new(*pts.first()?);
+    contour.closed = closed;
+    if closed {
+        pts.push(pts[0]);
+    }
+    let mut i = 0;
+    while i + 1 < pts.len() {
+        let mut best = None;
+        let mut j = i + 3;
+        while j < pts.len() {
+            match fit_run(&pts[i..=j], tol) {
+                Some(arc) => best = Some((j, arc)),
+                None => break,
+            }
+            j += 1;
+        }
+        match best {
+            Some((end, arc)) => {
+                contour.segments.push(arc);
+                i = end;
+            }
+            None => {
+                contour.segments.push(Segment::Line { to: pts[i + 1] });
+                i += 1;
+            }
+        }
+    }
+    // closing lines are implicit
+    if closed && matches!(contour.segments.last(), Some(Segment::Line { .. })) {
+        contour.segments.pop();
+    }
+    Some(contour)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn svg_arc_half_circle() {
+        let segs = svg_arc(
+            Point { x: 0.0, y: 0.0 },
+            (5.0, 5.0),
+            0.0,
+            false,
+            true,
+            Point { x: 10.0, y: 0.0 },
+        );
+        let Segment::Arc {
+            center,
+            radius,
+            sweep,
+            ..
+        } = segs[0]
+        else {
+            panic!("expected an arc");
+        };
+        assert!((center.x - 5.0).abs() < 1e-9 && center.y.abs() < 1e-9);
+        assert!((radius - 5.0).abs() < 1e-9);
+        assert!((sweep - PI).abs() < 1e-9);
+    }
+
+    #[test]
+    fn circle_survives_rotation_and_flattens_within_tolerance() {
+        let c = circle(Point { x: 1.0, y: 2.0 }, 10.0).rotate(30.0).translate(5.0, 0.0);
+        assert!(!c.segments.is_empty() && c.has_curves());
+        assert!(matches!(c.segments[0], Segment::Arc { .. }));
+        let center = Point {
+            x: 1.0 * 30f64.to_radians().cos() - 2.0 * 30f64.to_radians().sin() + 5.0,
+            y: 1.0 * 30f64.to_radians().sin() + 2.0 * 30f64.to_radians().cos(),
+        };
+        for p in c.flatten(0.1) {
+            let r = hypot(p.x - center.x, p.y - center.y);
+            assert!((r - 10.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn reversed_round_trip() {
+        let mut c = Contour::new(Point { x: 0.0, y: 0.0 });
+        c.segments.push(Segment::Line { to: Point { x: 1.0, y: 0.0 } });
+        c.segments.push(Segment::Cubic {
+            ctrl1: Point { x: 2.0, y: 0.0 },
+            ctrl2: Point { x: 2.0, y: 1.0 },
+            to: Point { x: 1.0, y: 1.0 },
+        });
+        assert_eq!(c.reversed().start, Point { x: 1.0, y: 1.0 });
+        assert_eq!(c.reversed().reversed(), c);
+    }
+
+    #[test]
+    fn fit_arcs_recovers_circle_segments() {
+        // a square with one rounded corner
+        let mut pts = vec![Point { x: 0.0, y: 0.0 }, Point { x: 10.0, y: 0.0 }];
+        for i in 0..=8 {
+            let a = -FRAC_PI_2 + FRAC_PI_2 * i as f64 / 8.0;
+            pts.push(Point {
+                x: 10.0 + 5.0 * a.cos(),
+                y: 5.0 + 5.0 * a.sin(),
+            });
+        }
+        pts.push(Point { x: 0.0, y: 10.0 });
+        pts.dedup();
+        let c = fit_arcs(&pts, true, 1e-6).unwrap();
+        let arcs: Vec<_> = c
+            .segments
+            .iter()
+            .filter(|s| matches!(s, Segment::Arc { .. }))
+            .collect();
+        assert_eq!(arcs.len(), 1);
+        let Segment::Arc { center, radius, sweep, .. } = *arcs[0] else {
+            unreachable!();
+        };
+        assert!((center.x - 10.0).abs() < 1e-6 && (center.y - 5.0).abs() < 1e-6);
+        assert!((radius - 5.0).abs() < 1e-6);
+        assert!((sweep - FRAC_PI_2).abs() < 1e-6);
+        assert!(c.segments.len() < pts.len());
+    }
+}
@@ -0,0 +1,245 @@
+//! Crossover operators recombining the placement orders of two individuals.
+//!
+//! A gene is a part index together with the rotation it is placed at and
+//! the type of sheet it goes on, so both travel with their parts. Every operator runs its children
+//! through [`repair`], which turns them back into a permutation of the
+//! parent's parts should an operator ever produce duplicates or drop genes.
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+/// Part index, rotation in degrees and sheet type
+pub type Gene = (usize, f64, usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Crossover {
+    /// Head of one parent up to a random cut, the rest in the order of the
+    /// other, like svgnest.js
+    #[default]
+    OnePoint,
+    /// Order crossover: a random slice of one parent, the remaining genes in
+    /// the order of the other starting after the slice
+    Ox,
+    /// Partially mapped crossover: a random slice of one parent, the other
+    /// genes at their position in the other parent where possible
+    Pmx,
+    /// Edge recombination: keeps which parts follow each other in either
+    /// parent
+    Edge,
+}
+
+impl Crossover {
+    /// Two children of `male` and `female`, which must be permutations of
+    /// the same parts.
+    pub fn apply<R: Rng>(self, male: &[Gene], female: &[Gene], rng: &mut R) -> (Vec<Gene>, Vec<Gene>) {
+        let (c1, c2) = match self {
+            Crossover::OnePoint => {
+                let cut = (male.len() as f64 * rng.gen_range(0.1..0.9)).round() as usize;
+                (one_point(male, female, cut), one_point(female, male, cut))
+            }
+            Crossover::Ox => {
+                let (start, end) = slice(male.len(), rng);
+                (ox(male, female, start, end), ox(female, male, start, end))
+            }
+            Crossover::Pmx => {
+                let (start, end) = slice(male.len(), rng);
+                (pmx(male, female, start, end), pmx(female, male, start, end))
+            }
+            Crossover::Edge => (edge(male, female, rng), edge(female, male, rng)),
+        };
+        (repair(c1, male), repair(c2, female))
+    }
+}
+
+/// Random non-empty slice `start..end` of a sequence of `len` genes.
+fn slice<R: Rng>(len: usize, rng: &mut R) -> (usize, usize) {
+    if len < 2 {
+        return (0, len);
+    }
+    let a = rng.gen_range(0..len);
+    let b = rng.gen_range(0..len);
+    (a.min(b), a.max(b) + 1)
+}
+
+fn contains(genes: &[Gene], idx: usize) -> bool {
+    genes.iter().any(|g| g.0 == idx)
+}
+
+fn one_point(head: &[Gene], tail: &[Gene], cut: usize) -> Vec<Gene> {
+    let mut child = head[..cut.min(head.len())].to_vec();
+    for &g in tail {
+        if !contains(&child, g.0) {
+            child.push(g);
+        }
+    }
+    child
+}
+
+fn ox(a: &[Gene], b: &[Gene], start: usize, end: usize) -> Vec<Gene> {
+    let len = a.len();
+    let kept = &a[start..end];
+    let mut rest = (0..len).map(|k| b[(end + k) % len]).filter(|g| !contains(kept, g.0));
+    let mut child = vec![(0, 0.0, 0); len];
+    child[start..end].copy_from_slice(kept);
+    for k in 0..len - (end - start) {
+        if let Some(g) = rest.next() {
+            child[(end + k) % len] = g;
+        }
+    }
+    child
+}
+
+fn pmx(a: &[Gene], b: &[Gene], start: usize, end: usize) -> Vec<Gene> {
+    let kept = &a[start..end];
+    let mut child = b.to_vec();
+    child[start..end].copy_from_slice(kept);
+    for (k, slot) in child.iter_mut().enumerate() {
+        if (start..end).contains(&k) {
+            continue;
+        }
+        // follow the mapping of the slice until the gene is not in it; a
+        // chain never runs longer than the slice
+        let mut g = b[k];
+        for _ in 0..kept.len() {
+            match kept.iter().position(|c| c.0 == g.0) {
+                Some(p) => g = b[start + p],
+                None => break,
+            }
+        }
+        *slot = g;
+    }
+    child
+}
+
+fn edge<R: Rng>(a: &[Gene], b: &[Gene], rng: &mut R) -> Vec<Gene> {
+    let len = a.len();
+    if len == 0 {
+        return Vec::new();
+    }
+    // neighbours of every part in both parents, read as cycles
+    let mut neighbours: Vec<(usize, Vec<usize>)> = a.iter().map(|g| (g.0, Vec::new())).collect();
+    for parent in [a, b] {
+        for (k, g) in parent.iter().enumerate() {
+            let prev = parent[(k + len - 1) % len].0;
+            let next = parent[(k + 1) % len].0;
+            if let Some((_, list)) = neighbours.iter_mut().find(|(idx, _)| *idx == g.0) {
+                for n in [prev, next] {
+                    if n != g.0 && !list.contains(&n) {
+                        list.push(n);
+                    }
+                }
+            }
+        }
+    }
+    let mut child = Vec::with_capacity(len);
+    let mut current = a[0].0;
+    loop {
+        if let Some(g) = a.iter().find(|g| g.0 == current) {
+            child.push(*g);
+        }
+        for (_, list) in neighbours.iter_mut() {
+            list.retain(|&n| n != current);
+        }
+        let candidates = neighbours
+            .iter()
+            .position(|(idx, _)| *idx == current)
+            .map(|i| neighbours.remove(i).1)
+            .unwrap_or_default();
+        if neighbours.is_empty() {
+            break;
+        }
+        // continue with the neighbour that has the fewest neighbours left,
+        // or any remaining part once the current one has none
+        let fewest = candidates
+            .iter()
+            .filter_map(|&n| neighbours.iter().find(|(idx, _)| *idx == n))
+            .map(|(_, list)| list.len())
+            .min();
+        current = match fewest {
+            Some(min) => {
+                let best: Vec<usize> = candidates
+                    .into_iter()
+                    .filter(|&n| neighbours.iter().any(|(idx, list)| *idx == n && list.len() == min))
+                    .collect();
+                *best.choose(rng).unwrap_or(&neighbours[0].0)
+            }
+            None => neighbours[rng.gen_range(0..neighbours.len())].0,
+        };
+    }
+    child
+}
+
+/// Turn `child` into a permutation of the parts of `parent`: the first
+/// occurrence of every part is kept, parts `parent` does not have are
+/// dropped and missing parts are appended in the order of `parent`.
+pub fn repair(child: Vec<Gene>, parent: &[Gene]) -> Vec<Gene> {
+    let size = parent.iter().map(|g| g.0 + 1).max().unwrap_or(0);
+    let mut wanted = vec![false; size];
+    for g in parent {
+        wanted[g.0] = true;
+    }
+    let mut repaired = Vec::with_capacity(parent.len());
+    for g in child {
+        if g.0 < size && wanted[g.0] {
+            wanted[g.0] = false;
+            repaired.push(g);
+        }
+    }
+    for &g in parent {
+        if wanted[g.0] {
+            wanted[g.0] = false;
+            repaired.push(g);
+        }
+    }
+    repaired
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    fn genes(order: &[usize]) -> Vec<Gene> {
+        order.iter().map(|&i| (i, i as f64 * 10.0, i % 2)).collect()
+    }
+
+    fn order(genes: &[Gene]) -> Vec<usize> {
+        genes.iter().map(|g| g.0).collect()
+    }
+
+    #[test]
+    fn operators_produce_permutations() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let male = genes(&[0, 1, 2, 3, 4, 5, 6, 7]);
+        let female = genes(&[7, 5, 3, 1, 6, 4, 2, 0]);
+        for op in [Crossover::OnePoint, Crossover::Ox, Crossover::Pmx, Crossover::Edge] {
+            for _ in 0..50 {
+                let (c1, c2) = op.apply(&male, &female, &mut rng);
+                for child in [c1, c2] {
+                    let mut sorted = order(&child);
+                    sorted.sort();
+                    assert_eq!(sorted, (0..8).collect::<Vec<_>>(), "{:?}", op);
+                    // rotations and sheet types stay with their parts
+                    assert!(child.iter().all(|g| g.1 == g.0 as f64 * 10.0 && g.2 == g.0 % 2));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ox_and_pmx_keep_the_slice() {
+        let a = genes(&[0, 1, 2, 3, 4, 5, 6, 7]);
+        let b = genes(&[3, 7, 5, 1, 6, 0, 2, 4]);
+        assert_eq!(order(&ox(&a, &b, 3, 6)), [1, 6, 0, 3, 4, 5, 2, 7]);
+        assert_eq!(order(&pmx(&a, &b, 3, 6)), [1, 7, 0, 3, 4, 5, 2, 6]);
+    }
+
+    #[test]
+    fn repair_restores_missing_parts() {
+        let parent = genes(&[2, 0, 1, 3]);
+        let child = repair(genes(&[1, 1, 9, 3]), &parent);
+        assert_eq!(order(&child), [1, 3, 2, 0]);
+    }
+}
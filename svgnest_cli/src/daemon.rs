@@ -0,0 +1,174 @@
+//! Drop-folder watching for unattended nesting.
+//!
+//! Job directories (laid out as for [`crate::batch`]) that appear in the
+//! watched folder are nested once their contents stop changing, so jobs
+//! still being copied over a file share are not picked up half written.
+//! Progress is recorded in a `status.json` next to the outputs of each job,
+//! which also marks the job as handled across restarts.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::output::write_atomic;
+
+/// Name of the status file in every job's output directory.
+pub const STATUS_FILE: &str = "status.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Running,
+    Done,
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Status {
+    pub job: String,
+    pub state: JobState,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Seconds since the Unix epoch
+    pub started: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished: Option<u64>,
+}
+
+impl Status {
+    pub fn running(job: &str) -> Self {
+        Self {
+            job: job.to_string(),
+            state: JobState::Running,
+            error: None,
+            started: now(),
+            finished: None,
+        }
+    }
+
+    /// Mark the job finished, failed if `error` is given.
+    pub fn finish(&mut self, error: Option<String>) {
+        self.state = if error.is_some() {
+            JobState::Failed
+        } else {
+            JobState::Done
+        };
+        self.error = error;
+        self.finished = Some(now());
+    }
+
+    pub fn write(&self, out_dir: &Path) -> Result<()> {
+        fs::create_dir_all(out_dir).with_context(|| format!("failed to create {}", out_dir.display()))?;
+        let json = serde_json::to_string_pretty(self)?;
+        let path = out_dir.join(STATUS_FILE);
+        write_atomic(&path, json.as_bytes()).with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    pub fn read(out_dir: &Path) -> Option<Self> {
+        let data = fs::read_to_string(out_dir.join(STATUS_FILE)).ok()?;
+        serde_json::from_str(&data).ok()
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Names, sizes and modification times of the files in a job directory.
+type Fingerprint = Vec<(PathBuf, u64, Option<SystemTime>)>;
+
+fn fingerprint(dir: &Path) -> Result<Fingerprint> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let meta = entry.metadata()?;
+        files.push((entry.path(), meta.len(), meta.modified().ok()));
+    }
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(files)
+}
+
+/// Watched folder and the output folder its jobs are written to.
+pub struct DropFolder {
+    dir: PathBuf,
+    output: PathBuf,
+    /// Fingerprints from the previous poll of the jobs not yet handled
+    seen: HashMap<PathBuf, Fingerprint>,
+}
+
+impl DropFolder {
+    pub fn new(dir: &Path, output: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            output: output.to_path_buf(),
+            seen: HashMap::new(),
+        }
+    }
+
+    /// Output directory of the job in `job_dir`.
+    pub fn output_dir(&self, job_dir: &Path) -> PathBuf {
+        self.output.join(job_dir.file_name().unwrap_or_default())
+    }
+
+    /// Job directories without a finished status, in name order.
+    pub fn pending(&self) -> Result<Vec<PathBuf>> {
+        let mut dirs: Vec<PathBuf> = fs::read_dir(&self.dir)
+            .with_context(|| format!("failed to read {}", self.dir.display()))?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.is_dir())
+            .filter(|p| {
+                Status::read(&self.output_dir(p)).is_none_or(|s| s.state == JobState::Running)
+            })
+            .collect();
+        dirs.sort();
+        Ok(dirs)
+    }
+
+    /// Pending jobs whose contents did not change since the previous poll.
+    pub fn poll(&mut self) -> Result<Vec<PathBuf>> {
+        let mut ready = Vec::new();
+        let mut seen = HashMap::new();
+        for dir in self.pending()? {
+            let Ok(print) = fingerprint(&dir) else {
+                continue;
+            };
+            if self.seen.get(&dir) == Some(&print) {
+                ready.push(dir);
+            } else {
+                seen.insert(dir, print);
+            }
+        }
+        self.seen = seen;
+        Ok(ready)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jobs_are_ready_once_settled() {
+        let root = std::env::temp_dir().join(format!("svgnest_daemon_{}", std::process::id()));
+        let (drop, out) = (root.join("drop"), root.join("out"));
+        fs::create_dir_all(drop.join("job")).unwrap();
+        fs::write(drop.join("job/bin.svg"), "").unwrap();
+
+        let mut folder = DropFolder::new(&drop, &out);
+        assert!(folder.poll().unwrap().is_empty());
+        assert_eq!(folder.poll().unwrap(), vec![drop.join("job")]);
+
+        let mut status = Status::running("job");
+        status.finish(None);
+        status.write(&folder.output_dir(&drop.join("job"))).unwrap();
+        assert!(folder.pending().unwrap().is_empty());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,86 @@
+//! Old spellings of renamed command line flags. They keep working for at
+//! least one release after the rename, with a warning naming the new flag,
+//! so scripts calling the CLI do not break when flags are reorganized. The
+//! arguments are rewritten before clap sees them, so the old spellings stay
+//! out of `--help`.
+
+use std::ffi::OsString;
+
+use crate::warning::{Code, Warning};
+
+/// A flag renamed from `old` to `new`, both with their dashes, in release
+/// `since`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Renamed {
+    pub old: &'static str,
+    pub new: &'static str,
+    pub since: &'static str,
+}
+
+/// Flags renamed so far. An entry is dropped at the earliest one release
+/// after the one it was renamed in.
+pub const RENAMED: &[Renamed] = &[];
+
+/// `args` with the old spellings in `renamed` replaced by the new ones,
+/// given as `--old value` or `--old=value`, and a warning for every flag
+/// replaced. Arguments after `--` are left as they are.
+pub fn rewrite<I>(args: I, renamed: &[Renamed]) -> (Vec<OsString>, Vec<Warning>)
+where
+    I: IntoIterator<Item = OsString>,
+{
+    let mut warnings: Vec<Warning> = Vec::new();
+    let mut rest = false;
+    let args = args
+        .into_iter()
+        .map(|arg| {
+            let Some(s) = arg.to_str().filter(|_| !rest) else {
+                return arg;
+            };
+            if s == "--" {
+                rest = true;
+                return arg;
+            }
+            let (flag, value) = match s.split_once('=') {
+                Some((flag, value)) => (flag, Some(value)),
+                None => (s, None),
+            };
+            let Some(r) = renamed.iter().find(|r| r.old == flag) else {
+                return arg;
+            };
+            let message = format!("renamed to {} in {} and will be removed, use {}", r.new, r.since, r.new);
+            let warning = Warning::new(Code::DeprecatedFlag, r.old, message);
+            if !warnings.contains(&warning) {
+                warnings.push(warning);
+            }
+            match value {
+                Some(value) => format!("{}={}", r.new, value).into(),
+                None => r.new.into(),
+            }
+        })
+        .collect();
+    (args, warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn old_flags_are_rewritten_with_a_warning() {
+        let renamed = [Renamed {
+            old: "--gap",
+            new: "--spacing",
+            since: "0.2.0",
+        }];
+        let args = ["svgnest_cli", "--gap", "2", "--inputs", "a.svg", "--gap=3", "--", "--gap"];
+        let (args, warnings) = rewrite(args.map(OsString::from), &renamed);
+        let expected = ["svgnest_cli", "--spacing", "2", "--inputs", "a.svg", "--spacing=3", "--", "--gap"];
+        assert_eq!(args, expected.map(OsString::from));
+        let warnings: Vec<String> = warnings.iter().map(|w| w.to_string()).collect();
+        assert_eq!(
+            warnings,
+            ["--gap: renamed to --spacing in 0.2.0 and will be removed, use --spacing [DEPRECATED_FLAG]"]
+        );
+        assert!(rewrite(["svgnest_cli", "--spacing", "2"].map(OsString::from), &renamed).1.is_empty());
+    }
+}
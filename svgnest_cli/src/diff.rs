@@ -0,0 +1,232 @@
+use serde::Serialize;
+
+use crate::geometry::Bounds;
+use crate::part::InstanceId;
+use crate::report::{PlacedPart, Report};
+
+const MOVE_TOLERANCE: f64 = 1e-6;
+
+/// How a single part changed between two layouts.
+#[derive(Debug, Clone, Serialize)]
+pub struct PartMovement {
+    pub part: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<InstanceId>,
+    pub dx: f64,
+    pub dy: f64,
+    pub dangle: f64,
+    pub sheet_changed: bool,
+}
+
+/// Differences between an old and a new layout.
+#[derive(Debug, Clone, Serialize)]
+pub struct LayoutDiff {
+    pub old_utilization: f64,
+    pub new_utilization: f64,
+    pub old_sheets: usize,
+    pub new_sheets: usize,
+    /// Parts placed in both layouts but at a different position or angle
+    pub moved: Vec<PartMovement>,
+    /// Parts only placed in the new layout
+    pub newly_placed: Vec<usize>,
+    /// Parts only placed in the old layout
+    pub newly_unplaced: Vec<usize>,
+}
+
+/// The placement of `part` in `report`. Parts are matched by id, so
+/// reordering the input does not move them, and by index in reports
+/// without ids.
+fn find<'a>(report: &'a Report, part: &PlacedPart) -> Option<&'a PlacedPart> {
+    report.placements.iter().find(|p| match (&p.id, &part.id) {
+        (Some(a), Some(b)) => a == b,
+        _ => p.part == part.part,
+    })
+}
+
+/// Compare two reports part by part.
+pub fn diff_reports(old: &Report, new: &Report) -> LayoutDiff {
+    let mut moved = Vec::new();
+    let mut newly_unplaced = Vec::new();
+    for o in &old.placements {
+        match find(new, o) {
+            Some(n) => {
+                let movement = PartMovement {
+                    part: o.part,
+                    id: o.id.clone(),
+                    dx: n.x - o.x,
+                    dy: n.y - o.y,
+                    dangle: n.angle - o.angle,
+                    sheet_changed: n.sheet != o.sheet,
+                };
+                if movement.dx.abs() > MOVE_TOLERANCE
+                    || movement.dy.abs() > MOVE_TOLERANCE
+                    || movement.dangle.abs() > MOVE_TOLERANCE
+                    || movement.sheet_changed
+                {
+                    moved.push(movement);
+                }
+            }
+            None => newly_unplaced.push(o.part),
+        }
+    }
+    let newly_placed = new
+        .placements
+        .iter()
+        .filter(|n| find(old, n).is_none())
+        .map(|n| n.part)
+        .collect();
+    LayoutDiff {
+        old_utilization: old.utilization,
+        new_utilization: new.utilization,
+        old_sheets: old.sheets,
+        new_sheets: new.sheets,
+        moved,
+        newly_placed,
+        newly_unplaced,
+    }
+}
+
+impl LayoutDiff {
+    /// Human readable summary of the differences.
+    pub fn summary(&self) -> String {
+        let mut out = format!(
+            "Utilization: {:.2}% -> {:.2}%\nSheets: {} -> {}\nMoved parts: {}\n",
+            self.old_utilization * 100.0,
+            self.new_utilization * 100.0,
+            self.old_sheets,
+            self.new_sheets,
+            self.moved.len()
+        );
+        for m in &self.moved {
+            let name = m.id.as_ref().map_or_else(|| m.part.to_string(), InstanceId::to_string);
+            out.push_str(&format!(
+                "  part {}: dx={} dy={} dangle={}{}\n",
+                name,
+                m.dx,
+                m.dy,
+                m.dangle,
+                if m.sheet_changed { " (sheet changed)" } else { "" }
+            ));
+        }
+        if !self.newly_placed.is_empty() {
+            out.push_str(&format!("Newly placed: {:?}\n", self.newly_placed));
+        }
+        if !self.newly_unplaced.is_empty() {
+            out.push_str(&format!("Newly unplaced: {:?}\n", self.newly_unplaced));
+        }
+        out
+    }
+}
+
+fn render_report(report: &Report, dx: f64, highlight: &[usize]) -> String {
+    let mut body = String::new();
+    for p in &report.placements {
+        let stroke = if highlight.contains(&p.part) { "red" } else { "black" };
+        for contour in &p.contours {
+            let points: Vec<String> = contour
+                .iter()
+                .map(|pt| format!("{},{}", pt.x + dx, pt.y))
+                .collect();
+            body.push_str(&format!(
+                "<polygon points=\"{}\" fill=\"none\" stroke=\"{}\"/>\n",
+                points.join(" "),
+                stroke
+            ));
+        }
+    }
+    for sheet in report.sheet_bounds() {
+        body.push_str(&format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"none\" stroke=\"blue\"/>\n",
+            dx, sheet.y, sheet.width, sheet.height
+        ));
+    }
+    body
+}
+
+/// Render both layouts next to each other, old on the left. Parts that moved
+/// or changed placement state are outlined in red.
+pub fn side_by_side_svg(old: &Report, new: &Report, diff: &LayoutDiff) -> String {
+    let mut highlight: Vec<usize> = diff.moved.iter().map(|m| m.part).collect();
+    highlight.extend(&diff.newly_placed);
+    highlight.extend(&diff.newly_unplaced);
+    let (old_sheets, new_sheets) = (old.sheet_bounds(), new.sheet_bounds());
+    let extent = |sheets: &[Bounds]| {
+        let width = sheets.iter().map(|s| s.width).fold(0.0, f64::max);
+        (width, sheets.iter().map(|s| s.height).sum::<f64>())
+    };
+    let ((old_width, old_height), (new_width, new_height)) = (extent(&old_sheets), extent(&new_sheets));
+    let gap = old_width.max(new_width) * 0.1;
+    let offset = old_width + gap;
+    let width = offset + new_width;
+    let height = old_height.max(new_height);
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">{}{}</svg>",
+        width,
+        height,
+        render_report(old, 0.0, &highlight),
+        render_report(new, offset, &highlight)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(x: f64, utilization: f64) -> Report {
+        Report {
+            bin_width: 10.0,
+            bin_height: 10.0,
+            turned_sheets: Vec::new(),
+            sheets: 1,
+            utilization,
+            placements: vec![PlacedPart {
+                part: 0,
+                id: None,
+                sheet: 0,
+                x,
+                y: 0.0,
+                angle: 0.0,
+                alignment: 0.0,
+                contours: Vec::new(),
+                frames: None,
+                metadata: Default::default(),
+            }],
+            unplaced: Vec::new(),
+            unplaced_ids: Vec::new(),
+            sheet_totals: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn detects_moved_part() {
+        let diff = diff_reports(&report(0.0, 0.25), &report(5.0, 0.25));
+        assert_eq!(diff.moved.len(), 1);
+        assert!((diff.moved[0].dx - 5.0).abs() < 1e-9);
+        assert!(diff_reports(&report(0.0, 0.25), &report(0.0, 0.25)).moved.is_empty());
+    }
+
+    #[test]
+    fn matches_parts_by_id() {
+        let id = |element: &str| {
+            Some(InstanceId {
+                part: crate::part::PartId {
+                    element: Some(element.to_string()),
+                    ..Default::default()
+                },
+                copy: 0,
+            })
+        };
+        let mut old = report(0.0, 0.25);
+        old.placements[0].id = id("a");
+        // the same part, now second in the input
+        let mut new = report(0.0, 0.25);
+        new.placements[0].part = 1;
+        new.placements[0].id = id("a");
+        let diff = diff_reports(&old, &new);
+        assert!(diff.moved.is_empty());
+        assert!(diff.newly_placed.is_empty());
+        new.placements[0].id = id("b");
+        assert_eq!(diff_reports(&old, &new).newly_unplaced, [0]);
+    }
+}
@@ -0,0 +1,297 @@
+//! Evaluation of individuals on worker processes across machines.
+//!
+//! The coordinator listens on a TCP address and sends every worker that
+//! connects the job: parts, bin and GA configuration. Each generation the
+//! population is split between the connected workers, which answer with the
+//! fitness of every individual they received. Messages are JSON objects, one
+//! per line. Individuals a worker fails to evaluate, e.g. because it
+//! disconnected or stopped answering, are evaluated by the coordinator
+//! itself.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::config::NestingConfig;
+use crate::ga::{GeneticAlgorithm, Individual};
+use crate::part::Part;
+use crate::svg_parser::Polygon;
+
+/// Time a worker is given to take or answer a message before it is dropped.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Message {
+    Job {
+        parts: Vec<Part>,
+        bin: Polygon,
+        /// boxed, as it is far larger than the other messages
+        config: Box<NestingConfig>,
+    },
+    Evaluate {
+        id: usize,
+        placement: Vec<usize>,
+        rotation: Vec<f64>,
+        #[serde(default)]
+        sheet_type: Vec<usize>,
+    },
+    /// Fitness of individual `id`; JSON has no infinity, so layouts that
+    /// do not fit are sent as `None`
+    Fitness { id: usize, fitness: Option<f64> },
+}
+
+fn send(stream: &mut TcpStream, msg: &Message) -> Result<()> {
+    let mut line = serde_json::to_string(msg)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+fn receive(reader: &mut BufReader<TcpStream>) -> Result<Option<Message>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_str(&line)?))
+}
+
+struct Connection {
+    peer: SocketAddr,
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Connection {
+    /// Evaluate the individuals at `ids` and return their fitness values in
+    /// the same order. Fails if the worker takes longer than `timeout`, if
+    /// not zero, to take an individual or to answer one.
+    fn evaluate(&mut self, batch: &[Individual], ids: &[usize], timeout: Duration) -> Result<Vec<f64>> {
+        // the reader is a clone of the same socket, so this covers both
+        let timeout = Some(timeout).filter(|t| !t.is_zero());
+        self.writer.set_read_timeout(timeout)?;
+        self.writer.set_write_timeout(timeout)?;
+        for &id in ids {
+            let msg = Message::Evaluate {
+                id,
+                placement: batch[id].placement.clone(),
+                rotation: batch[id].rotation.clone(),
+                sheet_type: batch[id].sheet_type.clone(),
+            };
+            send(&mut self.writer, &msg)?;
+        }
+        self.writer.flush()?;
+        let mut results = Vec::with_capacity(ids.len());
+        for &id in ids {
+            match receive(&mut self.reader).with_context(|| format!("no fitness for individual {}", id))? {
+                Some(Message::Fitness { id: got, fitness }) if got == id => {
+                    results.push(fitness.unwrap_or(f64::INFINITY));
+                }
+                Some(other) => bail!("unexpected message {:?}", other),
+                None => bail!("connection closed"),
+            }
+        }
+        Ok(results)
+    }
+}
+
+/// Accepts workers in the background and spreads batches of individuals
+/// across them.
+pub struct Coordinator {
+    addr: SocketAddr,
+    workers: Arc<Mutex<Vec<Connection>>>,
+    timeout: Duration,
+}
+
+impl Coordinator {
+    /// Listen on `addr` and send the job to every worker that connects.
+    pub fn bind(addr: &str, parts: &[Part], bin: &Polygon, config: &NestingConfig) -> Result<Self> {
+        let listener = TcpListener::bind(addr).with_context(|| format!("failed to listen on {}", addr))?;
+        let addr = listener.local_addr()?;
+        let job = Message::Job {
+            parts: parts.to_vec(),
+            bin: bin.clone(),
+            config: Box::new(config.clone()),
+        };
+        let mut job = serde_json::to_string(&job)?;
+        job.push('\n');
+
+        let workers = Arc::new(Mutex::new(Vec::new()));
+        let accepted = Arc::clone(&workers);
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut writer) = stream else {
+                    continue;
+                };
+                let Ok(peer) = writer.peer_addr() else {
+                    continue;
+                };
+                let reader = match writer.try_clone() {
+                    Ok(r) => BufReader::new(r),
+                    Err(_) => continue,
+                };
+                if writer.write_all(job.as_bytes()).is_err() {
+                    continue;
+                }
+                eprintln!("Worker {} connected", peer);
+                accepted.lock().unwrap().push(Connection {
+                    peer,
+                    reader,
+                    writer,
+                });
+            }
+        });
+        Ok(Self {
+            addr,
+            workers,
+            timeout: DEFAULT_TIMEOUT,
+        })
+    }
+
+    /// Drop workers that take longer than `timeout` to take or answer a
+    /// message, instead of [`DEFAULT_TIMEOUT`]. Zero waits for them forever.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Address the coordinator listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn worker_count(&self) -> usize {
+        self.workers.lock().unwrap().len()
+    }
+
+    /// Evaluate `batch` on the connected workers. Individuals are dealt out
+    /// round-robin; workers that fail or stop answering are dropped and their
+    /// share is left as `None`.
+    pub fn evaluate(&self, batch: &[Individual]) -> Vec<Option<f64>> {
+        let mut fitness = vec![None; batch.len()];
+        // take the workers so new ones can be accepted while evaluating
+        let mut workers = std::mem::take(&mut *self.workers.lock().unwrap());
+        if workers.is_empty() {
+            return fitness;
+        }
+        let n = workers.len();
+        let shares: Vec<Vec<usize>> = (0..n).map(|k| (k..batch.len()).step_by(n).collect()).collect();
+        let timeout = self.timeout;
+        let results: Vec<Result<Vec<f64>>> = std::thread::scope(|s| {
+            let handles: Vec<_> = workers
+                .iter_mut()
+                .zip(&shares)
+                .map(|(w, ids)| s.spawn(move || w.evaluate(batch, ids, timeout)))
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        let mut alive = Vec::with_capacity(n);
+        for ((worker, ids), res) in workers.into_iter().zip(&shares).zip(results) {
+            match res {
+                Ok(values) => {
+                    for (&id, f) in ids.iter().zip(values) {
+                        fitness[id] = Some(f);
+                    }
+                    alive.push(worker);
+                }
+                Err(e) => eprintln!("Worker {} dropped: {}", worker.peer, e),
+            }
+        }
+        self.workers.lock().unwrap().extend(alive);
+        fitness
+    }
+}
+
+/// Connect to the coordinator at `addr` and evaluate individuals until it
+/// closes the connection. Returns the number of individuals evaluated.
+pub fn run_worker(addr: &str) -> Result<usize> {
+    let mut writer = TcpStream::connect(addr).with_context(|| format!("failed to connect to {}", addr))?;
+    let mut reader = BufReader::new(writer.try_clone()?);
+    let (parts, bin, config) = match receive(&mut reader)? {
+        Some(Message::Job { parts, bin, config }) => (parts, bin, *config),
+        Some(other) => bail!("expected a job, got {:?}", other),
+        None => bail!("coordinator closed the connection"),
+    };
+    let mut ga = GeneticAlgorithm::new(&parts, &bin, config)?;
+    let mut evaluated = 0;
+    while let Some(msg) = receive(&mut reader)? {
+        let (id, placement, rotation, sheet_type) = match msg {
+            Message::Evaluate {
+                id,
+                placement,
+                rotation,
+                sheet_type,
+            } => (id, placement, rotation, sheet_type),
+            other => bail!("expected an individual, got {:?}", other),
+        };
+        let fitness = ga.fitness(&Individual {
+            placement,
+            rotation,
+            sheet_type,
+            fitness: f64::MAX,
+        });
+        send(
+            &mut writer,
+            &Message::Fitness {
+                id,
+                fitness: fitness.is_finite().then_some(fitness),
+            },
+        )?;
+        evaluated += 1;
+    }
+    Ok(evaluated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::rect;
+
+    #[test]
+    fn worker_matches_local_fitness() {
+        let parts = vec![Part::new(vec![rect(4.0, 2.0)]), Part::new(vec![rect(3.0, 3.0)])];
+        let bin = rect(10.0, 10.0);
+        let config = NestingConfig::builder().population_size(4).build().unwrap();
+        let coordinator = Coordinator::bind("127.0.0.1:0", &parts, &bin, &config).unwrap();
+        let addr = coordinator.local_addr().to_string();
+        std::thread::spawn(move || run_worker(&addr));
+        while coordinator.worker_count() == 0 {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        let mut ga = GeneticAlgorithm::new(&parts, &bin, config).unwrap();
+        let batch = ga.population.clone();
+        let remote = coordinator.evaluate(&batch);
+        for (ind, fitness) in batch.iter().zip(remote) {
+            assert_eq!(fitness, Some(ga.fitness(ind)));
+        }
+    }
+
+    #[test]
+    fn workers_that_stop_answering_are_dropped() {
+        let parts = vec![Part::new(vec![rect(4.0, 2.0)])];
+        let bin = rect(10.0, 10.0);
+        let config = NestingConfig::builder().population_size(2).build().unwrap();
+        let coordinator = Coordinator::bind("127.0.0.1:0", &parts, &bin, &config)
+            .unwrap()
+            .with_timeout(Duration::from_millis(100));
+        // a worker taking the job and then never answering, without closing
+        // the connection
+        let stream = TcpStream::connect(coordinator.local_addr()).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        assert!(matches!(receive(&mut reader).unwrap(), Some(Message::Job { .. })));
+        while coordinator.worker_count() == 0 {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        let ga = GeneticAlgorithm::new(&parts, &bin, config).unwrap();
+        assert_eq!(coordinator.evaluate(&ga.population), vec![None, None]);
+        assert_eq!(coordinator.worker_count(), 0);
+        drop(stream);
+    }
+}
@@ -2,16 +2,14 @@
 use dxf::{Drawing, entities::EntityType};
 use std::path::Path;
 
+#[cfg(feature = "dxf")]
 use crate::{
-    part::Part,
+    contour::{self, Contour, Segment},
+    line_merge::connect_open_polys,
+    part::{InstanceId, PartId},
     svg_parser::{Point, Polygon},
 };
-
-const CONNECT_TOLERANCE: f64 = 1e-6;
-
-fn points_equal(a: &Point, b: &Point) -> bool {
-    (a.x - b.x).abs() < CONNECT_TOLERANCE && (a.y - b.y).abs() < CONNECT_TOLERANCE
-}
+use crate::{geometry::Tolerances, part::Part, warning::{Code, Warning}};
 
 #[cfg(feature = "dxf")]
 fn approximate_arc(cx: f64, cy: f64, r: f64, start: f64, end: f64, segments: usize) -> Vec<Point> {
@@ -113,66 +111,85 @@ fn approximate_bulge(p1: &Point, p2: &Point, bulge: f64, segments: usize) -> Vec
     pts
 }
 
-fn connect_open_polys(mut open: Vec<Vec<Point>>, mut closed: Vec<Polygon>) -> Vec<Polygon> {
-    while let Some(mut current) = open.pop() {
-        let mut changed = true;
-        while changed {
-            changed = false;
-            let mut i = 0;
-            while i < open.len() {
-                let other = &open[i];
-                let first_cur = current.first().unwrap();
-                let last_cur = current.last().unwrap();
-                let first_other = other.first().unwrap();
-                let last_other = other.last().unwrap();
-
-                if points_equal(last_cur, first_other) {
-                    current.extend(other.iter().skip(1).cloned());
-                    open.remove(i);
-                    changed = true;
-                } else if points_equal(last_cur, last_other) {
-                    current.extend(other.iter().rev().skip(1).cloned());
-                    open.remove(i);
-                    changed = true;
-                } else if points_equal(first_cur, last_other) {
-                    let mut add: Vec<Point> = other.iter().rev().skip(1).cloned().collect();
-                    add.extend(current);
-                    current = add;
-                    open.remove(i);
-                    changed = true;
-                } else if points_equal(first_cur, first_other) {
-                    let mut add: Vec<Point> = other.iter().skip(1).rev().cloned().collect();
-                    add.extend(current);
-                    current = add;
-                    open.remove(i);
-                    changed = true;
-                } else {
-                    i += 1;
-                }
-                if changed {
-                    break;
-                }
-            }
-        }
+#[cfg(feature = "dxf")]
+fn bulge_segment(p1: Point, p2: Point, bulge: f64) -> Segment {
+    let dx = p2.x - p1.x;
+    let dy = p2.y - p1.y;
+    let chord = (dx * dx + dy * dy).sqrt();
+    if bulge.abs() <= f64::EPSILON || chord == 0.0 {
+        return Segment::Line { to: p2 };
+    }
+    let theta = 4.0 * bulge.atan();
+    // signed distance of the center to the left of the chord
+    let h = chord / 2.0 / (theta / 2.0).tan();
+    Segment::Arc {
+        center: Point {
+            x: (p1.x + p2.x) / 2.0 - dy / chord * h,
+            y: (p1.y + p2.y) / 2.0 + dx / chord * h,
+        },
+        radius: chord / (2.0 * (theta / 2.0).sin().abs()),
+        sweep: theta,
+        to: p2,
+    }
+}
 
-        let is_closed = points_equal(current.first().unwrap(), current.last().unwrap());
-        if is_closed && current.len() > 1 {
-            current.pop();
-        }
-        closed.push(Polygon {
-            id: 0,
-            points: current,
-            closed: is_closed,
-        });
+/// Exact contour of a polyline whose vertices carry DXF bulge values.
+#[cfg(feature = "dxf")]
+fn bulge_contour(vertices: &[(Point, f64)], closed: bool) -> Option<Contour> {
+    let &(start, _) = vertices.first()?;
+    let mut contour = Contour::new(start);
+    let edges = if closed { vertices.len() } else { vertices.len() - 1 };
+    for i in 0..edges {
+        let (p1, bulge) = vertices[i];
+        let (p2, _) = vertices[(i + 1) % vertices.len()];
+        contour.segments.push(bulge_segment(p1, p2, bulge));
     }
-    closed
+    contour.closed = closed;
+    Some(contour)
 }
 
+/// Identifies an entity in warnings, e.g. `SPLINE (handle 2F, layer CUT)`.
 #[cfg(feature = "dxf")]
-pub fn part_from_dxf(path: &Path) -> anyhow::Result<Part> {
+fn entity_name(e: &dxf::entities::Entity) -> String {
+    let debug = format!("{:?}", e.specific);
+    let kind = debug.split('(').next().unwrap_or_default().to_uppercase();
+    format!("{} (handle {:X}, layer {})", kind, e.common.handle.0, e.common.layer)
+}
+
+/// Read the part drawn in a DXF file. Entities that are not understood are
+/// skipped and appended to `warnings`, unless `strict` makes them an error.
+#[cfg(feature = "dxf")]
+pub fn part_from_dxf(
+    path: &Path,
+    tol: &Tolerances,
+    strict: bool,
+    warnings: &mut Vec<Warning>,
+) -> anyhow::Result<Part> {
     let drawing = Drawing::load_file(path)?;
+    part_from_drawing(&drawing, Some(path), tol, strict, warnings)
+}
+
+/// Part drawn by the entities of an already loaded drawing. `file` is only
+/// used to identify the entities in warnings and the part in its id.
+#[cfg(feature = "dxf")]
+pub fn part_from_drawing(
+    drawing: &Drawing,
+    file: Option<&Path>,
+    tol: &Tolerances,
+    strict: bool,
+    warnings: &mut Vec<Warning>,
+) -> anyhow::Result<Part> {
     let mut open = Vec::new();
     let mut closed = Vec::new();
+    let mut skip = |e: &dxf::entities::Entity, code: Code, message: &str| -> anyhow::Result<()> {
+        let mut warning = Warning::new(code, entity_name(e), message);
+        warning.file = file.map(Path::to_path_buf);
+        if strict {
+            anyhow::bail!("{}", warning);
+        }
+        warnings.push(warning);
+        Ok(())
+    };
     for e in drawing.entities() {
         match &e.specific {
             EntityType::Line(line) => {
@@ -187,6 +204,9 @@ pub fn part_from_dxf(path: &Path) -> anyhow::Result<Part> {
                     },
                 ]);
             }
+            EntityType::LwPolyline(poly) if poly.vertices.len() < 2 => {
+                skip(e, Code::InvalidGeometry, "polyline with fewer than two vertices, skipped")?;
+            }
             EntityType::LwPolyline(poly) => {
                 let mut pts = Vec::new();
                 let vtx = &poly.vertices;
@@ -235,14 +255,23 @@ pub fn part_from_dxf(path: &Path) -> anyhow::Result<Part> {
                         }
                         open.push(pts);
                     } else {
+                        let bulges: Vec<_> = vtx
+                            .iter()
+                            .map(|v| (Point { x: v.x, y: v.y }, v.bulge))
+                            .collect();
                         closed.push(Polygon {
                             id: 0,
                             points: pts,
                             closed: true,
+                            contour: bulge_contour(&bulges, true),
+                            ..Default::default()
                         });
                     }
                 }
             }
+            EntityType::Polyline(poly) if poly.vertices().count() < 2 => {
+                skip(e, Code::InvalidGeometry, "polyline with fewer than two vertices, skipped")?;
+            }
             EntityType::Polyline(poly) => {
                 let verts: Vec<_> = poly.vertices().cloned().collect();
                 if !verts.is_empty() {
@@ -291,14 +320,29 @@ pub fn part_from_dxf(path: &Path) -> anyhow::Result<Part> {
                         }
                         open.push(pts);
                     } else {
+                        let bulges: Vec<_> = verts
+                            .iter()
+                            .map(|v| {
+                                let p = Point {
+                                    x: v.location.x,
+                                    y: v.location.y,
+                                };
+                                (p, v.bulge)
+                            })
+                            .collect();
                         closed.push(Polygon {
                             id: 0,
                             points: pts,
                             closed: true,
+                            contour: bulge_contour(&bulges, true),
+                            ..Default::default()
                         });
                     }
                 }
             }
+            EntityType::Circle(c) if c.radius.is_nan() || c.radius <= 0.0 => {
+                skip(e, Code::InvalidGeometry, "circle without a positive radius, skipped")?;
+            }
             EntityType::Circle(c) => {
                 let segments = 32;
                 let mut pts = Vec::new();
@@ -308,14 +352,21 @@ pub fn part_from_dxf(path: &Path) -> anyhow::Result<Part> {
                     let y = c.center.y + c.radius * theta.sin();
                     pts.push(Point { x, y });
                 }
+                let center = Point {
+                    x: c.center.x,
+                    y: c.center.y,
+                };
                 closed.push(Polygon {
                     id: 0,
                     points: pts,
                     closed: true,
+                    contour: Some(contour::circle(center, c.radius)),
+                    ..Default::default()
                 });
             }
             EntityType::Arc(arc) => {
-                let mut end = arc.end_angle - arc.start_angle;
+                // angles are not limited to one turn in the file
+                let mut end = (arc.end_angle - arc.start_angle).rem_euclid(360.0);
                 if end <= 0.0 {
                     end += 360.0;
                 }
@@ -331,7 +382,7 @@ pub fn part_from_dxf(path: &Path) -> anyhow::Result<Part> {
                 open.push(pts);
             }
             EntityType::Ellipse(el) => {
-                let mut end = el.end_parameter - el.start_parameter;
+                let mut end = (el.end_parameter - el.start_parameter).rem_euclid(std::f64::consts::TAU);
                 if end <= 0.0 {
                     end += std::f64::consts::TAU;
                 }
@@ -345,19 +396,35 @@ pub fn part_from_dxf(path: &Path) -> anyhow::Result<Part> {
                     el.start_parameter + end,
                     segs.max(1),
                 );
-                open.push(pts);
+                if pts.is_empty() {
+                    skip(e, Code::InvalidGeometry, "ellipse without a major axis, skipped")?;
+                } else {
+                    open.push(pts);
+                }
             }
-            _ => {}
+            _ => skip(e, Code::UnsupportedElement, "unsupported entity, skipped")?,
         }
     }
-    let mut all = connect_open_polys(open, closed);
+    let mut all = connect_open_polys(open, closed, tol.connect);
     for (i, p) in all.iter_mut().enumerate() {
         p.id = i;
     }
-    Ok(Part::new(all))
+    let id = InstanceId {
+        part: PartId {
+            file: file.map(Path::to_path_buf),
+            ..Default::default()
+        },
+        copy: 0,
+    };
+    Ok(Part::new(all).with_id(id))
 }
 
 #[cfg(not(feature = "dxf"))]
-pub fn part_from_dxf(_path: &Path) -> anyhow::Result<Part> {
+pub fn part_from_dxf(
+    _path: &Path,
+    _tol: &Tolerances,
+    _strict: bool,
+    _warnings: &mut Vec<Warning>,
+) -> anyhow::Result<Part> {
     Err(anyhow::anyhow!("DXF support not enabled"))
 }
@@ -0,0 +1,112 @@
+#[cfg(feature = "dxf")]
+use dxf::{
+    Drawing, LwPolylineVertex,
+    entities::{Entity, EntityType, LwPolyline},
+};
+use std::path::Path;
+
+use crate::contour::{self, Contour};
+#[cfg(feature = "dxf")]
+use crate::contour::Segment;
+use crate::output::write_atomic;
+use crate::svg_parser::Point;
+
+/// Write closed polylines to a DXF file, one `LWPOLYLINE` per ring.
+pub fn write_polygons(path: &Path, rings: &[Vec<Point>]) -> anyhow::Result<()> {
+    write_atomic(path, &polygons_dxf(rings)?)?;
+    Ok(())
+}
+
+/// DXF document of closed polylines, as [`write_polygons`] writes it.
+pub fn polygons_dxf(rings: &[Vec<Point>]) -> anyhow::Result<Vec<u8>> {
+    let contours: Vec<Contour> = rings
+        .iter()
+        .filter_map(|ring| contour::polyline(ring, true))
+        .collect();
+    contours_dxf(&contours, 0.0)
+}
+
+/// Vertices and bulges of an `LWPOLYLINE` following `contour`. Arcs become
+/// bulges, Béziers are flattened within `tol`.
+#[cfg(feature = "dxf")]
+fn bulge_vertices(contour: &Contour, tol: f64) -> Vec<(Point, f64)> {
+    let mut verts = vec![(contour.start, 0.0)];
+    let mut from = contour.start;
+    for seg in &contour.segments {
+        match *seg {
+            Segment::Line { to } => verts.push((to, 0.0)),
+            Segment::Arc {
+                center,
+                radius,
+                sweep,
+                to,
+            } => {
+                // a bulge cannot describe more than a half circle
+                let pieces = if sweep.abs() > std::f64::consts::PI { 2 } else { 1 };
+                let a0 = (from.y - center.y).atan2(from.x - center.x);
+                for i in 1..=pieces {
+                    if let Some(last) = verts.last_mut() {
+                        last.1 = (sweep / pieces as f64 / 4.0).tan();
+                    }
+                    let end = if i == pieces {
+                        to
+                    } else {
+                        let a = a0 + sweep * i as f64 / pieces as f64;
+                        Point {
+                            x: center.x + radius * a.cos(),
+                            y: center.y + radius * a.sin(),
+                        }
+                    };
+                    verts.push((end, 0.0));
+                }
+            }
+            Segment::Quadratic { .. } | Segment::Cubic { .. } => {
+                let curve = Contour {
+                    start: from,
+                    segments: vec![*seg],
+                    closed: false,
+                };
+                verts.extend(curve.flatten(tol).into_iter().skip(1).map(|p| (p, 0.0)));
+            }
+        }
+        from = seg.end();
+    }
+    if contour.closed && verts.len() > 1 && verts[verts.len() - 1].0 == contour.start {
+        verts.pop();
+    }
+    verts
+}
+
+/// Write contours to a DXF file, one `LWPOLYLINE` per contour with arcs
+/// stored as bulges. `tol` bounds the error of flattened Bézier segments.
+pub fn write_contours(path: &Path, contours: &[Contour], tol: f64) -> anyhow::Result<()> {
+    write_atomic(path, &contours_dxf(contours, tol)?)?;
+    Ok(())
+}
+
+/// DXF document of contours, as [`write_contours`] writes it.
+#[cfg(feature = "dxf")]
+pub fn contours_dxf(contours: &[Contour], tol: f64) -> anyhow::Result<Vec<u8>> {
+    let mut drawing = Drawing::new();
+    for contour in contours {
+        let mut poly = LwPolyline::default();
+        for (p, bulge) in bulge_vertices(contour, tol) {
+            poly.vertices.push(LwPolylineVertex {
+                x: p.x,
+                y: p.y,
+                bulge,
+                ..Default::default()
+            });
+        }
+        poly.set_is_closed(contour.closed);
+        drawing.add_entity(Entity::new(EntityType::LwPolyline(poly)));
+    }
+    let mut data = Vec::new();
+    drawing.save(&mut data)?;
+    Ok(data)
+}
+
+#[cfg(not(feature = "dxf"))]
+pub fn contours_dxf(_contours: &[Contour], _tol: f64) -> anyhow::Result<Vec<u8>> {
+    Err(anyhow::anyhow!("DXF support not enabled"))
+}
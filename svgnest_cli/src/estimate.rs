@@ -0,0 +1,106 @@
+//! Quick estimate of what a job takes, for quoting. The parts are laid out
+//! once, largest first, bottom-left fill without any optimization, which
+//! gives a sheet count the optimizer usually matches or beats.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::config::NestingConfig;
+use crate::ga::{GeneticAlgorithm, Individual};
+use crate::geometry::Bounds;
+use crate::part::Part;
+use crate::svg_parser::Polygon;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Estimate {
+    /// Sheets the layout takes
+    pub sheets: usize,
+    /// Share of the area of those sheets covered by parts
+    pub utilization: f64,
+    /// Length of all contours of the placed parts
+    pub cut_length: f64,
+    pub placed: usize,
+    /// Parts that fit no sheet
+    pub unplaced: usize,
+}
+
+impl Estimate {
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// Estimate the nesting of `parts` into `bin` with the options of `config`.
+/// Every part takes the first of the configured rotations it fits the bin at.
+pub fn estimate(parts: &[Part], bin: &Polygon, config: &NestingConfig) -> Result<Estimate> {
+    let config = NestingConfig {
+        population_size: 1,
+        elitism: 1,
+        local_search_iters: 0,
+        ..config.clone()
+    };
+    let rotations = config.rotations.clone();
+    let mut ga = GeneticAlgorithm::new(parts, bin, config)?;
+    let ind = largest_first(parts, ga.bin_bounds(), &rotations);
+    let report = ga.to_layout(&ind).to_report(parts);
+    Ok(Estimate {
+        sheets: report.sheets,
+        utilization: report.utilization,
+        cut_length: report.placements.iter().map(|p| parts[p.part].perimeter()).sum(),
+        placed: report.placements.len(),
+        unplaced: report.unplaced.len(),
+    })
+}
+
+/// The parts largest first, each at the first of `rotations` it fits an
+/// empty sheet of `bin_bounds` at. Parts fitting at none are left out.
+pub(crate) fn largest_first(parts: &[Part], bin_bounds: Bounds, rotations: &[f64]) -> Individual {
+    let mut placement: Vec<usize> = (0..parts.len()).collect();
+    placement.sort_by(|&a, &b| parts[b].area().total_cmp(&parts[a].area()));
+    // no rotations means the parts as they are
+    let rotations = if rotations.is_empty() { &[0.0][..] } else { rotations };
+    // parts fitting at no angle are left out of the genes and so unplaced
+    let (placement, rotation) = placement
+        .into_iter()
+        .filter_map(|i| {
+            let fits = |angle: &&f64| {
+                parts[i]
+                    .bounds_rotated(**angle)
+                    .is_some_and(|b| b.width <= bin_bounds.width && b.height <= bin_bounds.height)
+            };
+            Some((i, *rotations.iter().find(fits)?))
+        })
+        .unzip();
+    Individual {
+        placement,
+        rotation,
+        sheet_type: Vec::new(),
+        fitness: f64::MAX,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::rect;
+
+    #[test]
+    fn estimates_sheets_and_cut_length() {
+        // closed outlines, so the cut runs round the whole square
+        let square = |side: f64| {
+            Part::new(vec![Polygon {
+                closed: true,
+                ..rect(side, side)
+            }])
+        };
+        let parts = vec![square(4.0), square(6.0), square(20.0)];
+        let bin = rect(11.0, 6.0);
+        let config = NestingConfig::builder().rotations(&[0.0]).build().unwrap();
+        let estimate = estimate(&parts, &bin, &config).unwrap();
+        assert_eq!(estimate.placed, 2);
+        assert_eq!(estimate.unplaced, 1);
+        assert_eq!(estimate.sheets, 1);
+        assert!((estimate.cut_length - 40.0).abs() < 1e-9);
+        assert!((estimate.utilization - 52.0 / 66.0).abs() < 1e-9);
+    }
+}
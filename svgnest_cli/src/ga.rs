@@ -1,28 +1,41 @@
+use std::cell::RefCell;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
 use rand::prelude::*;
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, IntoParallelRefMutIterator, ParallelIterator};
 
+use crate::config::{AngleFallback, NestingConfig, PackDirection, PositionScore};
+use crate::contour::fit_arcs;
+use crate::crossover::Gene;
 use crate::geometry::{
-    Bounds, get_polygon_bounds, get_polygons_bounds, point_in_polygon,
-    polygon_area, polygons_intersect, polygon_contains_polygon,
+    Bounds, batch, get_polygon_bounds, get_polygons_bounds, get_rings_bounds, offset_polygon, parts_intersect,
+    shared_length,
 };
-use crate::nfp::{self, NfpCache};
-use crate::part::Part;
+use crate::layout::Layout;
+use crate::leads::with_leads;
+use crate::line_merge::merge_lines;
+use crate::nfp::{NfpCache, Orientation, inner_fit_polygon};
+use crate::output::{escape_xml, metadata_attributes, sheet_marks_layer, today};
+use crate::palette::{ColorBy, Palette};
+use crate::part::{InstanceId, Part};
+use crate::profile::{self, Stage};
+use crate::progress::ProgressHandle;
+use crate::remnant::{compute_remnants, free_space_layer};
+use crate::report::SheetTotals;
+use crate::stats::GenerationStats;
 use crate::svg_parser::{Point, Polygon};
 use anyhow::{self, Result};
+use geo::{Area, ConvexHull};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy)]
-pub struct GAConfig {
-    pub population_size: usize,
-    pub mutation_rate: usize,
-    pub rotations: usize,
-    pub spacing: f64,
-    pub use_holes: bool,
-    pub explore_concave: bool,
-    pub angle_precision: f64,
-}
-
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Placement {
     pub idx: usize,
+    /// Sheet the part is on, numbered from 0. Sheets are stacked downward
+    /// in layout coordinates, so `y` runs on across them.
+    #[serde(default)]
+    pub sheet: usize,
     pub angle: f64,
     pub x: f64,
     pub y: f64,
@@ -34,129 +47,556 @@ struct FreeRect {
     y: f64,
     width: f64,
     height: f64,
+    /// Sheet the rect is on
+    sheet: usize,
 }
 
-#[derive(Clone)]
+/// Hole of a placed part that later parts can be nested into, with
+/// [`use_holes`](NestingConfig::use_holes) a region of free space of its own
+/// next to the free rects of the sheets
+#[derive(Clone, Debug)]
+struct VirtualBin {
+    /// The hole in layout coordinates
+    ring: Vec<Point>,
+    bounds: Bounds,
+    sheet: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Individual {
+    /// Order the parts are placed in, as indices into the parts the
+    /// algorithm was created with. [`Part::id`] names them beyond a run.
+    /// Parts fitting at no angle are left out.
     pub placement: Vec<usize>,
     pub rotation: Vec<f64>,
+    /// Type of sheet every part goes on, parallel to `placement`: an index
+    /// into the sheet orientations, the bin first. Empty in individuals
+    /// saved before parts carried it, whose parts go on the first type they
+    /// fit.
+    #[serde(default)]
+    pub sheet_type: Vec<usize>,
     pub fitness: f64,
 }
 
 pub struct GeneticAlgorithm<'a> {
     parts: &'a [Part],
+    /// Outline of the bin, drawn around every sheet of the output
+    bin: &'a Polygon,
     bin_bounds: Bounds,
-    config: GAConfig,
+    config: NestingConfig,
     nfp_cache: NfpCache,
+    scratch: Scratch,
+    evaluator: Option<BatchEvaluator<'a>>,
+    stats: Vec<GenerationStats>,
+    /// Number of individuals kept in `hall_of_fame`
+    top_k: usize,
+    hall_of_fame: Vec<Individual>,
+    progress: ProgressHandle,
+    /// Set once a layout meets the target utilization, possibly by another
+    /// run sharing the flag
+    stop: Arc<AtomicBool>,
+    /// Source of all randomness of the run, seeded from the configuration
+    /// when it has a seed
+    rng: RefCell<StdRng>,
+    /// Angles for the parts fitting at none of the rotations, found with
+    /// [`AngleFallback::Continuous`]; empty for every other part
+    fallback_angles: Vec<Vec<f64>>,
     pub population: Vec<Individual>,
 }
 
+/// Evaluates a batch of individuals outside this process, e.g. on remote
+/// workers. Returns the fitness of every individual, `None` for those that
+/// have to be evaluated locally.
+pub type BatchEvaluator<'a> = Box<dyn FnMut(&[Individual]) -> Vec<Option<f64>> + 'a>;
+
+/// Buffers reused between evaluations so laying out an individual does not
+/// allocate rotated geometry for every collision check.
+#[derive(Default)]
+struct Scratch {
+    /// Rotated rings of the part currently being placed
+    candidate: Vec<Vec<Point>>,
+    /// Bounds of `candidate`
+    candidate_bounds: Option<Bounds>,
+    /// Rotated rings of the placed parts in placement order. Entries past the
+    /// number of placed parts are spare buffers.
+    placed: Vec<Vec<Vec<Point>>>,
+    /// Bounds of the rotated placed parts, parallel to `placed`
+    placed_bounds: Vec<Option<Bounds>>,
+    /// Holes of the bin in sheet coordinates, grown by the spacing, with
+    /// their bounds
+    bin_holes: Vec<(Vec<Point>, Bounds)>,
+    /// Depth up to which parts may overlap and still count as touching
+    contact: f64,
+    /// Which parts fit an empty sheet at which rotations
+    fits: FitMatrix,
+}
+
+/// Whether every part fits an empty sheet of every type at every configured
+/// rotation, worked out once per run so choosing angles and sheet types and
+/// filtering individuals does not rotate and measure the parts again. The
+/// sheet types are the orientations of the bin; every part carries the type
+/// of sheet it goes on next to its rotation.
+#[derive(Clone, Default)]
+struct FitMatrix {
+    /// The configured rotations, or only 0 if there are none
+    rotations: Vec<f64>,
+    /// The sheet types, see [`sheet_orientations`]
+    sheets: Vec<Bounds>,
+    /// `fits[sheet][part * rotations.len() + rotation]`
+    fits: Vec<Vec<bool>>,
+}
+
+impl FitMatrix {
+    fn new(parts: &[Part], bin_bounds: Bounds, config: &NestingConfig) -> Self {
+        let rotations = if config.rotations.is_empty() { vec![0.0] } else { config.rotations.clone() };
+        let sheets = sheet_orientations(bin_bounds, config);
+        let fits = sheets
+            .iter()
+            .map(|&sheet| {
+                parts
+                    .iter()
+                    .flat_map(|part| rotations.iter().map(move |&angle| (part, angle)))
+                    .map(|(part, angle)| part.bounds_rotated(angle).is_some_and(|b| fits_sheet(part, b, sheet, config)))
+                    .collect()
+            })
+            .collect();
+        Self {
+            rotations,
+            sheets,
+            fits,
+        }
+    }
+
+    /// Whether part `idx` fits an empty `sheet` at `angle`, looked up for
+    /// the configured rotations and sheet orientations and worked out for
+    /// any other.
+    fn fits(&self, parts: &[Part], idx: usize, angle: f64, sheet: Bounds, config: &NestingConfig) -> bool {
+        let s = self.sheets.iter().position(|&b| b == sheet);
+        let r = self.rotations.iter().position(|&a| a == angle);
+        match (s, r) {
+            (Some(s), Some(r)) => self.fits[s][idx * self.rotations.len() + r],
+            _ => parts[idx]
+                .bounds_rotated(angle)
+                .is_some_and(|b| fits_sheet(&parts[idx], b, sheet, config)),
+        }
+    }
+
+    /// Sheet types, as indices into `sheets`, whose empty sheets part `idx`
+    /// fits at `angle`.
+    fn sheet_types(&self, parts: &[Part], idx: usize, angle: f64, config: &NestingConfig) -> Vec<usize> {
+        (0..self.sheets.len())
+            .filter(|&t| self.fits(parts, idx, angle, self.sheets[t], config))
+            .collect()
+    }
+
+    /// `ind` without the parts fitting no empty sheet of their type at
+    /// their angle, and the number of parts left out. Parts without a type
+    /// take the first one they fit.
+    fn placeable(&self, parts: &[Part], ind: &Individual, config: &NestingConfig) -> (Individual, usize) {
+        let mut filtered = Individual {
+            placement: Vec::new(),
+            rotation: Vec::new(),
+            sheet_type: Vec::new(),
+            fitness: 0.0,
+        };
+        let mut unplaceable = 0usize;
+        for (i, (&idx, &angle)) in ind.placement.iter().zip(&ind.rotation).enumerate() {
+            let sheet_type = match ind.sheet_type.get(i) {
+                Some(&t) => {
+                    let fits = self.sheets.get(t).is_some_and(|&sheet| self.fits(parts, idx, angle, sheet, config));
+                    fits.then_some(t)
+                }
+                None => self.sheet_types(parts, idx, angle, config).first().copied(),
+            };
+            match sheet_type {
+                Some(t) => {
+                    filtered.placement.push(idx);
+                    filtered.rotation.push(angle);
+                    filtered.sheet_type.push(t);
+                }
+                None => unplaceable += 1,
+            }
+        }
+        (filtered, unplaceable)
+    }
+
+    /// Whether part `idx` fits an empty sheet of any orientation at
+    /// rotation number `r`.
+    fn fits_any(&self, idx: usize, r: usize) -> bool {
+        self.fits.iter().any(|f| f[idx * self.rotations.len() + r])
+    }
+}
+
+impl Scratch {
+    /// Empty buffers with the run-constant data of these, for laying out
+    /// individuals on another thread.
+    fn for_task(&self) -> Self {
+        Self {
+            bin_holes: self.bin_holes.clone(),
+            contact: self.contact,
+            fits: self.fits.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Rotate `part` into the candidate buffer and return its bounds.
+    fn rotate(&mut self, part: &Part, angle: f64) -> Option<Bounds> {
+        part.rotate_into(angle, &mut self.candidate);
+        self.candidate_bounds = get_rings_bounds(&self.candidate);
+        self.candidate_bounds
+    }
+
+    /// Keep the candidate rings as those of the part placed at index `k`.
+    fn place(&mut self, k: usize) {
+        if self.placed.len() <= k {
+            self.placed.resize_with(k + 1, Vec::new);
+            self.placed_bounds.resize(k + 1, None);
+        }
+        std::mem::swap(&mut self.placed[k], &mut self.candidate);
+        self.placed_bounds[k] = self.candidate_bounds;
+    }
+
+    /// If the candidate at `candidate`, on a sheet whose top is at `top`,
+    /// runs into a hole of the bin at any of `offsets`, the x it has to move
+    /// to at least to pass that hole.
+    fn bin_hole_at(&self, candidate: &Placement, top: f64, offsets: &[f64]) -> Option<f64> {
+        let bounds = self.candidate_bounds?;
+        let y = candidate.y - top;
+        self.bin_holes.iter().find_map(|(hole, hole_bounds)| {
+            offsets.iter().find_map(|&dx| {
+                let x = candidate.x + dx;
+                let hit = bounds.translated(x, y).overlaps(hole_bounds, 0.0)
+                    && parts_intersect(std::slice::from_ref(hole), &self.candidate, 0.0, 0.0, x, y, self.contact);
+                hit.then_some(hole_bounds.x + hole_bounds.width - bounds.x - dx)
+            })
+        })
+    }
+
+    /// Free space of sheet number `index`, at `sheet` in layout coordinates:
+    /// the sheet, split around the bounds of the bin holes into strips that
+    /// may overlap.
+    fn sheet_free_rects(&self, sheet: Bounds, index: usize) -> Vec<FreeRect> {
+        let top = sheet.y;
+        let mut free = vec![FreeRect {
+            x: 0.0,
+            y: top,
+            width: sheet.width,
+            height: sheet.height,
+            sheet: index,
+        }];
+        for (_, hole) in &self.bin_holes {
+            let (hx0, hy0) = (hole.x, hole.y + top);
+            let (hx1, hy1) = (hx0 + hole.width, hy0 + hole.height);
+            let mut split = Vec::with_capacity(free.len());
+            for r in free {
+                let (x1, y1) = (r.x + r.width, r.y + r.height);
+                if hx0 >= x1 || hx1 <= r.x || hy0 >= y1 || hy1 <= r.y {
+                    split.push(r);
+                    continue;
+                }
+                let strips = [
+                    (r.x, r.y, hx0 - r.x, r.height),
+                    (hx1, r.y, x1 - hx1, r.height),
+                    (r.x, r.y, r.width, hy0 - r.y),
+                    (r.x, hy1, r.width, y1 - hy1),
+                ];
+                for (x, y, width, height) in strips {
+                    if width > 0.0 && height > 0.0 {
+                        split.push(FreeRect {
+                            x,
+                            y,
+                            width,
+                            height,
+                            sheet: index,
+                        });
+                    }
+                }
+            }
+            free = split;
+        }
+        free
+    }
+}
+
 impl<'a> GeneticAlgorithm<'a> {
-    pub fn new(parts: &'a [Part], bin: &'a Polygon, config: GAConfig) -> Result<Self> {
+    pub fn new(parts: &'a [Part], bin: &'a Polygon, config: NestingConfig) -> Result<Self> {
+        config.validate()?;
         let bin_bounds = get_polygon_bounds(&bin.points)
             .ok_or_else(|| anyhow::anyhow!("failed to compute bin bounds"))?;
+        let mut nfp_cache = NfpCache::new(config.tolerances.angle);
+        if let Some(resolution) = config.raster_nfp {
+            nfp_cache = nfp_cache.with_raster(resolution);
+        }
+        let mut scratch = Scratch {
+            contact: config.tolerances.contact,
+            fits: FitMatrix::new(parts, bin_bounds, &config),
+            ..Default::default()
+        };
+        for hole in &config.bin_holes {
+            let mut moved = Vec::with_capacity(hole.len());
+            batch::translate_into(hole, -bin_bounds.x, -bin_bounds.y, &mut moved);
+            let grown = if config.spacing > 0.0 {
+                offset_polygon(&moved, config.spacing)
+            } else {
+                vec![moved]
+            };
+            for ring in grown {
+                if let Some(b) = get_polygon_bounds(&ring) {
+                    scratch.bin_holes.push((ring, b));
+                }
+            }
+        }
+        if config.start_offset > 0.0 {
+            if config.start_offset >= bin_bounds.width {
+                anyhow::bail!(
+                    "start offset {} leaves no room on a sheet {} wide",
+                    config.start_offset,
+                    bin_bounds.width
+                );
+            }
+            // the strip is kept clear like a hole of the bin spanning the sheet
+            let strip = Bounds {
+                x: 0.0,
+                y: 0.0,
+                width: config.start_offset,
+                height: bin_bounds.height,
+            };
+            let ring = vec![
+                Point { x: 0.0, y: 0.0 },
+                Point { x: strip.width, y: 0.0 },
+                Point { x: strip.width, y: strip.height },
+                Point { x: 0.0, y: strip.height },
+            ];
+            scratch.bin_holes.push((ring, strip));
+        }
+        let rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        #[cfg(feature = "gpu")]
+        let nfp_cache = match config.gpu.then(|| crate::gpu::GpuNfp::new(crate::gpu::DEFAULT_RESOLUTION)) {
+            Some(Some(gpu)) => nfp_cache.with_gpu(gpu),
+            _ => nfp_cache,
+        };
         let mut ga = GeneticAlgorithm {
             parts,
+            bin,
             bin_bounds,
             config,
-            nfp_cache: NfpCache::new(config.angle_precision),
+            nfp_cache,
+            scratch,
+            evaluator: None,
+            stats: Vec::new(),
+            top_k: 1,
+            hall_of_fame: Vec::new(),
+            progress: ProgressHandle::default(),
+            stop: Arc::new(AtomicBool::new(false)),
+            rng: RefCell::new(rng),
+            fallback_angles: vec![Vec::new(); parts.len()],
             population: Vec::new(),
         };
-        let angles: Vec<f64> = parts.iter().map(|p| ga.random_angle(p)).collect();
-        let base = Individual {
-            placement: (0..parts.len()).collect(),
-            rotation: angles,
+        for f in feasibility_in(parts, &ga.scratch.fits, &ga.config) {
+            if let Some(reason) = f.reason {
+                if ga.config.angle_fallback == AngleFallback::Error {
+                    anyhow::bail!("part {} cannot be placed: {}", f.id, reason);
+                }
+            } else if !f.angles.iter().any(|a| ga.config.rotations.contains(a)) {
+                ga.fallback_angles[f.index] = f.angles;
+            }
+        }
+        // parts no angle fits are left out of the genes, so the placer never
+        // sees them and they end up unplaced like the feasibility says
+        let mut base = Individual {
+            placement: Vec::new(),
+            rotation: Vec::new(),
+            sheet_type: Vec::new(),
             fitness: f64::MAX,
         };
+        for idx in 0..parts.len() {
+            let mut rng = ga.rng.borrow_mut();
+            if let Some(angle) = ga.random_angle(idx, &mut *rng) {
+                base.placement.push(idx);
+                base.rotation.push(angle);
+                base.sheet_type.push(ga.random_sheet_type(idx, angle, &mut *rng));
+            }
+        }
         ga.population.push(base.clone());
-        while ga.population.len() < config.population_size {
+        while ga.population.len() < ga.config.population_size {
             let m = ga.mutate(&base);
             ga.population.push(m);
         }
         Ok(ga)
     }
 
-    fn random_angle(&self, part: &Part) -> f64 {
-        if self.config.rotations == 0 {
-            return 0.0;
+    /// Angle for part `idx`, picked by the rotation weights among those at
+    /// which it fits an empty sheet, or `None` if there is no such angle.
+    fn random_angle<R: Rng>(&self, idx: usize, rng: &mut R) -> Option<f64> {
+        let part = &self.parts[idx];
+        if let Some(&angle) = self.fallback_angles[idx].choose(rng) {
+            return Some(angle);
         }
-        let mut angles: Vec<f64> = (0..self.config.rotations)
-            .map(|i| i as f64 * 360.0 / self.config.rotations as f64)
+        let fits = |r: usize| self.scratch.fits.fits_any(idx, r);
+        let rotations = &self.scratch.fits.rotations;
+        let overrides = part.rotation_weights();
+        if self.config.rotation_weights.is_empty() && overrides.is_empty() {
+            let mut order: Vec<usize> = (0..rotations.len()).collect();
+            order.shuffle(rng);
+            return order.into_iter().find(|&r| fits(r)).map(|r| rotations[r]);
+        }
+        let weighted: Vec<(f64, f64)> = rotations
+            .iter()
+            .enumerate()
+            .map(|(i, &angle)| {
+                let weight = overrides
+                    .iter()
+                    .find(|(a, _)| (a - angle).abs() < self.config.tolerances.angle)
+                    .map(|&(_, w)| w)
+                    .unwrap_or_else(|| self.config.rotation_weights.get(i).copied().unwrap_or(1.0));
+                (i, angle, weight)
+            })
+            .filter(|&(i, _, weight)| weight > 0.0 && fits(i))
+            .map(|(_, angle, weight)| (angle, weight))
             .collect();
-        let mut rng = thread_rng();
-        angles.shuffle(&mut rng);
-        for angle in angles {
-            let rotated = part.rotated(angle);
-            if let Some(b) = get_polygons_bounds(&rotated) {
-                if b.width <= self.bin_bounds.width && b.height <= self.bin_bounds.height {
-                    return angle;
-                }
+        let total: f64 = weighted.iter().map(|(_, w)| w).sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut pick = rng.gen_range(0.0..total);
+        for &(angle, weight) in &weighted {
+            if pick < weight {
+                return Some(angle);
             }
+            pick -= weight;
+        }
+        weighted.last().map(|&(angle, _)| angle)
+    }
+
+    /// Type of sheet for part `idx` at `angle`, picked at random among those
+    /// it fits an empty sheet of.
+    fn random_sheet_type<R: Rng>(&self, idx: usize, angle: f64, rng: &mut R) -> usize {
+        let types = self.scratch.fits.sheet_types(self.parts, idx, angle, &self.config);
+        // a single type is taken without drawing, so seeded runs on one
+        // sheet type go as they did
+        if types.len() < 2 {
+            return types.first().copied().unwrap_or(0);
         }
-        0.0
+        types[rng.gen_range(0..types.len())]
     }
 
-    fn evaluate(&mut self, ind: &Individual) -> f64 {
-        evaluate_static(ind, self.parts, self.bin_bounds, self.config, &mut self.nfp_cache)
+    /// Sheet types of `ind`, one per part. Parts without one, as in
+    /// individuals from before sheet types, take the first type they fit.
+    fn full_sheet_types(&self, ind: &Individual) -> Vec<usize> {
+        let fits = &self.scratch.fits;
+        (0..ind.placement.len())
+            .map(|i| {
+                ind.sheet_type.get(i).copied().unwrap_or_else(|| {
+                    let types = fits.sheet_types(self.parts, ind.placement[i], ind.rotation[i], &self.config);
+                    types.first().copied().unwrap_or(0)
+                })
+            })
+            .collect()
+    }
+
+    /// Hand population evaluation to `evaluator`, falling back to local
+    /// evaluation for the individuals it leaves out.
+    pub fn with_evaluator(mut self, evaluator: BatchEvaluator<'a>) -> Self {
+        self.evaluator = Some(evaluator);
+        self
+    }
+
+    /// Remember the `k` best distinct individuals evaluated during the run
+    /// instead of only the best one.
+    pub fn keep_top(mut self, k: usize) -> Self {
+        self.top_k = k.max(1);
+        self
+    }
+
+    /// Stop together with every other run sharing `stop`, as soon as one of
+    /// them reaches the target utilization.
+    fn sharing_stop(mut self, stop: Arc<AtomicBool>) -> Self {
+        self.stop = stop;
+        self
+    }
+
+    /// Returns true once a layout meeting the
+    /// [`target_utilization`](NestingConfig::target_utilization) was found,
+    /// so the run can end early.
+    pub fn target_reached(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    /// The best distinct individuals evaluated so far, best first.
+    pub fn hall_of_fame(&self) -> &[Individual] {
+        &self.hall_of_fame
+    }
+
+    /// Fitness of `ind`, evaluated in this process.
+    pub fn fitness(&mut self, ind: &Individual) -> f64 {
+        evaluate(
+            ind,
+            self.parts,
+            self.bin_bounds,
+            &self.config,
+            &self.nfp_cache,
+            &mut self.scratch,
+        )
     }
 
     fn mutate(&self, ind: &Individual) -> Individual {
-        let mut rng = thread_rng();
+        let mut rng = self.rng.borrow_mut();
         let mut placement = ind.placement.clone();
         let mut rotation = ind.rotation.clone();
+        let mut sheet_type = self.full_sheet_types(ind);
         for i in 0..placement.len() {
             if rng.r#gen::<f64>() < self.config.mutation_rate as f64 * 0.01 {
+                // the angle and sheet type stay with their part, which may be
+                // the only one they fit or have weight for
                 if i + 1 < placement.len() {
                     placement.swap(i, i + 1);
+                    rotation.swap(i, i + 1);
+                    sheet_type.swap(i, i + 1);
                 }
             }
             if rng.r#gen::<f64>() < self.config.mutation_rate as f64 * 0.01 {
-                rotation[i] = self.random_angle(&self.parts[placement[i]]);
+                if let Some(angle) = self.random_angle(placement[i], &mut *rng) {
+                    rotation[i] = angle;
+                    sheet_type[i] = self.random_sheet_type(placement[i], angle, &mut *rng);
+                }
+            }
+            // with a choice of sheet types, the part may also move to
+            // another type at the angle it has
+            let choice = self.scratch.fits.sheets.len() > 1;
+            if choice && rng.r#gen::<f64>() < self.config.mutation_rate as f64 * 0.01 {
+                sheet_type[i] = self.random_sheet_type(placement[i], rotation[i], &mut *rng);
             }
         }
         Individual {
             placement,
             rotation,
+            sheet_type,
             fitness: f64::MAX,
         }
     }
 
     fn mate(&self, male: &Individual, female: &Individual) -> (Individual, Individual) {
-        let len = male.placement.len();
-        let mut rng = thread_rng();
-        let cut = ((len as f64 * rng.gen_range(0.1..0.9)).round()) as usize;
-        let mut gene1 = male.placement[..cut].to_vec();
-        let mut rot1 = male.rotation[..cut].to_vec();
-        for (&p, &r) in female.placement.iter().zip(&female.rotation) {
-            if !gene1.contains(&p) {
-                gene1.push(p);
-                rot1.push(r);
-            }
-        }
-        let mut gene2 = female.placement[..cut].to_vec();
-        let mut rot2 = female.rotation[..cut].to_vec();
-        for (&p, &r) in male.placement.iter().zip(&male.rotation) {
-            if !gene2.contains(&p) {
-                gene2.push(p);
-                rot2.push(r);
-            }
-        }
-        (
-            Individual {
-                placement: gene1,
-                rotation: rot1,
-                fitness: f64::MAX,
-            },
-            Individual {
-                placement: gene2,
-                rotation: rot2,
-                fitness: f64::MAX,
-            },
-        )
+        let genes = |ind: &Individual| -> Vec<Gene> {
+            let sheet_type = self.full_sheet_types(ind);
+            (0..ind.placement.len())
+                .map(|i| (ind.placement[i], ind.rotation[i], sheet_type[i]))
+                .collect()
+        };
+        let (c1, c2) = self.config.crossover.apply(&genes(male), &genes(female), &mut *self.rng.borrow_mut());
+        let child = |genes: Vec<Gene>| Individual {
+            placement: genes.iter().map(|g| g.0).collect(),
+            rotation: genes.iter().map(|g| g.1).collect(),
+            sheet_type: genes.iter().map(|g| g.2).collect(),
+            fitness: f64::MAX,
+        };
+        (child(c1), child(c2))
     }
 
     fn random_weighted_index(&self, exclude: Option<usize>) -> usize {
-        let mut rng = thread_rng();
+        let mut rng = self.rng.borrow_mut();
         let mut idxs: Vec<usize> = (0..self.population.len()).collect();
         if let Some(e) = exclude {
             idxs.retain(|&v| v != e);
@@ -176,12 +616,151 @@ impl<'a> GeneticAlgorithm<'a> {
     }
 
     pub fn evaluate_population(&mut self) {
+        let start = std::time::Instant::now();
+        let (hits, misses) = self.nfp_cache.lookups();
         let parts = self.parts;
         let bounds = self.bin_bounds;
-        let cfg = self.config;
-        for ind in &mut self.population {
-            ind.fitness = evaluate_static(ind, parts, bounds, cfg, &mut self.nfp_cache);
+        let remote = match &mut self.evaluator {
+            Some(evaluate) => evaluate(&self.population),
+            None => Vec::new(),
+        };
+        let (config, nfp_cache, scratch) = (&self.config, &self.nfp_cache, &self.scratch);
+        // every rayon task lays out its individuals in buffers of its own
+        self.population.par_iter_mut().enumerate().for_each_init(
+            || scratch.for_task(),
+            |scratch, (i, ind)| {
+                ind.fitness = match remote.get(i).copied().flatten() {
+                    Some(fitness) => fitness,
+                    None => evaluate(ind, parts, bounds, config, nfp_cache, scratch),
+                };
+            },
+        );
+        for ind in &self.population {
+            remember(&mut self.hall_of_fame, ind, self.top_k);
+        }
+        let (hits_now, misses_now) = self.nfp_cache.lookups();
+        self.stats.push(GenerationStats::new(
+            self.stats.len(),
+            &self.population,
+            hits_now - hits,
+            misses_now - misses,
+            start.elapsed(),
+        ));
+        self.publish_progress();
+    }
+
+    /// Update the progress after evaluating a population.
+    fn publish_progress(&mut self) {
+        let Some(best) = self.best().cloned() else {
+            return;
+        };
+        let improved = self.progress.get().best_fitness.is_none_or(|f| best.fitness < f);
+        let utilization = improved.then(|| self.utilization(&best));
+        let n = self.parts.len();
+        let angles = self.config.rotations.len().max(1);
+        let (_, computed) = self.nfp_cache.lookups();
+        let generation = self.stats.len().saturating_sub(1);
+        if let (Some(target), Some(utilization)) = (self.config.target_utilization, utilization) {
+            if utilization >= target {
+                self.stop.store(true, Ordering::Relaxed);
+            }
+        }
+        self.progress.update(|p| {
+            p.generations_done = generation;
+            if let Some(utilization) = utilization {
+                p.best_fitness = Some(best.fitness);
+                p.best_utilization = Some(utilization);
+            }
+            p.nfps_computed = computed;
+            p.nfps_remaining = (n * n.saturating_sub(1) * angles * angles).saturating_sub(computed);
+        });
+    }
+
+    /// Placed part area of the layout of `ind` divided by the area of the
+    /// sheets it uses, as in the report.
+    fn utilization(&mut self, ind: &Individual) -> f64 {
+        let layout = self.to_layout(ind);
+        let placed: f64 = layout.placements.iter().map(|p| self.parts[p.idx].area()).sum();
+        let sheets: f64 = layout.sheet_bounds().iter().map(|s| s.width * s.height).sum();
+        if sheets > 0.0 { placed / sheets } else { 0.0 }
+    }
+
+    /// Handle to the progress of the run, to be read from other threads.
+    pub fn progress(&self) -> ProgressHandle {
+        self.progress.clone()
+    }
+
+    /// Use `cache`, e.g. that of an earlier algorithm on the same parts and
+    /// options, instead of generating every NFP again.
+    pub fn with_nfp_cache(mut self, cache: NfpCache) -> Self {
+        self.nfp_cache = cache;
+        self
+    }
+
+    /// The NFPs generated so far, for a later algorithm on the same parts.
+    pub fn into_nfp_cache(self) -> NfpCache {
+        self.nfp_cache
+    }
+
+    /// Continue from `population`, e.g. that of an earlier run on a list of
+    /// parts that has changed since. Indices past the parts are dropped,
+    /// parts an individual lacks are appended at a random rotation they fit
+    /// at, if there is one, on a random sheet type taking them at it, and the
+    /// individuals replace the start of the current population. Individuals
+    /// left as they were keep their fitness.
+    pub fn resume(&mut self, population: &[Individual]) {
+        let n = self.parts.len();
+        let mut resumed = Vec::with_capacity(population.len());
+        for seed in population.iter().take(self.population.len()) {
+            let mut seen = vec![false; n];
+            let mut ind = Individual {
+                placement: Vec::with_capacity(n),
+                rotation: Vec::with_capacity(n),
+                sheet_type: Vec::with_capacity(n),
+                fitness: f64::MAX,
+            };
+            let mut rng = self.rng.borrow_mut();
+            for (i, (&idx, &angle)) in seed.placement.iter().zip(&seed.rotation).enumerate() {
+                if idx < n && !seen[idx] {
+                    seen[idx] = true;
+                    ind.placement.push(idx);
+                    ind.rotation.push(angle);
+                    // parts of seeds from before they carried a sheet type go
+                    // on the first type they fit, as they were evaluated
+                    let sheet_type = seed.sheet_type.get(i).copied().unwrap_or_else(|| {
+                        let types = self.scratch.fits.sheet_types(self.parts, idx, angle, &self.config);
+                        types.first().copied().unwrap_or(0)
+                    });
+                    ind.sheet_type.push(sheet_type);
+                }
+            }
+            for idx in (0..n).filter(|&i| !seen[i]) {
+                if let Some(angle) = self.random_angle(idx, &mut *rng) {
+                    ind.placement.push(idx);
+                    ind.rotation.push(angle);
+                    ind.sheet_type.push(self.random_sheet_type(idx, angle, &mut *rng));
+                }
+            }
+            if ind.placement == seed.placement {
+                ind.fitness = seed.fitness;
+            }
+            resumed.push(ind);
         }
+        for (slot, ind) in self.population.iter_mut().zip(resumed) {
+            *slot = ind;
+        }
+    }
+
+    /// Announce that the run evaluates `generations` generations after the
+    /// initial population, for [`Progress::fraction`](crate::progress::Progress::fraction)
+    /// and the time estimate.
+    pub fn plan_generations(&mut self, generations: usize) {
+        self.progress.update(|p| p.generations_total = generations);
+    }
+
+    /// Statistics of every population evaluated so far, in generation order.
+    pub fn stats(&self) -> &[GenerationStats] {
+        &self.stats
     }
 
     pub fn generation(&mut self) {
@@ -190,7 +769,8 @@ impl<'a> GeneticAlgorithm<'a> {
                 .partial_cmp(&b.fitness)
                 .unwrap_or(std::cmp::Ordering::Equal)
         });
-        let mut newpop = vec![self.population[0].clone()];
+        let elite = self.config.elitism.min(self.population.len());
+        let mut newpop = self.population[..elite].to_vec();
         while newpop.len() < self.population.len() {
             let m_idx = self.random_weighted_index(None);
             let f_idx = self.random_weighted_index(Some(m_idx));
@@ -200,106 +780,718 @@ impl<'a> GeneticAlgorithm<'a> {
                 newpop.push(self.mutate(&c2));
             }
         }
+        if self.config.local_search_iters > 0 {
+            for ind in newpop.iter_mut().skip(elite) {
+                self.local_search(ind);
+            }
+        }
         self.population = newpop;
     }
 
+    /// Improve `ind` by trying `local_search_iters` random moves, swapping
+    /// two neighbouring parts or rotating a single part onto a sheet type it
+    /// fits at the new angle, and keeping those that lower the fitness.
+    /// Leaves `ind` evaluated.
+    fn local_search(&mut self, ind: &mut Individual) {
+        ind.sheet_type = self.full_sheet_types(ind);
+        ind.fitness = self.fitness(ind);
+        let len = ind.placement.len();
+        if len == 0 {
+            return;
+        }
+        // a generator of its own, as evaluating candidates needs the
+        // algorithm mutably
+        let mut rng = StdRng::seed_from_u64(self.rng.borrow_mut().r#gen());
+        for _ in 0..self.config.local_search_iters {
+            let mut candidate = ind.clone();
+            let i = rng.gen_range(0..len);
+            if len > 1 && rng.r#gen::<bool>() {
+                let j = if i + 1 < len { i + 1 } else { i - 1 };
+                candidate.placement.swap(i, j);
+                candidate.rotation.swap(i, j);
+                candidate.sheet_type.swap(i, j);
+            } else {
+                let idx = candidate.placement[i];
+                match self.random_angle(idx, &mut rng) {
+                    Some(angle) if angle != ind.rotation[i] => {
+                        candidate.rotation[i] = angle;
+                        candidate.sheet_type[i] = self.random_sheet_type(idx, angle, &mut rng);
+                    }
+                    _ => continue,
+                }
+            }
+            candidate.fitness = self.fitness(&candidate);
+            if candidate.fitness < ind.fitness {
+                *ind = candidate;
+            }
+        }
+    }
+
     pub fn evolve(&mut self, generations: usize) {
-        for _ in 0..generations {
+        self.evolve_with(generations, |_, _| {});
+    }
+
+    /// Run the optimizer like [`evolve`](Self::evolve), calling `on_improve`
+    /// every time an individual better than all previous ones is found. Ends
+    /// early once the target utilization is reached.
+    pub fn evolve_with<F>(&mut self, generations: usize, mut on_improve: F)
+    where
+        F: FnMut(&mut Self, &Individual),
+    {
+        self.plan_generations(generations);
+        let mut best_fitness = f64::INFINITY;
+        for i in 0..=generations {
+            profile::set_generation(i);
             self.evaluate_population();
-            self.generation();
+            if let Some(best) = self.best().cloned() {
+                if best.fitness < best_fitness {
+                    best_fitness = best.fitness;
+                    on_improve(self, &best);
+                }
+            }
+            if self.target_reached() {
+                break;
+            }
+            if i < generations {
+                self.generation();
+            }
         }
-        self.evaluate_population();
+    }
+
+    /// Individual with the lowest fitness in the current population.
+    pub fn best(&self) -> Option<&Individual> {
+        self.population.iter().min_by(|a, b| {
+            a.fitness
+                .partial_cmp(&b.fitness)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+    }
+
+    pub fn bin_bounds(&self) -> Bounds {
+        self.bin_bounds
+    }
+
+    /// Returns true if NFPs are generated on the GPU.
+    pub fn uses_gpu(&self) -> bool {
+        self.nfp_cache.uses_gpu()
+    }
+
+    /// Final layout of `ind`, see [`placements`](Self::placements).
+    pub fn to_layout(&mut self, ind: &Individual) -> Layout {
+        let (sheets, placements) = self.sheet_placements(ind);
+        Layout {
+            bin: self.bin_bounds,
+            height: sheets.iter().map(|s| s.height).sum(),
+            placements,
+            turned: (0..sheets.len())
+                .filter(|&k| sheets[k].width != self.bin_bounds.width)
+                .collect(),
+        }
+    }
+
+    /// Lay out `ind` and return the used height together with the final
+    /// placements. Parts that cannot fit into the bin are skipped, mirroring
+    /// the filtering done during evaluation.
+    pub fn placements(&mut self, ind: &Individual) -> (f64, Vec<Placement>) {
+        let (sheets, placements) = self.sheet_placements(ind);
+        (sheets.iter().map(|s| s.height).sum(), placements)
+    }
+
+    /// Like [`placements`](Self::placements), returning the sheets the parts
+    /// were placed on, in layout coordinates, instead of their height.
+    fn sheet_placements(&mut self, ind: &Individual) -> (Vec<Bounds>, Vec<Placement>) {
+        let orientations = sheet_orientations(self.bin_bounds, &self.config);
+        let (filtered, _) = self.scratch.fits.placeable(self.parts, ind, &self.config);
+        let (sheets, mut placements) = layout(
+            &filtered,
+            self.parts,
+            &orientations,
+            &self.config,
+            &self.nfp_cache,
+            &mut self.scratch,
+        )
+        .unwrap_or_else(|| {
+            let sheet = Bounds {
+                x: 0.0,
+                y: 0.0,
+                ..self.bin_bounds
+            };
+            (vec![sheet], Vec::new())
+        });
+        pack_toward(
+            self.config.pack_direction,
+            &sheets,
+            self.config.start_offset,
+            &mut placements,
+            &self.scratch.placed_bounds,
+        );
+        (sheets, placements)
     }
 
     pub fn create_svg(&mut self, ind: &Individual) -> String {
-        // reuse the filtering logic from evaluation so that SVG output ignores
-        // parts that cannot fit into the bin
-        let mut placement_ids = Vec::new();
-        let mut rotation = Vec::new();
-        for (&idx, &angle) in ind.placement.iter().zip(&ind.rotation) {
-            let rotated = self.parts[idx].rotated(angle);
-            if let Some(b) = get_polygons_bounds(&rotated) {
-                if b.width <= self.bin_bounds.width && b.height <= self.bin_bounds.height {
-                    placement_ids.push(idx);
-                    rotation.push(angle);
+        let layout = self.to_layout(ind);
+        self.layout_svg(&layout)
+    }
+
+    /// SVG drawing of `layout`, a layout of the parts of this algorithm, as
+    /// [`create_svg`](Self::create_svg) draws it.
+    pub fn layout_svg(&self, layout: &Layout) -> String {
+        let _span = profile::span(Stage::Output);
+        let (sheet, placement) = (layout.bin, &layout.placements);
+        let sheets = layout.sheet_bounds();
+        // the drawing keeps the coordinates of the bin, so it lines up with
+        // the sheet drawing
+        let (ox, oy) = (self.bin_bounds.x, self.bin_bounds.y);
+        let mut body = String::new();
+        let mut uses_inkscape = false;
+        let mut moved: Vec<Point> = Vec::new();
+        let mut cuts: Vec<Polygon> = Vec::new();
+        let mut marking = String::new();
+        let mut palette = Palette::default();
+        for p in placement {
+            let part = &self.parts[p.idx];
+            let stroke = match self.config.color_by {
+                Some(by) => palette.color_of(&color_key(by, part, p)),
+                None => "black".to_string(),
+            };
+            let rotated = part.rotated(p.angle);
+            // parts crossing the seam are drawn a second time on the other side
+            let mut offsets = vec![0.0];
+            if self.config.wrap_x {
+                if let Some(b) = get_polygons_bounds(&rotated) {
+                    if p.x + b.width > sheet.width {
+                        offsets.push(-sheet.width);
+                    }
+                }
+            }
+            let leads = self.config.lead_in.is_some() || self.config.lead_out.is_some();
+            // with part transforms the geometry is drawn as it is, in groups
+            // moving it into place
+            let baked = if self.config.part_transforms {
+                uses_inkscape |= part.polygons.iter().any(|poly| poly.metadata.contains_key("inkscape:label"));
+                body.push_str(&self.part_groups(part, p, &offsets, (ox, oy), &stroke));
+                Vec::new()
+            } else {
+                rotated
+            };
+            for (i, poly) in baked.into_iter().enumerate() {
+                uses_inkscape |= poly.metadata.contains_key("inkscape:label");
+                for (copy, &dx) in offsets.iter().enumerate() {
+                    // identifiers are only carried by the primary copy, which
+                    // is labelled with its instance unless the design uses
+                    // the attribute itself
+                    let attrs = if copy > 0 {
+                        String::new()
+                    } else if poly.metadata.contains_key("data-part") {
+                        metadata_attributes(&poly.metadata)
+                    } else {
+                        format!(
+                            "{} data-part=\"{}\"",
+                            metadata_attributes(&poly.metadata),
+                            escape_xml(&part.id.to_string())
+                        )
+                    };
+                    moved.clear();
+                    batch::translate_into(&poly.points, ox + p.x + dx, oy + p.y, &mut moved);
+                    // holes fall out anyway, only outlines get tabs
+                    let pieces = match self.config.tabs {
+                        Some(tabs) if poly.closed && !part.is_hole(i) => tabs.split(&moved),
+                        _ => None,
+                    };
+                    if self.config.merge_common_cuts {
+                        match pieces {
+                            Some(pieces) => cuts.extend(pieces.into_iter().map(|points| Polygon {
+                                points,
+                                closed: false,
+                                ..Default::default()
+                            })),
+                            None => cuts.push(Polygon {
+                                points: moved.clone(),
+                                closed: poly.closed,
+                                ..Default::default()
+                            }),
+                        }
+                        continue;
+                    }
+                    let led = if leads && poly.closed {
+                        let c = &self.config;
+                        with_leads(&moved, part.is_hole(i), c.lead_in, c.lead_out, c.tolerances.curve)
+                    } else {
+                        None
+                    };
+                    if let Some(path) = led {
+                        body.push_str(&cut_element(&path, false, "polyline", self.config.fit_arcs, &stroke, &attrs));
+                        continue;
+                    }
+                    match pieces {
+                        Some(pieces) => {
+                            for (k, piece) in pieces.iter().enumerate() {
+                                let attrs = if k == 0 { attrs.as_str() } else { "" };
+                                body.push_str(&cut_element(piece, false, "polyline", self.config.fit_arcs, &stroke, attrs));
+                            }
+                        }
+                        None => body.push_str(&cut_element(
+                            &moved,
+                            poly.closed,
+                            "polygon",
+                            self.config.fit_arcs,
+                            &stroke,
+                            &attrs,
+                        )),
+                    }
+                }
+            }
+            for poly in part.rotated_marking(p.angle) {
+                for &dx in &offsets {
+                    moved.clear();
+                    batch::translate_into(&poly.points, ox + p.x + dx, oy + p.y, &mut moved);
+                    let element = if poly.closed { "polygon" } else { "polyline" };
+                    let points: Vec<String> = moved.iter().map(|pt| format!("{},{}", pt.x, pt.y)).collect();
+                    marking.push_str(&format!("<{} points=\"{}\"/>\n", element, points.join(" ")));
                 }
             }
         }
-        let filtered = Individual {
-            placement: placement_ids,
-            rotation,
-            fitness: 0.0,
+        if !marking.is_empty() {
+            body.push_str(&format!(
+                "<g id=\"marking\" inkscape:groupmode=\"layer\" inkscape:label=\"Marking\" fill=\"none\" stroke=\"green\">\n{}</g>\n",
+                marking
+            ));
+            uses_inkscape = true;
+        }
+        if self.config.merge_common_cuts {
+            // a shared edge belongs to no single part, so the merged cuts
+            // carry no metadata
+            for cut in merge_lines(&cuts, self.config.tolerances.merge) {
+                let element = if cut.closed { "polygon" } else { "polyline" };
+                body.push_str(&cut_element(&cut.points, cut.closed, element, self.config.fit_arcs, "black", ""));
+            }
+        }
+        let width = sheets.iter().map(|s| s.width).fold(sheet.width, f64::max);
+        let height = layout.height;
+        if self.config.wrap_x {
+            body = format!(
+                "<clipPath id=\"sheet\"><rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/></clipPath><g clip-path=\"url(#sheet)\">\n{}</g>\n",
+                ox, oy, width, height, body
+            );
+        }
+        // every sheet is outlined by the bin, with its holes, or by the bin
+        // turned into the frame the parts of a turned sheet are drawn in
+        let (outline, turned_outline) = (self.sheet_outline(false), self.sheet_outline(true));
+        for (k, s) in sheets.iter().enumerate() {
+            let top = s.y;
+            moved.clear();
+            if layout.turned.contains(&k) {
+                batch::translate_into(&turned_outline, ox, oy + top, &mut moved);
+                body.push_str(&sheet_element(&moved, k, " data-sheet-rotation=\"90\""));
+                continue;
+            }
+            batch::translate_into(&outline, 0.0, top, &mut moved);
+            body.push_str(&sheet_element(&moved, k, ""));
+            for hole in &self.config.bin_holes {
+                moved.clear();
+                batch::translate_into(hole, 0.0, top, &mut moved);
+                body.push_str(&sheet_element(&moved, k, ""));
+            }
+        }
+        if self.config.free_space_overlay {
+            let holes: Vec<Vec<Point>> = self
+                .config
+                .bin_holes
+                .iter()
+                .map(|hole| {
+                    let mut moved = Vec::with_capacity(hole.len());
+                    batch::translate_into(hole, -self.bin_bounds.x, -self.bin_bounds.y, &mut moved);
+                    moved
+                })
+                .collect();
+            let remnants = compute_remnants(self.parts, placement, &sheets, &holes, self.config.wrap_x, 0.0);
+            body.push_str(&free_space_layer(&remnants, &sheets, ox, oy));
+            uses_inkscape = true;
+        }
+        let (mut view_width, mut view_height) = (width, height);
+        if self.config.show_unplaced {
+            if let Some((area, w, h)) = self.unplaced_area(layout, ox + width, oy) {
+                body.push_str(&area);
+                view_width += w;
+                view_height = view_height.max(h);
+            }
+        }
+        if self.config.color_by.is_some() {
+            let size = sheet.width.max(sheet.height) / 40.0;
+            let (legend, w, h) = palette.legend(ox + view_width + size, oy, size);
+            if !legend.is_empty() {
+                body.push_str(&legend);
+                view_width += w + size;
+                view_height = view_height.max(h);
+                uses_inkscape = true;
+            }
+        }
+        let marks = &self.config.marks;
+        if marks.any() {
+            let layer = sheet_marks_layer(marks, &sheets, &today());
+            if ox == 0.0 && oy == 0.0 {
+                body.push_str(&layer);
+            } else {
+                body.push_str(&format!("<g transform=\"translate({} {})\">\n{}</g>\n", ox, oy, layer));
+            }
+            uses_inkscape = true;
+        }
+        let namespaces = if uses_inkscape {
+            " xmlns:inkscape=\"http://www.inkscape.org/namespaces/inkscape\""
+        } else {
+            ""
         };
-        let (_height, placement) = layout(&filtered, self.parts, self.bin_bounds, self.config, &mut self.nfp_cache);
-        let mut body = String::new();
-        for p in &placement {
-            let part = &self.parts[p.idx];
-            let rotated = part.rotated(p.angle);
-            for poly in rotated {
-                let points: Vec<String> = poly
-                    .points
-                    .into_iter()
-                    .map(|pt| format!("{},{}", pt.x + p.x, pt.y + p.y))
-                    .collect();
-                body.push_str(&format!(
-                    "<polygon points=\"{}\" fill=\"none\" stroke=\"black\"/>\n",
-                    points.join(" ")
-                ));
-            }
-        }
-        let width = self.bin_bounds.width;
-        let height = _height;
         format!(
-            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">{}<rect x=\"0\" y=\"0\" width=\"{}\" height=\"{}\" fill=\"none\" stroke=\"blue\"/></svg>",
-            width, height, body, width, height
+            "<svg xmlns=\"http://www.w3.org/2000/svg\"{} width=\"{}\" height=\"{}\" viewBox=\"{} {} {} {}\">{}</svg>",
+            namespaces, view_width, view_height, ox, oy, view_width, view_height, body
         )
     }
+
+    /// `part`, placed at `p` and once more at every further offset of
+    /// `offsets`, as groups whose transform turns the geometry of the part
+    /// and moves it into place, drawn in `stroke`. Only the first group
+    /// carries the identifier of the instance.
+    fn part_groups(&self, part: &Part, p: &Placement, offsets: &[f64], (ox, oy): (f64, f64), stroke: &str) -> String {
+        let corner = part.rotated_corner(p.angle);
+        let mut groups = String::new();
+        for (copy, &dx) in offsets.iter().enumerate() {
+            let attrs = if copy > 0 {
+                String::new()
+            } else {
+                format!(" data-part=\"{}\"", escape_xml(&part.id.to_string()))
+            };
+            groups.push_str(&format!(
+                "<g transform=\"translate({} {}) rotate({})\"{}>\n",
+                ox + p.x + dx - corner.x,
+                oy + p.y - corner.y,
+                p.angle,
+                attrs
+            ));
+            for poly in &part.polygons {
+                let element = if poly.closed { "polygon" } else { "polyline" };
+                let attrs = if copy > 0 {
+                    String::new()
+                } else {
+                    metadata_attributes(&poly.metadata)
+                };
+                groups.push_str(&cut_element(&poly.points, poly.closed, element, self.config.fit_arcs, stroke, &attrs));
+            }
+            groups.push_str("</g>\n");
+        }
+        groups
+    }
+
+    /// The parts `layout` leaves unplaced, unrotated and outlined dashed red
+    /// in columns no taller than the layout, in a framed area whose top left
+    /// corner is at `left`, `top`. Returns the markup of the area with its
+    /// width and height, or `None` if every part is placed.
+    fn unplaced_area(&self, layout: &Layout, left: f64, top: f64) -> Option<(String, f64, f64)> {
+        let unplaced: Vec<usize> = (0..self.parts.len())
+            .filter(|i| !layout.placements.iter().any(|p| p.idx == *i))
+            .collect();
+        if unplaced.is_empty() {
+            return None;
+        }
+        let gap = layout.bin.width.max(layout.bin.height) / 50.0;
+        // the label takes the first row
+        let first = top + 3.0 * gap;
+        let (mut x, mut y, mut column) = (left + 2.0 * gap, first, 0.0f64);
+        let (mut right, mut bottom) = (x, first);
+        let mut moved: Vec<Point> = Vec::new();
+        let mut items = String::new();
+        for &i in &unplaced {
+            let part = &self.parts[i];
+            let polys = part.rotated(0.0);
+            let Some(b) = get_polygons_bounds(&polys) else {
+                continue;
+            };
+            if y > first && y + b.height > top + layout.height {
+                x += column + gap;
+                y = first;
+                column = 0.0;
+            }
+            let attrs = format!(" data-part=\"{}\"", escape_xml(&part.id.to_string()));
+            for poly in &polys {
+                moved.clear();
+                batch::translate_into(&poly.points, x - b.x, y - b.y, &mut moved);
+                let element = if poly.closed { "polygon" } else { "polyline" };
+                let points: Vec<String> = moved.iter().map(|pt| format!("{},{}", pt.x, pt.y)).collect();
+                items.push_str(&format!("<{} points=\"{}\"{}/>\n", element, points.join(" "), attrs));
+            }
+            column = column.max(b.width);
+            right = right.max(x + b.width);
+            bottom = bottom.max(y + b.height);
+            y += b.height + gap;
+        }
+        let (width, height) = (right + gap - left, bottom + gap - top);
+        let area = format!(
+            "<g id=\"unplaced\" data-unplaced=\"{}\" fill=\"none\" stroke=\"red\" stroke-dasharray=\"{} {}\">\n\
+             <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/>\n\
+             <text x=\"{}\" y=\"{}\" font-size=\"{}\" fill=\"red\" stroke=\"none\">Unplaced: {}</text>\n{}</g>\n",
+            unplaced.len(),
+            gap / 2.0,
+            gap / 2.0,
+            left + gap,
+            top,
+            width - gap,
+            height,
+            left + 2.0 * gap,
+            top + 2.0 * gap,
+            1.5 * gap,
+            unplaced.len(),
+            items
+        );
+        Some((area, width, height))
+    }
+
+    /// Outline of the first sheet: the bin itself, in the coordinates of the
+    /// drawing, or, if `rotated`, the bin turned by 90° into the frame of a
+    /// turned sheet.
+    fn sheet_outline(&self, rotated: bool) -> Vec<Point> {
+        let b = self.bin_bounds;
+        if !rotated {
+            return self.bin.points.clone();
+        }
+        self.bin
+            .points
+            .iter()
+            .map(|p| Point {
+                x: p.y - b.y,
+                y: b.x + b.width - p.x,
+            })
+            .collect()
+    }
+}
+
+/// What `part`, placed at `p`, is colored by.
+fn color_key(by: ColorBy, part: &Part, p: &Placement) -> String {
+    match by {
+        ColorBy::Source => match &part.id.part.file {
+            Some(file) => file.display().to_string(),
+            None => "(no file)".to_string(),
+        },
+        ColorBy::Sheet => format!("Sheet {}", p.sheet + 1),
+        ColorBy::Part => part.id.part.to_string(),
+    }
+}
+
+/// Outline of sheet `k` or one of its holes. Sheet elements are not cut and
+/// are recognized by their `data-sheet` attribute.
+fn sheet_element(points: &[Point], k: usize, attrs: &str) -> String {
+    let points: Vec<String> = points.iter().map(|pt| format!("{},{}", pt.x, pt.y)).collect();
+    format!(
+        "<polygon points=\"{}\" fill=\"none\" stroke=\"blue\" data-sheet=\"{}\"{}/>\n",
+        points.join(" "),
+        k,
+        attrs
+    )
+}
+
+/// SVG `element` cutting along `points` drawn in `stroke`, or a path when
+/// arcs are fitted with tolerance `fit`. Empty if no arcs can be fitted.
+fn cut_element(points: &[Point], closed: bool, element: &str, fit: Option<f64>, stroke: &str, attrs: &str) -> String {
+    if let Some(tol) = fit {
+        return match fit_arcs(points, closed, tol) {
+            Some(contour) => format!(
+                "<path d=\"{}\" fill=\"none\" stroke=\"{}\"{}/>\n",
+                contour.to_path_data(),
+                stroke,
+                attrs
+            ),
+            None => String::new(),
+        };
+    }
+    let points: Vec<String> = points.iter().map(|pt| format!("{},{}", pt.x, pt.y)).collect();
+    format!(
+        "<{} points=\"{}\" fill=\"none\" stroke=\"{}\"{}/>\n",
+        element,
+        points.join(" "),
+        stroke,
+        attrs
+    )
+}
+
+/// Insert `ind` into the sorted `hall_of_fame` unless an individual with the
+/// same genes is already in it, keeping the `k` best.
+fn remember(hall_of_fame: &mut Vec<Individual>, ind: &Individual, k: usize) {
+    if !ind.fitness.is_finite()
+        || hall_of_fame
+            .iter()
+            .any(|h| h.placement == ind.placement && h.rotation == ind.rotation && h.sheet_type == ind.sheet_type)
+    {
+        return;
+    }
+    let pos = hall_of_fame.partition_point(|h| h.fitness <= ind.fitness);
+    if pos < k {
+        hall_of_fame.insert(pos, ind.clone());
+        hall_of_fame.truncate(k);
+    }
+}
+
+/// Outcome of an optimizer run.
+pub struct Run {
+    pub best: Individual,
+    pub stats: Vec<GenerationStats>,
+    /// The best distinct individuals of the run, best first
+    pub hall_of_fame: Vec<Individual>,
+}
+
+/// Evolve `restarts` independent populations for `generations` generations
+/// each, in parallel, all of them ending once one reaches the target
+/// utilization, and return the run that ended with the best individual
+/// together with the `top_k` best individuals of all runs. `on_improve` is
+/// called from the run that found an individual better than all runs found
+/// before.
+pub fn evolve_restarts<'a, F>(
+    parts: &'a [Part],
+    bin: &'a Polygon,
+    config: &NestingConfig,
+    generations: usize,
+    restarts: usize,
+    top_k: usize,
+    on_improve: F,
+) -> Result<Run>
+where
+    F: Fn(&mut GeneticAlgorithm<'a>, &Individual) + Sync,
+{
+    let best_fitness = std::sync::Mutex::new(f64::INFINITY);
+    let stop = Arc::new(AtomicBool::new(false));
+    let runs: Vec<Result<Run>> = (0..restarts.max(1))
+        .into_par_iter()
+        .map(|run| {
+            // runs of a seeded configuration differ but stay reproducible
+            let mut config = config.clone();
+            config.seed = config.seed.map(|seed| seed.wrapping_add(run as u64));
+            let mut ga = GeneticAlgorithm::new(parts, bin, config)?
+                .keep_top(top_k)
+                .sharing_stop(stop.clone());
+            ga.evolve_with(generations, |ga, best| {
+                let mut fitness = best_fitness.lock().unwrap();
+                if best.fitness < *fitness {
+                    *fitness = best.fitness;
+                    on_improve(ga, best);
+                }
+            });
+            let best = ga
+                .best()
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("no population available to evaluate"))?;
+            Ok(Run {
+                best,
+                stats: ga.stats,
+                hall_of_fame: ga.hall_of_fame,
+            })
+        })
+        .collect();
+    let mut winner: Option<Run> = None;
+    let mut hall_of_fame = Vec::new();
+    for run in runs {
+        let run = run?;
+        for ind in &run.hall_of_fame {
+            remember(&mut hall_of_fame, ind, top_k.max(1));
+        }
+        if winner.as_ref().is_none_or(|w| run.best.fitness < w.best.fitness) {
+            winner = Some(run);
+        }
+    }
+    let mut winner = winner.ok_or_else(|| anyhow::anyhow!("no run finished"))?;
+    winner.hall_of_fame = hall_of_fame;
+    Ok(winner)
+}
+
+/// Move the parts of every sheet toward `direction`. The placer packs toward
+/// the top left corner, so the parts are shifted as a whole, which keeps
+/// their distances and the fitness of the layout, but never left of `start`.
+/// `sheets` are the sheets in layout coordinates and `bounds` those of the
+/// rotated parts, parallel to `placements`.
+fn pack_toward(
+    direction: PackDirection,
+    sheets: &[Bounds],
+    start: f64,
+    placements: &mut [Placement],
+    bounds: &[Option<Bounds>],
+) {
+    if matches!(direction, PackDirection::Left | PackDirection::Top) {
+        return;
+    }
+    // extent of the parts of each sheet, in sheet coordinates
+    let mut extents: Vec<Option<(f64, f64, f64, f64)>> = Vec::new();
+    for (p, b) in placements.iter().zip(bounds) {
+        let Some(b) = b else {
+            continue;
+        };
+        let index = p.sheet;
+        if extents.len() <= index {
+            extents.resize(index + 1, None);
+        }
+        let top = p.y - sheets[index].y + b.y;
+        let (x0, y0, x1, y1) = (p.x + b.x, top, p.x + b.x + b.width, top + b.height);
+        extents[index] = Some(match extents[index] {
+            Some((a0, b0, a1, b1)) => (a0.min(x0), b0.min(y0), a1.max(x1), b1.max(y1)),
+            None => (x0, y0, x1, y1),
+        });
+    }
+    for p in placements.iter_mut() {
+        let Some(Some((x0, y0, x1, y1))) = extents.get(p.sheet).copied() else {
+            continue;
+        };
+        let sheet = sheets[p.sheet];
+        let (dx, dy) = match direction {
+            PackDirection::Right => (sheet.width - x1, 0.0),
+            PackDirection::Bottom => (0.0, sheet.height - y1),
+            PackDirection::CenterOut => ((sheet.width - x1 - x0) / 2.0, (sheet.height - y1 - y0) / 2.0),
+            PackDirection::Left | PackDirection::Top => (0.0, 0.0),
+        };
+        // never push parts over the edge they are at or into the strip
+        // kept clear at the start of the sheet
+        p.x += dx.max(start - x0);
+        p.y += dy.max(-y0);
+    }
+}
+
+/// Sheets a layout may use: the bin and, with `rotate_sheets`, the bin
+/// turned by 90° unless it is square, the bin first.
+fn sheet_orientations(bin: Bounds, config: &NestingConfig) -> Vec<Bounds> {
+    let mut sheets = vec![bin];
+    if config.rotate_sheets && bin.width != bin.height {
+        sheets.push(Bounds {
+            width: bin.height,
+            height: bin.width,
+            ..bin
+        });
+    }
+    sheets
 }
 
-fn evaluate_static(
+/// Fitness of `ind`, laid out on sheets of the orientations `config` allows.
+fn evaluate(
     ind: &Individual,
     parts: &[Part],
     bin_bounds: Bounds,
-    config: GAConfig,
-    nfp_cache: &mut NfpCache,
+    config: &NestingConfig,
+    nfp_cache: &NfpCache,
+    scratch: &mut Scratch,
 ) -> f64 {
-    // filter out parts that cannot possibly fit inside the bin
-    let mut placement = Vec::new();
-    let mut rotation = Vec::new();
-    let mut unplaceable = 0usize;
-    for (&idx, &angle) in ind.placement.iter().zip(&ind.rotation) {
-        let part = &parts[idx];
-        let rotated = part.rotated(angle);
-        match get_polygons_bounds(&rotated) {
-            Some(b) if b.width <= bin_bounds.width && b.height <= bin_bounds.height => {
-                placement.push(idx);
-                rotation.push(angle);
-            }
-            _ => unplaceable += 1,
-        }
-    }
-
-    let filtered = Individual {
-        placement,
-        rotation,
-        fitness: 0.0,
-    };
+    let _span = profile::span(Stage::Layout);
+    // filter out parts that cannot possibly fit inside a sheet of their type
+    let sheets = sheet_orientations(bin_bounds, config);
+    let (filtered, unplaceable) = scratch.fits.placeable(parts, ind, config);
 
-    let (height, placed) = layout(&filtered, parts, bin_bounds, config, nfp_cache);
-    if !height.is_finite() {
+    let Some((stack, placed)) = layout(&filtered, parts, &sheets, config, nfp_cache, scratch) else {
         return f64::INFINITY;
-    }
+    };
 
     // compute width used in each bin
     use std::collections::HashMap;
     let mut bin_width: HashMap<usize, f64> = HashMap::new();
-    for p in &placed {
-        let part = &parts[p.idx];
-        if let Some(b) = get_polygons_bounds(&part.rotated(p.angle)) {
-            let bin_idx = (p.y / bin_bounds.height).floor() as usize;
-            let w = p.x + b.width;
+    for (p, rings) in placed.iter().zip(&scratch.placed) {
+        if let Some(b) = get_rings_bounds(rings) {
+            let mut w = p.x + b.width;
+            if config.wrap_x {
+                w = w.min(stack[p.sheet].width);
+            }
             bin_width
-                .entry(bin_idx)
+                .entry(p.sheet)
                 .and_modify(|v| {
                     if w > *v {
                         *v = w;
@@ -309,209 +1501,485 @@ fn evaluate_static(
         }
     }
 
+    let weights = &config.fitness_weights;
     let bin_area = bin_bounds.width * bin_bounds.height;
-    let mut fitness = bin_width.len() as f64;
+    let mut fitness = weights.sheet * bin_width.len() as f64;
     for width in bin_width.values() {
-        fitness += width / bin_area;
+        fitness += weights.width * width / bin_area;
+    }
+    fitness += weights.unplaced * unplaceable as f64;
+    if weights.contact > 0.0 {
+        let share = contact_share(&placed, scratch, config.spacing + scratch.contact);
+        fitness += weights.contact * (1.0 - share);
     }
-    fitness += 2.0 * unplaceable as f64;
     fitness
 }
 
+/// Edges of `ring` placed at `p`.
+fn segments<'a>(p: &'a Placement, ring: &'a [Point]) -> impl Iterator<Item = (Point, Point)> + 'a {
+    let moved = move |q: Point| Point {
+        x: q.x + p.x,
+        y: q.y + p.y,
+    };
+    let n = ring.len();
+    (0..n).map(move |i| (moved(ring[i]), moved(ring[(i + 1) % n])))
+}
+
+/// Share of the boundary of the `placed` parts, whose rotated rings are in
+/// `scratch.placed`, that runs along another part on the same sheet no more
+/// than `gap` away, from 0 to 1.
+fn contact_share(placed: &[Placement], scratch: &Scratch, gap: f64) -> f64 {
+    let parts: Vec<_> = placed.iter().zip(&scratch.placed).zip(&scratch.placed_bounds).collect();
+    let mut perimeter = 0.0;
+    let mut shared = 0.0;
+    for (i, &((p, rings), bounds)) in parts.iter().enumerate() {
+        for ring in rings {
+            perimeter += segments(p, ring).map(|(a, b)| (b.x - a.x).hypot(b.y - a.y)).sum::<f64>();
+        }
+        let Some(bounds) = bounds.map(|b| b.translated(p.x, p.y)) else {
+            continue;
+        };
+        for &((q, others), other_bounds) in &parts[i + 1..] {
+            let near = other_bounds.is_some_and(|b| b.translated(q.x, q.y).overlaps(&bounds, gap));
+            if !near || q.sheet != p.sheet {
+                continue;
+            }
+            for (a0, a1) in rings.iter().flat_map(|r| segments(p, r)) {
+                for (b0, b1) in others.iter().flat_map(|r| segments(q, r)) {
+                    // the length is shared by the boundaries of both parts
+                    shared += 2.0 * shared_length(a0, a1, b0, b1, gap);
+                }
+            }
+        }
+    }
+    if perimeter > 0.0 { (shared / perimeter).min(1.0) } else { 0.0 }
+}
+
+/// Whether a part can be placed at all, found before a run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Feasibility {
+    /// Index of the part in the parts of the run
+    pub index: usize,
+    pub id: InstanceId,
+    /// Angles at which the part fits an empty sheet: those of the allowed
+    /// rotations or, with [`AngleFallback::Continuous`] and none of them
+    /// fitting, the whole degrees that do
+    pub angles: Vec<f64>,
+    /// Why the part cannot be placed, if no angle fits
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Whether each of `parts` fits an empty sheet of `bin` as `config` allows.
+pub fn feasibility(parts: &[Part], bin: &Polygon, config: &NestingConfig) -> Result<Vec<Feasibility>> {
+    let bin_bounds =
+        get_polygon_bounds(&bin.points).ok_or_else(|| anyhow::anyhow!("failed to compute bin bounds"))?;
+    Ok(feasibility_in(parts, &FitMatrix::new(parts, bin_bounds, config), config))
+}
+
+fn feasibility_in(parts: &[Part], matrix: &FitMatrix, config: &NestingConfig) -> Vec<Feasibility> {
+    parts
+        .iter()
+        .enumerate()
+        .map(|(index, part)| {
+            let mut angles: Vec<f64> = (0..matrix.rotations.len())
+                .filter(|&r| matrix.fits_any(index, r))
+                .map(|r| matrix.rotations[r])
+                .collect();
+            if angles.is_empty() && config.angle_fallback == AngleFallback::Continuous {
+                let fits = |angle: f64| {
+                    part.bounds_rotated(angle)
+                        .is_some_and(|b| matrix.sheets.iter().any(|&s| fits_sheet(part, b, s, config)))
+                };
+                angles = (0..360).map(f64::from).filter(|&a| fits(a)).collect();
+            }
+            let reason = angles.is_empty().then(|| {
+                if let Some(reason) = part.degenerate() {
+                    reason.to_string()
+                } else if !config.sheet_limits.admit(&SheetTotals::default(), part) {
+                    "it exceeds the limits of a sheet on its own".to_string()
+                } else if let Some(b) = part.bounds_rotated(0.0) {
+                    format!(
+                        "at {} x {} it is larger than the {} x {} bin at every allowed rotation",
+                        b.width, b.height, matrix.sheets[0].width, matrix.sheets[0].height
+                    )
+                } else {
+                    "it has no outline".to_string()
+                }
+            });
+            Feasibility {
+                index,
+                id: part.id.clone(),
+                angles,
+                reason,
+            }
+        })
+        .collect()
+}
+
+/// Whether `part`, with the rotated bounds `b`, fits on an empty sheet of
+/// `bin_bounds` within the limits of `config`. Degenerate parts fit nowhere,
+/// so the placers never see them. Rotated bounds carry rounding errors, so
+/// parts exactly as large as the sheet fit within the contact tolerance.
+fn fits_sheet(part: &Part, b: Bounds, bin_bounds: Bounds, config: &NestingConfig) -> bool {
+    let tol = config.tolerances.contact;
+    part.degenerate().is_none()
+        && b.width <= bin_bounds.width + tol
+        && b.height <= bin_bounds.height + tol
+        && config.sheet_limits.admit(&SheetTotals::default(), part)
+}
+
+/// Horizontal offsets at which placed parts have to be tested for collisions.
+/// In wrap mode the copies one bin width to either side are checked as well.
+fn wrap_offsets(config: &NestingConfig, bin_width: f64) -> Vec<f64> {
+    if config.wrap_x {
+        vec![0.0, -bin_width, bin_width]
+    } else {
+        vec![0.0]
+    }
+}
+
+/// Returns true if `candidate`, whose rotated rings are in
+/// `scratch.candidate`, overlaps any of the parts already in `placement`,
+/// shifted by each of `offsets`.
+///
+/// Pairs whose bounding boxes, grown by `spacing`, are apart are skipped
+/// before any NFP or polygon intersection test. A rasterized NFP only rules
+/// out positions overlapping for sure, so the position that passes it is
+/// still checked exactly, as are positions within a hole of the other part.
+fn collides(
+    placement: &[Placement],
+    scratch: &Scratch,
+    candidate: &Placement,
+    offsets: &[f64],
+    spacing: f64,
+    nfp_cache: &NfpCache,
+) -> bool {
+    let rotated = &scratch.candidate;
+    let (Some(cand_bounds), Some(outline)) = (scratch.candidate_bounds, rotated.first()) else {
+        return false;
+    };
+    let cand_bounds = cand_bounds.translated(candidate.x, candidate.y);
+    let others = placement
+        .iter()
+        .zip(&scratch.placed)
+        .zip(&scratch.placed_bounds);
+    for ((p, other_rot), other_bounds) in others {
+        // the largest outline of each part comes first
+        let Some((other_outline, other_holes)) = other_rot.split_first() else {
+            continue;
+        };
+        let near = |dx: f64| {
+            other_bounds.is_some_and(|b| b.translated(p.x + dx, p.y).overlaps(&cand_bounds, spacing))
+        };
+        if !offsets.iter().any(|&dx| near(dx)) {
+            continue;
+        }
+        let nfp = nfp_cache.get_or_generate(
+            Orientation {
+                part: p.idx,
+                angle: p.angle,
+                flipped: false,
+            },
+            Orientation {
+                part: candidate.idx,
+                angle: candidate.angle,
+                flipped: false,
+            },
+            0.0,
+            other_outline,
+            outline,
+        );
+        for &dx in offsets {
+            if !near(dx) {
+                continue;
+            }
+            let px = p.x + dx;
+            // the NFP is of the outline alone and covers the holes of the
+            // other part, so a candidate that may lie in one is left to the
+            // exact test
+            let in_hole = other_holes
+                .iter()
+                .any(|ring| get_polygon_bounds(ring).is_some_and(|h| h.translated(px, p.y).contains(&cand_bounds)));
+            // positions on the NFP boundary are the candidate touching the
+            // other part, which the sliding placer produces on purpose; the
+            // NFP holds positions of the first vertex of the candidate
+            let first = outline.first().copied().unwrap_or_default();
+            let (x, y) = (candidate.x - px + first.x, candidate.y - p.y + first.y);
+            if !in_hole && nfp.contains_by(x, y, scratch.contact) {
+                return true;
+            }
+            if parts_intersect(other_rot, rotated, px, p.y, candidate.x, candidate.y, scratch.contact) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Score of placing the part whose rotated rings are in `scratch.candidate`
+/// at `candidate` on sheet `sheet`, at `sheet_bounds` in layout coordinates,
+/// next to the parts in `placement`, by the measure of
+/// [`position_score`](NestingConfig::position_score). Lower is better.
+fn position_score(
+    placement: &[Placement],
+    scratch: &Scratch,
+    candidate: &Placement,
+    sheet: usize,
+    sheet_bounds: Bounds,
+    config: &NestingConfig,
+) -> f64 {
+    let Some(bounds) = scratch.candidate_bounds.map(|b| b.translated(candidate.x, candidate.y)) else {
+        return 0.0;
+    };
+    let sheet_y = sheet_bounds.y;
+    let on_sheet = || {
+        placement
+            .iter()
+            .zip(&scratch.placed)
+            .zip(&scratch.placed_bounds)
+            .filter(move |((p, _), _)| p.sheet == sheet)
+    };
+    match config.position_score {
+        PositionScore::First | PositionScore::LowestY => 0.0,
+        PositionScore::Hull => {
+            let mut points: Vec<(f64, f64)> = Vec::new();
+            for ((p, rings), _) in on_sheet() {
+                points.extend(rings.iter().take(1).flatten().map(|q| (q.x + p.x, q.y + p.y)));
+            }
+            let outline = scratch.candidate.iter().take(1).flatten();
+            points.extend(outline.map(|q| (q.x + candidate.x, q.y + candidate.y)));
+            geo::MultiPoint::from(points).convex_hull().unsigned_area()
+        }
+        PositionScore::Contact => {
+            let neighbours = on_sheet().filter_map(|((p, _), b)| b.map(|b| b.translated(p.x, p.y)));
+            -contact_length(bounds, sheet_bounds, neighbours, config.spacing + scratch.contact)
+        }
+        PositionScore::Gravity => {
+            let cx = bounds.x + bounds.width / 2.0 - config.gravity_point.x;
+            let cy = bounds.y + bounds.height / 2.0 - sheet_y - config.gravity_point.y;
+            cx.hypot(cy)
+        }
+    }
+}
+
+/// Length of the sides of `b` lying along the edges of `sheet` or within
+/// `gap` of the bounds of one of `neighbours`.
+fn contact_length(b: Bounds, sheet: Bounds, neighbours: impl Iterator<Item = Bounds>, gap: f64) -> f64 {
+    let near = |u: f64, v: f64| (u - v).abs() <= gap;
+    let mut length = 0.0;
+    if near(b.x, sheet.x) {
+        length += b.height;
+    }
+    if near(b.x + b.width, sheet.x + sheet.width) {
+        length += b.height;
+    }
+    if near(b.y, sheet.y) {
+        length += b.width;
+    }
+    if near(b.y + b.height, sheet.y + sheet.height) {
+        length += b.width;
+    }
+    length + neighbours.map(|n| b.touching(&n, gap)).sum::<f64>()
+}
+
+/// Rank of a position: its sheet, score, `y` and `x`, the lowest best
+type PositionKey = (usize, f64, f64, f64);
+
+/// Lay out `ind` on sheets of the types in `sheets`, the bin first. Every
+/// part goes on a sheet of the type its gene names, a new one if none of
+/// those open takes it. Returns the sheets in layout coordinates with the
+/// placements, or `None` if a part fits no empty sheet of its type.
 fn layout(
     ind: &Individual,
     parts: &[Part],
-    bin_bounds: Bounds,
-    config: GAConfig,
-    nfp_cache: &mut NfpCache,
-) -> (f64, Vec<Placement>) {
-    let bin_polygon = vec![
-        Point { x: 0.0, y: 0.0 },
-        Point {
-            x: bin_bounds.width,
-            y: 0.0,
-        },
-        Point {
-            x: bin_bounds.width,
-            y: bin_bounds.height,
-        },
-        Point {
-            x: 0.0,
-            y: bin_bounds.height,
-        },
-    ];
+    sheets: &[Bounds],
+    config: &NestingConfig,
+    nfp_cache: &NfpCache,
+    scratch: &mut Scratch,
+) -> Option<(Vec<Bounds>, Vec<Placement>)> {
+    let offsets = wrap_offsets(config, sheets[0].width);
+    let tol = config.tolerances.contact;
+    let fits = |sheet: &Bounds, b: &Bounds| b.width <= sheet.width + tol && b.height <= sheet.height + tol;
+    // a sheet of type `kind` at `top`
+    let open = |top: f64, kind: &Bounds| Bounds {
+        x: 0.0,
+        y: top,
+        width: kind.width,
+        height: kind.height,
+    };
+    let same = |sheet: &Bounds, kind: &Bounds| sheet.width == kind.width && sheet.height == kind.height;
+    // the sheets opened so far, in layout coordinates
+    let mut stack = vec![Bounds {
+        x: 0.0,
+        y: 0.0,
+        ..sheets[0]
+    }];
+
+    let limits = &config.sheet_limits;
+    // what every sheet carries so far
+    let mut totals = vec![SheetTotals::default()];
 
     if !config.explore_concave {
         let mut x = 0.0;
-        let mut y = 0.0;
-        let mut bins = 1;
         let mut placement: Vec<Placement> = Vec::new();
-        for (&idx, &angle) in ind.placement.iter().zip(&ind.rotation) {
-            let part = &parts[idx];
-            let rotated = part.rotated(angle);
-            let b = match get_polygons_bounds(&rotated) {
+        let genes = ind.placement.iter().zip(&ind.rotation).zip(&ind.sheet_type);
+        for ((&idx, &angle), &t) in genes {
+            let b = match scratch.rotate(&parts[idx], angle) {
                 Some(v) => v,
                 None => continue,
             };
 
-            if b.width > bin_bounds.width || b.height > bin_bounds.height {
-                return (f64::INFINITY, Vec::new());
+            let kind = sheets[t];
+            if !fits(&kind, &b) {
+                return None;
             }
-
-            if x + b.width >= bin_bounds.width {
-                bins += 1;
-                x = 0.0;
-                y += bin_bounds.height;
+            let mut k = stack.len() - 1;
+            // an empty sheet takes the type of the part
+            if totals[k].parts == 0 {
+                stack[k] = open(stack[k].y, &kind);
             }
 
-            // bin nfp for usage (computed but not used directly)
-            let _bin_nfp = nfp::inner_fit_polygon(&bin_polygon, &rotated[0].points, config.spacing);
-
-            // check against already placed parts
-            for p in &placement {
-                let other_rot = parts[p.idx].rotated(p.angle);
-                let orient_other = polygon_area(&other_rot[0].points).signum();
-                let nfp = nfp_cache.get_or_generate(
-                    p.idx,
-                    idx,
-                    p.angle,
-                    angle,
-                    &other_rot[0].points,
-                    &rotated[0].points,
-                );
-                if nfp.len() >= 3 && point_in_polygon(&nfp, x - p.x, y - p.y) {
-                    return (f64::INFINITY, Vec::new());
+            let full = !limits.admit(&totals[k], &parts[idx]);
+            if full || x + b.width >= stack[k].width || !same(&stack[k], &kind) {
+                // in wrap mode a part may cross the seam as long as its
+                // wrapped end does not run into the start of the row
+                let candidate = Placement { idx, sheet: k, angle, x, y: stack[k].y };
+                let crosses_seam = !full
+                    && same(&stack[k], &kind)
+                    && config.wrap_x
+                    && x < stack[k].width
+                    && !collides(
+                        &placement,
+                        scratch,
+                        &candidate,
+                        &offsets,
+                        config.spacing,
+                        nfp_cache,
+                    );
+                if !crosses_seam {
+                    stack.push(open(stack[k].y + stack[k].height, &kind));
+                    k += 1;
+                    x = 0.0;
+                    totals.push(SheetTotals::default());
                 }
-                for op in &other_rot {
-                    if polygon_area(&op.points).signum() != orient_other {
-                        continue; // hole
-                    }
-                    for rp in &rotated {
-                        if polygons_intersect(
-                            &op.points,
-                            &rp.points,
-                            p.x,
-                            p.y,
-                            x,
-                            y,
-                        ) {
-                            let mut in_hole = false;
-                            for hole in &other_rot {
-                                if polygon_area(&hole.points).signum() == orient_other {
-                                    continue;
-                                }
-                                if polygon_contains_polygon(&hole.points, &rp.points, p.x, p.y, x, y) {
-                                    in_hole = true;
-                                    break;
-                                }
-                            }
-                            if !in_hole {
-                                return (f64::INFINITY, Vec::new());
-                            }
-                        }
+            }
+
+            // step over the holes of the bin, onto a new sheet if need be
+            let mut fresh = x == 0.0;
+            while let Some(past) = scratch.bin_hole_at(
+                &Placement { idx, sheet: k, angle, x, y: stack[k].y },
+                stack[k].y,
+                &offsets,
+            ) {
+                x = past + config.spacing;
+                if x + b.width >= stack[k].width {
+                    if fresh {
+                        return None;
                     }
+                    stack.push(open(stack[k].y + stack[k].height, &kind));
+                    k += 1;
+                    x = 0.0;
+                    totals.push(SheetTotals::default());
+                    fresh = true;
                 }
             }
 
-            placement.push(Placement { idx, angle, x, y });
+            let candidate = Placement { idx, sheet: k, angle, x, y: stack[k].y };
+            if collides(
+                &placement,
+                scratch,
+                &candidate,
+                &offsets,
+                config.spacing,
+                nfp_cache,
+            ) {
+                return None;
+            }
+
+            scratch.place(placement.len());
+            placement.push(candidate);
+            totals[k].add(parts[idx].weight(), parts[idx].area());
             x += b.width + config.spacing;
         }
-        (bin_bounds.height * bins as f64, placement)
+        Some((stack, placement))
     } else {
-        let mut bins = 1usize;
-        let mut free = vec![FreeRect {
-            x: 0.0,
-            y: 0.0,
-            width: bin_bounds.width,
-            height: bin_bounds.height,
-        }];
+        let mut free = scratch.sheet_free_rects(stack[0], 0);
+        let mut virtual_bins: Vec<VirtualBin> = Vec::new();
         let mut placement: Vec<Placement> = Vec::new();
-        for (&idx, &angle) in ind.placement.iter().zip(&ind.rotation) {
-            let part = &parts[idx];
-            let rotated = part.rotated(angle);
-            let b = match get_polygons_bounds(&rotated) {
+        let genes = ind.placement.iter().zip(&ind.rotation).zip(&ind.sheet_type);
+        for ((&idx, &angle), &t) in genes {
+            let b = match scratch.rotate(&parts[idx], angle) {
                 Some(v) => v,
                 None => continue,
             };
 
-            if b.width > bin_bounds.width || b.height > bin_bounds.height {
-                return (f64::INFINITY, Vec::new());
+            let kind = sheets[t];
+            if !fits(&kind, &b) {
+                return None;
             }
-
+            let mut fresh = false;
             loop {
-                let mut placed = false;
-                for i in 0..free.len() {
-                    let rect = free[i];
-                    if b.width <= rect.width && b.height <= rect.height {
-                        let x = rect.x;
-                        let y = rect.y;
-
-                        // compute bin nfp (not used directly)
-                        let _ = nfp::inner_fit_polygon(
-                            &bin_polygon,
-                            &rotated[0].points,
+                // the corners of the positions inside the holes of placed
+                // parts come first, lowest then leftmost, then the corners of
+                // the free rects with the index of their rect
+                let in_holes = virtual_bins
+                    .iter()
+                    .filter(|v| same(&stack[v.sheet], &kind))
+                    .filter(|v| b.width <= v.bounds.width && b.height <= v.bounds.height)
+                    .filter(|v| limits.admit(&totals[v.sheet], &parts[idx]))
+                    .filter_map(|v| Some((v, scratch.candidate.first()?)))
+                    .flat_map(|(v, outline)| {
+                        let ifp = inner_fit_polygon(&v.ring, outline, config.spacing);
+                        let mut corners: Vec<Point> = ifp.into_iter().flatten().collect();
+                        corners.sort_by(|p, q| p.y.total_cmp(&q.y).then(p.x.total_cmp(&q.x)));
+                        corners.into_iter().map(|t| (None, v.sheet, t.x, t.y))
+                    });
+                let in_free = free
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, rect)| {
+                        // free space touching the seam continues on the other side
+                        let at_seam = config.wrap_x && (rect.x + rect.width - stack[0].width).abs() < 1e-9;
+                        same(&stack[rect.sheet], &kind)
+                            && (b.width <= rect.width || at_seam)
+                            && b.height <= rect.height
+                            && limits.admit(&totals[rect.sheet], &parts[idx])
+                    })
+                    .map(|(i, rect)| (Some(i), rect.sheet, rect.x, rect.y));
+                // the position scoring best, on the earliest sheet and the
+                // lowest, then leftmost, among equal scores
+                let mut best: Option<(Option<usize>, PositionKey)> = None;
+                for (spot, sheet, x, y) in in_holes.chain(in_free) {
+                    let candidate = Placement { idx, sheet, angle, x, y };
+                    let collide = scratch.bin_hole_at(&candidate, stack[sheet].y, &offsets).is_some()
+                        || collides(
+                            &placement,
+                            scratch,
+                            &candidate,
+                            &offsets,
                             config.spacing,
+                            nfp_cache,
                         );
-
-                        let mut collide = false;
-                        for p in &placement {
-                            let other_rot = parts[p.idx].rotated(p.angle);
-                            let orient_other = polygon_area(&other_rot[0].points).signum();
-                            let nfp = nfp_cache.get_or_generate(
-                                p.idx,
-                                idx,
-                                p.angle,
-                                angle,
-                                &other_rot[0].points,
-                                &rotated[0].points,
-                            );
-                            if nfp.len() >= 3 && point_in_polygon(&nfp, x - p.x, y - p.y) {
-                                collide = true;
-                                break;
-                            }
-                            for op in &other_rot {
-                                if polygon_area(&op.points).signum() != orient_other {
-                                    continue;
-                                }
-                                for rp in &rotated {
-                                    if polygons_intersect(
-                                        &op.points,
-                                        &rp.points,
-                                        p.x,
-                                        p.y,
-                                        x,
-                                        y,
-                                    ) {
-                                        let mut in_hole = false;
-                                        for hole in &other_rot {
-                                            if polygon_area(&hole.points).signum() == orient_other {
-                                                continue;
-                                            }
-                                            if polygon_contains_polygon(&hole.points, &rp.points, p.x, p.y, x, y) {
-                                                in_hole = true;
-                                                break;
-                                            }
-                                        }
-                                        if !in_hole {
-                                            collide = true;
-                                            break;
-                                        }
-                                    }
-                                }
-                                if collide {
-                                    break;
-                                }
-                            }
-                            if collide {
-                                break;
-                            }
-                        }
-                        if collide {
-                            continue;
-                        }
-
-                        placement.push(Placement { idx, angle, x, y });
-                        free.remove(i);
+                    if collide {
+                        continue;
+                    }
+                    if config.position_score == PositionScore::First {
+                        best = Some((spot, (sheet, 0.0, y, x)));
+                        break;
+                    }
+                    let score = position_score(&placement, scratch, &candidate, sheet, stack[sheet], config);
+                    let key = (sheet, score, y, x);
+                    if best.is_none_or(|(_, best)| key < best) {
+                        best = Some((spot, key));
+                    }
+                }
+                if let Some((spot, (sheet, _, y, x))) = best {
+                    placement.push(Placement { idx, sheet, angle, x, y });
+                    totals[sheet].add(parts[idx].weight(), parts[idx].area());
+                    // a part in a hole leaves the free rects as they are
+                    if let Some(i) = spot {
+                        let rect = free.remove(i);
                         let right_w = rect.width - b.width - config.spacing;
                         if right_w > 0.0 {
                             free.push(FreeRect {
@@ -519,6 +1987,7 @@ fn layout(
                                 y,
                                 width: right_w,
                                 height: b.height,
+                                sheet,
                             });
                         }
                         let bottom_h = rect.height - b.height - config.spacing;
@@ -528,44 +1997,928 @@ fn layout(
                                 y: y + b.height + config.spacing,
                                 width: rect.width,
                                 height: bottom_h,
+                                sheet,
                             });
                         }
-                        if config.use_holes {
-                            let orient = polygon_area(&rotated[0].points).signum();
-                            for poly in rotated.iter().skip(1) {
-                                let area = polygon_area(&poly.points);
-                                if orient != 0.0 && area.signum() != orient {
-                                    if let Some(hb) = get_polygon_bounds(&poly.points) {
-                                        free.insert(
-                                            0,
-                                            FreeRect {
-                                                x: x + hb.x,
-                                                y: y + hb.y,
-                                                width: hb.width,
-                                                height: hb.height,
-                                            },
-                                        );
-                                    }
-                                }
+                    }
+                    if config.use_holes {
+                        // the rings are in the order of the contours
+                        let holes = parts[idx].shapes().iter().flat_map(|s| &s.holes);
+                        for hole in holes.filter_map(|&h| scratch.candidate.get(h)) {
+                            let ring: Vec<Point> = hole
+                                .iter()
+                                .map(|q| Point { x: q.x + x, y: q.y + y })
+                                .collect();
+                            if let Some(bounds) = get_polygon_bounds(&ring) {
+                                virtual_bins.push(VirtualBin { ring, bounds, sheet });
                             }
                         }
-                        placed = true;
-                        break;
                     }
-                }
-                if placed {
+                    scratch.place(placement.len() - 1);
                     break;
                 }
-                let start_y = bin_bounds.height * bins as f64;
-                free.push(FreeRect {
-                    x: 0.0,
-                    y: start_y,
-                    width: bin_bounds.width,
-                    height: bin_bounds.height,
-                });
-                bins += 1;
+                if fresh {
+                    // not even an empty sheet takes the part
+                    return None;
+                }
+                // an empty last sheet is opened again as one of the type of
+                // the part rather than left empty
+                let last = stack.len() - 1;
+                if totals[last].parts == 0 {
+                    stack.pop();
+                    totals.pop();
+                    free.retain(|r| r.sheet != last);
+                }
+                let top = stack.last().map_or(0.0, |s| s.y + s.height);
+                stack.push(open(top, &kind));
+                free.extend(scratch.sheet_free_rects(stack[stack.len() - 1], stack.len() - 1));
+                totals.push(SheetTotals::default());
+                fresh = true;
+            }
+        }
+        Some((stack, placement))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::rect;
+    use crate::config::{FitnessWeights, SheetLimits};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn local_search_leaves_evaluated_permutations() {
+        let parts: Vec<Part> = (0..6).map(|i| Part::new(vec![rect(2.0 + i as f64, 3.0)])).collect();
+        let bin = rect(12.0, 12.0);
+        let config = NestingConfig::builder()
+            .population_size(6)
+            .local_search_iters(10)
+            .build()
+            .unwrap();
+        let mut ga = GeneticAlgorithm::new(&parts, &bin, config).unwrap();
+        ga.evaluate_population();
+        ga.generation();
+        for ind in ga.population.clone().iter().skip(1) {
+            let mut sorted = ind.placement.clone();
+            sorted.sort();
+            assert_eq!(sorted, (0..6).collect::<Vec<_>>());
+            // the local search left the fitness of its result behind
+            assert_eq!(ind.fitness, ga.fitness(ind));
+        }
+    }
+
+    #[test]
+    fn elite_individuals_survive_a_generation() {
+        let parts: Vec<Part> = (0..5).map(|i| Part::new(vec![rect(1.0 + i as f64, 2.0)])).collect();
+        let bin = rect(12.0, 12.0);
+        let config = NestingConfig::builder()
+            .population_size(6)
+            .elitism(3)
+            .mutation_rate(100)
+            .seed(Some(5))
+            .build()
+            .unwrap();
+        let mut ga = GeneticAlgorithm::new(&parts, &bin, config).unwrap();
+        ga.evaluate_population();
+        let mut before = ga.population.clone();
+        before.sort_by(|a, b| a.fitness.total_cmp(&b.fitness));
+        ga.generation();
+        for (kept, elite) in ga.population.iter().zip(&before[..3]) {
+            assert_eq!(kept.placement, elite.placement);
+            assert_eq!(kept.rotation, elite.rotation);
+        }
+    }
+
+    #[test]
+    fn hall_of_fame_keeps_best_distinct_individuals() {
+        let ind = |placement: Vec<usize>, fitness: f64| Individual {
+            rotation: vec![0.0; placement.len()],
+            sheet_type: vec![0; placement.len()],
+            placement,
+            fitness,
+        };
+        let mut hof = Vec::new();
+        remember(&mut hof, &ind(vec![0, 1], 5.0), 2);
+        remember(&mut hof, &ind(vec![0, 1], 5.0), 2);
+        remember(&mut hof, &ind(vec![1, 0], f64::INFINITY), 2);
+        remember(&mut hof, &ind(vec![1, 0], 3.0), 2);
+        remember(&mut hof, &ind(vec![0, 1, 2], 4.0), 2);
+        let fitness: Vec<f64> = hof.iter().map(|i| i.fitness).collect();
+        assert_eq!(fitness, [3.0, 4.0]);
+    }
+
+    #[test]
+    fn turned_sheet_takes_parts_the_bin_is_too_narrow_for() {
+        let parts = vec![
+            Part::new(vec![rect(1.5, 8.0)]),
+            Part::new(vec![rect(1.5, 8.0)]),
+            Part::new(vec![rect(9.0, 3.0)]),
+        ];
+        let bin = rect(10.0, 4.0);
+        for rotate_sheets in [false, true] {
+            let config = NestingConfig::builder()
+                .population_size(1)
+                .rotations(&[0.0])
+                .rotate_sheets(rotate_sheets)
+                .build()
+                .unwrap();
+            let mut ga = GeneticAlgorithm::new(&parts, &bin, config).unwrap();
+            ga.evaluate_population();
+            let best = ga.best().cloned().unwrap();
+            let layout = ga.to_layout(&best);
+            assert_eq!((layout.bin.width, layout.bin.height), (10.0, 4.0));
+            if rotate_sheets {
+                // the wide part keeps a sheet of the bin, the narrow ones
+                // share a turned sheet
+                assert_eq!(layout.placements.len(), 3);
+                assert_eq!(layout.turned.len(), 1);
+                assert_eq!((layout.sheets(), layout.height), (2, 14.0));
+                for p in &layout.placements {
+                    assert_eq!(layout.turned.contains(&p.sheet), p.idx < 2);
+                }
+                assert_eq!(ga.create_svg(&best).matches("data-sheet-rotation=\"90\"").count(), 1);
+            } else {
+                assert_eq!(layout.placements.len(), 1);
+                assert!(layout.turned.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn rotations_are_picked_by_weight() {
+        let mut metadata = BTreeMap::new();
+        metadata.insert("data-rotation-weights".to_string(), "0:1, 90:0".to_string());
+        let parts = vec![
+            Part::new(vec![rect(2.0, 1.0)]),
+            Part::new(vec![rect(2.0, 1.0)]).with_metadata(metadata),
+        ];
+        let bin = rect(10.0, 10.0);
+        let config = NestingConfig::builder()
+            .population_size(20)
+            .mutation_rate(50)
+            .rotations(&[0.0, 90.0])
+            .rotation_weights(&[0.0, 1.0])
+            .seed(Some(3))
+            .build()
+            .unwrap();
+        let ga = GeneticAlgorithm::new(&parts, &bin, config).unwrap();
+        for ind in &ga.population {
+            for (&idx, &angle) in ind.placement.iter().zip(&ind.rotation) {
+                assert_eq!(angle, if idx == 0 { 90.0 } else { 0.0 });
+            }
+        }
+    }
+
+    #[test]
+    fn fitness_uses_configured_weights() {
+        let parts = vec![Part::new(vec![rect(4.0, 4.0)]), Part::new(vec![rect(20.0, 20.0)])];
+        let bin = rect(10.0, 10.0);
+        let ind = Individual {
+            placement: vec![0, 1],
+            rotation: vec![0.0, 0.0],
+            sheet_type: vec![0, 0],
+            fitness: f64::MAX,
+        };
+        let fitness = |weights: FitnessWeights| {
+            let config = NestingConfig::builder().rotations(&[0.0]).fitness_weights(weights).build().unwrap();
+            GeneticAlgorithm::new(&parts, &bin, config).unwrap().fitness(&ind)
+        };
+        // one sheet, a sliver of its width and twice the unplaced part
+        let default = fitness(FitnessWeights::default());
+        assert!(default > 3.0 && default < 4.0);
+        let weights = FitnessWeights {
+            sheet: 3.0,
+            width: 0.0,
+            unplaced: 5.0,
+            contact: 0.0,
+        };
+        assert!((fitness(weights) - 8.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn contact_term_rewards_shared_boundary() {
+        let bin = rect(10.0, 10.0);
+        let weights = FitnessWeights {
+            sheet: 0.0,
+            width: 0.0,
+            unplaced: 0.0,
+            contact: 1.0,
+        };
+        let fitness = |parts: &[Part]| {
+            let config = NestingConfig::builder().rotations(&[0.0]).fitness_weights(weights).build().unwrap();
+            let ind = Individual {
+                placement: (0..parts.len()).collect(),
+                rotation: vec![0.0; parts.len()],
+                sheet_type: Vec::new(),
+                fitness: f64::MAX,
+            };
+            GeneticAlgorithm::new(parts, &bin, config).unwrap().fitness(&ind)
+        };
+        // side by side, each square shares one of its four sides
+        let squares = vec![Part::new(vec![rect(2.0, 2.0)]), Part::new(vec![rect(2.0, 2.0)])];
+        assert!((fitness(&squares) - 0.75).abs() < 1e-9);
+        // the short part shares its side with half the side of the tall one
+        let steps = vec![Part::new(vec![rect(2.0, 4.0)]), Part::new(vec![rect(2.0, 2.0)])];
+        assert!((fitness(&steps) - (1.0 - 4.0 / 20.0)).abs() < 1e-9);
+        assert_eq!(fitness(&squares[..1]), 1.0);
+    }
+
+    #[test]
+    fn sheet_limits_open_new_sheets() {
+        let heavy = |w: &str| {
+            let mut metadata = BTreeMap::new();
+            metadata.insert("data-weight".to_string(), w.to_string());
+            Part::new(vec![rect(2.0, 2.0)]).with_metadata(metadata)
+        };
+        let parts = vec![heavy("3"), heavy("3"), heavy("3"), heavy("9")];
+        let bin = rect(20.0, 5.0);
+        for explore_concave in [false, true] {
+            let limits = SheetLimits {
+                weight: Some(6.0),
+                ..Default::default()
+            };
+            let config = NestingConfig::builder()
+                .population_size(1)
+                .rotations(&[0.0])
+                .explore_concave(explore_concave)
+                .sheet_limits(limits)
+                .build()
+                .unwrap();
+            let mut ga = GeneticAlgorithm::new(&parts, &bin, config).unwrap();
+            ga.evaluate_population();
+            let best = ga.best().cloned().unwrap();
+            let layout = ga.to_layout(&best);
+            assert!(layout.placements.iter().all(|p| p.sheet == (p.y / 5.0).floor() as usize));
+            let report = layout.to_report(&parts);
+            // the part heavier than a sheet may carry is left out
+            assert_eq!(report.unplaced, vec![3]);
+            assert_eq!(report.sheets, 2);
+            let weights: Vec<f64> = report.sheet_totals.iter().map(|t| t.weight).collect();
+            assert_eq!(weights, vec![6.0, 3.0]);
+        }
+    }
+
+    #[test]
+    fn position_score_picks_among_free_positions() {
+        let parts = vec![Part::new(vec![rect(6.0, 4.0)]), Part::new(vec![rect(2.0, 2.0)])];
+        let bin = rect(10.0, 10.0);
+        let second = |score: PositionScore| {
+            let config = NestingConfig::builder()
+                .population_size(1)
+                .rotations(&[0.0])
+                .explore_concave(true)
+                .position_score(score)
+                .gravity_point(Point { x: 0.0, y: 10.0 })
+                .build()
+                .unwrap();
+            let mut ga = GeneticAlgorithm::new(&parts, &bin, config).unwrap();
+            ga.evaluate_population();
+            let best = ga.best().cloned().unwrap();
+            let (_, placements) = ga.placements(&best);
+            let p = placements.iter().find(|p| p.idx == 1).unwrap();
+            (p.x, p.y)
+        };
+        assert_eq!(second(PositionScore::First), (6.0, 0.0));
+        assert_eq!(second(PositionScore::LowestY), (6.0, 0.0));
+        assert_eq!(second(PositionScore::Hull), (6.0, 0.0));
+        assert_eq!(second(PositionScore::Gravity), (0.0, 4.0));
+        // beside and below the first part touch along as much, so the lower wins
+        assert_eq!(second(PositionScore::Contact), (6.0, 0.0));
+    }
+
+    #[test]
+    fn holes_of_placed_parts_take_later_parts() {
+        let hole = Polygon {
+            points: vec![
+                Point { x: 4.0, y: 1.0 },
+                Point { x: 1.0, y: 1.0 },
+                Point { x: 1.0, y: 4.0 },
+                Point { x: 4.0, y: 4.0 },
+            ],
+            ..Default::default()
+        };
+        let frame = Part::new(vec![rect(5.0, 5.0), hole]);
+        let parts = vec![frame, Part::new(vec![rect(1.0, 1.0)]), Part::new(vec![rect(1.5, 1.0)])];
+        let bin = rect(10.0, 10.0);
+        let config = NestingConfig::builder()
+            .population_size(1)
+            .rotations(&[0.0])
+            .explore_concave(true)
+            .use_holes(true)
+            .build()
+            .unwrap();
+        let mut ga = GeneticAlgorithm::new(&parts, &bin, config).unwrap();
+        let ind = Individual {
+            placement: vec![0, 1, 2],
+            rotation: vec![0.0; 3],
+            sheet_type: Vec::new(),
+            fitness: 0.0,
+        };
+        let (_, placements) = ga.placements(&ind);
+        assert_eq!(placements.len(), 3);
+        for p in &placements[1..] {
+            let b = parts[p.idx].bounds_rotated(0.0).unwrap().translated(p.x, p.y);
+            assert!(b.x >= 1.0 && b.y >= 1.0 && b.x + b.width <= 4.0 && b.y + b.height <= 4.0, "{:?}", p);
+        }
+    }
+
+    #[test]
+    fn degenerate_parts_are_left_unplaced() {
+        let contour = |points: &[(f64, f64)]| Polygon {
+            points: points.iter().map(|&(x, y)| Point { x, y }).collect(),
+            ..Default::default()
+        };
+        let parts = vec![
+            Part::new(vec![rect(2.0, 2.0)]),
+            Part::new(Vec::new()),
+            Part::new(vec![contour(&[])]),
+            Part::new(vec![contour(&[(0.0, 0.0), (3.0, 1.0)])]),
+            Part::new(vec![contour(&[(0.0, 0.0), (f64::NAN, 1.0), (1.0, 1.0)])]),
+            Part::new(vec![rect(1.0, 1.0)]),
+        ];
+        let bin = rect(10.0, 10.0);
+        let config = |fallback: AngleFallback| {
+            NestingConfig::builder()
+                .population_size(1)
+                .rotations(&[0.0, 90.0])
+                .angle_fallback(fallback)
+                .build()
+                .unwrap()
+        };
+        let found = feasibility(&parts, &bin, &config(AngleFallback::Continuous)).unwrap();
+        let reasons: Vec<Option<&str>> = found.iter().map(|f| f.reason.as_deref()).collect();
+        assert_eq!(
+            reasons,
+            [
+                None,
+                Some("it has no outline"),
+                Some("its outline has fewer than 3 points"),
+                Some("its outline has fewer than 3 points"),
+                Some("some of its coordinates are not finite"),
+                None,
+            ]
+        );
+        let err = GeneticAlgorithm::new(&parts, &bin, config(AngleFallback::Error)).err().unwrap();
+        assert!(err.to_string().contains("has no outline"), "{}", err);
+
+        let ind = Individual {
+            placement: (0..parts.len()).collect(),
+            rotation: vec![0.0; parts.len()],
+            sheet_type: Vec::new(),
+            fitness: 0.0,
+        };
+        for explore_concave in [false, true] {
+            let config = NestingConfig::builder()
+                .population_size(1)
+                .rotations(&[0.0])
+                .explore_concave(explore_concave)
+                .use_holes(true)
+                .position_score(PositionScore::Hull)
+                .build()
+                .unwrap();
+            let mut ga = GeneticAlgorithm::new(&parts, &bin, config).unwrap();
+            assert!(ga.fitness(&ind).is_finite());
+            let (_, placements) = ga.placements(&ind);
+            let placed: Vec<usize> = placements.iter().map(|p| p.idx).collect();
+            assert_eq!(placed, [0, 5]);
+        }
+    }
+
+    #[test]
+    fn parts_stay_out_of_bin_holes() {
+        let parts = vec![Part::new(vec![rect(2.0, 2.0)]), Part::new(vec![rect(2.0, 2.0)])];
+        let bin = rect(10.0, 4.0);
+        let hole = vec![
+            Point { x: 2.5, y: 0.5 },
+            Point { x: 3.5, y: 0.5 },
+            Point { x: 3.5, y: 3.5 },
+            Point { x: 2.5, y: 3.5 },
+        ];
+        for explore_concave in [false, true] {
+            let config = NestingConfig::builder()
+                .population_size(1)
+                .rotations(&[0.0])
+                .explore_concave(explore_concave)
+                .bin_holes(vec![hole.clone()])
+                .build()
+                .unwrap();
+            let mut ga = GeneticAlgorithm::new(&parts, &bin, config).unwrap();
+            ga.evaluate_population();
+            let best = ga.best().cloned().unwrap();
+            let (height, placements) = ga.placements(&best);
+            assert_eq!(height, 4.0);
+            assert_eq!(placements.len(), 2);
+            for p in &placements {
+                assert!(p.x + 2.0 <= 2.5 || p.x >= 3.5, "{:?} overlaps the hole", p);
+            }
+            assert!(ga.create_svg(&best).contains("points=\"2.5,0.5 3.5,0.5 3.5,3.5 2.5,3.5\""));
+        }
+    }
+
+    #[test]
+    fn svg_keeps_the_coordinates_of_the_bin() {
+        let parts = vec![Part::new(vec![rect(2.0, 2.0)]), Part::new(vec![rect(2.0, 2.0)])];
+        // an L-shaped bin away from the origin, too narrow for both parts
+        let bin = Polygon {
+            points: vec![
+                Point { x: 100.0, y: 50.0 },
+                Point { x: 103.0, y: 50.0 },
+                Point { x: 103.0, y: 51.0 },
+                Point { x: 102.0, y: 52.0 },
+                Point { x: 100.0, y: 52.0 },
+            ],
+            ..Default::default()
+        };
+        let config = NestingConfig::builder().population_size(1).rotations(&[0.0]).build().unwrap();
+        let mut ga = GeneticAlgorithm::new(&parts, &bin, config).unwrap();
+        ga.evaluate_population();
+        let best = ga.best().cloned().unwrap();
+        let svg = ga.create_svg(&best);
+        assert!(svg.contains("viewBox=\"100 50 3 4\""), "{}", svg);
+        assert!(svg.contains("points=\"100,50 102,50 102,52 100,52\""));
+        assert!(svg.contains("points=\"100,52 102,52 102,54 100,54\""));
+        assert!(svg.contains("points=\"100,50 103,50 103,51 102,52 100,52\" fill=\"none\" stroke=\"blue\" data-sheet=\"0\""));
+        assert!(svg.contains("points=\"100,52 103,52 103,53 102,54 100,54\" fill=\"none\" stroke=\"blue\" data-sheet=\"1\""));
+        assert!(!svg.contains("<rect"));
+    }
+
+    #[test]
+    fn unplaced_parts_are_drawn_beside_the_sheets() {
+        let parts = vec![Part::new(vec![rect(2.0, 2.0)]), Part::new(vec![rect(20.0, 20.0)])];
+        let bin = rect(5.0, 5.0);
+        let svg = |show: bool| {
+            let config = NestingConfig::builder()
+                .population_size(1)
+                .rotations(&[0.0])
+                .show_unplaced(show)
+                .build()
+                .unwrap();
+            let mut ga = GeneticAlgorithm::new(&parts, &bin, config).unwrap();
+            ga.evaluate_population();
+            let best = ga.best().cloned().unwrap();
+            ga.create_svg(&best)
+        };
+        assert!(!svg(false).contains("data-unplaced"));
+        let shown = svg(true);
+        assert!(shown.contains("data-unplaced=\"1\""));
+        assert!(shown.contains(">Unplaced: 1</text>"));
+        assert!(shown.contains("stroke-dasharray"));
+        // the area is not part of the layout
+        assert_eq!(crate::check::shapes_from_svg(&shown).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn part_transforms_move_the_original_geometry() {
+        let parts = vec![Part::new(vec![rect(2.0, 1.0)]), Part::new(vec![rect(2.0, 1.0)])];
+        let bin = rect(10.0, 10.0);
+        let config = NestingConfig::builder()
+            .population_size(2)
+            .rotations(&[90.0])
+            .part_transforms(true)
+            .build()
+            .unwrap();
+        let mut ga = GeneticAlgorithm::new(&parts, &bin, config).unwrap();
+        ga.evolve(1);
+        let best = ga.best().cloned().unwrap();
+        let layout = ga.to_layout(&best);
+        assert_eq!(layout.placements.len(), 2);
+        let svg = ga.create_svg(&best);
+        assert_eq!(svg.matches("rotate(90)").count(), 2);
+        assert_eq!(svg.matches("points=\"0,0 2,0 2,1 0,1\"").count(), 2);
+        // read back, the parts are where the layout put them
+        let shapes = crate::check::shapes_from_svg(&svg).unwrap();
+        let sorted = |mut v: Vec<(f64, f64, f64, f64)>| {
+            v.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+            v
+        };
+        let read = sorted(
+            shapes
+                .iter()
+                .filter_map(|s| get_polygon_bounds(&s.outline))
+                .map(|b| (b.x, b.y, b.width, b.height))
+                .collect(),
+        );
+        let placed = sorted(layout.placements.iter().map(|p| (p.x, p.y, 1.0, 2.0)).collect());
+        assert_eq!(read.len(), 2);
+        for (a, b) in read.iter().zip(&placed) {
+            let close = [(a.0, b.0), (a.1, b.1), (a.2, b.2), (a.3, b.3)].iter().all(|(u, v)| (u - v).abs() < 1e-9);
+            assert!(close, "read {:?}, placed {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn parts_are_colored_by_sheet_with_a_legend() {
+        let parts = vec![Part::new(vec![rect(4.0, 4.0)]), Part::new(vec![rect(4.0, 4.0)])];
+        let bin = rect(5.0, 5.0);
+        let config = NestingConfig::builder()
+            .population_size(1)
+            .rotations(&[0.0])
+            .color_by(Some(ColorBy::Sheet))
+            .build()
+            .unwrap();
+        let mut ga = GeneticAlgorithm::new(&parts, &bin, config).unwrap();
+        ga.evaluate_population();
+        let best = ga.best().cloned().unwrap();
+        let svg = ga.create_svg(&best);
+        for color in [crate::palette::color(0), crate::palette::color(1)] {
+            assert!(svg.contains(&format!("stroke=\"{}\"", color)), "{}", svg);
+        }
+        assert!(svg.contains(">Sheet 2</text>"));
+        assert!(svg.contains("inkscape:label=\"Legend\""));
+        assert_eq!(crate::check::shapes_from_svg(&svg).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn free_space_is_shaded_in_an_overlay() {
+        let parts = vec![Part::new(vec![rect(5.0, 10.0)])];
+        let bin = rect(10.0, 10.0);
+        let config = NestingConfig::builder()
+            .population_size(1)
+            .rotations(&[0.0])
+            .free_space_overlay(true)
+            .build()
+            .unwrap();
+        let mut ga = GeneticAlgorithm::new(&parts, &bin, config).unwrap();
+        ga.evaluate_population();
+        let best = ga.best().cloned().unwrap();
+        let svg = ga.create_svg(&best);
+        assert!(svg.contains("inkscape:label=\"Free space\""));
+        assert!(svg.contains("50.0% of sheet 1 free"));
+        assert_eq!(crate::check::shapes_from_svg(&svg).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn start_offset_keeps_a_strip_clear() {
+        let parts = vec![Part::new(vec![rect(2.0, 2.0)]), Part::new(vec![rect(2.0, 2.0)])];
+        let bin = rect(10.0, 4.0);
+        for explore_concave in [false, true] {
+            for direction in [PackDirection::Left, PackDirection::CenterOut] {
+                let config = NestingConfig::builder()
+                    .population_size(1)
+                    .rotations(&[0.0])
+                    .explore_concave(explore_concave)
+                    .pack_direction(direction)
+                    .start_offset(3.0)
+                    .build()
+                    .unwrap();
+                let mut ga = GeneticAlgorithm::new(&parts, &bin, config).unwrap();
+                ga.evaluate_population();
+                let best = ga.best().cloned().unwrap();
+                let layout = ga.to_layout(&best);
+                assert_eq!(layout.placements.len(), 2);
+                assert!(layout.placements.iter().all(|p| p.x >= 3.0), "{:?}", layout.placements);
             }
         }
-        (bin_bounds.height * bins as f64, placement)
+        let config = NestingConfig::builder().start_offset(10.0).build().unwrap();
+        assert!(GeneticAlgorithm::new(&parts, &bin, config).is_err());
+    }
+
+    #[test]
+    fn fit_matrix_covers_every_sheet_orientation() {
+        let parts = vec![Part::new(vec![rect(8.0, 3.0)]).with_rotations(&[0.0, 90.0])];
+        let bin = Bounds {
+            x: 0.0,
+            y: 0.0,
+            width: 10.0,
+            height: 4.0,
+        };
+        let turned = Bounds {
+            width: 4.0,
+            height: 10.0,
+            ..bin
+        };
+        let config = NestingConfig::builder().rotations(&[0.0, 90.0]).build().unwrap();
+        let matrix = FitMatrix::new(&parts, bin, &config);
+        assert!(matrix.fits_any(0, 0) && !matrix.fits_any(0, 1));
+        assert!(matrix.fits(&parts, 0, 0.0, bin, &config));
+        // angles and sheets outside the matrix are worked out
+        assert!(!matrix.fits(&parts, 0, 45.0, bin, &config));
+        assert!(matrix.fits(&parts, 0, 90.0, turned, &config));
+
+        let config = NestingConfig::builder().rotations(&[0.0, 90.0]).rotate_sheets(true).build().unwrap();
+        let matrix = FitMatrix::new(&parts, bin, &config);
+        assert!(matrix.fits_any(0, 1));
+        assert!(!matrix.fits(&parts, 0, 90.0, bin, &config));
+        assert_eq!(matrix.sheet_types(&parts, 0, 0.0, &config), [0]);
+        assert_eq!(matrix.sheet_types(&parts, 0, 90.0, &config), [1]);
+    }
+
+    #[test]
+    fn parts_go_on_sheets_of_their_type() {
+        let parts = vec![Part::new(vec![rect(1.5, 3.0)]), Part::new(vec![rect(1.5, 3.0)])];
+        let bin = rect(10.0, 4.0);
+        let config = NestingConfig::builder()
+            .population_size(4)
+            .rotations(&[0.0])
+            .rotate_sheets(true)
+            .build()
+            .unwrap();
+        let mut ga = GeneticAlgorithm::new(&parts, &bin, config).unwrap();
+        let ind = |sheet_type: Vec<usize>| Individual {
+            placement: vec![0, 1],
+            rotation: vec![0.0, 0.0],
+            sheet_type,
+            fitness: 0.0,
+        };
+
+        let layout = ga.to_layout(&ind(vec![0, 1]));
+        assert_eq!(layout.placements.len(), 2);
+        assert_eq!(layout.turned, [1]);
+        assert_eq!(layout.height, 14.0);
+
+        let layout = ga.to_layout(&ind(vec![1, 1]));
+        assert_eq!(layout.placements.len(), 2);
+        assert_eq!(layout.turned, [0]);
+        assert_eq!(layout.height, 10.0);
+
+        // a part too wide for the turned bin is given the bin
+        let parts = vec![Part::new(vec![rect(8.0, 3.0)]), Part::new(vec![rect(3.0, 3.0)])];
+        let config = NestingConfig::builder()
+            .population_size(8)
+            .rotations(&[0.0])
+            .rotate_sheets(true)
+            .mutation_rate(50)
+            .build()
+            .unwrap();
+        let mut ga = GeneticAlgorithm::new(&parts, &bin, config).unwrap();
+        assert_eq!(ga.population[0].sheet_type[0], 0);
+        assert!(ga.population.iter().all(|ind| ind.sheet_type.iter().all(|&t| t < 2)));
+        for _ in 0..5 {
+            ga.evaluate_population();
+            ga.generation();
+        }
+        ga.evaluate_population();
+        let best = ga.best().cloned().unwrap();
+        assert_eq!(ga.to_layout(&best).placements.len(), 2);
+    }
+
+    #[test]
+    fn individuals_without_sheet_types_are_completed() {
+        let parts = vec![Part::new(vec![rect(8.0, 3.0)]), Part::new(vec![rect(3.0, 3.0)])];
+        let bin = rect(10.0, 4.0);
+        let config = NestingConfig::builder()
+            .population_size(2)
+            .rotations(&[0.0])
+            .rotate_sheets(true)
+            .mutation_rate(100)
+            .local_search_iters(4)
+            .build()
+            .unwrap();
+        let mut ga = GeneticAlgorithm::new(&parts, &bin, config).unwrap();
+        let mut ind = Individual {
+            placement: vec![0, 1],
+            rotation: vec![0.0, 0.0],
+            sheet_type: Vec::new(),
+            fitness: 0.0,
+        };
+        assert_eq!(ga.mutate(&ind).sheet_type.len(), 2);
+        let (c1, c2) = ga.mate(&ind, &ind);
+        assert_eq!((c1.sheet_type.len(), c2.sheet_type.len()), (2, 2));
+        ga.local_search(&mut ind);
+        // the wide part only fits the sheet as given
+        assert_eq!(ind.sheet_type[ind.placement.iter().position(|&p| p == 0).unwrap()], 0);
+    }
+
+    #[test]
+    fn angle_fallback_decides_about_parts_fitting_no_rotation() {
+        let parts = vec![Part::new(vec![rect(12.0, 2.0)]), Part::new(vec![rect(2.0, 2.0)])];
+        let bin = rect(10.0, 10.0);
+        let config = |fallback| {
+            NestingConfig::builder()
+                .population_size(1)
+                .rotations(&[0.0, 90.0])
+                .angle_fallback(fallback)
+                .build()
+                .unwrap()
+        };
+
+        let found = feasibility(&parts, &bin, &config(AngleFallback::Unplace)).unwrap();
+        assert!(found[0].angles.is_empty());
+        assert!(found[0].reason.as_deref().unwrap().contains("larger than the 10 x 10 bin"));
+        assert_eq!(found[1].angles, [0.0, 90.0]);
+        // the part fitting nowhere gets no gene, so no angle is made up for it
+        let ga = GeneticAlgorithm::new(&parts, &bin, config(AngleFallback::Unplace)).unwrap();
+        assert!(ga.population.iter().all(|ind| ind.placement == [1]));
+
+        let err = GeneticAlgorithm::new(&parts, &bin, config(AngleFallback::Error)).err().unwrap();
+        assert!(err.to_string().contains("cannot be placed"));
+
+        // the diagonal of the bin is long enough
+        let found = feasibility(&parts, &bin, &config(AngleFallback::Continuous)).unwrap();
+        assert!(found[0].reason.is_none() && found[0].angles.contains(&45.0));
+        let mut ga = GeneticAlgorithm::new(&parts, &bin, config(AngleFallback::Continuous)).unwrap();
+        ga.evaluate_population();
+        let best = ga.best().cloned().unwrap();
+        assert_eq!(ga.to_layout(&best).placements.len(), 2);
+    }
+
+    #[test]
+    fn pack_direction_moves_parts_as_a_whole() {
+        let sheet = Bounds {
+            x: 0.0,
+            y: 0.0,
+            width: 10.0,
+            height: 10.0,
+        };
+        let part = |w: f64, h: f64| {
+            Some(Bounds {
+                x: 0.0,
+                y: 0.0,
+                width: w,
+                height: h,
+            })
+        };
+        let bounds = [part(2.0, 4.0), part(3.0, 2.0), part(4.0, 4.0)];
+        let placed = [
+            Placement { idx: 0, sheet: 0, angle: 0.0, x: 0.0, y: 0.0 },
+            Placement { idx: 1, sheet: 0, angle: 0.0, x: 2.0, y: 0.0 },
+            Placement { idx: 2, sheet: 1, angle: 0.0, x: 0.0, y: 10.0 },
+        ];
+        let sheets = crate::layout::stack_sheets(sheet, &[], 2);
+        let moved = |direction| {
+            let mut placements = placed;
+            pack_toward(direction, &sheets, 0.0, &mut placements, &bounds);
+            placements.map(|p| (p.x, p.y))
+        };
+        assert_eq!(moved(PackDirection::Left), [(0.0, 0.0), (2.0, 0.0), (0.0, 10.0)]);
+        assert_eq!(moved(PackDirection::Right), [(5.0, 0.0), (7.0, 0.0), (6.0, 10.0)]);
+        assert_eq!(moved(PackDirection::Bottom), [(0.0, 6.0), (2.0, 6.0), (0.0, 16.0)]);
+        assert_eq!(moved(PackDirection::CenterOut), [(2.5, 3.0), (4.5, 3.0), (3.0, 13.0)]);
+    }
+
+    #[test]
+    fn common_cuts_are_drawn_once() {
+        let outline = Polygon {
+            closed: true,
+            ..rect(5.0, 10.0)
+        };
+        let parts = vec![Part::new(vec![outline.clone()]), Part::new(vec![outline])];
+        let bin = rect(10.0, 10.0);
+        // edges drawn in the SVG, counted from the points of every element
+        let edges = |merge: bool| {
+            let config = NestingConfig::builder()
+                .population_size(1)
+                .rotations(&[0.0])
+                .merge_common_cuts(merge)
+                .build()
+                .unwrap();
+            let ga = GeneticAlgorithm::new(&parts, &bin, config).unwrap();
+            // the parts side by side, sharing the edge at x = 5
+            let layout = Layout {
+                bin: ga.bin_bounds(),
+                height: 10.0,
+                placements: vec![
+                    Placement { idx: 0, sheet: 0, angle: 0.0, x: 0.0, y: 0.0 },
+                    Placement { idx: 1, sheet: 0, angle: 0.0, x: 5.0, y: 0.0 },
+                ],
+                turned: Vec::new(),
+            };
+            let svg = ga.layout_svg(&layout);
+            svg.split('<')
+                .filter(|element| !element.contains("data-sheet"))
+                .filter_map(|element| {
+                    let points = element.split("points=\"").nth(1)?.split('"').next()?;
+                    let n = points.split_whitespace().count();
+                    Some(if element.starts_with("polyline") { n - 1 } else { n })
+                })
+                .sum::<usize>()
+        };
+        assert_eq!(edges(false), 8);
+        assert_eq!(edges(true), 7);
+    }
+
+    /// Polygons in `svg` other than the sheet outlines.
+    fn cut_polygons(svg: &str) -> usize {
+        svg.split("<polygon").skip(1).filter(|e| !e.split("/>").next().unwrap().contains("data-sheet")).count()
+    }
+
+    #[test]
+    fn marking_follows_its_part() {
+        let line = Polygon {
+            points: vec![Point { x: 1.0, y: 1.0 }, Point { x: 4.0, y: 1.0 }],
+            ..Default::default()
+        };
+        let parts = vec![Part::new_with_marking(vec![rect(5.0, 2.0)], vec![line])];
+        let bin = rect(10.0, 10.0);
+        let config = NestingConfig::builder().population_size(1).rotations(&[90.0]).build().unwrap();
+        let mut ga = GeneticAlgorithm::new(&parts, &bin, config).unwrap();
+        let best = ga.population[0].clone();
+        let svg = ga.create_svg(&best);
+        let layer = svg.split("<g id=\"marking\"").nth(1).unwrap();
+        assert_eq!(layer.matches("<polyline").count(), 1);
+        assert_eq!(cut_polygons(&svg), 1);
+    }
+
+    #[test]
+    fn tabs_split_outlines_only() {
+        let mut outline = rect(6.0, 6.0);
+        outline.points.reverse();
+        outline.closed = true;
+        let mut hole = rect(2.0, 2.0);
+        hole.closed = true;
+        for p in &mut hole.points {
+            p.x += 2.0;
+            p.y += 2.0;
+        }
+        let parts = vec![Part::new(vec![outline, hole])];
+        let bin = rect(10.0, 10.0);
+        let tabs = crate::tabs::Tabs { count: 3, width: 0.5 };
+        let config = NestingConfig::builder().population_size(1).rotations(&[0.0]).tabs(Some(tabs)).build().unwrap();
+        let mut ga = GeneticAlgorithm::new(&parts, &bin, config).unwrap();
+        let best = ga.population[0].clone();
+        let svg = ga.create_svg(&best);
+        assert_eq!(svg.matches("<polyline").count(), 3);
+        assert_eq!(svg.matches("data-part=").count(), 2);
+        // the hole is drawn whole
+        assert_eq!(cut_polygons(&svg), 1);
+    }
+
+    #[test]
+    fn leads_open_closed_contours() {
+        let mut outline = rect(4.0, 4.0);
+        outline.points.reverse();
+        outline.closed = true;
+        let parts = vec![Part::new(vec![outline])];
+        let bin = rect(10.0, 10.0);
+        let config = NestingConfig::builder()
+            .population_size(1)
+            .rotations(&[0.0])
+            .lead_in(Some("line:1".parse().unwrap()))
+            .build()
+            .unwrap();
+        let mut ga = GeneticAlgorithm::new(&parts, &bin, config).unwrap();
+        let best = ga.population[0].clone();
+        let svg = ga.create_svg(&best);
+        let points = svg.split("<polyline points=\"").nth(1).unwrap().split('"').next().unwrap();
+        // the lead, the four corners and back to the start
+        assert_eq!(points.split_whitespace().count(), 6);
+        assert_eq!(cut_polygons(&svg), 0);
+    }
+
+    #[test]
+    fn marks_go_to_their_own_layer() {
+        let parts = vec![Part::new(vec![rect(5.0, 5.0)])];
+        let bin = rect(10.0, 10.0);
+        let marks = crate::config::SheetMarks {
+            origin: true,
+            ..Default::default()
+        };
+        let config = NestingConfig::builder().population_size(1).marks(marks).build().unwrap();
+        let mut ga = GeneticAlgorithm::new(&parts, &bin, config).unwrap();
+        let best = ga.population[0].clone();
+        let svg = ga.create_svg(&best);
+        assert!(svg.contains("xmlns:inkscape="));
+        let layer = svg.split("<g id=\"marks\"").nth(1).unwrap();
+        assert!(layer.contains("<path d=\"M-10 0H10M0 -10V10\"/>"));
+        assert!(!layer.contains("<polygon"));
+    }
+
+    #[test]
+    fn progress_is_visible_through_the_handle() {
+        // narrow enough to share a row of the bin at any rotation
+        let parts = vec![Part::new(vec![rect(4.0, 4.0)]), Part::new(vec![rect(4.0, 4.0)])];
+        let bin = rect(10.0, 10.0);
+        let config = NestingConfig::builder().population_size(2).build().unwrap();
+        let mut ga = GeneticAlgorithm::new(&parts, &bin, config).unwrap();
+        let progress = ga.progress();
+        ga.evolve(2);
+        let p = progress.get();
+        assert_eq!((p.generations_done, p.generations_total), (2, 2));
+        assert_eq!(p.fraction(), 1.0);
+        assert_eq!(p.best_utilization, Some(0.32));
+        assert_eq!(p.nfps_computed + p.nfps_remaining, 2 * 4 * 4);
+    }
+
+    #[test]
+    fn same_seed_gives_same_run() {
+        let parts: Vec<Part> = (1..6).map(|i| Part::new(vec![rect(i as f64, 2.0)])).collect();
+        let bin = rect(10.0, 10.0);
+        let run = |seed: u64| {
+            let config = NestingConfig::builder().population_size(4).seed(Some(seed)).build().unwrap();
+            let mut ga = GeneticAlgorithm::new(&parts, &bin, config).unwrap();
+            ga.evolve(3);
+            ga.population
+        };
+        assert_eq!(run(7), run(7));
+    }
+
+    #[test]
+    fn stops_at_target_utilization() {
+        // the two parts share a row, covering 0.32 of the bin
+        let parts = vec![Part::new(vec![rect(4.0, 4.0)]), Part::new(vec![rect(4.0, 4.0)])];
+        let bin = rect(10.0, 10.0);
+        let run = |target: f64| {
+            let config = NestingConfig::builder()
+                .population_size(2)
+                .target_utilization(Some(target))
+                .build()
+                .unwrap();
+            let mut ga = GeneticAlgorithm::new(&parts, &bin, config).unwrap();
+            ga.evolve(10);
+            (ga.target_reached(), ga.stats().len())
+        };
+        assert_eq!(run(0.3), (true, 1));
+        assert_eq!(run(0.5), (false, 11));
     }
 }
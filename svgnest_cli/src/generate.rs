@@ -0,0 +1,194 @@
+//! Random part sets for stress tests and benchmarks.
+//!
+//! Instances are written in the layout `batch` expects: a `bin.svg` and one
+//! SVG file per part, so a generated directory can be nested directly or
+//! dropped into a batch or daemon folder.
+
+use std::f64::consts::TAU;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use crate::{
+    geometry::get_polygons_bounds,
+    part::Part,
+    svg_parser::{Point, Polygon},
+};
+
+/// Kinds of parts the generator draws from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ShapeKind {
+    /// Convex polygons with 3 to 10 corners
+    Convex,
+    /// Star shaped polygons with notches
+    Concave,
+    /// Convex outlines with a hole in the middle
+    Ring,
+}
+
+/// Smallest and largest width or height of a part
+const MIN_SIZE: f64 = 5.0;
+const MAX_SIZE: f64 = 50.0;
+
+/// Fraction of the bin the parts would cover if packed perfectly
+const FILL: f64 = 0.6;
+
+/// A generated bin with the parts to nest into it.
+pub struct Instance {
+    pub bin: Polygon,
+    pub parts: Vec<Part>,
+}
+
+/// Coordinates are rounded like in files written by design tools, so the
+/// instance nests the same whether it is used directly or written first.
+fn round(v: f64) -> f64 {
+    (v * 1000.0).round() / 1000.0
+}
+
+/// Ring around the center of a `w` by `h` box through `n` corners at random
+/// angles, corner `i` at `radius(i)` times the half extents. Corners are no
+/// more than half a turn apart, so the center is always inside.
+fn star(rng: &mut StdRng, n: usize, w: f64, h: f64, radius: impl Fn(usize, &mut StdRng) -> f64) -> Vec<Point> {
+    (0..n)
+        .map(|i| {
+            let a = (i as f64 + rng.gen_range(0.0..0.8)) * TAU / n as f64;
+            let r = radius(i, rng);
+            Point {
+                x: round(w / 2.0 * (1.0 + r * a.cos())),
+                y: round(h / 2.0 * (1.0 + r * a.sin())),
+            }
+        })
+        .collect()
+}
+
+fn ring(points: Vec<Point>) -> Polygon {
+    Polygon {
+        points,
+        closed: true,
+        ..Default::default()
+    }
+}
+
+fn random_part(rng: &mut StdRng, kind: ShapeKind) -> Part {
+    let w = rng.gen_range(MIN_SIZE..MAX_SIZE);
+    let h = rng.gen_range(MIN_SIZE..MAX_SIZE);
+    match kind {
+        ShapeKind::Convex => {
+            let n = rng.gen_range(3..=10);
+            Part::new(vec![ring(star(rng, n, w, h, |_, _| 1.0))])
+        }
+        ShapeKind::Concave => {
+            let n = 2 * rng.gen_range(3..=6);
+            let outline = star(rng, n, w, h, |i, rng| if i % 2 == 1 { rng.gen_range(0.3..0.7) } else { 1.0 });
+            Part::new(vec![ring(outline)])
+        }
+        ShapeKind::Ring => {
+            let n = rng.gen_range(4..=10);
+            let outline = star(rng, n, w, h, |_, _| 1.0);
+            let scale = rng.gen_range(0.3..0.6);
+            let mut hole: Vec<Point> = outline
+                .iter()
+                .map(|p| Point {
+                    x: round(w / 2.0 + (p.x - w / 2.0) * scale),
+                    y: round(h / 2.0 + (p.y - h / 2.0) * scale),
+                })
+                .collect();
+            hole.reverse();
+            Part::new(vec![ring(outline), ring(hole)])
+        }
+    }
+}
+
+/// `count` random parts of the kinds in `mix` and a square bin they would
+/// fill to about 60%. The same seed always gives the same instance.
+pub fn generate(count: usize, mix: &[ShapeKind], seed: u64) -> Instance {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let parts: Vec<Part> = (0..count)
+        .map(|_| {
+            let kind = if mix.is_empty() {
+                ShapeKind::Convex
+            } else {
+                mix[rng.gen_range(0..mix.len())]
+            };
+            random_part(&mut rng, kind)
+        })
+        .collect();
+    let area: f64 = parts.iter().map(Part::area).sum();
+    let side = round((area / FILL).sqrt().max(MAX_SIZE));
+    let bin = ring(vec![
+        Point { x: 0.0, y: 0.0 },
+        Point { x: side, y: 0.0 },
+        Point { x: side, y: side },
+        Point { x: 0.0, y: side },
+    ]);
+    Instance { bin, parts }
+}
+
+fn path_data(polygons: &[Polygon]) -> String {
+    let rings: Vec<String> = polygons
+        .iter()
+        .map(|poly| {
+            let coords: Vec<String> = poly.points.iter().map(|p| format!("{},{}", p.x, p.y)).collect();
+            format!("M{} Z", coords.join(" L"))
+        })
+        .collect();
+    rings.join(" ")
+}
+
+fn svg_document(polygons: &[Polygon]) -> String {
+    let (width, height) = get_polygons_bounds(polygons).map_or((0.0, 0.0), |b| (b.x + b.width, b.y + b.height));
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">\n<path d=\"{}\" fill=\"none\" fill-rule=\"evenodd\" stroke=\"black\"/>\n</svg>\n",
+        width,
+        height,
+        path_data(polygons)
+    )
+}
+
+impl Instance {
+    /// Write `bin.svg` and `part_<n>.svg` for every part into `dir`.
+    pub fn write(&self, dir: &Path) -> Result<()> {
+        fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+        let mut files = vec![(dir.join("bin.svg"), svg_document(std::slice::from_ref(&self.bin)))];
+        // pad the numbers so the files sort in part order
+        let digits = self.parts.len().saturating_sub(1).to_string().len();
+        for (i, part) in self.parts.iter().enumerate() {
+            let name = format!("part_{:0width$}.svg", i, width = digits);
+            files.push((dir.join(name), svg_document(&part.polygons)));
+        }
+        for (path, svg) in files {
+            fs::write(&path, svg).with_context(|| format!("failed to write {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::polygon_area;
+
+    #[test]
+    fn instances_are_reproducible() {
+        let mix = [ShapeKind::Convex, ShapeKind::Concave, ShapeKind::Ring];
+        let a = generate(30, &mix, 7);
+        let b = generate(30, &mix, 7);
+        assert_eq!(a.parts.len(), 30);
+        for (pa, pb) in a.parts.iter().zip(&b.parts) {
+            assert_eq!(pa.polygons[0].points, pb.polygons[0].points);
+        }
+        let rings = a.parts.iter().filter(|p| p.polygons.len() == 2).count();
+        assert!(rings > 0 && rings < 30);
+        for part in &a.parts {
+            let outline = polygon_area(&part.polygons[0].points).abs();
+            assert!(outline > 0.0);
+            if let Some(hole) = part.polygons.get(1) {
+                assert!(polygon_area(&hole.points).abs() < outline);
+            }
+        }
+        let bounds = get_polygons_bounds(std::slice::from_ref(&a.bin)).unwrap();
+        assert!(bounds.width >= MAX_SIZE);
+    }
+}
@@ -1,8 +1,12 @@
+pub(crate) mod backend;
+pub mod batch;
+pub mod ops;
+
 use crate::svg_parser::{Point, Polygon};
-use geo::{Area, BoundingRect, LineString, Rotate, point};
+use serde::{Deserialize, Serialize};
 
 /// Bounding box of a polygon
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Bounds {
     pub x: f64,
     pub y: f64,
@@ -10,14 +14,126 @@ pub struct Bounds {
     pub height: f64,
 }
 
+impl Bounds {
+    pub fn translated(self, dx: f64, dy: f64) -> Self {
+        Self {
+            x: self.x + dx,
+            y: self.y + dy,
+            ..self
+        }
+    }
+
+    /// Returns true unless the boxes are more than `gap` apart along either
+    /// axis. Touching boxes count as overlapping.
+    pub fn overlaps(&self, other: &Bounds, gap: f64) -> bool {
+        self.x <= other.x + other.width + gap
+            && other.x <= self.x + self.width + gap
+            && self.y <= other.y + other.height + gap
+            && other.y <= self.y + self.height + gap
+    }
+
+    /// Whether `other` lies within this box, edges included.
+    pub fn contains(&self, other: &Bounds) -> bool {
+        self.x <= other.x
+            && self.y <= other.y
+            && other.x + other.width <= self.x + self.width
+            && other.y + other.height <= self.y + self.height
+    }
+
+    /// Length along which a side of this box faces a side of `other` no
+    /// more than `gap` away. Boxes further apart give 0.
+    pub fn touching(&self, other: &Bounds, gap: f64) -> f64 {
+        let overlap = |a0: f64, a1: f64, b0: f64, b1: f64| (a1.min(b1) - a0.max(b0)).max(0.0);
+        let near = |u: f64, v: f64| (u - v).abs() <= gap;
+        let mut length = 0.0;
+        if near(self.x + self.width, other.x) || near(other.x + other.width, self.x) {
+            length += overlap(self.y, self.y + self.height, other.y, other.y + other.height);
+        }
+        if near(self.y + self.height, other.y) || near(other.y + other.height, self.y) {
+            length += overlap(self.x, self.x + self.width, other.x, other.x + other.width);
+        }
+        length
+    }
+}
+
+/// Sine of the largest angle between segments that still count as running
+/// side by side in [`shared_length`]
+const PARALLEL_SINE: f64 = 0.01;
+
+/// Length along which segments `a0`-`a1` and `b0`-`b1` run side by side,
+/// parallel and no more than `gap` apart, measured along the first one.
+pub fn shared_length(a0: Point, a1: Point, b0: Point, b1: Point, gap: f64) -> f64 {
+    let (dx, dy) = (a1.x - a0.x, a1.y - a0.y);
+    let (ex, ey) = (b1.x - b0.x, b1.y - b0.y);
+    let (len, len_b) = (dx.hypot(dy), ex.hypot(ey));
+    if len == 0.0 || len_b == 0.0 {
+        return 0.0;
+    }
+    let (ux, uy) = (dx / len, dy / len);
+    if ((ux * ey - uy * ex) / len_b).abs() > PARALLEL_SINE {
+        return 0.0;
+    }
+    let across = |p: Point| (ux * (p.y - a0.y) - uy * (p.x - a0.x)).abs();
+    if across(b0) > gap || across(b1) > gap {
+        return 0.0;
+    }
+    let along = |p: Point| ux * (p.x - a0.x) + uy * (p.y - a0.y);
+    let (t0, t1) = (along(b0), along(b1));
+    (t0.max(t1).min(len) - t0.min(t1).max(0.0)).max(0.0)
+}
+
 /// Default scale factor used when interfacing with Clipper
 pub const CLIPPER_SCALE: f64 = 10_000_000.0;
 
 /// Default curve tolerance when approximating curves
 pub const CURVE_TOLERANCE: f64 = 0.3;
 
-fn to_linestring(points: &[Point]) -> LineString<f64> {
-    points.iter().map(|p| (p.x, p.y)).collect::<Vec<_>>().into()
+/// Numerical tolerances used while reading and nesting parts. The defaults
+/// suit parts measured in millimetres; very small or very large parts may
+/// need them scaled.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Tolerances {
+    /// Maximum error allowed when approximating curves
+    pub curve: f64,
+    /// Distance below which the endpoints of DXF entities are joined
+    pub connect: f64,
+    /// Distance below which line segments count as duplicates when merging
+    pub merge: f64,
+    /// Precision used when caching NFPs based on angles
+    pub angle: f64,
+    /// Depth up to which parts may run into each other and still count as
+    /// touching, so positions slid flush against a part are not rejected
+    /// for rounding errors
+    pub contact: f64,
+}
+
+impl Default for Tolerances {
+    fn default() -> Self {
+        Self {
+            curve: CURVE_TOLERANCE,
+            connect: 1e-6,
+            merge: 1e-6,
+            angle: 1e-3,
+            contact: 1e-6,
+        }
+    }
+}
+
+/// Bounds of `points` if they outline an axis-aligned rectangle: every
+/// vertex lies on the bounds and the ring covers them, both up to `tol`
+/// times the size of the rectangle. Collinear extra vertices are allowed.
+pub fn rectangle_bounds(points: &[Point], tol: f64) -> Option<Bounds> {
+    let b = get_polygon_bounds(points)?;
+    let eps = tol * b.width.max(b.height);
+    let on_bounds = |p: &Point| {
+        (p.x - b.x).abs() <= eps
+            || (p.x - b.x - b.width).abs() <= eps
+            || (p.y - b.y).abs() <= eps
+            || (p.y - b.y - b.height).abs() <= eps
+    };
+    let covered = polygon_area(points).abs() >= b.width * b.height - eps * 2.0 * (b.width + b.height);
+    (points.iter().all(on_bounds) && covered).then_some(b)
 }
 
 /// Calculate the rectangular bounds of the polygon.
@@ -26,13 +142,25 @@ pub fn get_polygon_bounds(points: &[Point]) -> Option<Bounds> {
     if points.len() < 3 {
         return None;
     }
-    let ls = to_linestring(points);
-    let rect = ls.bounding_rect()?;
+    let (mut min_x, mut min_y) = (points[0].x, points[0].y);
+    let (mut max_x, mut max_y) = (min_x, min_y);
+    for p in &points[1..] {
+        if p.x > max_x {
+            max_x = p.x;
+        } else if p.x < min_x {
+            min_x = p.x;
+        }
+        if p.y > max_y {
+            max_y = p.y;
+        } else if p.y < min_y {
+            min_y = p.y;
+        }
+    }
     Some(Bounds {
-        x: rect.min().x,
-        y: rect.min().y,
-        width: rect.width(),
-        height: rect.height(),
+        x: min_x,
+        y: min_y,
+        width: max_x - min_x,
+        height: max_y - min_y,
     })
 }
 
@@ -51,18 +179,163 @@ pub fn polygon_area(points: &[Point]) -> f64 {
     0.5 * area
 }
 
-/// Rotate polygon by the given angle in degrees around the origin.
-pub fn rotate_polygon(points: &[Point], angle_deg: f64) -> Vec<Point> {
-    if points.is_empty() {
+/// Length of the ring, with the edge back to the first point if `closed`.
+pub fn polygon_perimeter(points: &[Point], closed: bool) -> f64 {
+    let n = points.len();
+    let edges = if closed { n } else { n.saturating_sub(1) };
+    (0..edges)
+        .map(|i| {
+            let (a, b) = (points[i], points[(i + 1) % n]);
+            (b.x - a.x).hypot(b.y - a.y)
+        })
+        .sum()
+}
+
+/// Centroid of the area enclosed by the ring, whichever way it winds.
+/// `None` for rings enclosing no area.
+pub fn polygon_centroid(points: &[Point]) -> Option<Point> {
+    let moments = AreaMoments::of(points)?;
+    Some(Point {
+        x: moments.x / moments.area,
+        y: moments.y / moments.area,
+    })
+}
+
+/// Area and its first and second moments about the origin, signed like
+/// the winding of the ring they were taken of, so their ratios are not.
+struct AreaMoments {
+    area: f64,
+    x: f64,
+    y: f64,
+    xx: f64,
+    yy: f64,
+    xy: f64,
+}
+
+impl AreaMoments {
+    fn of(points: &[Point]) -> Option<Self> {
+        if points.len() < 3 {
+            return None;
+        }
+        let mut m = AreaMoments {
+            area: 0.0,
+            x: 0.0,
+            y: 0.0,
+            xx: 0.0,
+            yy: 0.0,
+            xy: 0.0,
+        };
+        let mut j = points.len() - 1;
+        for (i, &b) in points.iter().enumerate() {
+            let a = points[j];
+            j = i;
+            let cross = a.x * b.y - b.x * a.y;
+            m.area += cross / 2.0;
+            m.x += (a.x + b.x) * cross / 6.0;
+            m.y += (a.y + b.y) * cross / 6.0;
+            m.xx += (a.x * a.x + a.x * b.x + b.x * b.x) * cross / 12.0;
+            m.yy += (a.y * a.y + a.y * b.y + b.y * b.y) * cross / 12.0;
+            m.xy += (a.x * b.y + 2.0 * a.x * a.y + 2.0 * b.x * b.y + b.x * a.y) * cross / 24.0;
+        }
+        (m.area != 0.0 && m.area.is_finite()).then_some(m)
+    }
+}
+
+/// Angle in degrees, in (-90, 90], of the axis about which the area of the
+/// ring is spread the least, i.e. along which it is longest. `None` for
+/// rings enclosing no area; rings without a longer direction, like squares
+/// and circles, give an arbitrary angle.
+pub fn principal_axis(points: &[Point]) -> Option<f64> {
+    let m = AreaMoments::of(points)?;
+    let (cx, cy) = (m.x / m.area, m.y / m.area);
+    // second moments about the centroid
+    let mu20 = m.xx / m.area - cx * cx;
+    let mu02 = m.yy / m.area - cy * cy;
+    let mu11 = m.xy / m.area - cx * cy;
+    let angle = 0.5 * (2.0 * mu11).atan2(mu20 - mu02).to_degrees();
+    Some(if angle <= -90.0 { angle + 180.0 } else { angle })
+}
+
+/// Convex hull of `points`, wound like an outline (counter-clockwise, see
+/// [`polygon_area`]), without repeating the first point at the end. Points
+/// that are not finite are left out.
+pub fn convex_hull(points: impl IntoIterator<Item = Point>) -> Vec<Point> {
+    let points: geo::MultiPoint<f64> = points
+        .into_iter()
+        .filter(|p| p.x.is_finite() && p.y.is_finite())
+        .map(|p| geo::Point::new(p.x, p.y))
+        .collect();
+    if points.0.is_empty() {
         return Vec::new();
     }
-    let ls = to_linestring(points);
-    let origin = point!(x: 0.0, y: 0.0);
-    let rotated = ls.rotate_around_point(angle_deg, origin);
-    rotated
+    let mut hull: Vec<Point> = points
+        .convex_hull()
+        .exterior()
         .points()
         .map(|c| Point { x: c.x(), y: c.y() })
-        .collect()
+        .collect();
+    if hull.len() > 1 && hull[0] == hull[hull.len() - 1] {
+        hull.pop();
+    }
+    if polygon_area(&hull) > 0.0 {
+        hull.reverse();
+    }
+    hull
+}
+
+/// Rectangle turned by `angle` degrees around its center. Rotating the
+/// rectangle by `-angle` with [`rotate_polygon`] makes it axis-aligned,
+/// `width` wide and `height` high.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RotatedRect {
+    pub center: Point,
+    pub width: f64,
+    pub height: f64,
+    /// In [0, 90)
+    pub angle: f64,
+}
+
+/// Rectangle of least area around `points`, at any angle. One of its sides
+/// lies along an edge of the convex hull of the points, so only those
+/// angles are tried. `None` without points.
+pub fn minimum_rotated_rect(points: &[Point]) -> Option<RotatedRect> {
+    let hull = convex_hull(points.iter().copied());
+    let mut best: Option<(f64, RotatedRect)> = None;
+    let mut j = hull.len().checked_sub(1)?;
+    for (i, &b) in hull.iter().enumerate() {
+        let a = hull[j];
+        j = i;
+        let angle = (b.y - a.y).atan2(b.x - a.x).to_degrees();
+        let Some(r) = get_polygon_bounds(&rotate_polygon(&hull, -angle)) else {
+            continue;
+        };
+        let area = r.width * r.height;
+        if best.is_some_and(|(least, _)| least <= area) {
+            continue;
+        }
+        let center = batch::Affine::rotation(angle).apply(Point {
+            x: r.x + r.width / 2.0,
+            y: r.y + r.height / 2.0,
+        });
+        // a quarter turn swaps the sides
+        let turns = (angle / 90.0).floor();
+        let (width, height) = if turns.rem_euclid(2.0) == 0.0 { (r.width, r.height) } else { (r.height, r.width) };
+        let rect = RotatedRect {
+            center,
+            width,
+            height,
+            angle: angle - turns * 90.0,
+        };
+        best = Some((area, rect));
+    }
+    best.map(|(_, rect)| rect)
+}
+
+/// Rotate polygon by the given angle in degrees around the origin.
+pub fn rotate_polygon(points: &[Point], angle_deg: f64) -> Vec<Point> {
+    let mut rotated = Vec::with_capacity(points.len());
+    batch::transform_into(points, &batch::Affine::rotation(angle_deg), &mut rotated);
+    rotated
 }
 
 /// Rotate a collection of polygons by the given angle.
@@ -73,6 +346,8 @@ pub fn rotate_polygons(polys: &[Polygon], angle_deg: f64) -> Vec<Polygon> {
             id: p.id,
             points: rotate_polygon(&p.points, angle_deg),
             closed: p.closed,
+            metadata: p.metadata.clone(),
+            contour: p.contour.as_ref().map(|c| c.rotate(angle_deg)),
         })
         .collect()
 }
@@ -102,12 +377,24 @@ pub fn normalize_polygons(polys: &mut [Polygon]) {
             p.x -= min_x;
             p.y -= min_y;
         }
+        if let Some(contour) = &mut poly.contour {
+            *contour = contour.translate(-min_x, -min_y);
+        }
     }
 }
 
 /// Bounding box that encompasses all provided polygons.
 pub fn get_polygons_bounds(polys: &[Polygon]) -> Option<Bounds> {
-    let mut iter = polys.iter().filter_map(|p| get_polygon_bounds(&p.points));
+    bounds_of(polys.iter().map(|p| p.points.as_slice()))
+}
+
+/// Bounding box that encompasses all provided rings.
+pub fn get_rings_bounds(rings: &[Vec<Point>]) -> Option<Bounds> {
+    bounds_of(rings.iter().map(Vec::as_slice))
+}
+
+fn bounds_of<'a>(rings: impl Iterator<Item = &'a [Point]>) -> Option<Bounds> {
+    let mut iter = rings.filter_map(get_polygon_bounds);
     let first = iter.next()?;
     let mut min_x = first.x;
     let mut min_y = first.y;
@@ -127,8 +414,7 @@ pub fn get_polygons_bounds(polys: &[Polygon]) -> Option<Bounds> {
     })
 }
 
-use geo::{prelude::*, LineString as GeoLineString, MultiPolygon, Polygon as GeoPolygon};
-use geo_clipper::{Clipper, EndType, JoinType};
+use geo::{prelude::*, InteriorPoint, LineString as GeoLineString, MultiPolygon, Polygon as GeoPolygon};
 
 fn to_geo_polygon(points: &[Point]) -> GeoPolygon<f64> {
     let exterior: GeoLineString<f64> = points.iter().map(|p| (p.x, p.y)).collect::<Vec<_>>().into();
@@ -136,38 +422,89 @@ fn to_geo_polygon(points: &[Point]) -> GeoPolygon<f64> {
 }
 
 fn to_geo_polygon_translated(points: &[Point], tx: f64, ty: f64) -> GeoPolygon<f64> {
-    let exterior: GeoLineString<f64> = points
-        .iter()
-        .map(|p| (p.x + tx, p.y + ty))
-        .collect::<Vec<_>>()
-        .into();
-    GeoPolygon::new(exterior, vec![])
+    let mut moved = Vec::with_capacity(points.len());
+    batch::translate_into(points, tx, ty, &mut moved);
+    to_geo_polygon(&moved)
+}
+
+/// Rings of a multi-contour part as geo polygons with interior rings. Rings
+/// are grouped into outlines and holes by nesting, see
+/// [`ops::shapes_from_rings`], so booleans see the holes of the part.
+pub fn to_geo_multipolygon(rings: &[Vec<Point>]) -> MultiPolygon<f64> {
+    ops::to_geo(&ops::shapes_from_rings(rings))
 }
 
-/// Offset a polygon by the given delta using the Clipper library.
+/// Offset a polygon by the given delta.
 pub fn offset_polygon(points: &[Point], delta: f64) -> Vec<Vec<Point>> {
     if points.is_empty() {
         return Vec::new();
     }
-    let poly = to_geo_polygon(points);
-    let mp = poly.offset(delta, JoinType::Miter(1.0), EndType::ClosedPolygon, CLIPPER_SCALE);
-    mp.0
-        .into_iter()
-        .map(|p| {
-            p.exterior()
-                .points()
-                .map(|c| Point { x: c.x(), y: c.y() })
-                .collect()
-        })
-        .collect()
+    offset_rings(&[points.to_vec()], delta)
+}
+
+/// Offset a multi-contour part by the given delta. Holes shrink as the
+/// outlines grow; every outline of the result is followed by its holes.
+pub fn offset_rings(rings: &[Vec<Point>], delta: f64) -> Vec<Vec<Point>> {
+    let shapes = ops::shapes_from_rings(rings);
+    ops::rings_of(ops::offset(&shapes, delta, LineJoin::Miter(1.0), 0.0))
 }
 
-/// General Minkowski difference using the Clipper library.
+/// How the corners of a stroked polyline are joined.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineJoin {
+    /// Sharp corners, beveled once they exceed the given miter limit
+    Miter(f64),
+    Round,
+    Bevel,
+}
+
+/// How the ends of a stroked polyline are capped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineCap {
+    Butt,
+    Round,
+    Square,
+}
+
+/// Outline of an open polyline stroked with half-width `delta`. Rings are
+/// returned with outlines wound with negative area and holes with positive
+/// area. `tol` bounds the error of round joins and caps.
+pub fn offset_polyline(
+    points: &[Point],
+    delta: f64,
+    join: LineJoin,
+    cap: LineCap,
+    tol: f64,
+) -> Vec<Vec<Point>> {
+    if points.len() < 2 || delta <= 0.0 {
+        return Vec::new();
+    }
+    let line: GeoLineString<f64> = points.iter().map(|p| (p.x, p.y)).collect::<Vec<_>>().into();
+    let ring = |ls: &GeoLineString<f64>, outline: bool| {
+        let mut pts: Vec<Point> = ls.points().map(|c| Point { x: c.x(), y: c.y() }).collect();
+        if let (Some(first), Some(last)) = (pts.first(), pts.last()) {
+            if pts.len() > 1 && first.x == last.x && first.y == last.y {
+                pts.pop();
+            }
+        }
+        if (polygon_area(&pts) < 0.0) != outline {
+            pts.reverse();
+        }
+        pts
+    };
+    let mut rings = Vec::new();
+    for poly in backend::stroke(&line, delta, join, cap, tol).0 {
+        rings.push(ring(poly.exterior(), true));
+        rings.extend(poly.interiors().iter().map(|h| ring(h, false)));
+    }
+    rings
+}
+
+/// General Minkowski difference of two polygons.
 ///
 /// This implementation mirrors the JavaScript version used by SVGnest and
 /// correctly handles concave polygons by constructing the Minkowski sum of `a`
-/// with the negated `b` polygon and unioning the intermediate quads via
-/// `geo_clipper::Clipper`.
+/// with the negated `b` polygon and unioning the intermediate quads.
 pub fn minkowski_difference_clip(a: &[Point], b: &[Point]) -> Vec<Point> {
     use std::cmp::Ordering;
 
@@ -175,6 +512,73 @@ pub fn minkowski_difference_clip(a: &[Point], b: &[Point]) -> Vec<Point> {
         return Vec::new();
     }
 
+    let mp = match minkowski_quads(a, b) {
+        Some(mp) => mp,
+        None => return Vec::new(),
+    };
+
+    // Select the polygon with the smallest (most negative) area
+    let poly_opt = mp.0.into_iter().min_by(|p1, p2| {
+        p1.signed_area()
+            .partial_cmp(&p2.signed_area())
+            .unwrap_or(Ordering::Equal)
+    });
+
+    if let Some(poly) = poly_opt {
+        let mut pts: Vec<Point> = poly
+            .exterior()
+            .points()
+            .map(|c| Point { x: c.x(), y: c.y() })
+            .collect();
+        // Translate by the first vertex of B
+        for p in &mut pts {
+            p.x += b[0].x;
+            p.y += b[0].y;
+        }
+        pts
+    } else {
+        Vec::new()
+    }
+}
+
+/// Minkowski difference of `a` and `b` as outlines with holes, relative to
+/// the first vertex of `b` like [`minkowski_difference_clip`]. Every loop of
+/// the quad union is kept, and of its holes those that are pockets:
+/// translations at which `b` lies clear of `a`, e.g. inside a bay of `a`
+/// whose mouth is too narrow for `b` to leave.
+pub fn minkowski_difference_shapes(a: &[Point], b: &[Point]) -> Vec<ops::Shape> {
+    if a.is_empty() || b.is_empty() {
+        return Vec::new();
+    }
+    let Some(mp) = minkowski_quads(a, b) else {
+        return Vec::new();
+    };
+    let mut shapes = ops::from_geo(mp);
+    for shape in &mut shapes {
+        shape.holes.retain(|hole| is_pocket(a, b, hole));
+        for p in shape.outline.iter_mut().chain(shape.holes.iter_mut().flatten()) {
+            p.x += b[0].x;
+            p.y += b[0].y;
+        }
+    }
+    shapes
+}
+
+/// Whether `b` moved by the translations in `hole`, a hole of the union of
+/// the Minkowski quads of `a` and `b`, lies clear of `a`. The quads cover
+/// every translation at which the boundaries cross, so throughout a hole
+/// the rings either stay apart or one stays inside the other.
+fn is_pocket(a: &[Point], b: &[Point], hole: &[Point]) -> bool {
+    let Some(t) = to_geo_polygon(hole).interior_point() else {
+        return false;
+    };
+    let (tx, ty) = (t.x(), t.y());
+    !point_in_polygon(a, b[0].x + tx, b[0].y + ty) && !point_in_polygon(b, a[0].x - tx, a[0].y - ty)
+}
+
+/// Union of the quads spanned by every edge of `a` and every edge of the
+/// negated `b`, which covers the boundary of their Minkowski sum.
+fn minkowski_quads(a: &[Point], b: &[Point]) -> Option<MultiPolygon<f64>> {
     let la = a.len();
     let lb = b.len();
 
@@ -208,79 +612,218 @@ pub fn minkowski_difference_clip(a: &[Point], b: &[Point]) -> Vec<Point> {
         }
     }
 
-    // Union all quads using Clipper
-    let mut acc: Option<MultiPolygon<f64>> = None;
-    for quad in &quads {
-        let g = to_geo_polygon(quad);
-        acc = Some(match acc {
-            Some(mp) => Clipper::union(&mp, &g, CLIPPER_SCALE),
-            None => MultiPolygon(vec![g]),
-        });
-    }
-
-    let mp = match acc {
-        Some(mp) => mp,
-        None => return Vec::new(),
-    };
-
-    // Select the polygon with the smallest (most negative) area
-    let poly_opt = mp.0.into_iter().min_by(|p1, p2| {
-        p1.signed_area()
-            .partial_cmp(&p2.signed_area())
-            .unwrap_or(Ordering::Equal)
-    });
-
-    if let Some(poly) = poly_opt {
-        let mut pts: Vec<Point> = poly
-            .exterior()
-            .points()
-            .map(|c| Point { x: c.x(), y: c.y() })
-            .collect();
-        // Translate by the first vertex of B
-        for p in &mut pts {
-            p.x += b[0].x;
-            p.y += b[0].y;
+    // Union the quads pairwise in a balanced tree. Growing one result quad
+    // by quad makes every union work on the whole result so far; this way
+    // both sides of a union are of similar size, and neighbouring quads,
+    // which are next to each other in the list, are merged first.
+    let mut layer: Vec<MultiPolygon<f64>> = quads.iter().map(|q| MultiPolygon(vec![to_geo_polygon(q)])).collect();
+    while layer.len() > 1 {
+        let mut next = Vec::with_capacity(layer.len().div_ceil(2));
+        let mut pending = layer.into_iter();
+        while let Some(a) = pending.next() {
+            next.push(match pending.next() {
+                Some(b) => backend::union(&a, &b),
+                None => a,
+            });
         }
-        pts
-    } else {
-        Vec::new()
+        layer = next;
     }
+    layer.pop()
 }
 
 /// Returns true if the two polygons intersect when translated by (ax,ay) and (bx,by)
 pub fn polygons_intersect(a: &[Point], b: &[Point], ax: f64, ay: f64, bx: f64, by: f64) -> bool {
     let pa = to_geo_polygon_translated(a, ax, ay);
     let pb = to_geo_polygon_translated(b, bx, by);
-    !Clipper::intersection(&pa, &pb, CLIPPER_SCALE).0.is_empty()
+    !backend::intersection(&MultiPolygon(vec![pa]), &MultiPolygon(vec![pb]))
+        .0
+        .is_empty()
+}
+
+/// Returns true if the parts made of the rings `a` translated by (ax,ay) and
+/// `b` translated by (bx,by) overlap. Holes are respected: a part lying in a
+/// hole of the other does not overlap it. Overlaps no thicker than `contact`
+/// on average are parts touching along an edge, not overlapping.
+pub fn parts_intersect(a: &[Vec<Point>], b: &[Vec<Point>], ax: f64, ay: f64, bx: f64, by: f64, contact: f64) -> bool {
+    let translated = |rings: &[Vec<Point>], tx: f64, ty: f64| -> Vec<Vec<Point>> {
+        rings
+            .iter()
+            .map(|ring| {
+                let mut moved = Vec::with_capacity(ring.len());
+                batch::translate_into(ring, tx, ty, &mut moved);
+                moved
+            })
+            .collect()
+    };
+    let pa = to_geo_multipolygon(&translated(a, ax, ay));
+    let pb = to_geo_multipolygon(&translated(b, bx, by));
+    backend::intersection(&pa, &pb)
+        .0
+        .iter()
+        .any(|overlap| overlap_depth(overlap) > contact)
+}
+
+/// Mean thickness of an overlap: twice its area over its perimeter. A sliver
+/// of depth d along an edge of length l has area d·l and perimeter about 2l.
+fn overlap_depth(overlap: &GeoPolygon<f64>) -> f64 {
+    let perimeter: f64 = std::iter::once(overlap.exterior())
+        .chain(overlap.interiors())
+        .flat_map(|ring| ring.lines())
+        .map(|line| line.dx().hypot(line.dy()))
+        .sum();
+    if perimeter > 0.0 {
+        2.0 * overlap.unsigned_area() / perimeter
+    } else {
+        0.0
+    }
 }
 
 /// Returns true if polygon `b` translated by (bx,by) lies completely inside
-/// polygon `a` translated by (ax,ay).
+/// polygon `a` translated by (ax,ay). Vertices of `b` touching the boundary
+/// of `a` count as inside.
 pub fn polygon_contains_polygon(a: &[Point], b: &[Point], ax: f64, ay: f64, bx: f64, by: f64) -> bool {
-    for p in b {
-        if !point_in_polygon(a, p.x + bx - ax, p.y + by - ay) {
-            return false;
-        }
-    }
-    true
+    let mut moved = Vec::with_capacity(b.len());
+    batch::translate_into(b, bx - ax, by - ay, &mut moved);
+    moved
+        .iter()
+        .all(|p| locate_point(a, p.x, p.y) != PointLocation::Outside)
 }
 
-/// Returns true if point (x,y) lies inside the polygon using even-odd rule.
-pub fn point_in_polygon(poly: &[Point], x: f64, y: f64) -> bool {
+/// Where a point lies relative to a polygon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointLocation {
+    Inside,
+    Boundary,
+    Outside,
+}
+
+fn orient(a: Point, b: Point, c: Point) -> f64 {
+    let coord = |p: Point| robust::Coord { x: p.x, y: p.y };
+    robust::orient2d(coord(a), coord(b), coord(c))
+}
+
+/// Locate point (x,y) relative to the polygon using the even-odd rule. The
+/// test uses exact orientation predicates, so points on an edge or vertex are
+/// always reported as [`PointLocation::Boundary`].
+pub fn locate_point(poly: &[Point], x: f64, y: f64) -> PointLocation {
+    if poly.len() < 3 {
+        return PointLocation::Outside;
+    }
+    let p = Point { x, y };
     let mut inside = false;
     let mut j = poly.len() - 1;
     for i in 0..poly.len() {
-        let xi = poly[i].x;
-        let yi = poly[i].y;
-        let xj = poly[j].x;
-        let yj = poly[j].y;
-        let intersect = ((yi > y) != (yj > y)) && (x < (xj - xi) * (y - yi) / (yj - yi + 1e-9) + xi);
-        if intersect {
+        let (a, b) = (poly[j], poly[i]);
+        j = i;
+        let o = orient(a, b, p);
+        if o == 0.0 && x >= a.x.min(b.x) && x <= a.x.max(b.x) && y >= a.y.min(b.y) && y <= a.y.max(b.y) {
+            return PointLocation::Boundary;
+        }
+        // the edge crosses the horizontal ray to the right of the point when
+        // the point lies left of the upward directed edge
+        if (a.y > y) != (b.y > y) && (o > 0.0) == (b.y > a.y) {
             inside = !inside;
         }
+    }
+    if inside {
+        PointLocation::Inside
+    } else {
+        PointLocation::Outside
+    }
+}
+
+/// Returns true if point (x,y) lies strictly inside the polygon using the
+/// even-odd rule. Points on the boundary are not inside.
+pub fn point_in_polygon(poly: &[Point], x: f64, y: f64) -> bool {
+    locate_point(poly, x, y) == PointLocation::Inside
+}
+
+/// Returns true if point (x,y) lies inside the polygon by more than
+/// `margin`. Points closer than that to an edge count as on the boundary.
+pub fn point_inside_by(poly: &[Point], x: f64, y: f64, margin: f64) -> bool {
+    point_in_polygon(poly, x, y) && !near_edge(poly, x, y, margin)
+}
+
+/// Returns true if point (x,y) lies outside the polygon by more than
+/// `margin`. Points closer than that to an edge count as on the boundary.
+pub fn point_outside_by(poly: &[Point], x: f64, y: f64, margin: f64) -> bool {
+    locate_point(poly, x, y) == PointLocation::Outside && !near_edge(poly, x, y, margin)
+}
+
+/// Whether point (x,y) is within `margin` of an edge of the polygon.
+fn near_edge(poly: &[Point], x: f64, y: f64, margin: f64) -> bool {
+    if poly.is_empty() {
+        return false;
+    }
+    let mut j = poly.len() - 1;
+    for i in 0..poly.len() {
+        let (a, b) = (poly[j], poly[i]);
         j = i;
+        let (ex, ey) = (b.x - a.x, b.y - a.y);
+        let len2 = ex * ex + ey * ey;
+        let t = if len2 > 0.0 {
+            (((x - a.x) * ex + (y - a.y) * ey) / len2).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        if (x - a.x - t * ex).hypot(y - a.y - t * ey) <= margin {
+            return true;
+        }
+    }
+    false
+}
+
+/// Winding direction of a ring. Counter-clockwise rings have negative
+/// [`polygon_area`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Winding {
+    CounterClockwise,
+    Clockwise,
+    /// Fewer than three distinct points or all points collinear
+    Degenerate,
+}
+
+impl Winding {
+    /// Returns true if both windings are defined and differ, i.e. one ring is
+    /// a hole of the other.
+    pub fn opposes(self, other: Winding) -> bool {
+        self != Winding::Degenerate && other != Winding::Degenerate && self != other
+    }
+}
+
+/// Winding of a simple ring, decided exactly by the turn at its lowest
+/// leftmost vertex rather than by the sign of its area.
+pub fn ring_winding(points: &[Point]) -> Winding {
+    let Some(lowest) = (0..points.len()).min_by(|&i, &j| {
+        let (a, b) = (points[i], points[j]);
+        a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y))
+    }) else {
+        return Winding::Degenerate;
+    };
+    let v = points[lowest];
+    let n = points.len();
+    let Some(prev) = (1..n).map(|k| points[(lowest + n - k) % n]).find(|p| *p != v) else {
+        return Winding::Degenerate;
+    };
+    let Some(next) = (1..n).map(|k| points[(lowest + k) % n]).find(|p| *p != v) else {
+        return Winding::Degenerate;
+    };
+    let o = orient(prev, v, next);
+    if o > 0.0 {
+        Winding::CounterClockwise
+    } else if o < 0.0 {
+        Winding::Clockwise
+    } else {
+        // the extreme vertex sits on a straight run, fall back to the area
+        let area = polygon_area(points);
+        if area < 0.0 {
+            Winding::CounterClockwise
+        } else if area > 0.0 {
+            Winding::Clockwise
+        } else {
+            Winding::Degenerate
+        }
     }
-    inside
 }
 
 #[cfg(test)]
@@ -301,6 +844,79 @@ mod tests {
         assert_eq!(bounds.height, 1.0);
     }
 
+    #[test]
+    fn shape_measures_ignore_winding() {
+        // a 4 by 2 rectangle turned by 30 degrees
+        let rect = vec![
+            Point { x: 0.0, y: 0.0 },
+            Point { x: 4.0, y: 0.0 },
+            Point { x: 4.0, y: 2.0 },
+            Point { x: 0.0, y: 2.0 },
+        ];
+        let turned = rotate_polygon(&rect, 30.0);
+        let reversed: Vec<Point> = turned.iter().rev().copied().collect();
+        for ring in [&turned, &reversed] {
+            let c = polygon_centroid(ring).unwrap();
+            let expected = batch::Affine::rotation(30.0).apply(Point { x: 2.0, y: 1.0 });
+            assert!((c.x - expected.x).abs() < 1e-9 && (c.y - expected.y).abs() < 1e-9);
+            assert!((principal_axis(ring).unwrap() - 30.0).abs() < 1e-9);
+            let r = minimum_rotated_rect(ring).unwrap();
+            assert!((r.angle - 30.0).abs() < 1e-9, "{:?}", r);
+            assert!((r.width - 4.0).abs() < 1e-9 && (r.height - 2.0).abs() < 1e-9, "{:?}", r);
+            assert!((r.center.x - expected.x).abs() < 1e-9 && (r.center.y - expected.y).abs() < 1e-9);
+        }
+        assert!((polygon_perimeter(&rect, true) - 12.0).abs() < 1e-12);
+        assert!((polygon_perimeter(&rect, false) - 10.0).abs() < 1e-12);
+        assert_eq!(convex_hull(rect.iter().copied().chain([Point { x: 1.0, y: 1.0 }])).len(), 4);
+        assert_eq!(polygon_centroid(&rect[..2]), None);
+        assert_eq!(minimum_rotated_rect(&[]), None);
+    }
+
+    #[test]
+    fn bounds_overlap_with_gap() {
+        let a = Bounds { x: 0.0, y: 0.0, width: 2.0, height: 2.0 };
+        let b = a.translated(3.0, 0.0);
+        assert!(!a.overlaps(&b, 0.0));
+        assert!(a.overlaps(&b, 1.0));
+        assert!(a.overlaps(&a.translated(2.0, 2.0), 0.0));
+        assert!(!a.overlaps(&a.translated(0.0, -2.5), 0.25));
+    }
+
+    #[test]
+    fn points_on_edges_are_on_the_boundary() {
+        let tri = vec![
+            Point { x: 0.0, y: 0.0 },
+            Point { x: 3.0, y: 0.0 },
+            Point { x: 0.0, y: 3.0 },
+        ];
+        assert_eq!(locate_point(&tri, 1.0, 1.0), PointLocation::Inside);
+        assert_eq!(locate_point(&tri, 1.0, 2.0), PointLocation::Boundary);
+        assert_eq!(locate_point(&tri, 0.0, 3.0), PointLocation::Boundary);
+        assert_eq!(locate_point(&tri, 1.5, 1.5), PointLocation::Boundary);
+        assert_eq!(locate_point(&tri, 1.5, 1.5 + 1e-12), PointLocation::Outside);
+        assert_eq!(locate_point(&tri, -1.0, 0.0), PointLocation::Outside);
+        assert!(!point_in_polygon(&tri, 3.0, 0.0));
+        assert!(!point_in_polygon(&[], 0.0, 0.0));
+        assert_eq!(locate_point(&tri[..1], 0.0, 0.0), PointLocation::Outside);
+    }
+
+    #[test]
+    fn winding_follows_area_sign() {
+        let mut pts = vec![
+            Point { x: 0.0, y: 0.0 },
+            Point { x: 1.0, y: 0.0 },
+            Point { x: 1.0, y: 1.0 },
+            Point { x: 0.0, y: 1.0 },
+        ];
+        assert_eq!(ring_winding(&pts), Winding::CounterClockwise);
+        pts.reverse();
+        assert_eq!(ring_winding(&pts), Winding::Clockwise);
+        assert!(Winding::Clockwise.opposes(Winding::CounterClockwise));
+        let line = vec![Point { x: 0.0, y: 0.0 }, Point { x: 1.0, y: 1.0 }, Point { x: 2.0, y: 2.0 }];
+        assert_eq!(ring_winding(&line), Winding::Degenerate);
+        assert!(!Winding::Degenerate.opposes(Winding::Clockwise));
+    }
+
     #[test]
     fn area_of_triangle_ccw() {
         let pts = vec![
@@ -331,4 +947,61 @@ mod tests {
         assert_eq!(polygon_area(&pts), 0.0);
         assert!(get_polygon_bounds(&pts).is_none());
     }
+
+    fn square(x: f64, y: f64, size: f64) -> Vec<Point> {
+        vec![
+            Point { x, y },
+            Point { x: x + size, y },
+            Point { x: x + size, y: y + size },
+            Point { x, y: y + size },
+        ]
+    }
+
+    #[test]
+    fn ring_parts_keep_their_holes() {
+        let frame = vec![square(0.0, 0.0, 10.0), square(2.0, 2.0, 6.0)];
+        let mp = to_geo_multipolygon(&frame);
+        assert_eq!(mp.0.len(), 1);
+        assert_eq!(mp.0[0].interiors().len(), 1);
+
+        let small = vec![square(0.0, 0.0, 2.0)];
+        assert!(!parts_intersect(&frame, &small, 0.0, 0.0, 3.0, 3.0, 0.0));
+        assert!(parts_intersect(&frame, &small, 0.0, 0.0, 1.0, 3.0, 0.0));
+        // the frame placed around an already placed part
+        assert!(!parts_intersect(&small, &frame, 3.0, 3.0, 0.0, 0.0, 0.0));
+
+        let grown = offset_rings(&frame, 1.0);
+        assert_eq!(grown.len(), 2);
+        assert!(polygon_area(&grown[0]) < 0.0);
+        assert!((polygon_area(&grown[1]) - 16.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn touching_parts_do_not_overlap() {
+        let a = vec![square(0.0, 0.0, 2.0)];
+        // run into the neighbour by a rounding error
+        assert!(!parts_intersect(&a, &a, 0.0, 0.0, 2.0 - 1e-5, 0.0, 1e-4));
+        assert!(parts_intersect(&a, &a, 0.0, 0.0, 2.0 - 1e-5, 0.0, 0.0));
+        assert!(parts_intersect(&a, &a, 0.0, 0.0, 1.9, 0.0, 1e-4));
+
+        let nfp = square(-2.0, -2.0, 4.0);
+        assert!(point_inside_by(&nfp, 0.0, 0.0, 1e-4));
+        assert!(!point_inside_by(&nfp, 2.0 - 1e-5, 0.0, 1e-4));
+        assert!(point_in_polygon(&nfp, 2.0 - 1e-5, 0.0));
+    }
+
+    #[test]
+    fn stroked_corner_gets_joins_and_caps() {
+        let line = [Point { x: 0.0, y: 0.0 }, Point { x: 10.0, y: 0.0 }, Point { x: 10.0, y: 10.0 }];
+        let bounds = |join, cap| {
+            let rings = offset_polyline(&line, 1.0, join, cap, 0.01);
+            assert_eq!(rings.len(), 1);
+            assert!(polygon_area(&rings[0]) < 0.0);
+            let b = get_polygon_bounds(&rings[0]).unwrap();
+            [b.x, b.y, b.x + b.width, b.y + b.height].map(|v| (v * 10.0).round() / 10.0)
+        };
+        assert_eq!(bounds(LineJoin::Miter(4.0), LineCap::Butt), [0.0, -1.0, 11.0, 10.0]);
+        assert_eq!(bounds(LineJoin::Bevel, LineCap::Square), [-1.0, -1.0, 11.0, 11.0]);
+        assert_eq!(bounds(LineJoin::Round, LineCap::Round), [-1.0, -1.0, 11.0, 11.0]);
+    }
 }
@@ -0,0 +1,19 @@
+//! Clipping and offsetting backends. The default binds the C++ Clipper
+//! library through `geo-clipper`; the `pure-rust` feature swaps in
+//! `i_overlay`, which needs no C++ toolchain and builds for WASM.
+//!
+//! Both backends take and return geo multipolygons with outlines wound
+//! counter-clockwise and holes clockwise.
+
+#[cfg(not(feature = "pure-rust"))]
+mod clipper;
+#[cfg(not(feature = "pure-rust"))]
+pub use clipper::*;
+
+#[cfg(feature = "pure-rust")]
+mod overlay;
+#[cfg(feature = "pure-rust")]
+pub use overlay::*;
+
+#[cfg(not(any(feature = "clipper", feature = "pure-rust")))]
+compile_error!("enable either the `clipper` or the `pure-rust` feature");
@@ -0,0 +1,131 @@
+use std::f64::consts::{FRAC_PI_4, TAU};
+
+use geo::{Coord, LineString, MultiPolygon, Polygon, coord};
+use geo_clipper::{Clipper, EndType, JoinType};
+
+use crate::geometry::{CLIPPER_SCALE, LineCap, LineJoin};
+use crate::profile::{self, Stage};
+
+fn join_type(join: LineJoin, tol: f64) -> JoinType {
+    match join {
+        LineJoin::Miter(limit) => JoinType::Miter(limit),
+        LineJoin::Round => JoinType::Round(tol),
+        LineJoin::Bevel => JoinType::Square,
+    }
+}
+
+pub fn union(a: &MultiPolygon<f64>, b: &MultiPolygon<f64>) -> MultiPolygon<f64> {
+    let _span = profile::span(Stage::Boolean);
+    Clipper::union(a, b, CLIPPER_SCALE)
+}
+
+pub fn intersection(a: &MultiPolygon<f64>, b: &MultiPolygon<f64>) -> MultiPolygon<f64> {
+    let _span = profile::span(Stage::Boolean);
+    Clipper::intersection(a, b, CLIPPER_SCALE)
+}
+
+pub fn difference(a: &MultiPolygon<f64>, b: &MultiPolygon<f64>) -> MultiPolygon<f64> {
+    let _span = profile::span(Stage::Boolean);
+    Clipper::difference(a, b, CLIPPER_SCALE)
+}
+
+pub fn xor(a: &MultiPolygon<f64>, b: &MultiPolygon<f64>) -> MultiPolygon<f64> {
+    let _span = profile::span(Stage::Boolean);
+    Clipper::xor(a, b, CLIPPER_SCALE)
+}
+
+/// Offset closed polygons by `delta`. `tol` bounds the error of round joins.
+pub fn offset(mp: &MultiPolygon<f64>, delta: f64, join: LineJoin, tol: f64) -> MultiPolygon<f64> {
+    let _span = profile::span(Stage::Boolean);
+    Clipper::offset(mp, delta, join_type(join, tol), EndType::ClosedPolygon, CLIPPER_SCALE)
+}
+
+/// Outline of the open `line` stroked with half-width `delta`. Clipper
+/// drops open paths before it offsets them, so the outline is the union of
+/// a rectangle around every segment with the joins and caps.
+pub fn stroke(line: &LineString<f64>, delta: f64, join: LineJoin, cap: LineCap, tol: f64) -> MultiPolygon<f64> {
+    let _span = profile::span(Stage::Boolean);
+    let mut pts: Vec<Coord<f64>> = line.coords().copied().collect();
+    pts.dedup();
+    if pts.len() < 2 {
+        return MultiPolygon::new(Vec::new());
+    }
+    let n = pts.len();
+    // unit direction of segment `i` and its left normal of length `delta`
+    let dir = |i: usize| {
+        let d = pts[i + 1] - pts[i];
+        d / d.x.hypot(d.y)
+    };
+    let normal = |u: Coord<f64>| coord! { x: -u.y, y: u.x } * delta;
+    let mut pieces = Vec::new();
+    for i in 0..n - 1 {
+        let u = dir(i);
+        let (mut a, mut b) = (pts[i], pts[i + 1]);
+        if cap == LineCap::Square {
+            if i == 0 {
+                a = a - u * delta;
+            }
+            if i == n - 2 {
+                b = b + u * delta;
+            }
+        }
+        let nrm = normal(u);
+        pieces.push(piece(vec![a - nrm, b - nrm, b + nrm, a + nrm]));
+    }
+    for (i, &p) in pts.iter().enumerate().take(n - 1).skip(1) {
+        let (u1, u2) = (dir(i - 1), dir(i));
+        let turn = u1.x * u2.y - u1.y * u2.x;
+        if join == LineJoin::Round {
+            pieces.push(circle(p, delta, tol));
+            continue;
+        }
+        if turn.abs() < 1e-12 {
+            continue;
+        }
+        // the corner is filled on the outer side of the turn
+        let side = if turn > 0.0 { -1.0 } else { 1.0 };
+        let (n1, n2) = (normal(u1) * side, normal(u2) * side);
+        let mid = n1 + n2;
+        let cos_half = (mid.x.hypot(mid.y) / 2.0) / delta;
+        match join {
+            LineJoin::Miter(limit) if cos_half > 0.0 && 1.0 / cos_half <= limit => {
+                let tip = p + mid / (mid.x.hypot(mid.y) * cos_half) * delta;
+                pieces.push(piece(vec![p, p + n1, tip, p + n2]));
+            }
+            _ => pieces.push(piece(vec![p, p + n1, p + n2])),
+        }
+    }
+    if cap == LineCap::Round {
+        pieces.push(circle(pts[0], delta, tol));
+        pieces.push(circle(pts[n - 1], delta, tol));
+    }
+    Clipper::union(&MultiPolygon::new(pieces), &MultiPolygon::new(Vec::new()), CLIPPER_SCALE)
+}
+
+/// Polygon on the corners `ring`, wound counter-clockwise so overlapping
+/// pieces add up under the non-zero fill of the union.
+fn piece(mut ring: Vec<Coord<f64>>) -> Polygon<f64> {
+    let area: f64 = (0..ring.len())
+        .map(|i| {
+            let (a, b) = (ring[i], ring[(i + 1) % ring.len()]);
+            a.x * b.y - b.x * a.y
+        })
+        .sum();
+    if area < 0.0 {
+        ring.reverse();
+    }
+    Polygon::new(LineString::new(ring), Vec::new())
+}
+
+/// Circle of radius `r` around `c`, off by at most `tol`.
+fn circle(c: Coord<f64>, r: f64, tol: f64) -> Polygon<f64> {
+    let step = if tol <= 0.0 || r <= tol { FRAC_PI_4 } else { 2.0 * (1.0 - tol / r).acos() };
+    let steps = (TAU / step).ceil().max(8.0) as usize;
+    let ring = (0..steps)
+        .map(|k| {
+            let a = TAU * k as f64 / steps as f64;
+            coord! { x: c.x + r * a.cos(), y: c.y + r * a.sin() }
+        })
+        .collect();
+    piece(ring)
+}
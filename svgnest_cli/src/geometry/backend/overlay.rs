@@ -0,0 +1,127 @@
+use std::f64::consts::FRAC_PI_4;
+
+use geo::{LineString, MultiPolygon, Polygon};
+use i_overlay::core::fill_rule::FillRule;
+use i_overlay::core::overlay_rule::OverlayRule;
+use i_overlay::float::single::SingleFloatOverlay;
+use i_overlay::mesh::outline::offset::OutlineOffset;
+use i_overlay::mesh::stroke::offset::StrokeOffset;
+use i_overlay::mesh::style::{self, OutlineStyle, StrokeStyle};
+
+use crate::geometry::{LineCap, LineJoin};
+use crate::profile::{self, Stage};
+
+type Shapes = Vec<Vec<Vec<[f64; 2]>>>;
+
+/// Twice the signed area, positive for counter-clockwise rings.
+fn signed_area(ring: &[[f64; 2]]) -> f64 {
+    let mut area = 0.0;
+    for (i, a) in ring.iter().enumerate() {
+        let b = ring[(i + 1) % ring.len()];
+        area += a[0] * b[1] - b[0] * a[1];
+    }
+    area
+}
+
+/// Open ring oriented clockwise for outlines and counter-clockwise for holes,
+/// which is what `i_overlay` expects.
+fn to_ring(ls: &LineString<f64>, outline: bool) -> Vec<[f64; 2]> {
+    let mut pts: Vec<[f64; 2]> = ls.coords().map(|c| [c.x, c.y]).collect();
+    if pts.len() > 1 && pts[0] == pts[pts.len() - 1] {
+        pts.pop();
+    }
+    if (signed_area(&pts) < 0.0) != outline {
+        pts.reverse();
+    }
+    pts
+}
+
+fn to_shapes(mp: &MultiPolygon<f64>) -> Shapes {
+    mp.0.iter()
+        .map(|p| {
+            std::iter::once(to_ring(p.exterior(), true))
+                .chain(p.interiors().iter().map(|h| to_ring(h, false)))
+                .collect()
+        })
+        .collect()
+}
+
+/// Convert back to geo types, flipping the rings to the orientation the
+/// Clipper backend produces.
+fn from_shapes(shapes: Shapes) -> MultiPolygon<f64> {
+    MultiPolygon(
+        shapes
+            .into_iter()
+            .filter_map(|shape| {
+                let mut rings = shape.into_iter().map(|mut ring| {
+                    ring.reverse();
+                    LineString::from(ring.into_iter().map(|[x, y]| (x, y)).collect::<Vec<_>>())
+                });
+                let exterior = rings.next()?;
+                Some(Polygon::new(exterior, rings.collect()))
+            })
+            .collect(),
+    )
+}
+
+fn overlay(a: &MultiPolygon<f64>, b: &MultiPolygon<f64>, rule: OverlayRule) -> MultiPolygon<f64> {
+    let _span = profile::span(Stage::Boolean);
+    from_shapes(to_shapes(a).overlay(&to_shapes(b), rule, FillRule::NonZero))
+}
+
+/// Angle per segment that keeps a round join of radius `r` within `tol`.
+fn round_step(r: f64, tol: f64) -> f64 {
+    if tol <= 0.0 || r.abs() <= tol {
+        return FRAC_PI_4;
+    }
+    2.0 * (1.0 - tol / r.abs()).acos()
+}
+
+fn line_join(join: LineJoin, delta: f64, tol: f64) -> style::LineJoin<f64> {
+    match join {
+        // i_overlay limits miters by the sharpest corner angle instead of
+        // the miter length
+        LineJoin::Miter(limit) => style::LineJoin::Miter(2.0 * (1.0 / limit.max(1.0)).asin()),
+        LineJoin::Round => style::LineJoin::Round(round_step(delta, tol)),
+        LineJoin::Bevel => style::LineJoin::Bevel,
+    }
+}
+
+pub fn union(a: &MultiPolygon<f64>, b: &MultiPolygon<f64>) -> MultiPolygon<f64> {
+    overlay(a, b, OverlayRule::Union)
+}
+
+pub fn intersection(a: &MultiPolygon<f64>, b: &MultiPolygon<f64>) -> MultiPolygon<f64> {
+    overlay(a, b, OverlayRule::Intersect)
+}
+
+pub fn difference(a: &MultiPolygon<f64>, b: &MultiPolygon<f64>) -> MultiPolygon<f64> {
+    overlay(a, b, OverlayRule::Difference)
+}
+
+pub fn xor(a: &MultiPolygon<f64>, b: &MultiPolygon<f64>) -> MultiPolygon<f64> {
+    overlay(a, b, OverlayRule::Xor)
+}
+
+/// Offset closed polygons by `delta`. `tol` bounds the error of round joins.
+pub fn offset(mp: &MultiPolygon<f64>, delta: f64, join: LineJoin, tol: f64) -> MultiPolygon<f64> {
+    let _span = profile::span(Stage::Boolean);
+    let style = OutlineStyle::new(delta).line_join(line_join(join, delta, tol));
+    from_shapes(to_shapes(mp).outline(style))
+}
+
+/// Outline of the open `line` stroked with half-width `delta`.
+pub fn stroke(line: &LineString<f64>, delta: f64, join: LineJoin, cap: LineCap, tol: f64) -> MultiPolygon<f64> {
+    let _span = profile::span(Stage::Boolean);
+    let path: Vec<[f64; 2]> = line.coords().map(|c| [c.x, c.y]).collect();
+    let cap = || match cap {
+        LineCap::Butt => style::LineCap::Butt,
+        LineCap::Round => style::LineCap::Round(round_step(delta, tol)),
+        LineCap::Square => style::LineCap::Square,
+    };
+    let style = StrokeStyle::new(2.0 * delta)
+        .line_join(line_join(join, delta, tol))
+        .start_cap(cap())
+        .end_cap(cap());
+    from_shapes(path.stroke(style, false))
+}
@@ -0,0 +1,251 @@
+//! Boolean operations, offsetting and Minkowski sums on polygons with holes.
+//!
+//! Rings may be passed in either orientation and without a repeated closing
+//! point. Results follow the convention of the rest of the crate: outlines are
+//! wound with negative [`polygon_area`], holes with positive area.
+
+use geo::{LineString, MultiPolygon, Polygon};
+
+use super::{LineJoin, PointLocation, backend, locate_point, polygon_area};
+use crate::svg_parser::Point;
+
+/// Polygon with holes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Shape {
+    pub outline: Vec<Point>,
+    pub holes: Vec<Vec<Point>>,
+}
+
+impl Shape {
+    pub fn new(outline: Vec<Point>) -> Self {
+        Self {
+            outline,
+            holes: Vec::new(),
+        }
+    }
+
+    pub fn with_holes(outline: Vec<Point>, holes: Vec<Vec<Point>>) -> Self {
+        Self { outline, holes }
+    }
+
+    /// Area covered by the shape, i.e. the outline minus its holes.
+    pub fn area(&self) -> f64 {
+        polygon_area(&self.outline).abs() - self.holes.iter().map(|h| polygon_area(h).abs()).sum::<f64>()
+    }
+}
+
+/// Group rings into polygons with holes by how deeply they are nested:
+/// rings inside an even number of other rings are outlines, the others holes
+/// of the innermost outline around them. Rings of touching or overlapping
+/// contours are not inside each other.
+pub fn shapes_from_rings(rings: &[Vec<Point>]) -> Vec<Shape> {
+    let inside = |i: usize, j: usize| {
+        let locations: Vec<PointLocation> = rings[i].iter().map(|p| locate_point(&rings[j], p.x, p.y)).collect();
+        i != j
+            && !locations.contains(&PointLocation::Outside)
+            && locations.contains(&PointLocation::Inside)
+    };
+    let parents: Vec<Vec<usize>> = (0..rings.len())
+        .map(|i| (0..rings.len()).filter(|&j| inside(i, j)).collect())
+        .collect();
+    let mut shapes: Vec<Shape> = Vec::new();
+    let mut index = vec![None; rings.len()];
+    for i in (0..rings.len()).filter(|&i| parents[i].len().is_multiple_of(2)) {
+        index[i] = Some(shapes.len());
+        shapes.push(Shape::new(rings[i].clone()));
+    }
+    for i in (0..rings.len()).filter(|&i| parents[i].len() % 2 == 1) {
+        // the innermost ring around a hole has the most rings around itself
+        let owner = parents[i].iter().copied().max_by_key(|&j| parents[j].len());
+        if let Some(shape) = owner.and_then(|j| index[j]) {
+            shapes[shape].holes.push(rings[i].clone());
+        }
+    }
+    shapes
+}
+
+/// Rings of `shapes`, every outline followed by its holes.
+pub fn rings_of(shapes: Vec<Shape>) -> Vec<Vec<Point>> {
+    shapes
+        .into_iter()
+        .flat_map(|s| std::iter::once(s.outline).chain(s.holes))
+        .collect()
+}
+
+/// Ring as a geo line string, wound with negative area for outlines and
+/// positive area for holes.
+fn ring_to_geo(ring: &[Point], outline: bool) -> LineString<f64> {
+    let mut coords: Vec<(f64, f64)> = ring.iter().map(|p| (p.x, p.y)).collect();
+    if (polygon_area(ring) < 0.0) != outline {
+        coords.reverse();
+    }
+    coords.into()
+}
+
+fn ring_from_geo(ring: &LineString<f64>, outline: bool) -> Vec<Point> {
+    let mut pts: Vec<Point> = ring.points().map(|c| Point { x: c.x(), y: c.y() }).collect();
+    if pts.len() > 1 && pts[0] == pts[pts.len() - 1] {
+        pts.pop();
+    }
+    if (polygon_area(&pts) < 0.0) != outline {
+        pts.reverse();
+    }
+    pts
+}
+
+pub(crate) fn to_geo(shapes: &[Shape]) -> MultiPolygon<f64> {
+    MultiPolygon(
+        shapes
+            .iter()
+            .filter(|s| s.outline.len() >= 3)
+            .map(|s| {
+                Polygon::new(
+                    ring_to_geo(&s.outline, true),
+                    s.holes.iter().map(|h| ring_to_geo(h, false)).collect(),
+                )
+            })
+            .collect(),
+    )
+}
+
+pub(crate) fn from_geo(mp: MultiPolygon<f64>) -> Vec<Shape> {
+    mp.0.iter()
+        .map(|p| Shape {
+            outline: ring_from_geo(p.exterior(), true),
+            holes: p.interiors().iter().map(|h| ring_from_geo(h, false)).collect(),
+        })
+        .collect()
+}
+
+/// Area covered by `a` or `b`.
+pub fn union(a: &[Shape], b: &[Shape]) -> Vec<Shape> {
+    from_geo(backend::union(&to_geo(a), &to_geo(b)))
+}
+
+/// Area covered by both `a` and `b`.
+pub fn intersection(a: &[Shape], b: &[Shape]) -> Vec<Shape> {
+    from_geo(backend::intersection(&to_geo(a), &to_geo(b)))
+}
+
+/// Area covered by `a` but not by `b`.
+pub fn difference(a: &[Shape], b: &[Shape]) -> Vec<Shape> {
+    from_geo(backend::difference(&to_geo(a), &to_geo(b)))
+}
+
+/// Area covered by exactly one of `a` and `b`.
+pub fn xor(a: &[Shape], b: &[Shape]) -> Vec<Shape> {
+    from_geo(backend::xor(&to_geo(a), &to_geo(b)))
+}
+
+/// Grow (positive `delta`) or shrink (negative `delta`) the shapes. Holes
+/// shrink as the outline grows. `tol` bounds the error of round joins.
+pub fn offset(shapes: &[Shape], delta: f64, join: LineJoin, tol: f64) -> Vec<Shape> {
+    from_geo(backend::offset(&to_geo(shapes), delta, join, tol))
+}
+
+/// Minkowski sum of `a` and the simple polygon `b`: every point of `a`
+/// translated by every point of `b`.
+///
+/// The sum is assembled from `a` translated by a vertex of `b`, `b`
+/// translated by a vertex of every ring of `a`, and the quads swept by each
+/// edge of `a` along each edge of `b`, unioned in a single pass.
+pub fn minkowski_sum(a: &Shape, b: &[Point]) -> Vec<Shape> {
+    if a.outline.len() < 3 || b.len() < 3 {
+        return Vec::new();
+    }
+    let shift = |ring: &[Point], by: Point| -> Vec<Point> {
+        ring.iter()
+            .map(|p| Point {
+                x: p.x + by.x,
+                y: p.y + by.y,
+            })
+            .collect()
+    };
+
+    let mut pieces = vec![Shape::with_holes(
+        shift(&a.outline, b[0]),
+        a.holes.iter().map(|h| shift(h, b[0])).collect(),
+    )];
+    for ring in std::iter::once(&a.outline).chain(&a.holes) {
+        let Some(&first) = ring.first() else {
+            continue;
+        };
+        pieces.push(Shape::new(shift(b, first)));
+        for i in 0..ring.len() {
+            let (p1, p2) = (ring[i], ring[(i + 1) % ring.len()]);
+            for j in 0..b.len() {
+                let (q1, q2) = (b[j], b[(j + 1) % b.len()]);
+                let quad = vec![
+                    Point { x: p1.x + q1.x, y: p1.y + q1.y },
+                    Point { x: p2.x + q1.x, y: p2.y + q1.y },
+                    Point { x: p2.x + q2.x, y: p2.y + q2.y },
+                    Point { x: p1.x + q2.x, y: p1.y + q2.y },
+                ];
+                if polygon_area(&quad) != 0.0 {
+                    pieces.push(Shape::new(quad));
+                }
+            }
+        }
+    }
+    union(&pieces, &[])
+}
+
+/// Minkowski difference `a ⊕ -b`, the set of translations of `b` that make
+/// it overlap `a`. This is the no-fit polygon of `b` around `a`, relative to
+/// the origin of `b`.
+pub fn minkowski_difference(a: &Shape, b: &[Point]) -> Vec<Shape> {
+    let negated: Vec<Point> = b.iter().map(|p| Point { x: -p.x, y: -p.y }).collect();
+    minkowski_sum(a, &negated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(x: f64, y: f64, size: f64) -> Vec<Point> {
+        vec![
+            Point { x, y },
+            Point { x: x + size, y },
+            Point { x: x + size, y: y + size },
+            Point { x, y: y + size },
+        ]
+    }
+
+    #[test]
+    fn boolean_ops_on_overlapping_squares() {
+        let a = [Shape::new(square(0.0, 0.0, 2.0))];
+        let b = [Shape::new(square(1.0, 1.0, 2.0))];
+        let area = |s: Vec<Shape>| s.iter().map(Shape::area).sum::<f64>();
+        assert!((area(union(&a, &b)) - 7.0).abs() < 1e-6);
+        assert!((area(intersection(&a, &b)) - 1.0).abs() < 1e-6);
+        assert!((area(difference(&a, &b)) - 3.0).abs() < 1e-6);
+        assert!((area(xor(&a, &b)) - 6.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn difference_keeps_holes() {
+        let frame = difference(&[Shape::new(square(0.0, 0.0, 4.0))], &[Shape::new(square(1.0, 1.0, 2.0))]);
+        assert_eq!(frame.len(), 1);
+        assert_eq!(frame[0].holes.len(), 1);
+        assert!(polygon_area(&frame[0].outline) < 0.0);
+        assert!(polygon_area(&frame[0].holes[0]) > 0.0);
+        assert!((frame[0].area() - 12.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn minkowski_sum_of_squares() {
+        let sum = minkowski_sum(&Shape::new(square(0.0, 0.0, 4.0)), &square(0.0, 0.0, 1.0));
+        assert_eq!(sum.len(), 1);
+        assert!(sum[0].holes.is_empty());
+        assert!((sum[0].area() - 25.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn minkowski_sum_shrinks_holes() {
+        let frame = Shape::with_holes(square(0.0, 0.0, 10.0), vec![square(2.0, 2.0, 6.0)]);
+        let sum = minkowski_sum(&frame, &square(0.0, 0.0, 1.0));
+        assert_eq!(sum.len(), 1);
+        assert_eq!(sum[0].holes.len(), 1);
+        assert!((sum[0].area() - (121.0 - 25.0)).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,348 @@
+//! Optional GPU backend for no-fit polygons.
+//!
+//! A compute shader forms the Minkowski point sums of the two polygons and
+//! rasterizes the quads swept by every pair of edges into an occupancy grid.
+//! Covering a cell from any quad unions the quads for free. The quads cover
+//! the translations at which the outlines cross; regions they enclose are
+//! filled on the CPU where one polygon lies inside the other. The grid is
+//! accurate to one cell, so it is kept as a [`RasterNfp`], which only rules
+//! out positions overlapping for sure.
+
+use wgpu::util::DeviceExt;
+
+use crate::geometry::{get_polygon_bounds, point_in_polygon};
+use crate::nfp::RasterNfp;
+use crate::svg_parser::Point;
+
+/// Number of grid cells along the longer side of an NFP.
+pub const DEFAULT_RESOLUTION: u32 = 512;
+
+pub struct GpuNfp {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    layout: wgpu::BindGroupLayout,
+    sums: wgpu::ComputePipeline,
+    raster: wgpu::ComputePipeline,
+    resolution: u32,
+}
+
+fn storage_entry(binding: u32, read_only: bool) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+impl GpuNfp {
+    /// Connect to the default GPU adapter. Returns `None` when no adapter or
+    /// device is available, in which case callers stay on the CPU.
+    pub fn new(resolution: u32) -> Option<Self> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            ..Default::default()
+        }))?;
+        let (device, queue) =
+            pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default(), None)).ok()?;
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("nfp"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("gpu/nfp.wgsl").into()),
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("nfp"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                storage_entry(1, true),
+                storage_entry(2, true),
+                storage_entry(3, false),
+                storage_entry(4, false),
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("nfp"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = |entry: &str| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry),
+                layout: Some(&pipeline_layout),
+                module: &module,
+                entry_point: Some(entry),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+        let sums = pipeline("sums_main");
+        let raster = pipeline("raster_main");
+        Some(Self {
+            device,
+            queue,
+            layout,
+            sums,
+            raster,
+            resolution: resolution.max(8),
+        })
+    }
+
+    /// Outer no-fit polygon of `b` around `a` on a grid, positioned like
+    /// [`crate::geometry::minkowski_difference_clip`]. Returns `None` if the
+    /// GPU could not produce a result.
+    pub fn raster_nfp(&self, a: &[Point], b: &[Point]) -> Option<RasterNfp> {
+        let ab = get_polygon_bounds(a)?;
+        let bb = get_polygon_bounds(b)?;
+        let (a0, b0) = (*a.first()?, *b.first()?);
+        // bounds of a - b
+        let (min_x, min_y) = (ab.x - (bb.x + bb.width), ab.y - (bb.y + bb.height));
+        let (width, height) = (ab.width + bb.width, ab.height + bb.height);
+        let cell = width.max(height) / self.resolution as f64;
+        if cell <= 0.0 {
+            return None;
+        }
+        let nx = (width / cell).ceil() as u32 + 2;
+        let ny = (height / cell).ceil() as u32 + 2;
+
+        let to_f32 = |pts: &[Point]| -> Vec<[f32; 2]> { pts.iter().map(|p| [p.x as f32, p.y as f32]).collect() };
+        let params: [u32; 8] = [
+            a.len() as u32,
+            b.len() as u32,
+            nx,
+            ny,
+            (min_x as f32).to_bits(),
+            (min_y as f32).to_bits(),
+            (cell as f32).to_bits(),
+            0,
+        ];
+        let init = |label: &str, contents: &[u8], usage: wgpu::BufferUsages| {
+            self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents,
+                usage,
+            })
+        };
+        let params_buf = init("params", bytemuck::cast_slice(&params), wgpu::BufferUsages::UNIFORM);
+        let a_buf = init("a", bytemuck::cast_slice(&to_f32(a)), wgpu::BufferUsages::STORAGE);
+        let b_buf = init("b", bytemuck::cast_slice(&to_f32(b)), wgpu::BufferUsages::STORAGE);
+        let sums_buf = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("sums"),
+            size: (a.len() * b.len() * 8) as u64,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let grid_size = nx as u64 * ny as u64 * 4;
+        let grid_buf = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("grid"),
+            size: grid_size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size: grid_size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("nfp"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: a_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: b_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: sums_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: grid_buf.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        {
+            let mut pass = encoder.begin_compute_pass(&Default::default());
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.set_pipeline(&self.sums);
+            pass.dispatch_workgroups(((a.len() * b.len()) as u32).div_ceil(64), 1, 1);
+        }
+        {
+            let mut pass = encoder.begin_compute_pass(&Default::default());
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.set_pipeline(&self.raster);
+            pass.dispatch_workgroups(nx.div_ceil(8), ny.div_ceil(8), 1);
+        }
+        encoder.copy_buffer_to_buffer(&grid_buf, 0, &readback, 0, grid_size);
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = readback.slice(..);
+        let (tx, rx) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |res| {
+            let _ = tx.send(res);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        rx.recv().ok()?.ok()?;
+        let grid: Vec<u32> = slice
+            .get_mapped_range()
+            .chunks_exact(4)
+            .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+            .collect();
+        readback.unmap();
+
+        // cell (gx, gy) is centered on the translation
+        // (min + (g - 0.5) * cell) of `b` relative to its first vertex
+        let origin = Point {
+            x: min_x - cell + b0.x,
+            y: min_y - cell + b0.y,
+        };
+        let (nx, ny) = (nx as usize, ny as usize);
+        let contained = |gx: usize, gy: usize| {
+            let x = origin.x + (gx as f64 + 0.5) * cell;
+            let y = origin.y + (gy as f64 + 0.5) * cell;
+            point_in_polygon(a, x, y) || point_in_polygon(b, a0.x - x + b0.x, a0.y - y + b0.y)
+        };
+        let covered = fill_enclosed(&grid, nx, ny, contained);
+        Some(RasterNfp::from_cells(origin, cell, nx, ny, &covered))
+    }
+}
+
+/// Covered cells of an `nx` x `ny` grid, with the regions enclosed by
+/// covered cells added where `contained` is true for one of their cells.
+/// Within such a region the outlines do not cross, so either one polygon
+/// lies inside the other throughout or they are apart throughout, as when
+/// one sits in a pocket of the other.
+fn fill_enclosed(grid: &[u32], nx: usize, ny: usize, contained: impl Fn(usize, usize) -> bool) -> Vec<bool> {
+    let mut covered: Vec<bool> = grid.iter().take(nx * ny).map(|&c| c != 0).collect();
+    let mut seen = covered.clone();
+    let mut region = Vec::new();
+    for start in 0..nx * ny {
+        if seen[start] {
+            continue;
+        }
+        // flood the uncovered region around `start`
+        region.clear();
+        region.push(start);
+        seen[start] = true;
+        let mut at_border = false;
+        let mut k = 0;
+        while k < region.len() {
+            let c = region[k];
+            k += 1;
+            let (x, y) = (c % nx, c / nx);
+            at_border |= x == 0 || y == 0 || x + 1 == nx || y + 1 == ny;
+            let neighbours = [
+                (x > 0).then(|| c - 1),
+                (x + 1 < nx).then(|| c + 1),
+                (y > 0).then(|| c - nx),
+                (y + 1 < ny).then(|| c + nx),
+            ];
+            for n in neighbours.into_iter().flatten() {
+                if !seen[n] {
+                    seen[n] = true;
+                    region.push(n);
+                }
+            }
+        }
+        if !at_border && contained(start % nx, start / nx) {
+            for &c in &region {
+                covered[c] = true;
+            }
+        }
+    }
+    covered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::minkowski_difference_shapes;
+    use crate::nfp::Nfp;
+
+    #[test]
+    fn fills_enclosed_regions_where_contained() {
+        // a 3x3 ring of covered cells with an empty middle, inside a margin
+        let (nx, ny) = (5, 5);
+        let mut grid = vec![0u32; nx * ny];
+        for y in 1..4 {
+            for x in 1..4 {
+                if (x, y) != (2, 2) {
+                    grid[y * nx + x] = 1;
+                }
+            }
+        }
+        let count = |covered: Vec<bool>| covered.into_iter().filter(|&c| c).count();
+        assert_eq!(count(fill_enclosed(&grid, nx, ny, |_, _| true)), 9);
+        // the middle is a pocket the rings are apart in
+        assert_eq!(count(fill_enclosed(&grid, nx, ny, |_, _| false)), 8);
+        // the margin is never filled
+        assert_eq!(count(fill_enclosed(&[0; 25], nx, ny, |_, _| true)), 0);
+    }
+
+    #[test]
+    fn agrees_with_the_cpu_nfp() {
+        // without a GPU adapter there is nothing to compare
+        let Some(gpu) = GpuNfp::new(128) else {
+            return;
+        };
+        let pts = |coords: &[(f64, f64)]| coords.iter().map(|&(x, y)| Point { x, y }).collect::<Vec<_>>();
+        // a U with a slot the square fits into
+        let a = pts(&[
+            (0.0, 0.0),
+            (12.0, 0.0),
+            (12.0, 10.0),
+            (8.0, 10.0),
+            (8.0, 4.0),
+            (4.0, 4.0),
+            (4.0, 10.0),
+            (0.0, 10.0),
+        ]);
+        let b = pts(&[(1.0, 1.0), (4.0, 1.0), (4.0, 4.0), (1.0, 4.0)]);
+        let exact = Nfp::from_shapes(minkowski_difference_shapes(&a, &b));
+        let raster = Nfp::from_raster(gpu.raster_nfp(&a, &b).unwrap());
+        assert!(!raster.is_exact());
+        let cell = 15.0 / 128.0;
+        let mut deep = 0;
+        for j in 0..=64 {
+            for i in 0..=72 {
+                let (x, y) = (-4.0 + i as f64 * 0.25, -4.0 + j as f64 * 0.25);
+                // the grid never claims an overlap the exact NFP does not
+                // have, and finds those away from the boundary
+                if raster.contains_by(x, y, 0.0) {
+                    assert!(exact.contains_by(x, y, 0.0), "({}, {})", x, y);
+                }
+                if exact.contains_by(x, y, 3.0 * cell) {
+                    assert!(raster.contains_by(x, y, 0.0), "({}, {})", x, y);
+                    deep += 1;
+                }
+            }
+        }
+        assert!(deep > 0);
+        // in the slot, clear of the walls
+        assert!(!raster.contains_by(4.5, 6.0, 0.0));
+    }
+}
@@ -0,0 +1,68 @@
+// Minkowski sums and quad rasterization for GPU no-fit polygons.
+
+struct Params {
+    la: u32,
+    lb: u32,
+    nx: u32,
+    ny: u32,
+    origin: vec2<f32>,
+    cell: f32,
+    pad: f32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> a: array<vec2<f32>>;
+@group(0) @binding(2) var<storage, read> b: array<vec2<f32>>;
+@group(0) @binding(3) var<storage, read_write> sums: array<vec2<f32>>;
+@group(0) @binding(4) var<storage, read_write> grid: array<u32>;
+
+// sums[i * la + j] = a[j] - b[i], relative to the grid origin
+@compute @workgroup_size(64)
+fn sums_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let k = id.x;
+    if (k >= params.la * params.lb) {
+        return;
+    }
+    let i = k / params.la;
+    let j = k % params.la;
+    sums[k] = a[j] - b[i] - params.origin;
+}
+
+fn cross2(o: vec2<f32>, p: vec2<f32>, q: vec2<f32>) -> f32 {
+    return (p.x - o.x) * (q.y - o.y) - (p.y - o.y) * (q.x - o.x);
+}
+
+fn sum_at(i: u32, j: u32) -> vec2<f32> {
+    return sums[(i % params.lb) * params.la + (j % params.la)];
+}
+
+// A cell is covered when its center lies in any quad swept by an edge of
+// `a` along an edge of `-b`. Setting the cell for any quad unions them.
+@compute @workgroup_size(8, 8)
+fn raster_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= params.nx || id.y >= params.ny) {
+        return;
+    }
+    // one cell of margin on every side keeps the border empty
+    let p = (vec2<f32>(id.xy) - vec2<f32>(0.5, 0.5)) * params.cell;
+    var covered = 0u;
+    for (var i = 0u; i < params.lb && covered == 0u; i++) {
+        for (var j = 0u; j < params.la; j++) {
+            let q0 = sum_at(i, j);
+            let q1 = sum_at(i + 1u, j);
+            let q2 = sum_at(i + 1u, j + 1u);
+            let q3 = sum_at(i, j + 1u);
+            let c0 = cross2(q0, q1, p);
+            let c1 = cross2(q1, q2, p);
+            let c2 = cross2(q2, q3, p);
+            let c3 = cross2(q3, q0, p);
+            let inside_ccw = c0 >= 0.0 && c1 >= 0.0 && c2 >= 0.0 && c3 >= 0.0;
+            let inside_cw = c0 <= 0.0 && c1 <= 0.0 && c2 <= 0.0 && c3 <= 0.0;
+            if (inside_ccw || inside_cw) {
+                covered = 1u;
+                break;
+            }
+        }
+    }
+    grid[id.y * params.nx + id.x] = covered;
+}
@@ -0,0 +1,188 @@
+//! Self-contained HTML summary of a nesting run, with a drawing of every
+//! sheet, the utilization per sheet, the placed and unplaced parts and the
+//! warnings. Everything is inline so the file can be mailed or
+//! attached to a work order as it is.
+
+use std::fmt::Write as _;
+
+use crate::geometry::{Bounds, polygon_area};
+use crate::output::escape_xml;
+use crate::report::{PlacedPart, Report};
+
+const STYLE: &str = "body{font-family:sans-serif;margin:2em;color:#222}\
+table{border-collapse:collapse;margin-bottom:2em}\
+td,th{border:1px solid #ccc;padding:.25em .6em;text-align:right}\
+th{background:#f0f0f0}td:first-child{text-align:left}\
+.sheet{display:inline-block;margin:0 1em 1em 0;vertical-align:top}\
+.sheet svg{border:1px solid #888;max-width:40em;height:auto}\
+.bar{background:#4a7;height:1em}";
+
+/// Net area of a placed part, its holes subtracted.
+fn part_area(p: &PlacedPart) -> f64 {
+    p.contours.iter().map(|c| polygon_area(c)).sum::<f64>().abs()
+}
+
+fn part_name(p: &PlacedPart) -> String {
+    match &p.id {
+        Some(id) => id.to_string(),
+        None => format!("part {}", p.part),
+    }
+}
+
+/// Drawing of the parts on `sheet`, at `bounds` in layout coordinates, in
+/// sheet coordinates.
+fn sheet_svg(report: &Report, sheet: usize, bounds: Bounds) -> String {
+    let top = bounds.y;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {} {}\" width=\"{}\" height=\"{}\">",
+        bounds.width, bounds.height, bounds.width, bounds.height
+    );
+    for p in report.placements.iter().filter(|p| p.sheet == sheet) {
+        let mut d = String::new();
+        for contour in &p.contours {
+            for (i, pt) in contour.iter().enumerate() {
+                let _ = write!(d, "{}{} {}", if i == 0 { "M" } else { "L" }, pt.x, pt.y - top);
+            }
+            d.push('Z');
+        }
+        let _ = write!(
+            svg,
+            "<path d=\"{}\" fill=\"#cde\" fill-rule=\"evenodd\" stroke=\"#246\" vector-effect=\"non-scaling-stroke\"><title>{}</title></path>",
+            d,
+            escape_xml(&part_name(p))
+        );
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+/// HTML page summarizing `report`.
+pub fn report_html(report: &Report) -> String {
+    let sheets = report.sheet_bounds();
+    let sheet_area = report.bin_width * report.bin_height;
+    let mut html = String::from("<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Nesting report</title>");
+    let _ = write!(html, "<style>{}</style></head><body>\n<h1>Nesting report</h1>\n", STYLE);
+    let _ = writeln!(
+        html,
+        "<p>{} sheet(s) of {} × {}, {} part(s) placed, {} unplaced, utilization {:.1}%</p>",
+        report.sheets,
+        report.bin_width,
+        report.bin_height,
+        report.placements.len(),
+        report.unplaced.len(),
+        report.utilization * 100.0
+    );
+
+    html.push_str("<h2>Sheets</h2>\n<table><tr><th>Sheet</th><th>Parts</th><th>Utilization</th><th></th></tr>\n");
+    for sheet in 0..report.sheets {
+        let placed: Vec<&PlacedPart> = report.placements.iter().filter(|p| p.sheet == sheet).collect();
+        let used = if sheet_area > 0.0 {
+            placed.iter().map(|p| part_area(p)).sum::<f64>() / sheet_area
+        } else {
+            0.0
+        };
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td>{:.1}%</td><td style=\"width:10em\"><div class=\"bar\" style=\"width:{:.1}%\"></div></td></tr>",
+            sheet + 1,
+            placed.len(),
+            used * 100.0,
+            (used * 100.0).min(100.0)
+        );
+    }
+    html.push_str("</table>\n");
+    for (sheet, &bounds) in sheets.iter().enumerate() {
+        let _ = writeln!(
+            html,
+            "<div class=\"sheet\"><h3>Sheet {}</h3>{}</div>",
+            sheet + 1,
+            sheet_svg(report, sheet, bounds)
+        );
+    }
+
+    html.push_str("<h2>Parts</h2>\n<table><tr><th>Part</th><th>Sheet</th><th>X</th><th>Y</th><th>Angle</th><th>Area</th></tr>\n");
+    for p in &report.placements {
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td>{:.2}</td><td>{:.2}</td><td>{}°</td><td>{:.2}</td></tr>",
+            escape_xml(&part_name(p)),
+            p.sheet + 1,
+            p.x,
+            p.y - sheets.get(p.sheet).map_or(0.0, |s| s.y),
+            p.angle,
+            part_area(p)
+        );
+    }
+    html.push_str("</table>\n");
+
+    if !report.unplaced.is_empty() {
+        html.push_str("<h2>Unplaced parts</h2>\n<ul>\n");
+        for (k, idx) in report.unplaced.iter().enumerate() {
+            let name = match report.unplaced_ids.get(k) {
+                Some(id) => id.to_string(),
+                None => format!("part {}", idx),
+            };
+            let _ = writeln!(html, "<li>{}</li>", escape_xml(&name));
+        }
+        html.push_str("</ul>\n");
+    }
+    if !report.warnings.is_empty() {
+        html.push_str("<h2>Warnings</h2>\n<ul>\n");
+        for w in &report.warnings {
+            let _ = writeln!(html, "<li>{}</li>", escape_xml(&w.to_string()));
+        }
+        html.push_str("</ul>\n");
+    }
+    html.push_str("</body></html>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::svg_parser::Point;
+    use crate::warning::{Code, Warning};
+
+    #[test]
+    fn summarizes_every_sheet() {
+        let square = |x: f64, y: f64| {
+            vec![
+                Point { x, y },
+                Point { x, y: y + 5.0 },
+                Point { x: x + 5.0, y: y + 5.0 },
+                Point { x: x + 5.0, y },
+            ]
+        };
+        let placed = |part: usize, sheet: usize| PlacedPart {
+            part,
+            id: None,
+            sheet,
+            x: 0.0,
+            y: sheet as f64 * 10.0,
+            angle: 0.0,
+            alignment: 0.0,
+            contours: vec![square(0.0, sheet as f64 * 10.0)],
+            frames: None,
+            metadata: Default::default(),
+        };
+        let report = Report {
+            bin_width: 10.0,
+            bin_height: 10.0,
+            turned_sheets: Vec::new(),
+            sheets: 2,
+            utilization: 0.25,
+            placements: vec![placed(0, 0), placed(1, 1)],
+            unplaced: vec![2],
+            unplaced_ids: Vec::new(),
+            sheet_totals: Vec::new(),
+            warnings: vec![Warning::new(Code::UnsupportedElement, "text", "unsupported element <skipped>")],
+        };
+        let html = report_html(&report);
+        assert_eq!(html.matches("<div class=\"sheet\">").count(), 2);
+        assert_eq!(html.matches("<td>25.0%</td>").count(), 2);
+        assert!(html.contains("<li>part 2</li>"));
+        assert!(html.contains("&lt;skipped&gt;"));
+        // parts are drawn relative to their sheet
+        assert!(html.contains("d=\"M0 0L0 5L5 5L5 0Z\""));
+    }
+}
@@ -0,0 +1,181 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::schema::{self, Format};
+use crate::{ga::Placement, geometry::Bounds, part::Part, report::Report};
+
+/// Final arrangement of the parts, which can be saved and reloaded to write
+/// outputs or compare runs without optimizing again.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Layout {
+    /// Bounds of the bin, the sheets not in `turned`
+    pub bin: Bounds,
+    /// Height used by the layout. Sheets are stacked vertically, so this is
+    /// the sum of the heights of the sheets.
+    pub height: f64,
+    pub placements: Vec<Placement>,
+    /// Sheets that are the bin turned by 90°, which may allow parts the bin
+    /// is too narrow for or fill the sheet better
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub turned: Vec<usize>,
+}
+
+impl Layout {
+    pub fn sheets(&self) -> usize {
+        if self.turned.is_empty() {
+            return ((self.height / self.bin.height).round() as usize).max(1);
+        }
+        let mut sheets = 1;
+        let mut top = sheet_height(self.bin, &self.turned, 0);
+        while top + 1e-6 * self.bin.height.max(self.bin.width) < self.height {
+            top += sheet_height(self.bin, &self.turned, sheets);
+            sheets += 1;
+        }
+        sheets
+    }
+
+    /// Bounds of every sheet in layout coordinates.
+    pub fn sheet_bounds(&self) -> Vec<Bounds> {
+        stack_sheets(self.bin, &self.turned, self.sheets())
+    }
+
+    /// Report of this layout for `parts`, the parts it was nested from.
+    pub fn to_report(&self, parts: &[Part]) -> Report {
+        Report::new(parts, &self.placements, self.bin, &self.sheet_bounds())
+    }
+
+    /// Layout described by `report`. Reports do not record where the sheet
+    /// was in the bin file, so the bin starts at the origin.
+    pub fn from_report(report: &Report) -> Self {
+        Self {
+            bin: Bounds {
+                x: 0.0,
+                y: 0.0,
+                width: report.bin_width,
+                height: report.bin_height,
+            },
+            height: report.sheet_bounds().iter().map(|s| s.height).sum(),
+            placements: report
+                .placements
+                .iter()
+                .map(|p| Placement {
+                    idx: p.part,
+                    sheet: p.sheet,
+                    angle: p.angle,
+                    x: p.x,
+                    y: p.y,
+                })
+                .collect(),
+            turned: report.turned_sheets.clone(),
+        }
+    }
+
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    fn from_json(data: &str) -> anyhow::Result<Self> {
+        let mut layout: Self = schema::from_str(Format::Layout, data)?;
+        // placements saved before they recorded their sheet have no `sheet`
+        // field; the stacking tells their sheet
+        let saved: serde_json::Value = serde_json::from_str(data)?;
+        for (i, p) in layout.placements.iter_mut().enumerate() {
+            if saved["placements"][i].get("sheet").is_none() {
+                p.sheet = (p.y / layout.bin.height + 1e-9).floor().max(0.0) as usize;
+            }
+        }
+        Ok(layout)
+    }
+
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        schema::to_json(Format::Layout, self)
+    }
+}
+
+fn sheet_height(bin: Bounds, turned: &[usize], sheet: usize) -> f64 {
+    if turned.contains(&sheet) { bin.width } else { bin.height }
+}
+
+/// Bounds of `sheets` sheets cut from `bin`, in layout coordinates: stacked
+/// from the top down, with those in `turned` turned by 90°.
+pub fn stack_sheets(bin: Bounds, turned: &[usize], sheets: usize) -> Vec<Bounds> {
+    let mut top = 0.0;
+    (0..sheets)
+        .map(|k| {
+            let (width, height) = if turned.contains(&k) {
+                (bin.height, bin.width)
+            } else {
+                (bin.width, bin.height)
+            };
+            let sheet = Bounds {
+                x: 0.0,
+                y: top,
+                width,
+                height,
+            };
+            top += height;
+            sheet
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::svg_parser::{Point, Polygon};
+
+    #[test]
+    fn round_trips_through_report() {
+        let square = Polygon {
+            points: vec![
+                Point { x: 0.0, y: 0.0 },
+                Point { x: 2.0, y: 0.0 },
+                Point { x: 2.0, y: 2.0 },
+                Point { x: 0.0, y: 2.0 },
+            ],
+            ..Default::default()
+        };
+        let parts = vec![Part::new(vec![square.clone()]), Part::new(vec![square])];
+        let layout = Layout {
+            bin: Bounds {
+                x: 0.0,
+                y: 0.0,
+                width: 10.0,
+                height: 5.0,
+            },
+            height: 15.0,
+            placements: vec![
+                Placement { idx: 1, sheet: 0, angle: 90.0, x: 2.0, y: 0.0 },
+                Placement { idx: 0, sheet: 1, angle: 0.0, x: 0.0, y: 5.0 },
+            ],
+            turned: vec![1],
+        };
+        assert_eq!(layout.sheets(), 2);
+        let report = layout.to_report(&parts);
+        assert_eq!(report.sheets, 2);
+        assert_eq!(report.turned_sheets, [1]);
+        let second = report.placements.iter().find(|p| p.sheet == 1).unwrap();
+        assert_eq!(second.frames.unwrap().sheet.y, 10.0);
+        assert_eq!(Layout::from_report(&report), layout);
+
+        let json = layout.to_json().unwrap();
+        assert_eq!(schema::from_str::<Layout>(Format::Layout, &json).unwrap(), layout);
+    }
+
+    #[test]
+    fn only_placements_without_a_sheet_take_it_from_the_stacking() {
+        // the first sheet is turned, so it reaches below the bin height
+        let json = r#"{
+            "bin": {"x": 0, "y": 0, "width": 10, "height": 5},
+            "height": 15,
+            "placements": [
+                {"idx": 0, "sheet": 0, "angle": 0, "x": 0, "y": 6},
+                {"idx": 1, "angle": 0, "x": 0, "y": 6}
+            ],
+            "turned": [0]
+        }"#;
+        let layout = Layout::from_json(json).unwrap();
+        assert_eq!(layout.placements[0].sheet, 0);
+        assert_eq!(layout.placements[1].sheet, 1);
+    }
+}
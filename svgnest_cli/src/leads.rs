@@ -0,0 +1,184 @@
+//! Lead-ins and lead-outs, short moves onto and off a contour at its start
+//! point so piercing and switching off the beam leave their marks in the
+//! waste instead of on the part.
+//!
+//! Leads go to the waste side of the contour: outside of outlines and inside
+//! of holes. Which side of an edge that is follows from the winding of the
+//! ring and whether it is a hole of its part.
+
+use std::f64::consts::FRAC_PI_2;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::geometry::polygon_area;
+use crate::svg_parser::Point;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LeadKind {
+    /// Straight move square to the first or last edge
+    Line,
+    /// Quarter circle meeting the contour tangentially
+    Arc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Lead {
+    pub kind: LeadKind,
+    /// Length of a line or radius of an arc
+    pub size: f64,
+}
+
+impl FromStr for Lead {
+    type Err = String;
+
+    /// Parse `line:LENGTH` or `arc:RADIUS`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, size) = s
+            .split_once(':')
+            .ok_or_else(|| format!("expected line:LENGTH or arc:RADIUS, got {}", s))?;
+        let kind = match kind {
+            "line" => LeadKind::Line,
+            "arc" => LeadKind::Arc,
+            _ => return Err(format!("lead kind must be line or arc, got {}", kind)),
+        };
+        match size.parse::<f64>() {
+            Ok(size) if size > 0.0 && size.is_finite() => Ok(Self { kind, size }),
+            _ => Err(format!("lead size must be a positive number, got {}", size)),
+        }
+    }
+}
+
+fn unit(a: Point, b: Point) -> Option<(f64, f64)> {
+    let len = (b.x - a.x).hypot(b.y - a.y);
+    (len > 0.0).then(|| ((b.x - a.x) / len, (b.y - a.y) / len))
+}
+
+/// Points of a lead of `lead` at `at`, where the contour runs along the unit
+/// direction `t` and `n` points to the waste. A lead-in ends at `at`, a
+/// lead-out starts there. Arcs are split so they stay within `tol` of the
+/// true circle.
+fn lead_points(lead: Lead, at: Point, t: (f64, f64), n: (f64, f64), lead_in: bool, tol: f64) -> Vec<Point> {
+    let r = lead.size;
+    let mut points = match lead.kind {
+        LeadKind::Line => vec![
+            Point {
+                x: at.x + n.0 * r,
+                y: at.y + n.1 * r,
+            },
+            at,
+        ],
+        LeadKind::Arc => {
+            let step = if tol > 0.0 && tol < r {
+                2.0 * (1.0 - tol / r).acos()
+            } else {
+                FRAC_PI_2
+            };
+            let steps = (FRAC_PI_2 / step).ceil().max(1.0) as usize;
+            // along the circle around at + n r, from a quarter turn behind
+            // `at` to `at`, where the arc runs along `t`
+            let (cx, cy) = (at.x + n.0 * r, at.y + n.1 * r);
+            (0..=steps)
+                .map(|k| {
+                    let phi = FRAC_PI_2 * (steps - k) as f64 / steps as f64;
+                    let (sin, cos) = phi.sin_cos();
+                    Point {
+                        x: cx - n.0 * r * cos - t.0 * r * sin,
+                        y: cy - n.1 * r * cos - t.1 * r * sin,
+                    }
+                })
+                .collect()
+        }
+    };
+    if !lead_in {
+        // a lead-out is a lead-in onto the contour run backwards
+        points = match lead.kind {
+            LeadKind::Line => points,
+            LeadKind::Arc => lead_points(lead, at, (-t.0, -t.1), n, true, tol),
+        };
+        points.reverse();
+    }
+    points
+}
+
+/// The closed `ring` as one open path starting with `lead_in` and ending
+/// with `lead_out` at its first vertex. `hole` tells whether the ring is a
+/// hole of its part, which puts the waste inside it. Returns `None` if the
+/// ring is too short or starts with a zero length edge.
+pub fn with_leads(
+    ring: &[Point],
+    hole: bool,
+    lead_in: Option<Lead>,
+    lead_out: Option<Lead>,
+    tol: f64,
+) -> Option<Vec<Point>> {
+    let n = ring.len();
+    if n < 3 {
+        return None;
+    }
+    let start = ring[0];
+    let first = unit(start, ring[1])?;
+    let last = unit(ring[n - 1], start)?;
+    // `polygon_area` is positive where the inside is right of the edges
+    let inside_left = polygon_area(ring) < 0.0;
+    let waste = |t: (f64, f64)| {
+        let left = (-t.1, t.0);
+        if inside_left == hole { left } else { (-left.0, -left.1) }
+    };
+    let mut path = match lead_in {
+        Some(lead) => lead_points(lead, start, first, waste(first), true, tol),
+        None => vec![start],
+    };
+    path.extend_from_slice(&ring[1..]);
+    path.push(start);
+    if let Some(lead) = lead_out {
+        path.extend(lead_points(lead, start, last, waste(last), false, tol).into_iter().skip(1));
+    }
+    Some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(size: f64) -> Vec<Point> {
+        // wound like an outline, with negative area
+        vec![
+            Point { x: 0.0, y: 0.0 },
+            Point { x: 0.0, y: size },
+            Point { x: size, y: size },
+            Point { x: size, y: 0.0 },
+        ]
+    }
+
+    #[test]
+    fn leads_lie_in_the_waste() {
+        let line = "line:1".parse::<Lead>().unwrap();
+        let path = with_leads(&square(10.0), false, Some(line), Some(line), 0.1).unwrap();
+        // outside the outline, square to the first and last edge
+        assert_eq!(path[0], Point { x: -1.0, y: 0.0 });
+        assert_eq!(path[1], Point { x: 0.0, y: 0.0 });
+        assert_eq!(path[path.len() - 1], Point { x: 0.0, y: -1.0 });
+        assert_eq!(path.len(), 7);
+
+        let path = with_leads(&square(10.0), true, Some(line), None, 0.1).unwrap();
+        assert_eq!(path[0], Point { x: 1.0, y: 0.0 });
+    }
+
+    #[test]
+    fn arcs_meet_the_contour_tangentially() {
+        let arc = "arc:2".parse::<Lead>().unwrap();
+        let path = with_leads(&square(10.0), false, Some(arc), Some(arc), 0.01).unwrap();
+        let close = |a: Point, x: f64, y: f64| (a.x - x).abs() < 1e-9 && (a.y - y).abs() < 1e-9;
+        // a quarter turn behind the start, outside the outline
+        assert!(close(path[0], -2.0, -2.0), "{:?}", path[0]);
+        let k = path.iter().position(|p| close(*p, 0.0, 0.0)).unwrap();
+        // arriving along the first edge
+        assert!((path[k].x - path[k - 1].x).abs() < 0.1);
+        let end = path[path.len() - 1];
+        assert!(close(end, -2.0, -2.0), "{:?}", end);
+        assert!("arc:0".parse::<Lead>().is_err());
+        assert!("spiral:1".parse::<Lead>().is_err());
+    }
+}
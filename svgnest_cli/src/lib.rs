@@ -1,7 +1,42 @@
+pub mod batch;
+pub mod check;
+pub mod config;
+pub mod contour;
+pub mod crossover;
+pub mod daemon;
+pub mod deprecation;
+pub mod diff;
+pub mod distributed;
 pub mod dxf_parser;
+pub mod dxf_writer;
+pub mod estimate;
 pub mod ga;
+pub mod generate;
 pub mod geometry;
+pub mod grid;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+pub mod html;
+pub mod layout;
+pub mod leads;
 pub mod line_merge;
+pub mod nester;
 pub mod nfp;
+pub mod output;
+pub mod palette;
 pub mod part;
+pub mod plan;
+pub mod profile;
+pub mod provenance;
+pub mod progress;
+pub mod remnant;
+pub mod report;
+pub mod schema;
+pub mod split;
+pub mod stats;
 pub mod svg_parser;
+pub mod tabs;
+#[cfg(test)]
+mod test_util;
+pub mod tune;
+pub mod warning;
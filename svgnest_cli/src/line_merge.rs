@@ -1,18 +1,150 @@
-use std::collections::HashMap;
+//! Turning loose line work into nestable shapes.
+//!
+//! Exploded CAD exports draw outlines as separate, often duplicated or
+//! overlapping segments. [`merge_lines`] collapses segments lying on the same
+//! line into one and chains the rest into polylines, closing them where they
+//! come back to their start.
 
-use crate::svg_parser::{Polygon, Point};
+use crate::svg_parser::{Point, Polygon};
 
-const MERGE_TOLERANCE: f64 = 1e-6;
+pub(crate) fn points_equal(a: &Point, b: &Point, tol: f64) -> bool {
+    (a.x - b.x).abs() < tol && (a.y - b.y).abs() < tol
+}
 
-fn key_for_point(p: &Point) -> (i64, i64) {
-    ((p.x / MERGE_TOLERANCE).round() as i64, (p.y / MERGE_TOLERANCE).round() as i64)
+/// First and last point of a chain, `None` when it is empty.
+fn ends(points: &[Point]) -> Option<(Point, Point)> {
+    Some((*points.first()?, *points.last()?))
 }
 
-/// Merge duplicate line segments across all polygons.
-/// Each edge is stored as an unordered pair of points so orientation does not matter.
-pub fn merge_lines(polys: &[Polygon]) -> Vec<Polygon> {
-    let mut edges: HashMap<((i64, i64), (i64, i64)), (Point, Point)> = HashMap::new();
+/// Join open chains whose ends meet within `tol` and append them to
+/// `closed`. Chains coming back to their start are closed.
+pub(crate) fn connect_open_polys(mut open: Vec<Vec<Point>>, mut closed: Vec<Polygon>, tol: f64) -> Vec<Polygon> {
+    open.retain(|chain| !chain.is_empty());
+    while let Some(mut current) = open.pop() {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            let mut i = 0;
+            while i < open.len() {
+                let other = &open[i];
+                let (Some((first_cur, last_cur)), Some((first_other, last_other))) = (ends(&current), ends(other))
+                else {
+                    break;
+                };
+
+                if points_equal(&last_cur, &first_other, tol) {
+                    current.extend(other.iter().skip(1).cloned());
+                    open.remove(i);
+                    changed = true;
+                } else if points_equal(&last_cur, &last_other, tol) {
+                    current.extend(other.iter().rev().skip(1).cloned());
+                    open.remove(i);
+                    changed = true;
+                } else if points_equal(&first_cur, &last_other, tol) {
+                    let mut add: Vec<Point> = other.iter().rev().skip(1).cloned().collect();
+                    add.extend(current);
+                    current = add;
+                    open.remove(i);
+                    changed = true;
+                } else if points_equal(&first_cur, &first_other, tol) {
+                    let mut add: Vec<Point> = other.iter().skip(1).rev().cloned().collect();
+                    add.extend(current);
+                    current = add;
+                    open.remove(i);
+                    changed = true;
+                } else {
+                    i += 1;
+                }
+                if changed {
+                    break;
+                }
+            }
+        }
+
+        let is_closed = ends(&current).is_some_and(|(first, last)| points_equal(&first, &last, tol));
+        if is_closed && current.len() > 1 {
+            current.pop();
+        }
+        closed.push(Polygon {
+            id: 0,
+            points: current,
+            closed: is_closed,
+            ..Default::default()
+        });
+    }
+    closed
+}
+
+/// Segments lying on one line, as positions along the line of the first
+/// segment added.
+struct Line {
+    origin: Point,
+    /// Unit direction
+    dir: (f64, f64),
+    /// Position along the line and point of both ends of every segment
+    spans: Vec<((f64, Point), (f64, Point))>,
+}
+
+impl Line {
+    fn new(a: Point, b: Point) -> Option<Self> {
+        let len = (b.x - a.x).hypot(b.y - a.y);
+        if len == 0.0 {
+            return None;
+        }
+        let mut line = Self {
+            origin: a,
+            dir: ((b.x - a.x) / len, (b.y - a.y) / len),
+            spans: Vec::new(),
+        };
+        line.push(a, b);
+        Some(line)
+    }
+
+    fn along(&self, p: Point) -> f64 {
+        (p.x - self.origin.x) * self.dir.0 + (p.y - self.origin.y) * self.dir.1
+    }
+
+    fn distance(&self, p: Point) -> f64 {
+        ((p.x - self.origin.x) * self.dir.1 - (p.y - self.origin.y) * self.dir.0).abs()
+    }
+
+    fn contains(&self, a: Point, b: Point, tol: f64) -> bool {
+        self.distance(a) < tol && self.distance(b) < tol
+    }
+
+    fn push(&mut self, a: Point, b: Point) {
+        let (ta, tb) = (self.along(a), self.along(b));
+        self.spans
+            .push(if ta <= tb { ((ta, a), (tb, b)) } else { ((tb, b), (ta, a)) });
+    }
 
+    /// Spans overlapping by more than `tol` merged into one. Spans that only
+    /// meet end to end stay apart, so other segments ending there can still
+    /// be chained to them.
+    fn merged(mut self, tol: f64) -> Vec<(Point, Point)> {
+        self.spans.sort_by(|a, b| a.0.0.total_cmp(&b.0.0));
+        let mut result: Vec<((f64, Point), (f64, Point))> = Vec::new();
+        for span in self.spans {
+            match result.last_mut() {
+                Some(last) if span.0.0 < last.1.0 - tol || points_equal(&span.0.1, &last.0.1, tol) => {
+                    if span.1.0 > last.1.0 {
+                        last.1 = span.1;
+                    }
+                }
+                _ => result.push(span),
+            }
+        }
+        result.into_iter().map(|(a, b)| (a.1, b.1)).collect()
+    }
+}
+
+/// Merge the line segments of all polygons: segments on a common line that
+/// overlap, duplicates included, become one, and segments meeting end to end
+/// are chained into polylines, or closed polygons where a chain returns to
+/// its start. Points closer than `tol` are treated as equal, and segments
+/// whose ends are within `tol` of a line lie on it.
+pub fn merge_lines(polys: &[Polygon], tol: f64) -> Vec<Polygon> {
+    let mut lines: Vec<Line> = Vec::new();
     for poly in polys {
         if poly.points.len() < 2 {
             continue;
@@ -23,17 +155,22 @@ pub fn merge_lines(polys: &[Polygon]) -> Vec<Polygon> {
             segments.push((poly.points[last], poly.points[0]));
         }
         for (a, b) in segments {
-            let ka = key_for_point(&a);
-            let kb = key_for_point(&b);
-            let key = if ka <= kb { (ka, kb) } else { (kb, ka) };
-            edges.entry(key).or_insert((a, b));
+            if points_equal(&a, &b, tol) {
+                continue;
+            }
+            match lines.iter_mut().find(|l| l.contains(a, b, tol)) {
+                Some(line) => line.push(a, b),
+                None => lines.extend(Line::new(a, b)),
+            }
         }
     }
 
-    let mut result: Vec<Polygon> = edges
+    let open: Vec<Vec<Point>> = lines
         .into_iter()
-        .map(|(_, (a, b))| Polygon { id: 0, points: vec![a, b], closed: false })
+        .flat_map(|l| l.merged(tol))
+        .map(|(a, b)| vec![a, b])
         .collect();
+    let mut result = connect_open_polys(open, Vec::new(), tol);
     result.sort_by(|a, b| {
         a.points[0]
             .x
@@ -52,11 +189,55 @@ mod tests {
 
     #[test]
     fn deduplicates_segments() {
-        let p1 = Polygon { id: 0, points: vec![Point { x: 0.0, y: 0.0 }, Point { x: 1.0, y: 0.0 }], closed: false };
-        let p2 = Polygon { id: 1, points: vec![Point { x: 1.0, y: 0.0 }, Point { x: 0.0, y: 0.0 }], closed: false };
-        let p3 = Polygon { id: 2, points: vec![Point { x: 2.0, y: 2.0 }, Point { x: 3.0, y: 2.0 }], closed: false };
-        let merged = merge_lines(&[p1, p2, p3]);
+        let p1 = Polygon { id: 0, points: vec![Point { x: 0.0, y: 0.0 }, Point { x: 1.0, y: 0.0 }], closed: false, ..Default::default() };
+        let p2 = Polygon { id: 1, points: vec![Point { x: 1.0, y: 0.0 }, Point { x: 0.0, y: 0.0 }], closed: false, ..Default::default() };
+        let p3 = Polygon { id: 2, points: vec![Point { x: 2.0, y: 2.0 }, Point { x: 3.0, y: 2.0 }], closed: false, ..Default::default() };
+        let merged = merge_lines(&[p1, p2, p3], 1e-6);
         assert_eq!(merged.len(), 2);
     }
-}
 
+    #[test]
+    fn tolerance_controls_duplicates() {
+        let p1 = Polygon { id: 0, points: vec![Point { x: 0.0, y: 0.0 }, Point { x: 10.0, y: 0.0 }], closed: false, ..Default::default() };
+        let p2 = Polygon { id: 1, points: vec![Point { x: 0.001, y: 0.0 }, Point { x: 10.0, y: 0.001 }], closed: false, ..Default::default() };
+        assert_eq!(merge_lines(&[p1.clone(), p2.clone()], 1e-6).len(), 2);
+        assert_eq!(merge_lines(&[p1, p2], 0.1).len(), 1);
+    }
+
+    #[test]
+    fn overlapping_segments_chain_into_a_square() {
+        let seg = |x1: f64, y1: f64, x2: f64, y2: f64| Polygon {
+            points: vec![Point { x: x1, y: y1 }, Point { x: x2, y: y2 }],
+            ..Default::default()
+        };
+        // the bottom edge is drawn in two overlapping pieces
+        let segments = [
+            seg(0.0, 0.0, 6.0, 0.0),
+            seg(4.0, 0.0, 10.0, 0.0),
+            seg(10.0, 10.0, 10.0, 0.0),
+            seg(10.0, 10.0, 0.0, 10.0),
+            seg(0.0, 10.0, 0.0, 0.0),
+        ];
+        let merged = merge_lines(&segments, 1e-6);
+        assert_eq!(merged.len(), 1);
+        assert!(merged[0].closed);
+        assert_eq!(merged[0].points.len(), 4);
+        assert_eq!(crate::geometry::polygon_area(&merged[0].points).abs(), 100.0);
+    }
+
+    #[test]
+    fn connects_chains_and_ignores_empty_ones() {
+        let p = |x: f64, y: f64| Point { x, y };
+        assert!(connect_open_polys(Vec::new(), Vec::new(), 1e-6).is_empty());
+        let open = vec![
+            vec![p(0.0, 0.0), p(1.0, 0.0)],
+            Vec::new(),
+            vec![p(1.0, 1.0), p(1.0, 0.0)],
+            vec![p(1.0, 1.0), p(0.0, 0.0)],
+        ];
+        let polys = connect_open_polys(open, Vec::new(), 1e-6);
+        assert_eq!(polys.len(), 1);
+        assert!(polys[0].closed);
+        assert_eq!(polys[0].points.len(), 3);
+    }
+}
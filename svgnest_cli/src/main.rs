@@ -1,16 +1,49 @@
-use clap::Parser;
-use std::path::PathBuf;
+use anyhow::{anyhow, bail};
+use clap::{Args, CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use rayon::prelude::*;
+use std::path::{Path, PathBuf};
 
-use svgnest_cli::{dxf_parser, ga, part, svg_parser};
+use svgnest_cli::config::{
+    AngleFallback, FitnessWeights, NestingConfig, PackDirection, PositionScore, SheetLimits, SheetMarks,
+    scaled_elitism, scaled_mutation_rate, scaled_population_size,
+};
+use svgnest_cli::crossover::Crossover;
+use svgnest_cli::geometry::Tolerances;
+use svgnest_cli::leads::Lead;
+use svgnest_cli::palette::ColorBy;
+use svgnest_cli::profile::{self, Stage};
+use svgnest_cli::tabs::Tabs;
+use svgnest_cli::{
+    batch, check, contour, daemon, deprecation, diff, distributed, dxf_parser, dxf_writer, estimate, ga, generate, grid, html, output, part, plan, provenance, remnant, report, split, stats, svg_parser, tune, warning,
+};
+
+/// Generations of a nesting run
+const GENERATIONS: usize = 100;
 
 /// Command line arguments for SVGnest
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct CliArgs {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// SVG input files to be nested
     #[arg(long, value_name = "FILES", required = true)]
     pub inputs: Vec<PathBuf>,
 
+    /// Directory receiving nested.svg and the other layout files
+    #[arg(long, default_value = ".", value_name = "DIR")]
+    pub output_dir: PathBuf,
+
+    #[command(flatten)]
+    pub options: NestOptions,
+}
+
+/// Options of a nesting run, shared by the default command and `batch`
+#[derive(Args, Debug, Clone)]
+pub struct NestOptions {
     /// Maximum error allowed when approximating curves
     #[arg(long = "approx-tolerance", default_value_t = 0.3)]
     pub approx_tolerance: f64,
@@ -23,13 +56,51 @@ pub struct CliArgs {
     #[arg(long, default_value_t = 4)]
     pub rotations: usize,
 
-    /// Population size for the genetic algorithm
-    #[arg(long, default_value_t = 10, value_name = "SIZE")]
-    pub population_size: usize,
+    /// How likely each rotation is picked for a part, in the order of the
+    /// rotations, e.g. 10,1,1,1 to mostly keep parts upright. Parts can
+    /// weight single angles with a data-rotation-weights="0:10 90:1"
+    /// attribute.
+    #[arg(long, value_delimiter = ',', value_name = "WEIGHTS")]
+    pub rotation_weights: Vec<f64>,
+
+    /// What happens to a part that fits the bin at none of the rotations:
+    /// leave it unplaced, refuse the job, try every whole degree or split it
+    /// into pieces the size of the bin
+    #[arg(long, value_enum, default_value_t = AngleFallback::Unplace, value_name = "MODE")]
+    pub angle_fallback: AngleFallback,
+
+    /// Overlap left between neighbouring pieces of a part split with
+    /// --angle-fallback split, for joining them again
+    #[arg(long, default_value_t = 0.0, value_name = "WIDTH")]
+    pub split_joint: f64,
+
+    /// Population size for the genetic algorithm [default: grows with the
+    /// number of parts and rotations]
+    #[arg(long, value_name = "SIZE")]
+    pub population_size: Option<usize>,
+
+    /// Best individuals kept unchanged from one generation to the next
+    /// [default: a tenth of the population]
+    #[arg(long, value_name = "N")]
+    pub elitism: Option<usize>,
+
+    /// Mutation rate of the genetic algorithm (1-50) [default: falls with
+    /// the number of parts]
+    #[arg(long, value_name = "RATE")]
+    pub mutation_rate: Option<usize>,
 
-    /// Mutation rate of the genetic algorithm (1-50)
-    #[arg(long, default_value_t = 10, value_name = "RATE")]
-    pub mutation_rate: usize,
+    /// Crossover operator recombining the placement orders of two parents
+    #[arg(long, value_enum, default_value_t = Crossover::OnePoint, value_name = "OPERATOR")]
+    pub crossover: Crossover,
+
+    /// Moves (swapping neighbouring parts, rotating a part) tried on every
+    /// offspring, keeping those that improve it
+    #[arg(long, default_value_t = 0, value_name = "N")]
+    pub local_search_iters: usize,
+
+    /// Seed of the random generator, to repeat a run exactly
+    #[arg(long, value_name = "N")]
+    pub seed: Option<u64>,
 
     /// Place parts inside the holes of other parts
     #[arg(long, default_value_t = false)]
@@ -43,122 +114,1323 @@ pub struct CliArgs {
     #[arg(long, default_value_t = 1e-3)]
     pub angle_precision: f64,
 
-    /// Merge overlapping line segments
+    /// Distance below which the endpoints of DXF entities are joined
+    #[arg(long, default_value_t = 1e-6, value_name = "DIST")]
+    pub connect_tolerance: f64,
+
+    /// Distance below which points are joined and segments count as
+    /// collinear with --merge-lines
+    #[arg(long, default_value_t = 1e-6, value_name = "DIST")]
+    pub merge_tolerance: f64,
+
+    /// Depth up to which parts may run into each other and still count as
+    /// touching rather than overlapping
+    #[arg(long, default_value_t = 1e-6, value_name = "DIST")]
+    pub contact_tolerance: f64,
+
+    /// Merge overlapping line segments and chain them into polylines and
+    /// closed shapes
     #[arg(long, default_value_t = false)]
     pub merge_lines: bool,
+
+    /// Let the bin wrap around in X (rotary attachments and tube cutting)
+    #[arg(long, default_value_t = false)]
+    pub wrap_x: bool,
+
+    /// Let every sheet be the bin turned by 90°, where that takes parts the
+    /// bin is too narrow for or fills the sheet better
+    #[arg(long, default_value_t = false)]
+    pub rotate_sheets: bool,
+
+    /// Edge of the sheets the parts are gathered at
+    #[arg(long, value_enum, default_value_t = PackDirection::Left, value_name = "DIRECTION")]
+    pub pack_direction: PackDirection,
+
+    /// How --explore-concave picks among the positions a part fits at
+    #[arg(long, value_enum, default_value_t = PositionScore::First, value_name = "SCORE")]
+    pub position_score: PositionScore,
+
+    /// Point on the sheet that `--position-score gravity` pulls parts to
+    #[arg(long, value_delimiter = ',', num_args = 2, default_values_t = [0.0, 0.0], value_name = "X,Y")]
+    pub gravity_point: Vec<f64>,
+
+    /// Keep a strip this wide along the left edge of every sheet clear,
+    /// e.g. where a clamp holds the sheet; packing starts past it
+    #[arg(long, default_value_t = 0.0, value_name = "WIDTH")]
+    pub start_offset: f64,
+
+    /// Stop optimizing as soon as a layout fills this share of its sheets,
+    /// e.g. 0.82
+    #[arg(long, value_name = "FRACTION")]
+    pub target_utilization: Option<f64>,
+
+    /// Fitness cost of every sheet a layout uses
+    #[arg(long, default_value_t = 1.0, value_name = "WEIGHT")]
+    pub sheet_weight: f64,
+
+    /// Fitness cost of the width used on a sheet, relative to the sheet
+    /// area; raise it to open another sheet sooner rather than spread parts
+    #[arg(long, default_value_t = 1.0, value_name = "WEIGHT")]
+    pub width_weight: f64,
+
+    /// Fitness cost of every part that fits no sheet
+    #[arg(long, default_value_t = 2.0, value_name = "WEIGHT")]
+    pub unplaced_penalty: f64,
+
+    /// Fitness cost of the share of the parts' boundary not running along a
+    /// neighbour; raise it for denser layouts with more common cuts
+    #[arg(long, default_value_t = 0.0, value_name = "WEIGHT")]
+    pub contact_weight: f64,
+
+    /// Most parts a sheet may take
+    #[arg(long, value_name = "N")]
+    pub max_parts_per_sheet: Option<usize>,
+
+    /// Most total weight a sheet may carry, from the data-weight attribute
+    /// of the parts
+    #[arg(long, value_name = "WEIGHT")]
+    pub max_sheet_weight: Option<f64>,
+
+    /// Most total part area a sheet may carry
+    #[arg(long, value_name = "AREA")]
+    pub max_sheet_area: Option<f64>,
+
+    /// Export the leftover sheet area as remnant_<sheet>.svg/.dxf
+    #[arg(long, default_value_t = false)]
+    pub export_remnants: bool,
+
+    /// Minimum area for a leftover region to be exported as a remnant
+    #[arg(long, default_value_t = 0.0, value_name = "AREA")]
+    pub remnant_min_area: f64,
+
+    /// Rewrite nested.live.svg whenever a better layout is found. nested.svg
+    /// is still only written once the run completes.
+    #[arg(long, default_value_t = false)]
+    pub live_output: bool,
+
+    /// Write a JSON report of the final layout
+    #[arg(long, value_name = "FILE")]
+    pub report: Option<PathBuf>,
+
+    /// Where the machine origin is on a sheet, measured from its bottom-left
+    /// corner with y up, for the machine frame of the report
+    #[arg(long, value_delimiter = ',', num_args = 2, default_values_t = [0.0, 0.0], value_name = "X,Y")]
+    pub machine_origin: Vec<f64>,
+
+    /// Write a self-contained HTML summary with drawings of the sheets,
+    /// their utilization, the parts and the warnings
+    #[arg(long, value_name = "FILE")]
+    pub report_html: Option<PathBuf>,
+
+    /// Report of an earlier run to re-nest some of the sheets of. Parts on
+    /// the other sheets stay where they are, the parts of the re-nested
+    /// sheets and any inputs not in the report are nested again, and the
+    /// report is written with both.
+    #[arg(long, value_name = "REPORT")]
+    pub renest: Option<PathBuf>,
+
+    /// Sheets of the --renest report to nest again, numbered from 0 as in
+    /// the report. Defaults to the last sheet.
+    #[arg(long, value_delimiter = ',', value_name = "SHEETS", requires = "renest")]
+    pub renest_sheets: Vec<usize>,
+
+    /// Write fitness, diversity, NFP cache hit rate and evaluation time of
+    /// every generation to FILE, as JSON for .json and CSV otherwise
+    #[arg(long, value_name = "FILE")]
+    pub stats_out: Option<PathBuf>,
+
+    /// Run N independent optimizers in parallel and keep the best layout
+    #[arg(long, default_value_t = 1, value_name = "N")]
+    pub restarts: usize,
+
+    /// Try a few population sizes, mutation rates and rotation sets in short
+    /// runs on the job first and nest with the one improving fastest
+    #[arg(long, default_value_t = false)]
+    pub auto_tune: bool,
+
+    /// Only lay the parts out once, largest first, and print the sheets,
+    /// utilization and cut length the job takes as JSON. No files are
+    /// written.
+    #[arg(long, default_value_t = false, conflicts_with_all = ["renest", "auto_tune"])]
+    pub estimate: bool,
+
+    /// Only read the inputs and print, for every part, the rotations at
+    /// which it fits the bin or why it cannot be placed. No files are
+    /// written and the exit status is 1 if a part fits nowhere.
+    #[arg(long, default_value_t = false, conflicts_with_all = ["renest", "auto_tune", "estimate"])]
+    pub validate: bool,
+
+    /// Only work out what the optimization will cost and print it: the NFPs
+    /// it may generate, the memory they take and a rough run time, from a
+    /// few sampled NFPs. No files are written.
+    #[arg(long, default_value_t = false, conflicts_with_all = ["renest", "auto_tune", "estimate", "validate"])]
+    pub plan: bool,
+
+    /// Lay jobs made of copies of one or two parts out on a repeating grid
+    /// instead of optimizing. Other jobs are optimized as usual.
+    #[arg(long, default_value_t = false)]
+    pub grid: bool,
+
+    /// Keep the K best distinct layouts and write them as top_<rank>.svg
+    #[arg(long, default_value_t = 1, value_name = "K")]
+    pub top_k: usize,
+
+    /// How shapes of SVG part files are grouped into parts
+    #[arg(long, value_enum, default_value_t = svg_parser::PartMode::File, value_name = "MODE")]
+    pub part_per: svg_parser::PartMode,
+
+    /// Expand stroked open paths into outlines using their stroke width
+    #[arg(long, default_value_t = false)]
+    pub stroke_to_path: bool,
+
+    /// Read the shapes of this group (by inkscape:label or id) as marking,
+    /// e.g. engraving, which moves with its part but is not cut; repeatable
+    #[arg(long, value_name = "LAYER")]
+    pub marking_layer: Vec<String>,
+
+    /// Read shapes stroked in this color as marking; repeatable
+    #[arg(long, value_name = "COLOR")]
+    pub marking_color: Vec<String>,
+
+    /// What to do with open paths: keep, ignore, close, or buffer:WIDTH to
+    /// replace them by a closed outline WIDTH wide
+    #[arg(long, default_value = "keep", value_name = "POLICY")]
+    pub open_paths: part::OpenPaths,
+
+    /// Drop closed contours with a smaller area, e.g. export artifacts
+    #[arg(long, default_value_t = 0.0, value_name = "AREA")]
+    pub min_area: f64,
+
+    /// Drop contours whose width and height are both below SIZE, e.g. stray
+    /// points
+    #[arg(long, default_value_t = 0.0, value_name = "SIZE")]
+    pub min_feature_size: f64,
+
+    /// Turn every part so the smallest rectangle around it is axis-aligned,
+    /// its longer side along x, before trying the rotations; lets a few
+    /// rotations reach good orientations of parts drawn at an angle
+    #[arg(long, default_value_t = false)]
+    pub align_parts: bool,
+
+    /// Abort on malformed or unsupported input elements instead of
+    /// skipping them with a warning
+    #[arg(long, default_value_t = false)]
+    pub strict: bool,
+
+    /// Fail the run, or the batch job, with exit status 1 if it raises a
+    /// warning with one of these codes, e.g. OPEN_CONTOUR_CLOSED. The
+    /// outputs are still written.
+    #[arg(long, value_delimiter = ',', value_name = "CODES")]
+    pub fail_on: Vec<warning::Code>,
+
+    /// Replace runs of cocircular points with arcs in the SVG and DXF output
+    #[arg(long, value_name = "TOL")]
+    pub fit_arcs: Option<f64>,
+
+    /// Draw edges shared by parts nested flush against each other as a
+    /// single cut instead of two coincident ones
+    #[arg(long, default_value_t = false)]
+    pub merge_common_cuts: bool,
+
+    /// Draw parts that could not be placed with dashed red outlines in an
+    /// area beside the sheets, so it is plain what is missing
+    #[arg(long, default_value_t = false)]
+    pub show_unplaced: bool,
+
+    /// Draw every part as its original geometry in a group with a
+    /// translate and rotate transform, which keeps it editable
+    #[arg(long, default_value_t = false)]
+    pub part_transforms: bool,
+
+    /// Color the parts by the file they come from, their sheet or the part
+    /// they are a copy of, with a legend beside the sheets
+    #[arg(long, value_enum, value_name = "MODE")]
+    pub color_by: Option<ColorBy>,
+
+    /// Shade the free space of every sheet in an overlay layer, the larger
+    /// a region the stronger, to show where space is wasted
+    #[arg(long, default_value_t = false)]
+    pub free_space_overlay: bool,
+
+    /// Leave N uncut tabs in the outline of every part so parts stay in the
+    /// sheet until the job is done
+    #[arg(long, value_name = "N")]
+    pub tabs: Option<usize>,
+
+    /// Length of outline every tab leaves uncut
+    #[arg(long, default_value_t = 1.0, value_name = "WIDTH")]
+    pub tab_width: f64,
+
+    /// Move onto every closed contour from the waste side: line:LENGTH or
+    /// arc:RADIUS
+    #[arg(long, value_name = "LEAD")]
+    pub lead_in: Option<Lead>,
+
+    /// Move off every closed contour to the waste side: line:LENGTH or
+    /// arc:RADIUS
+    #[arg(long, value_name = "LEAD")]
+    pub lead_out: Option<Lead>,
+
+    /// Draw fiducials in three corners of every sheet, in a non-cut layer
+    #[arg(long, default_value_t = false)]
+    pub registration_marks: bool,
+
+    /// Draw a crosshair at the origin of every sheet, in a non-cut layer
+    #[arg(long, default_value_t = false)]
+    pub origin_mark: bool,
+
+    /// Write the sheet name and date on every sheet, in a non-cut layer
+    #[arg(long, default_value_t = false)]
+    pub sheet_label: bool,
+
+    /// Size of the registration marks, origin crosshair and sheet label
+    #[arg(long, default_value_t = 10.0, value_name = "SIZE")]
+    pub mark_size: f64,
+
+    /// Generate no-fit polygons on the GPU (requires the `gpu` feature)
+    #[arg(long, default_value_t = false)]
+    pub gpu: bool,
+
+    /// Approximate the no-fit polygons of contours with thousands of
+    /// vertices on a grid of this many cells, much faster but coarser
+    #[arg(long, value_name = "CELLS")]
+    pub raster_nfp: Option<u32>,
+
+    /// Accept `worker` processes on this address and let them evaluate
+    /// individuals
+    #[arg(long, value_name = "HOST:PORT")]
+    pub listen: Option<String>,
+
+    /// Seconds to wait for a worker to answer before dropping it and
+    /// evaluating its individuals locally, 0 to wait forever
+    #[arg(long, default_value_t = distributed::DEFAULT_TIMEOUT.as_secs(), value_name = "SECS")]
+    pub worker_timeout: u64,
+
+    /// Print the time spent in each stage of the run
+    #[arg(long, default_value_t = false)]
+    pub profile: bool,
+
+    /// Write the profile as a Chrome trace (implies --profile)
+    #[arg(long, value_name = "FILE")]
+    pub profile_trace: Option<PathBuf>,
+}
+
+/// Subcommands besides the default nesting run
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Compare two JSON reports and render them side by side
+    Diff {
+        /// Report of the old layout
+        old: PathBuf,
+        /// Report of the new layout
+        new: PathBuf,
+        /// Side-by-side SVG to write
+        #[arg(long, default_value = "diff.svg", value_name = "FILE")]
+        output: PathBuf,
+    },
+    /// Verify a nested SVG: parts inside the bin, not overlapping and
+    /// keeping the spacing. Exits with status 1 if anything is wrong.
+    Check {
+        /// Nested SVG to verify
+        layout: PathBuf,
+        /// Bin the layout was nested into
+        #[arg(long, value_name = "FILE")]
+        bin: PathBuf,
+        /// Minimum distance required between parts
+        #[arg(long, default_value_t = 0.0)]
+        spacing: f64,
+        /// Overlaps, protrusions and spacing shortfalls up to this size are
+        /// accepted as rounding
+        #[arg(long, default_value_t = 1e-6, value_name = "TOL")]
+        tolerance: f64,
+    },
+    /// Evaluate individuals for a nesting run started with --listen
+    Worker {
+        /// Address of the coordinator
+        #[arg(long, value_name = "HOST:PORT")]
+        connect: String,
+    },
+    /// Nest every subdirectory of a directory as an independent job. Exits
+    /// with status 1 if any job failed.
+    Batch {
+        /// Directory of job directories, each with a bin.svg or bin.dxf, part
+        /// files and an optional config.json
+        dir: PathBuf,
+        /// Directory receiving a subdirectory of outputs per job and the
+        /// combined summary.json
+        #[arg(long, default_value = "batch-output", value_name = "DIR")]
+        output: PathBuf,
+        /// Nest the jobs in parallel instead of one after another
+        #[arg(long, default_value_t = false)]
+        parallel: bool,
+        /// Nest jobs of the same material and bin together so they share
+        /// sheets, attributing the sheet area to each job in the summary
+        #[arg(long, default_value_t = false)]
+        mix_materials: bool,
+        #[command(flatten)]
+        options: NestOptions,
+    },
+    /// Watch a drop folder and nest job directories as they appear
+    Daemon {
+        /// Folder to watch for job directories, laid out as for `batch`
+        dir: PathBuf,
+        /// Folder receiving the outputs and status.json of every job
+        #[arg(long, default_value = "daemon-output", value_name = "DIR")]
+        output: PathBuf,
+        /// Seconds between scans of the drop folder
+        #[arg(long, default_value_t = 5.0, value_name = "SECS")]
+        interval: f64,
+        /// Nest the jobs present now and exit instead of watching
+        #[arg(long, default_value_t = false)]
+        once: bool,
+        #[command(flatten)]
+        options: NestOptions,
+    },
+    /// Print the completion script for a shell, completing the subcommands,
+    /// flags and the values of every flag taking one of a set of values
+    Completions {
+        /// Shell to complete in
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    /// Write a random bin and part set for stress tests and benchmarks
+    Generate {
+        /// Number of parts
+        #[arg(long, default_value_t = 50)]
+        parts: usize,
+        /// Kinds of parts to draw from
+        #[arg(long, value_enum, value_delimiter = ',', default_value = "convex,concave,ring", value_name = "KINDS")]
+        shape_mix: Vec<generate::ShapeKind>,
+        /// Seed of the random generator, the same seed gives the same parts
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        /// Directory receiving bin.svg and one file per part
+        #[arg(long, value_name = "DIR")]
+        out: PathBuf,
+    },
 }
 
 /// Parsed configuration returned by the CLI
 #[derive(Debug)]
 pub struct Config {
     pub inputs: Vec<PathBuf>,
-    pub approx_tolerance: f64,
-    pub spacing: f64,
-    pub rotations: usize,
-    pub population_size: usize,
-    pub mutation_rate: usize,
-    pub use_holes: bool,
-    pub explore_concave: bool,
-    pub angle_precision: f64,
+    pub nesting: NestingConfig,
     pub merge_lines: bool,
+    pub export_remnants: bool,
+    pub remnant_min_area: f64,
+    pub live_output: bool,
+    pub report: Option<PathBuf>,
+    pub machine_origin: svg_parser::Point,
+    pub report_html: Option<PathBuf>,
+    pub renest: Option<PathBuf>,
+    pub renest_sheets: Vec<usize>,
+    pub stats_out: Option<PathBuf>,
+    pub restarts: usize,
+    pub auto_tune: bool,
+    pub estimate: bool,
+    pub validate: bool,
+    pub plan: bool,
+    pub grid: bool,
+    pub top_k: usize,
+    pub part_per: svg_parser::PartMode,
+    pub stroke_to_path: bool,
+    pub marking: svg_parser::MarkingSelector,
+    pub strict: bool,
+    pub fail_on: Vec<warning::Code>,
+    pub open_paths: part::OpenPaths,
+    pub feature_filter: part::FeatureFilter,
+    pub align_parts: bool,
+    pub listen: Option<String>,
+    pub worker_timeout: std::time::Duration,
+    /// Settings of the genetic algorithm given explicitly, the others are
+    /// scaled to the parts
+    pub population_size: Option<usize>,
+    pub elitism: Option<usize>,
+    pub mutation_rate: Option<usize>,
 }
 
-impl From<CliArgs> for Config {
-    fn from(args: CliArgs) -> Self {
-        Self {
-            inputs: args.inputs,
-            approx_tolerance: args.approx_tolerance,
-            spacing: args.spacing,
-            rotations: args.rotations,
-            population_size: args.population_size,
-            mutation_rate: args.mutation_rate,
-            use_holes: args.use_holes,
-            explore_concave: args.explore_concave,
-            angle_precision: args.angle_precision,
-            merge_lines: args.merge_lines,
+impl Config {
+    /// Nesting configuration for `parts` parts, with the population size,
+    /// elitism and mutation rate not given explicitly scaled to them.
+    fn nesting_for(&self, parts: usize) -> NestingConfig {
+        let population_size = self.population_size.unwrap_or_else(|| {
+            scaled_population_size(parts, self.nesting.rotations.len()).max(self.elitism.unwrap_or(1))
+        });
+        NestingConfig {
+            population_size,
+            elitism: self.elitism.unwrap_or_else(|| scaled_elitism(population_size)),
+            mutation_rate: self.mutation_rate.unwrap_or_else(|| scaled_mutation_rate(parts)),
+            ..self.nesting.clone()
         }
     }
-}
 
-/// Parse command line arguments into a configuration struct
-pub fn parse_config() -> Config {
-    let args = CliArgs::parse();
-    args.into()
+    /// Validated configuration for nesting `inputs` with the options of the
+    /// command line and those a batch job's config.json sets.
+    fn new(inputs: Vec<PathBuf>, args: &NestOptions, o: &batch::JobOverrides) -> anyhow::Result<Self> {
+        let nesting = NestingConfig::builder()
+            .spacing(o.spacing.unwrap_or(args.spacing))
+            .rotation_steps(o.rotations.unwrap_or(args.rotations))
+            .rotation_weights(o.rotation_weights.as_deref().unwrap_or(&args.rotation_weights))
+            .angle_fallback(o.angle_fallback.unwrap_or(args.angle_fallback))
+            .split_joint(o.split_joint.unwrap_or(args.split_joint))
+            .crossover(o.crossover.unwrap_or(args.crossover))
+            .local_search_iters(o.local_search_iters.unwrap_or(args.local_search_iters))
+            .seed(o.seed.or(args.seed))
+            .use_holes(o.use_holes.unwrap_or(args.use_holes))
+            .explore_concave(o.explore_concave.unwrap_or(args.explore_concave))
+            .tolerances(Tolerances {
+                curve: args.approx_tolerance,
+                connect: args.connect_tolerance,
+                merge: args.merge_tolerance,
+                angle: args.angle_precision,
+                contact: args.contact_tolerance,
+            })
+            .wrap_x(o.wrap_x.unwrap_or(args.wrap_x))
+            .rotate_sheets(o.rotate_sheets.unwrap_or(args.rotate_sheets))
+            .pack_direction(o.pack_direction.unwrap_or(args.pack_direction))
+            .position_score(o.position_score.unwrap_or(args.position_score))
+            .gravity_point(svg_parser::Point {
+                x: args.gravity_point[0],
+                y: args.gravity_point[1],
+            })
+            .start_offset(o.start_offset.unwrap_or(args.start_offset))
+            .target_utilization(o.target_utilization.or(args.target_utilization))
+            .fitness_weights(o.fitness_weights.unwrap_or(FitnessWeights {
+                sheet: args.sheet_weight,
+                width: args.width_weight,
+                unplaced: args.unplaced_penalty,
+                contact: args.contact_weight,
+            }))
+            .sheet_limits(SheetLimits {
+                parts: o.max_parts_per_sheet.or(args.max_parts_per_sheet),
+                weight: o.max_sheet_weight.or(args.max_sheet_weight),
+                area: o.max_sheet_area.or(args.max_sheet_area),
+            })
+            .fit_arcs(args.fit_arcs)
+            .merge_common_cuts(args.merge_common_cuts)
+            .show_unplaced(args.show_unplaced)
+            .part_transforms(args.part_transforms)
+            .color_by(args.color_by)
+            .free_space_overlay(args.free_space_overlay)
+            .tabs(args.tabs.map(|count| Tabs {
+                count,
+                width: args.tab_width,
+            }))
+            .lead_in(args.lead_in)
+            .lead_out(args.lead_out)
+            .marks(SheetMarks {
+                registration: args.registration_marks,
+                origin: args.origin_mark,
+                label: args.sheet_label,
+                size: args.mark_size,
+            })
+            .gpu(args.gpu)
+            .raster_nfp(args.raster_nfp)
+            .build()
+            .map_err(|e| anyhow!("Invalid configuration: {}", e))?;
+        let cfg = Self {
+            inputs,
+            nesting,
+            merge_lines: o.merge_lines.unwrap_or(args.merge_lines),
+            export_remnants: o.export_remnants.unwrap_or(args.export_remnants),
+            remnant_min_area: args.remnant_min_area,
+            live_output: args.live_output,
+            report: args.report.clone(),
+            machine_origin: svg_parser::Point {
+                x: args.machine_origin[0],
+                y: args.machine_origin[1],
+            },
+            report_html: args.report_html.clone(),
+            renest: args.renest.clone(),
+            renest_sheets: args.renest_sheets.clone(),
+            stats_out: args.stats_out.clone(),
+            restarts: args.restarts,
+            auto_tune: args.auto_tune,
+            estimate: args.estimate,
+            validate: args.validate,
+            plan: args.plan,
+            grid: args.grid,
+            top_k: args.top_k,
+            part_per: args.part_per,
+            stroke_to_path: args.stroke_to_path,
+            marking: svg_parser::MarkingSelector {
+                layers: args.marking_layer.clone(),
+                colors: args.marking_color.clone(),
+            },
+            strict: args.strict,
+            fail_on: args.fail_on.clone(),
+            open_paths: args.open_paths,
+            feature_filter: part::FeatureFilter {
+                min_area: args.min_area,
+                min_size: args.min_feature_size,
+            },
+            align_parts: o.align_parts.unwrap_or(args.align_parts),
+            listen: args.listen.clone(),
+            worker_timeout: std::time::Duration::from_secs(args.worker_timeout),
+            population_size: o.population_size.or(args.population_size),
+            elitism: o.elitism.or(args.elitism),
+            mutation_rate: o.mutation_rate.or(args.mutation_rate),
+        };
+        // the settings scaled to the parts are checked once they are known
+        cfg.nesting_for(0).validate().map_err(|e| anyhow!("Invalid configuration: {}", e))?;
+        Ok(cfg)
+    }
 }
 
 fn main() {
-    let cfg = parse_config();
+    let (argv, renamed) = deprecation::rewrite(std::env::args_os(), deprecation::RENAMED);
+    for warning in renamed {
+        warning.print();
+    }
+    let mut args = CliArgs::parse_from(argv);
+    match args.command.take() {
+        Some(Command::Diff { old, new, output }) => run_diff(&old, &new, &output),
+        Some(Command::Check {
+            layout,
+            bin,
+            spacing,
+            tolerance,
+        }) => run_check(&layout, &bin, spacing, tolerance),
+        Some(Command::Worker { connect }) => run_worker(&connect),
+        Some(Command::Completions { shell }) => {
+            let mut cmd = CliArgs::command();
+            let name = cmd.get_name().to_string();
+            clap_complete::generate(shell, &mut cmd, name, &mut std::io::stdout());
+        }
+        Some(Command::Generate {
+            parts,
+            shape_mix,
+            seed,
+            out,
+        }) => run_generate(parts, &shape_mix, seed, &out),
+        Some(Command::Batch {
+            dir,
+            output,
+            parallel,
+            mix_materials,
+            options,
+        }) => {
+            let mut ok = false;
+            profiled(&options, || ok = run_batch(&dir, &output, parallel, mix_materials, &options));
+            if !ok {
+                std::process::exit(1);
+            }
+        }
+        Some(Command::Daemon {
+            dir,
+            output,
+            interval,
+            once,
+            options,
+        }) => profiled(&options, || run_daemon(&dir, &output, interval, once, &options)),
+        None => {
+            let cfg = match Config::new(args.inputs, &args.options, &Default::default()) {
+                Ok(v) => v,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return;
+                }
+            };
+            profiled(&args.options, || run_nest(&cfg, &args.output_dir));
+        }
+    }
+}
+
+/// Run `f`, timing it as requested by --profile and --profile-trace.
+fn profiled(options: &NestOptions, f: impl FnOnce()) {
+    let profiling = options.profile || options.profile_trace.is_some();
+    if profiling {
+        profile::enable();
+    }
+    f();
+    if profiling {
+        print_profile(options.profile_trace.as_deref());
+    }
+}
+
+fn run_diff(old: &Path, new: &Path, output: &Path) {
+    let load = |path: &Path| {
+        report::Report::from_file(path).map_err(|e| {
+            eprintln!("Failed to read {}: {}", path.display(), e);
+        })
+    };
+    let (Ok(old_report), Ok(new_report)) = (load(old), load(new)) else {
+        return;
+    };
+    let layout_diff = diff::diff_reports(&old_report, &new_report);
+    print!("{}", layout_diff.summary());
+    let svg = diff::side_by_side_svg(&old_report, &new_report, &layout_diff);
+    if let Err(e) = output::write_atomic(output, svg.as_bytes()) {
+        eprintln!("Failed to write {}: {}", output.display(), e);
+        return;
+    }
+    println!("Side-by-side comparison written to {}", output.display());
+}
+
+fn print_profile(trace: Option<&Path>) {
+    print!("{}", profile::summary());
+    let Some(path) = trace else {
+        return;
+    };
+    let json = match profile::chrome_trace() {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("Failed to serialize trace: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = output::write_atomic(path, json.as_bytes()) {
+        eprintln!("Failed to write {}: {}", path.display(), e);
+        return;
+    }
+    println!("Trace written to {}", path.display());
+}
+
+/// Nest the jobs in `dir`, returning whether all of them succeeded.
+fn run_batch(dir: &Path, output: &Path, parallel: bool, mix_materials: bool, options: &NestOptions) -> bool {
+    let jobs = match batch::discover_jobs(dir) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("Failed to read jobs: {}", e);
+            return false;
+        }
+    };
+    // what is nested in one run: a single job, or the jobs sharing sheets
+    let mut units: Vec<(String, anyhow::Result<Vec<batch::Job>>)> = Vec::new();
+    let mut valid = Vec::new();
+    for (job_dir, job) in jobs {
+        let name = job_dir.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        match job {
+            Ok(job) if mix_materials => valid.push(job),
+            Ok(job) => units.push((name, Ok(vec![job]))),
+            Err(e) => units.push((name, Err(e))),
+        }
+    }
+    if mix_materials {
+        let groups = match batch::material_groups(&valid) {
+            Ok(v) => v,
+            Err(e) => {
+                eprintln!("Failed to group jobs: {}", e);
+                return false;
+            }
+        };
+        for group in groups {
+            let jobs: Vec<batch::Job> = group.iter().map(|&i| valid[i].clone()).collect();
+            let refs: Vec<&batch::Job> = jobs.iter().collect();
+            units.push((batch::merge_jobs(&refs).name, Ok(jobs)));
+        }
+    }
+    let run = |(name, jobs): &(String, anyhow::Result<Vec<batch::Job>>)| {
+        let start = std::time::Instant::now();
+        let res = jobs.as_ref().map_err(|e| anyhow!("{}", e)).and_then(|jobs| {
+            let refs: Vec<&batch::Job> = jobs.iter().collect();
+            let job = batch::merge_jobs(&refs);
+            let rep = nest_job(&job, &output.join(&job.name), options)?;
+            let shares = if jobs.len() > 1 {
+                batch::job_shares(&rep, &refs)
+            } else {
+                Vec::new()
+            };
+            Ok((rep, shares))
+        });
+        let seconds = start.elapsed().as_secs_f64();
+        match res {
+            Ok((rep, shares)) => batch::JobSummary {
+                shares,
+                ..batch::JobSummary::from_report(name, &rep, seconds)
+            },
+            Err(e) => batch::JobSummary::failed(name, e.to_string(), seconds),
+        }
+    };
+    let summary = batch::BatchSummary {
+        jobs: if parallel {
+            units.par_iter().map(run).collect()
+        } else {
+            units.iter().map(run).collect()
+        },
+    };
+    print!("{}", summary.table());
+    let path = output.join("summary.json");
+    let json = match summary.to_json() {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("Failed to serialize summary: {}", e);
+            return false;
+        }
+    };
+    if let Err(e) = std::fs::create_dir_all(output).and_then(|_| output::write_atomic(&path, json.as_bytes())) {
+        eprintln!("Failed to write {}: {}", path.display(), e);
+        return false;
+    }
+    println!("Summary written to {}", path.display());
+    summary.jobs.iter().all(|j| j.ok)
+}
+
+/// The earlier report at `path` with its kept placements renumbered to
+/// `parts`, the sheets to nest again and the indices of the parts to nest:
+/// those on the re-nested sheets and those the report does not have.
+fn renest_parts(
+    path: &Path,
+    sheets: &[usize],
+    parts: &[part::Part],
+) -> anyhow::Result<(report::Report, Vec<usize>, Vec<usize>)> {
+    let mut previous =
+        report::Report::from_file(path).map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+    let sheets = if sheets.is_empty() {
+        vec![previous.sheets.saturating_sub(1)]
+    } else {
+        sheets.to_vec()
+    };
+    if let Some(s) = sheets.iter().find(|&&s| s >= previous.sheets) {
+        bail!("{} has no sheet {}, only {}", path.display(), s, previous.sheets);
+    }
+    let mut free = vec![true; parts.len()];
+    for placed in previous.placements.iter_mut().filter(|p| !sheets.contains(&p.sheet)) {
+        let Some(id) = &placed.id else {
+            bail!("{} does not identify its parts, nest again to re-nest it", path.display());
+        };
+        let Some(i) = (0..parts.len()).find(|&i| free[i] && parts[i].id == *id) else {
+            bail!("Part {} on sheet {} of {} is not among the inputs", id, placed.sheet, path.display());
+        };
+        free[i] = false;
+        placed.part = i;
+    }
+    let nested = (0..parts.len()).filter(|&i| free[i]).collect();
+    Ok((previous, sheets, nested))
+}
+
+/// Nest a batch or drop-folder job into `out_dir`.
+fn nest_job(job: &batch::Job, out_dir: &Path, options: &NestOptions) -> anyhow::Result<report::Report> {
+    std::fs::create_dir_all(out_dir).map_err(|e| anyhow!("Failed to create {}: {}", out_dir.display(), e))?;
+    let mut cfg = Config::new(job.inputs(), options, &job.overrides)?;
+    if cfg.renest.is_some() {
+        bail!("--renest cannot be used for batch jobs");
+    }
+    if cfg.estimate {
+        bail!("--estimate cannot be used for batch jobs");
+    }
+    if cfg.validate {
+        bail!("--validate cannot be used for batch jobs");
+    }
+    if cfg.plan {
+        bail!("--plan cannot be used for batch jobs");
+    }
+    cfg.report = Some(out_dir.join("report.json"));
+    // every job gets its own HTML report when one is asked for
+    if cfg.report_html.is_some() {
+        cfg.report_html = Some(out_dir.join("report.html"));
+    }
+    let rep = nest(&cfg, out_dir)?;
+    if let Some(w) = warning::first_of(&rep.warnings, &cfg.fail_on) {
+        bail!("Failing on {}", w);
+    }
+    Ok(rep)
+}
+
+fn run_daemon(dir: &Path, output: &Path, interval: f64, once: bool, options: &NestOptions) {
+    let mut folder = daemon::DropFolder::new(dir, output);
+    if !once {
+        println!("Watching {} for jobs", dir.display());
+    }
+    loop {
+        let ready = if once { folder.pending() } else { folder.poll() };
+        match ready {
+            Ok(jobs) => {
+                for job_dir in jobs {
+                    run_daemon_job(&folder, &job_dir, options);
+                }
+            }
+            Err(e) => eprintln!("Failed to scan {}: {}", dir.display(), e),
+        }
+        if once {
+            return;
+        }
+        std::thread::sleep(std::time::Duration::from_secs_f64(interval.max(0.0)));
+    }
+}
 
+fn run_daemon_job(folder: &daemon::DropFolder, job_dir: &Path, options: &NestOptions) {
+    let out_dir = folder.output_dir(job_dir);
+    let name = job_dir.file_name().unwrap_or_default().to_string_lossy();
+    let mut status = daemon::Status::running(&name);
+    if let Err(e) = status.write(&out_dir) {
+        eprintln!("Failed to record status of {}: {}", name, e);
+        return;
+    }
+    println!("Nesting job {}", name);
+    let res = batch::Job::from_dir(job_dir).and_then(|job| nest_job(&job, &out_dir, options));
+    match &res {
+        Ok(_) => println!("Job {} done", name),
+        Err(e) => eprintln!("Job {} failed: {}", name, e),
+    }
+    status.finish(res.err().map(|e| e.to_string()));
+    if let Err(e) = status.write(&out_dir) {
+        eprintln!("Failed to record status of {}: {}", name, e);
+    }
+}
+
+fn run_check(layout: &Path, bin: &Path, spacing: f64, tol: f64) {
+    let (parts, violations) = match check_layout_file(layout, bin, spacing, tol) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    for v in &violations {
+        println!("{}", v);
+    }
+    println!("{} part(s) checked, {} violation(s)", parts, violations.len());
+    if !violations.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+/// Number of parts in the nested SVG `layout` and what is wrong with them.
+fn check_layout_file(layout: &Path, bin: &Path, spacing: f64, tol: f64) -> anyhow::Result<(usize, Vec<check::Violation>)> {
+    let mut warnings = Vec::new();
+    let ext = bin.extension().and_then(|e| e.to_str()).unwrap_or("");
+    let bin_part = if ext.eq_ignore_ascii_case("dxf") {
+        dxf_parser::part_from_dxf(bin, &Tolerances::default(), false, &mut warnings)
+    } else {
+        svg_parser::parts_from_file(bin, &svg_parser::ParseOptions::default(), &mut warnings).map(|mut p| p.remove(0))
+    };
+    let bin_part = bin_part.map_err(|e| anyhow!("Failed to parse {}: {}", bin.display(), e))?;
+    let Some(bin_polygon) = bin_part.outline() else {
+        bail!("No polygons found in {}", bin.display());
+    };
+    let data = std::fs::read_to_string(layout).map_err(|e| anyhow!("Failed to read {}: {}", layout.display(), e))?;
+    let shapes = check::shapes_from_svg(&data).map_err(|e| anyhow!("Failed to parse {}: {}", layout.display(), e))?;
+    Ok((shapes.len(), check::check_layout(&shapes, bin_polygon, spacing, tol)))
+}
+
+fn run_generate(parts: usize, mix: &[generate::ShapeKind], seed: u64, out: &Path) {
+    let instance = generate::generate(parts, mix, seed);
+    match instance.write(out) {
+        Ok(()) => println!("Generated {} part(s) in {}", parts, out.display()),
+        Err(e) => {
+            eprintln!("Failed to generate instance: {:#}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn run_worker(addr: &str) {
+    match distributed::run_worker(addr) {
+        Ok(n) => println!("Coordinator finished after {} evaluations", n),
+        Err(e) => {
+            eprintln!("Worker failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn run_nest(cfg: &Config, out_dir: &Path) {
+    if cfg.validate {
+        match run_validate(cfg) {
+            Ok(true) => {}
+            Ok(false) => std::process::exit(1),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(2);
+            }
+        }
+        return;
+    }
+    if cfg.estimate {
+        if let Err(e) = run_estimate(cfg) {
+            eprintln!("{}", e);
+        }
+        return;
+    }
+    if cfg.plan {
+        if let Err(e) = run_plan(cfg) {
+            eprintln!("{}", e);
+        }
+        return;
+    }
+    let res = std::fs::create_dir_all(out_dir)
+        .map_err(|e| anyhow!("Failed to create {}: {}", out_dir.display(), e))
+        .and_then(|_| nest(cfg, out_dir));
+    match res {
+        Ok(rep) => {
+            if let Some(w) = warning::first_of(&rep.warnings, &cfg.fail_on) {
+                eprintln!("Failing on {}", w);
+                std::process::exit(1);
+            }
+        }
+        Err(e) => eprintln!("{}", e),
+    }
+}
+
+/// Parts, bin and bin holes read from the inputs of a job, with the warnings
+/// raised while reading them.
+struct Inputs {
+    parts: Vec<part::Part>,
+    bin: svg_parser::Polygon,
+    bin_holes: Vec<Vec<svg_parser::Point>>,
+    warnings: Vec<warning::Warning>,
+}
+
+/// Read the bin and the parts of `cfg`, printing any warnings.
+fn read_inputs(cfg: &Config) -> anyhow::Result<Inputs> {
     let mut parts = Vec::new();
     let mut bin: Option<svg_parser::Polygon> = None;
+    let mut bin_holes = Vec::new();
+    let mut warnings = Vec::new();
     for path in &cfg.inputs {
         let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let mut opts = svg_parser::ParseOptions {
+            merge_lines: cfg.merge_lines,
+            tolerances: cfg.nesting.tolerances,
+            part_per: cfg.part_per,
+            stroke_to_path: cfg.stroke_to_path,
+            strict: cfg.strict,
+            marking: cfg.marking.clone(),
+        };
+        // the bin file is always read as a whole
+        if bin.is_none() {
+            opts.part_per = svg_parser::PartMode::File;
+        }
+        let _span = profile::span(Stage::Parse);
         let res = if ext.eq_ignore_ascii_case("dxf") {
-            dxf_parser::part_from_dxf(path)
+            dxf_parser::part_from_dxf(path, &cfg.nesting.tolerances, cfg.strict, &mut warnings)
+                .map(|p| vec![p])
         } else {
-            svg_parser::polygons_from_file(path, cfg.merge_lines, cfg.approx_tolerance)
-                .map(|p| crate::part::Part::new(p))
+            svg_parser::parts_from_file(path, &opts, &mut warnings)
         };
-        match res {
-            Ok(p) => {
-                if bin.is_none() {
-                    bin = p.polygons.first().cloned();
-                } else {
-                    parts.push(p);
-                }
+        let file_parts = res.map_err(|e| anyhow!("Failed to parse {}: {}", path.display(), e))?;
+        let file_parts = cfg
+            .open_paths
+            .apply(file_parts, cfg.nesting.tolerances.curve, Some(path), &mut warnings);
+        let file_parts = cfg.feature_filter.apply(file_parts, Some(path), &mut warnings);
+        if bin.is_none() {
+            // the bin is the largest outline, the holes in it are holes of
+            // the stock
+            if let Some(part) = file_parts.first() {
+                bin = part.outline().cloned();
+                bin_holes = part
+                    .shapes()
+                    .first()
+                    .map(|s| s.holes.iter().map(|&i| part.polygons[i].points.clone()).collect())
+                    .unwrap_or_default();
             }
-            Err(e) => {
-                eprintln!("Failed to parse {}: {}", path.display(), e);
-                return;
+        } else {
+            parts.extend(file_parts);
+        }
+    }
+
+    for warning in &warnings {
+        warning.print();
+    }
+
+    let Some(bin) = bin else {
+        bail!("No polygons found in input");
+    };
+    if parts.is_empty() {
+        bail!("No polygons found in input");
+    }
+    if cfg.align_parts {
+        parts = parts.into_iter().map(part::Part::aligned).collect();
+    }
+    let mut parts = split::split_oversize(parts, &bin, &cfg.nesting)?;
+    part::number_copies(&mut parts);
+    let parts = parts
+        .into_iter()
+        .map(|p| p.with_rotations(&cfg.nesting.rotations))
+        .collect();
+    Ok(Inputs {
+        parts,
+        bin,
+        bin_holes,
+        warnings,
+    })
+}
+
+/// Lay the inputs of `cfg` out once without optimizing and print what the
+/// job takes as JSON, writing no files.
+fn run_estimate(cfg: &Config) -> anyhow::Result<()> {
+    let inputs = read_inputs(cfg)?;
+    let mut nesting = cfg.nesting.clone();
+    nesting.bin_holes = inputs.bin_holes;
+    let estimate = estimate::estimate(&inputs.parts, &inputs.bin, &nesting)?;
+    println!("{}", estimate.to_json()?);
+    Ok(())
+}
+
+/// Print what optimizing the inputs of `cfg` will cost, writing no files.
+fn run_plan(cfg: &Config) -> anyhow::Result<()> {
+    let inputs = read_inputs(cfg)?;
+    let mut nesting = cfg.nesting_for(inputs.parts.len());
+    nesting.bin_holes = inputs.bin_holes;
+    let plan = plan::plan(&inputs.parts, &inputs.bin, &nesting, GENERATIONS, cfg.restarts)?;
+    println!("{}", plan);
+    Ok(())
+}
+
+/// Print for every part of the inputs of `cfg` the rotations at which it fits
+/// the bin, or why it fits at none, and return whether all parts fit.
+fn run_validate(cfg: &Config) -> anyhow::Result<bool> {
+    let inputs = read_inputs(cfg)?;
+    let mut nesting = cfg.nesting.clone();
+    nesting.bin_holes = inputs.bin_holes;
+    let feasibility = ga::feasibility(&inputs.parts, &inputs.bin, &nesting)?;
+    for f in &feasibility {
+        match &f.reason {
+            Some(reason) => println!("{}: cannot be placed, {}", f.id, reason),
+            None => {
+                let angles: Vec<String> = f.angles.iter().map(|a| format!("{}°", a)).collect();
+                println!("{}: fits at {}", f.id, angles.join(", "));
             }
         }
     }
+    let unplaceable = feasibility.iter().filter(|f| f.reason.is_some()).count();
+    println!("{} part(s) checked, {} cannot be placed", feasibility.len(), unplaceable);
+    Ok(unplaceable == 0)
+}
 
-    let bin = match bin {
-        Some(b) => b,
-        None => {
-            eprintln!("No polygons found in input");
-            return;
+/// Nest the inputs of `cfg`, writing the outputs into `out_dir`, and return
+/// the report of the final layout.
+fn nest(cfg: &Config, out_dir: &Path) -> anyhow::Result<report::Report> {
+    let Inputs {
+        parts,
+        bin,
+        bin_holes,
+        mut warnings,
+    } = read_inputs(cfg)?;
+    let (parts, renest) = match &cfg.renest {
+        Some(path) => {
+            let (previous, sheets, nested) = renest_parts(path, &cfg.renest_sheets, &parts)?;
+            let parts: Vec<_> = nested.iter().map(|&i| parts[i].clone()).collect();
+            if parts.is_empty() {
+                bail!("No parts left to re-nest");
+            }
+            (parts, Some((previous, sheets, nested)))
         }
+        None => (parts, None),
     };
 
-    if parts.is_empty() {
-        eprintln!("No polygons found in input");
-        return;
+    // a run without a seed gets one, so it can be repeated from its record
+    let mut nesting = cfg.nesting_for(parts.len());
+    nesting.seed = Some(nesting.seed.unwrap_or_else(rand::random));
+    nesting.bin_holes = bin_holes;
+    if cfg.auto_tune {
+        let (tuned, trials) = tune::auto_tune(&parts, &bin, &nesting, tune::PILOT_GENERATIONS)
+            .map_err(|e| anyhow!("Failed to tune settings: {}", e))?;
+        println!(
+            "Tuned over {} trials: population {}, mutation rate {}%, {} rotation(s)",
+            trials.len(),
+            tuned.population_size,
+            tuned.mutation_rate,
+            tuned.rotations.len()
+        );
+        nesting = tuned;
     }
-
-    let ga_cfg = ga::GAConfig {
-        population_size: cfg.population_size,
-        mutation_rate: cfg.mutation_rate,
-        rotations: cfg.rotations,
-        spacing: cfg.spacing,
-        use_holes: cfg.use_holes,
-        explore_concave: cfg.explore_concave,
-        angle_precision: cfg.angle_precision,
+    if nesting.angle_fallback != AngleFallback::Error {
+        for f in ga::feasibility(&parts, &bin, &nesting)? {
+            if let Some(reason) = f.reason {
+                let code = match parts[f.index].degenerate() {
+                    Some(_) => warning::Code::DegeneratePart,
+                    None => warning::Code::PartUnplaceable,
+                };
+                let warning = warning::Warning::new(
+                    code,
+                    f.id.to_string(),
+                    format!("cannot be placed: {}", reason),
+                );
+                warning.print();
+                warnings.push(warning);
+            }
+        }
+    }
+    let run_info = provenance::RunInfo::new(&nesting, &cfg.inputs)?;
+    let mut ga = ga::GeneticAlgorithm::new(&parts, &bin, nesting.clone())
+        .map_err(|e| anyhow!("Failed to initialize algorithm: {}", e))?
+        .keep_top(cfg.top_k);
+    if let Some(addr) = &cfg.listen {
+        if cfg.restarts > 1 {
+            bail!("--restarts cannot be combined with --listen");
+        }
+        let coordinator = distributed::Coordinator::bind(addr, &parts, &bin, &nesting)
+            .map_err(|e| anyhow!("Failed to start coordinator: {}", e))?
+            .with_timeout(cfg.worker_timeout);
+        println!("Accepting workers on {}", coordinator.local_addr());
+        ga = ga.with_evaluator(Box::new(move |batch| coordinator.evaluate(batch)));
+    }
+    if cfg.nesting.gpu && !ga.uses_gpu() {
+        let message = if cfg!(feature = "gpu") {
+            "no GPU adapter available, generating NFPs on the CPU"
+        } else {
+            "GPU support not enabled, generating NFPs on the CPU"
+        };
+        let warning = warning::Warning::new(warning::Code::GpuUnavailable, "", message);
+        warning.print();
+        warnings.push(warning);
+    }
+    let nested = out_dir.join("nested.svg");
+    // nothing is written before the run is complete, so a failure keeps the
+    // results of the previous run; progress goes to a file of its own
+    let mut outputs = output::OutputSet::default();
+    let mut notes = Vec::new();
+    let live = out_dir.join("nested.live.svg");
+    // carries the run info like the final SVG, so the last one written
+    // matches it
+    let write_live = |ga: &mut ga::GeneticAlgorithm<'_>, best: &ga::Individual| {
+        let written = run_info
+            .embed_in_svg(&ga.create_svg(best))
+            .and_then(|svg| Ok(output::write_atomic(&live, svg.as_bytes())?));
+        if let Err(e) = written {
+            eprintln!("Failed to write SVG: {}", e);
+        }
     };
-    let mut ga = match ga::GeneticAlgorithm::new(&parts, &bin, ga_cfg) {
-        Ok(v) => v,
-        Err(e) => {
-            eprintln!("Failed to initialize algorithm: {}", e);
-            return;
+    let grid = if cfg.grid {
+        let grid = grid::grid_layout(&parts, ga.bin_bounds(), &nesting);
+        if grid.is_none() {
+            println!("Parts do not suit a grid, optimizing instead");
         }
+        grid
+    } else {
+        None
     };
-    ga.evolve(100);
-    let best = match ga.population.iter().min_by(|a, b| {
-        a.fitness
-            .partial_cmp(&b.fitness)
-            .unwrap_or(std::cmp::Ordering::Equal)
-    }) {
-        Some(v) => v.clone(),
+    let layout = match grid {
+        Some(layout) => {
+            println!("Parts laid out on a grid");
+            layout
+        }
         None => {
-            eprintln!("No population available to evaluate");
-            return;
+            let run = if cfg.restarts > 1 {
+                ga::evolve_restarts(&parts, &bin, &nesting, GENERATIONS, cfg.restarts, cfg.top_k, |ga, best| {
+                    if cfg.live_output {
+                        write_live(ga, best);
+                    }
+                })?
+            } else {
+                if cfg.live_output {
+                    ga.evolve_with(GENERATIONS, write_live);
+                } else {
+                    ga.evolve(GENERATIONS);
+                }
+                let best = ga
+                    .best()
+                    .cloned()
+                    .ok_or_else(|| anyhow!("No population available to evaluate"))?;
+                ga::Run {
+                    best,
+                    stats: ga.stats().to_vec(),
+                    hall_of_fame: ga.hall_of_fame().to_vec(),
+                }
+            };
+            if let Some(path) = &cfg.stats_out {
+                let data = stats::render(path, &run.stats)
+                    .map_err(|e| anyhow!("Failed to serialize statistics: {}", e))?;
+                outputs.add(path, data);
+                notes.push(format!("Statistics written to {}", path.display()));
+            }
+            if cfg.top_k > 1 {
+                for (rank, ind) in run.hall_of_fame.iter().enumerate() {
+                    let path = out_dir.join(format!("top_{}.svg", rank + 1));
+                    outputs.add(path, run_info.embed_in_svg(&ga.create_svg(ind))?);
+                }
+                notes.push(format!(
+                    "{} best layouts written to {}",
+                    run.hall_of_fame.len(),
+                    out_dir.join("top_*.svg").display()
+                ));
+            }
+            ga.to_layout(&run.best)
         }
     };
-    let svg = ga.create_svg(&best);
-    if let Err(e) = std::fs::write("nested.svg", svg) {
-        eprintln!("Failed to write SVG: {}", e);
-        return;
+    let svg = run_info.embed_in_svg(&ga.layout_svg(&layout))?;
+    outputs.add(&nested, svg);
+    notes.push(format!("Nested result written to {}", nested.display()));
+    outputs.add(out_dir.join("run.json"), run_info.to_json()?);
+
+    let mut rep = layout.to_report(&parts).with_machine_origin(cfg.machine_origin);
+    rep.warnings = warnings;
+    if let Some((previous, sheets, nested)) = &renest {
+        // back to the numbering of all inputs
+        for p in &mut rep.placements {
+            p.part = nested[p.part];
+        }
+        for i in &mut rep.unplaced {
+            *i = nested[*i];
+        }
+        rep = previous.with_renested(sheets, &rep)?;
+        let sheets: Vec<String> = sheets.iter().map(|s| s.to_string()).collect();
+        notes.push(format!(
+            "Sheet(s) {} re-nested, the nested SVG holds their new layout and the report all sheets",
+            sheets.join(", ")
+        ));
+    }
+    if let Some(path) = &cfg.report {
+        let json = rep
+            .to_json()
+            .map_err(|e| anyhow!("Failed to serialize report: {}", e))?;
+        outputs.add(path, json);
+        notes.push(format!("Report written to {}", path.display()));
+    }
+    if let Some(path) = &cfg.report_html {
+        outputs.add(path, html::report_html(&rep));
+        notes.push(format!("HTML report written to {}", path.display()));
+    }
+
+    if cfg.export_remnants {
+        let sheets = layout.sheet_bounds();
+        let holes: Vec<Vec<svg_parser::Point>> = cfg
+            .nesting
+            .bin_holes
+            .iter()
+            .map(|hole| {
+                hole.iter()
+                    .map(|p| svg_parser::Point { x: p.x - layout.bin.x, y: p.y - layout.bin.y })
+                    .collect()
+            })
+            .collect();
+        let remnants = remnant::compute_remnants(
+            &parts,
+            &layout.placements,
+            &sheets,
+            &holes,
+            cfg.nesting.wrap_x,
+            cfg.remnant_min_area,
+        );
+        for (sheet, &bounds) in sheets.iter().enumerate() {
+            let regions: Vec<remnant::Remnant> = remnants
+                .iter()
+                .filter(|r| r.sheet == sheet)
+                .cloned()
+                .collect();
+            let svg_name = out_dir.join(format!("remnant_{}.svg", sheet));
+            outputs.add(svg_name, remnant::remnant_svg(&regions, bounds));
+            if cfg!(feature = "dxf") {
+                let rings: Vec<Vec<svg_parser::Point>> = regions
+                    .iter()
+                    .flat_map(|r| std::iter::once(r.outline.clone()).chain(r.holes.clone()))
+                    .collect();
+                let dxf_name = out_dir.join(format!("remnant_{}.dxf", sheet));
+                let res = match cfg.nesting.fit_arcs {
+                    Some(tol) => {
+                        let contours: Vec<_> = rings
+                            .iter()
+                            .filter_map(|ring| contour::fit_arcs(ring, true, tol))
+                            .collect();
+                        dxf_writer::contours_dxf(&contours, tol)
+                    }
+                    None => dxf_writer::polygons_dxf(&rings),
+                };
+                let data = res.map_err(|e| anyhow!("Failed to export {}: {}", dxf_name.display(), e))?;
+                outputs.add(dxf_name, data);
+            }
+        }
+        notes.push(format!("Remnants written for {} sheet(s)", sheets.len()));
+    }
+
+    {
+        let _span = profile::span(Stage::Output);
+        outputs.commit()?;
+    }
+    for note in notes {
+        println!("{}", note);
     }
-    println!("Nested result written to nested.svg");
+    Ok(rep)
 }
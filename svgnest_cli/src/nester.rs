@@ -0,0 +1,367 @@
+//! Library entry point to nesting.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, bail};
+
+use crate::config::NestingConfig;
+use crate::ga::{GeneticAlgorithm, Individual};
+use crate::layout::Layout;
+use crate::nfp::NfpCache;
+use crate::part::{self, Part, PartId};
+use crate::progress::ProgressHandle;
+use crate::stats::GenerationStats;
+use crate::svg_parser::Polygon;
+
+/// Parts to nest into a bin, together with the options to nest them with.
+///
+/// A nester is a session: parts may be added and removed between runs, and
+/// every run continues from the population the one before ended with, so an
+/// interactive front end does not start over whenever the parts change.
+///
+/// Hosts without threads, like a GUI or web event loop, can run the
+/// optimizer a slice at a time with [`step`](Self::step).
+pub struct Nester {
+    parts: Vec<Part>,
+    bin: Polygon,
+    config: NestingConfig,
+    generations: usize,
+    /// Population the last run got to, in terms of `parts`
+    population: RefCell<Vec<Individual>>,
+    /// Generations [`step`](Self::step) evaluated since the parts last changed
+    stepped: usize,
+    /// Fitness of the best layout `step` returned since the parts last changed
+    step_best: f64,
+    /// NFPs generated by earlier steps on the current parts
+    nfp_cache: Option<NfpCache>,
+}
+
+impl Nester {
+    /// Number of generations the optimizer runs unless changed with
+    /// [`generations`](Self::generations), the same as the CLI.
+    pub const DEFAULT_GENERATIONS: usize = 100;
+
+    pub fn new(mut parts: Vec<Part>, bin: Polygon, config: NestingConfig) -> Self {
+        part::number_copies(&mut parts);
+        Self {
+            parts: parts.into_iter().map(|p| p.with_rotations(&config.rotations)).collect(),
+            bin,
+            config,
+            generations: Self::DEFAULT_GENERATIONS,
+            population: RefCell::new(Vec::new()),
+            stepped: 0,
+            step_best: f64::INFINITY,
+            nfp_cache: None,
+        }
+    }
+
+    /// Stop optimizing after `generations` generations.
+    pub fn generations(mut self, generations: usize) -> Self {
+        self.generations = generations;
+        self
+    }
+
+    pub fn parts(&self) -> &[Part] {
+        &self.parts
+    }
+
+    /// Add `parts` to the session. Copies of parts already in it are
+    /// numbered on from the existing copies.
+    pub fn add_parts(&mut self, parts: Vec<Part>) {
+        self.restart_steps();
+        let mut next: HashMap<PartId, usize> = HashMap::new();
+        for p in &self.parts {
+            let n = next.entry(p.id.part.clone()).or_default();
+            *n = (*n).max(p.id.copy + 1);
+        }
+        for mut p in parts {
+            let n = next.entry(p.id.part.clone()).or_default();
+            p.id.copy = *n;
+            *n += 1;
+            self.parts.push(p.with_rotations(&self.config.rotations));
+        }
+    }
+
+    /// Remove the parts `remove` returns true for and return how many were
+    /// removed. The carried population keeps the order of the other parts.
+    pub fn remove_parts(&mut self, remove: impl Fn(&Part) -> bool) -> usize {
+        self.restart_steps();
+        let mut index = Vec::with_capacity(self.parts.len());
+        let mut kept = 0;
+        for p in &self.parts {
+            if remove(p) {
+                index.push(None);
+            } else {
+                index.push(Some(kept));
+                kept += 1;
+            }
+        }
+        let removed = self.parts.len() - kept;
+        let mut i = 0;
+        self.parts.retain(|_| {
+            i += 1;
+            index[i - 1].is_some()
+        });
+        for ind in self.population.get_mut() {
+            // individuals from older sessions may have no sheet types
+            let genes: Vec<(usize, f64, Option<usize>)> = ind
+                .placement
+                .iter()
+                .zip(&ind.rotation)
+                .enumerate()
+                .filter_map(|(i, (&idx, &angle))| {
+                    Some((index.get(idx).copied().flatten()?, angle, ind.sheet_type.get(i).copied()))
+                })
+                .collect();
+            ind.placement = genes.iter().map(|g| g.0).collect();
+            ind.rotation = genes.iter().map(|g| g.1).collect();
+            ind.sheet_type = genes.iter().filter_map(|g| g.2).collect();
+        }
+        removed
+    }
+
+    /// Make the session hold `quantity` copies of `part`, adding copies of
+    /// an existing one or removing those with the highest copy numbers.
+    pub fn set_quantity(&mut self, part: &PartId, quantity: usize) -> Result<()> {
+        let mut copies: Vec<usize> = self.parts.iter().filter(|p| p.id.part == *part).map(|p| p.id.copy).collect();
+        let Some(template) = self.parts.iter().find(|p| p.id.part == *part).cloned() else {
+            bail!("no part {} in the session", part);
+        };
+        if copies.len() < quantity {
+            self.add_parts(vec![template; quantity - copies.len()]);
+        } else if copies.len() > quantity {
+            copies.sort_unstable();
+            let extra = copies.split_off(quantity);
+            self.remove_parts(|p| p.id.part == *part && extra.contains(&p.id.copy));
+        }
+        Ok(())
+    }
+
+    /// Layouts in the order the optimizer finds them, each better than the
+    /// ones before. Optimizing happens as the iterator is advanced, so
+    /// dropping it stops the run. The iterator ends early once a layout
+    /// meets the target utilization.
+    pub fn solutions(&self) -> Result<Solutions<'_>> {
+        let mut ga = GeneticAlgorithm::new(&self.parts, &self.bin, self.config.clone())?;
+        ga.plan_generations(self.generations);
+        ga.resume(&self.population.borrow());
+        Ok(Solutions {
+            ga,
+            carried: &self.population,
+            generation: 0,
+            generations: self.generations,
+            best_fitness: f64::INFINITY,
+        })
+    }
+
+    /// Best layout found within the generation limit.
+    pub fn run(&self) -> Result<Option<Layout>> {
+        Ok(self.solutions()?.last())
+    }
+
+    /// Optimize for up to `budget` of wall-clock time and return. At least
+    /// one population is evaluated per call, so the budget may be overrun by
+    /// one generation. Steps continue each other until the generation limit
+    /// or the target utilization is reached; changing the parts starts the
+    /// count over.
+    pub fn step(&mut self, budget: Duration) -> Result<Step> {
+        let start = Instant::now();
+        let mut ga = GeneticAlgorithm::new(&self.parts, &self.bin, self.config.clone())?;
+        if let Some(cache) = self.nfp_cache.take() {
+            ga = ga.with_nfp_cache(cache);
+        }
+        ga.plan_generations(self.generations);
+        ga.resume(&self.population.borrow());
+        let first = self.stepped;
+        let mut improved = None;
+        while self.stepped <= self.generations {
+            // the carried population was evaluated by the step before
+            if self.stepped > 0 {
+                ga.generation();
+            }
+            self.stepped += 1;
+            ga.evaluate_population();
+            if let Some(best) = ga.best().cloned() {
+                if best.fitness < self.step_best {
+                    self.step_best = best.fitness;
+                    improved = Some(ga.to_layout(&best));
+                }
+            }
+            if ga.target_reached() {
+                self.stepped = self.generations + 1;
+            }
+            if start.elapsed() >= budget {
+                break;
+            }
+        }
+        *self.population.get_mut() = ga.population.clone();
+        let mut stats = ga.stats().to_vec();
+        for (i, s) in stats.iter_mut().enumerate() {
+            s.generation = first + i;
+        }
+        self.nfp_cache = Some(ga.into_nfp_cache());
+        Ok(Step {
+            generation: self.stepped,
+            best_fitness: self.step_best,
+            improved,
+            stats,
+            done: self.stepped > self.generations,
+        })
+    }
+
+    /// Forget the progress of [`step`](Self::step), whose population and
+    /// NFPs no longer match the parts.
+    fn restart_steps(&mut self) {
+        self.stepped = 0;
+        self.step_best = f64::INFINITY;
+        self.nfp_cache = None;
+    }
+}
+
+/// Outcome of a [`Nester::step`].
+#[derive(Debug, Clone)]
+pub struct Step {
+    /// Generations evaluated since the parts last changed, counting the
+    /// initial population
+    pub generation: usize,
+    /// Fitness of the best layout found since the parts last changed
+    pub best_fitness: f64,
+    /// The best layout, if this step found a better one
+    pub improved: Option<Layout>,
+    /// Statistics of the populations evaluated in this step
+    pub stats: Vec<GenerationStats>,
+    /// The generation limit or the target utilization is reached, so further
+    /// steps do nothing
+    pub done: bool,
+}
+
+/// Iterator returned by [`Nester::solutions`].
+pub struct Solutions<'a> {
+    ga: GeneticAlgorithm<'a>,
+    /// Where the population is left for the next run of the session
+    carried: &'a RefCell<Vec<Individual>>,
+    /// Generations evaluated so far
+    generation: usize,
+    generations: usize,
+    best_fitness: f64,
+}
+
+impl Solutions<'_> {
+    /// Handle to the progress of the run, which can be read from another
+    /// thread while the iterator is advanced.
+    pub fn progress(&self) -> ProgressHandle {
+        self.ga.progress()
+    }
+}
+
+impl Iterator for Solutions<'_> {
+    type Item = Layout;
+
+    fn next(&mut self) -> Option<Layout> {
+        // generation 0 is the initial population, so there are
+        // `generations + 1` evaluations like in `GeneticAlgorithm::evolve`
+        while self.generation <= self.generations {
+            if self.generation > 0 {
+                self.ga.generation();
+            }
+            self.generation += 1;
+            self.ga.evaluate_population();
+            *self.carried.borrow_mut() = self.ga.population.clone();
+            let best = self.ga.best().cloned()?;
+            if self.ga.target_reached() {
+                self.generation = self.generations + 1;
+            }
+            if best.fitness < self.best_fitness {
+                self.best_fitness = best.fitness;
+                return Some(self.ga.to_layout(&best));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::rect;
+
+    #[test]
+    fn solutions_improve_and_stop() {
+        let parts = (0..4).map(|i| Part::new(vec![rect(3.0 + i as f64, 2.0)])).collect();
+        let config = NestingConfig::builder().population_size(4).build().unwrap();
+        let nester = Nester::new(parts, rect(10.0, 10.0), config).generations(5);
+
+        let first = nester.solutions().unwrap().next().unwrap();
+        assert_eq!(first.placements.len(), 4);
+        let all: Vec<Layout> = nester.solutions().unwrap().collect();
+        assert!(!all.is_empty() && all.len() <= 6);
+    }
+
+    #[test]
+    fn sessions_carry_the_population_over_changes() {
+        let parts = (0..3)
+            .map(|i| {
+                let mut part = Part::new(vec![rect(3.0 + i as f64, 2.0)]);
+                part.id.part.index = i;
+                part
+            })
+            .collect();
+        let config = NestingConfig::builder().population_size(4).seed(Some(1)).build().unwrap();
+        let mut nester = Nester::new(parts, rect(10.0, 10.0), config).generations(2);
+        assert_eq!(nester.run().unwrap().unwrap().placements.len(), 3);
+        assert_eq!(nester.population.borrow().len(), 4);
+
+        // dragging a part out keeps the order the others were found in
+        let order = |nester: &Nester| nester.population.borrow()[0].placement.clone();
+        let before = order(&nester);
+        assert_eq!(nester.remove_parts(|p| p.id.part.index == 1), 1);
+        let kept: Vec<usize> = before
+            .iter()
+            .filter(|&&i| i != 1)
+            .map(|&i| if i > 1 { i - 1 } else { i })
+            .collect();
+        assert_eq!(order(&nester), kept);
+        assert_eq!(nester.run().unwrap().unwrap().placements.len(), 2);
+
+        let id = nester.parts()[0].id.part.clone();
+        nester.set_quantity(&id, 3).unwrap();
+        let copies: Vec<usize> = nester.parts().iter().filter(|p| p.id.part == id).map(|p| p.id.copy).collect();
+        assert_eq!(copies, [0, 1, 2]);
+        assert_eq!(nester.run().unwrap().unwrap().placements.len(), 4);
+        nester.set_quantity(&id, 1).unwrap();
+        assert_eq!(nester.parts().len(), 2);
+
+        let missing = PartId {
+            index: 7,
+            ..Default::default()
+        };
+        assert!(nester.set_quantity(&missing, 1).is_err());
+    }
+
+    #[test]
+    fn steps_share_the_work_of_a_run() {
+        let parts = (0..3).map(|i| Part::new(vec![rect(3.0 + i as f64, 2.0)])).collect();
+        let config = NestingConfig::builder().population_size(4).seed(Some(1)).build().unwrap();
+        let mut nester = Nester::new(parts, rect(10.0, 10.0), config).generations(3);
+
+        // a zero budget still evaluates one population
+        let step = nester.step(Duration::ZERO).unwrap();
+        assert_eq!(step.generation, 1);
+        assert_eq!(step.stats.len(), 1);
+        assert_eq!(step.improved.unwrap().placements.len(), 3);
+        assert!(!step.done);
+
+        let step = nester.step(Duration::from_secs(60)).unwrap();
+        assert!(step.done);
+        assert_eq!(step.generation, 4);
+        assert_eq!(step.stats.iter().map(|s| s.generation).collect::<Vec<_>>(), [1, 2, 3]);
+        assert!(nester.step(Duration::ZERO).unwrap().stats.is_empty());
+
+        nester.add_parts(vec![Part::new(vec![rect(1.0, 1.0)])]);
+        let step = nester.step(Duration::ZERO).unwrap();
+        assert_eq!(step.generation, 1);
+        assert_eq!(step.improved.unwrap().placements.len(), 4);
+    }
+}
@@ -1,13 +1,273 @@
 use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
 
 use crate::svg_parser::Point;
-use crate::geometry::{minkowski_difference_clip, offset_polygon, get_polygon_bounds, CLIPPER_SCALE};
-use geo::{LineString, Polygon as GeoPolygon, Translate};
-use geo_clipper::Clipper;
+use crate::geometry::ops::Shape;
+use crate::geometry::{
+    backend, get_polygon_bounds, minkowski_difference_clip, minkowski_difference_shapes, offset_polygon, ops,
+    point_inside_by, point_outside_by, rectangle_bounds, to_geo_multipolygon,
+};
+use crate::profile::{self, Stage};
+use geo::Translate;
 
+/// A part as it enters an NFP: which one, turned by `angle` degrees and
+/// possibly mirrored.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Orientation {
+    pub part: usize,
+    pub angle: f64,
+    /// Mirrored before it is turned
+    pub flipped: bool,
+}
+
+/// No-fit polygon of one ring around another: the translations at which the
+/// second ring overlaps the first. Concave pairs can give several loops, and
+/// holes are pockets in which the second ring fits into a bay of the first.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Nfp {
+    pub shapes: Vec<Shape>,
+    /// Grid the NFP was approximated on instead, for huge rings
+    pub raster: Option<RasterNfp>,
+}
+
+impl Nfp {
+    pub fn from_shapes(shapes: Vec<Shape>) -> Self {
+        Self { shapes, raster: None }
+    }
+
+    /// NFP made of the single loop `ring`.
+    pub fn from_ring(ring: Vec<Point>) -> Self {
+        Self::from_shapes(vec![Shape::new(ring)])
+    }
+
+    pub fn from_raster(raster: RasterNfp) -> Self {
+        Self {
+            shapes: Vec::new(),
+            raster: Some(raster),
+        }
+    }
+
+    /// Whether the NFP is exact rather than rasterized, so translations it
+    /// does not contain need no further check.
+    pub fn is_exact(&self) -> bool {
+        self.raster.is_none()
+    }
+
+    /// Bytes taken by the vertices or cells of the NFP.
+    pub fn bytes(&self) -> usize {
+        let points: usize = self
+            .shapes
+            .iter()
+            .map(|s| s.outline.len() + s.holes.iter().map(Vec::len).sum::<usize>())
+            .sum();
+        let cells = self.raster.as_ref().map_or(0, |r| r.bits.len() * std::mem::size_of::<u64>());
+        points * std::mem::size_of::<Point>() + cells
+    }
+
+    /// Whether translation (`x`, `y`) is inside a loop and outside its
+    /// holes, by more than `margin` from every edge. Translations closer to
+    /// an edge than that have the rings touching.
+    pub fn contains_by(&self, x: f64, y: f64, margin: f64) -> bool {
+        if let Some(raster) = &self.raster {
+            return raster.contains_by(x, y, margin);
+        }
+        self.shapes.iter().any(|s| {
+            s.outline.len() >= 3
+                && point_inside_by(&s.outline, x, y, margin)
+                && s.holes.iter().all(|h| point_outside_by(h, x, y, margin))
+        })
+    }
+}
+
+/// Pairs of rings spanning fewer Minkowski quads than this get an exact NFP
+/// even in raster mode, as it is cheap enough.
+pub const RASTER_MIN_QUADS: usize = 100_000;
+
+/// NFP approximated on a grid, for rings with thousands of vertices whose
+/// exact NFP takes far too long. Both rings are sampled at the cell size and
+/// a cell is covered when they overlap at the translation at its center, so
+/// the grid may be off by a cell along the boundary and placements found
+/// with it need an exact check.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RasterNfp {
+    /// Corner of cell (0, 0), in the frame of the exact NFP
+    origin: Point,
+    cell: f64,
+    nx: usize,
+    ny: usize,
+    /// One bit per cell, rows of `nx.div_ceil(64)` words
+    bits: Vec<u64>,
+}
+
+impl RasterNfp {
+    /// NFP of `b` around `a` on a grid with `resolution` cells along its
+    /// longer side, positioned like [`minkowski_difference_clip`]. `None` if
+    /// either ring has no extent.
+    pub fn new(a: &[Point], b: &[Point], resolution: u32) -> Option<Self> {
+        let ab = get_polygon_bounds(a)?;
+        let bb = get_polygon_bounds(b)?;
+        let b0 = b.first()?;
+        let (width, height) = (ab.width + bb.width, ab.height + bb.height);
+        let cell = width.max(height) / resolution.max(1) as f64;
+        if !(cell > 0.0 && cell.is_finite()) {
+            return None;
+        }
+        let nx = (width / cell).ceil() as usize + 1;
+        let ny = (height / cell).ceil() as usize + 1;
+        // translations are sampled at (ox, oy) + (i + 0.5) * cell and `b` at
+        // its corner + (u + 0.5) * cell, so their sums fall on the samples
+        // of `a` at (ox, oy) + b's corner + (i + u + 1) * cell
+        let (ox, oy) = (ab.x - (bb.x + bb.width), ab.y - (bb.y + bb.height));
+        let (bu, bv) = ((bb.width / cell).ceil() as usize, (bb.height / cell).ceil() as usize);
+        let b_rows = sample_rows(b, bb.x + 0.5 * cell, bb.y + 0.5 * cell, cell, bu, bv);
+        let a_rows = sample_rows(a, ox + bb.x, oy + bb.y, cell, nx + bu + 1, ny + bv + 1);
+        let words = nx.div_ceil(64);
+        let mut bits = vec![0u64; words * ny];
+        for (v, b_row) in b_rows.iter().enumerate() {
+            for u in (0..bu).filter(|&u| (b_row[u / 64] >> (u % 64)) & 1 == 1) {
+                for j in 0..ny {
+                    let a_row = &a_rows[j + v + 1];
+                    for w in 0..words {
+                        bits[j * words + w] |= shifted_word(a_row, u + 1 + 64 * w);
+                    }
+                }
+            }
+        }
+        Some(Self {
+            origin: Point {
+                x: ox + b0.x,
+                y: oy + b0.y,
+            },
+            cell,
+            nx,
+            ny,
+            bits,
+        })
+    }
+
+    /// NFP on a grid of `nx` x `ny` cells of size `cell` whose cell (0, 0)
+    /// has its corner at `origin`, covering the cells `covered` is true for,
+    /// given row by row. For grids filled elsewhere, e.g. on the GPU.
+    pub fn from_cells(origin: Point, cell: f64, nx: usize, ny: usize, covered: &[bool]) -> Self {
+        let words = nx.div_ceil(64);
+        let mut bits = vec![0u64; words * ny];
+        for k in (0..nx * ny).filter(|&k| covered.get(k).copied().unwrap_or(false)) {
+            let (i, j) = (k % nx, k / nx);
+            bits[j * words + i / 64] |= 1 << (i % 64);
+        }
+        Self {
+            origin,
+            cell,
+            nx,
+            ny,
+            bits,
+        }
+    }
+
+    fn covered(&self, i: i64, j: i64) -> bool {
+        if i < 0 || j < 0 || i as usize >= self.nx || j as usize >= self.ny {
+            return false;
+        }
+        let (i, j) = (i as usize, j as usize);
+        (self.bits[j * self.nx.div_ceil(64) + i / 64] >> (i % 64)) & 1 == 1
+    }
+
+    /// Whether translation (`x`, `y`) overlaps for sure: the cells around it
+    /// are covered for more than `margin` and a cell to every side.
+    pub fn contains_by(&self, x: f64, y: f64, margin: f64) -> bool {
+        let i = ((x - self.origin.x) / self.cell - 0.5).round() as i64;
+        let j = ((y - self.origin.y) / self.cell - 0.5).round() as i64;
+        let r = (margin / self.cell).ceil() as i64 + 1;
+        (j - r..=j + r).all(|j| (i - r..=i + r).all(|i| self.covered(i, j)))
+    }
+}
+
+/// Which of the points (`x0` + i * `cell`, `y0` + j * `cell`) with i < `nx`
+/// and j < `ny` lie inside `ring`, by even-odd scanlines, as rows of bits.
+fn sample_rows(ring: &[Point], x0: f64, y0: f64, cell: f64, nx: usize, ny: usize) -> Vec<Vec<u64>> {
+    let words = nx.div_ceil(64);
+    let mut crossings = Vec::new();
+    (0..ny)
+        .map(|j| {
+            let y = y0 + j as f64 * cell;
+            crossings.clear();
+            let mut k = ring.len().wrapping_sub(1);
+            for (i, p) in ring.iter().enumerate() {
+                let q = ring[k];
+                k = i;
+                if (p.y > y) != (q.y > y) {
+                    crossings.push(p.x + (y - p.y) / (q.y - p.y) * (q.x - p.x));
+                }
+            }
+            crossings.sort_by(f64::total_cmp);
+            let mut row = vec![0u64; words];
+            for span in crossings.chunks_exact(2) {
+                let from = ((span[0] - x0) / cell).ceil().max(0.0) as usize;
+                let to = (((span[1] - x0) / cell).ceil().max(0.0) as usize).min(nx);
+                for i in from..to {
+                    row[i / 64] |= 1 << (i % 64);
+                }
+            }
+            row
+        })
+        .collect()
+}
+
+/// The 64 bits of `row` from bit `start` on, zero past its end.
+fn shifted_word(row: &[u64], start: usize) -> u64 {
+    let (i, s) = (start / 64, start % 64);
+    let lo = row.get(i).copied().unwrap_or(0) >> s;
+    if s == 0 {
+        return lo;
+    }
+    lo | (row.get(i + 1).copied().unwrap_or(0) << (64 - s))
+}
+
+/// What a cached NFP was generated for. An NFP is only reused for an equal
+/// key, so NFPs of mirrored or grown rings never stand in for each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct NfpKey {
+    a: usize,
+    b: usize,
+    /// Angles in units of the angle precision
+    a_angle: i64,
+    b_angle: i64,
+    a_flipped: bool,
+    b_flipped: bool,
+    /// Bits of the distance the rings were grown by
+    inflation: u64,
+}
+
+/// Number of independently locked parts of the cache
+const SHARDS: usize = 16;
+
+/// Cached NFP, filled in by whichever thread asks for it first
+#[derive(Default)]
+struct Slot {
+    /// Set by the thread that generates the NFP
+    claimed: AtomicBool,
+    nfp: OnceLock<Arc<Nfp>>,
+}
+
+/// NFPs generated so far, shared between threads. The cache is split into
+/// shards with a lock each, held only to find or add the slot of a key, so
+/// threads generating different NFPs do not wait for each other. Threads
+/// asking for an NFP that is being generated do not generate it again but
+/// run other queued rayon tasks, such as the layouts of other individuals,
+/// until it is ready, and only wait once there are none.
 pub struct NfpCache {
-    cache: HashMap<(usize, usize, i64, i64), Vec<Point>>, // key with quantized angles
+    shards: Vec<Mutex<HashMap<NfpKey, Arc<Slot>>>>,
+    hasher: RandomState,
     pub angle_precision: f64,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+    /// Cells along the longer side of rasterized NFPs, `None` for exact ones
+    raster: Option<u32>,
+    #[cfg(feature = "gpu")]
+    gpu: Option<crate::gpu::GpuNfp>,
 }
 
 impl NfpCache {
@@ -15,33 +275,119 @@ impl NfpCache {
 
     pub fn new(angle_precision: f64) -> Self {
         Self {
-            cache: HashMap::new(),
+            shards: (0..SHARDS).map(|_| Mutex::new(HashMap::new())).collect(),
+            hasher: RandomState::new(),
             angle_precision,
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+            raster: None,
+            #[cfg(feature = "gpu")]
+            gpu: None,
         }
     }
 
+    /// Rasterize the NFPs of pairs spanning at least [`RASTER_MIN_QUADS`]
+    /// quads on a grid with `resolution` cells along the longer side.
+    pub fn with_raster(mut self, resolution: u32) -> Self {
+        self.raster = Some(resolution);
+        self
+    }
+
+    /// Generate NFPs on the GPU, falling back to the CPU for pairs the GPU
+    /// fails on.
+    #[cfg(feature = "gpu")]
+    pub fn with_gpu(mut self, gpu: crate::gpu::GpuNfp) -> Self {
+        self.gpu = Some(gpu);
+        self
+    }
+
+    /// Returns true if NFPs are generated on the GPU.
+    pub fn uses_gpu(&self) -> bool {
+        #[cfg(feature = "gpu")]
+        return self.gpu.is_some();
+        #[cfg(not(feature = "gpu"))]
+        false
+    }
+
+    /// Lookups answered from the cache and lookups that generated an NFP
+    /// so far.
+    pub fn lookups(&self) -> (usize, usize) {
+        (self.hits.load(Ordering::Relaxed), self.misses.load(Ordering::Relaxed))
+    }
+
+    /// Bytes taken by the NFPs generated so far, see [`Nfp::bytes`].
+    pub fn bytes(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| {
+                let slots = shard.lock().unwrap_or_else(|e| e.into_inner());
+                slots.values().filter_map(|slot| slot.nfp.get()).map(|nfp| nfp.bytes()).sum::<usize>()
+            })
+            .sum()
+    }
+
+    /// NFP of ring `b_ring` of `b` around ring `a_ring` of `a`, generated
+    /// on the first request. `inflation` is the distance both rings were
+    /// grown by, e.g. for spacing or kerf, 0 for the plain contours.
     pub fn get_or_generate(
-        &mut self,
-        a_id: usize,
-        b_id: usize,
-        a_angle: f64,
-        b_angle: f64,
-        a: &[Point],
-        b: &[Point],
-    ) -> Vec<Point> {
+        &self,
+        a: Orientation,
+        b: Orientation,
+        inflation: f64,
+        a_ring: &[Point],
+        b_ring: &[Point],
+    ) -> Arc<Nfp> {
         let factor = 1.0 / self.angle_precision;
-        let key = (
-            a_id,
-            b_id,
-            (a_angle * factor).round() as i64,
-            (b_angle * factor).round() as i64,
-        );
-        if let Some(v) = self.cache.get(&key) {
-            return v.clone();
+        let key = NfpKey {
+            a: a.part,
+            b: b.part,
+            a_angle: (a.angle * factor).round() as i64,
+            b_angle: (b.angle * factor).round() as i64,
+            a_flipped: a.flipped,
+            b_flipped: b.flipped,
+            // adding 0 turns -0 into 0
+            inflation: (inflation + 0.0).to_bits(),
+        };
+        let shard = &self.shards[self.hasher.hash_one(key) as usize % SHARDS];
+        let slot = shard
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(key)
+            .or_default()
+            .clone();
+        if slot.nfp.get().is_none() && slot.claimed.swap(true, Ordering::AcqRel) {
+            while slot.nfp.get().is_none() && matches!(rayon::yield_now(), Some(rayon::Yield::Executed)) {}
+        }
+        let mut generated = false;
+        let nfp = slot.nfp.get_or_init(|| {
+            generated = true;
+            Arc::new(self.generate(a_ring, b_ring))
+        });
+        let counter = if generated { &self.misses } else { &self.hits };
+        counter.fetch_add(1, Ordering::Relaxed);
+        nfp.clone()
+    }
+
+    fn generate(&self, a: &[Point], b: &[Point]) -> Nfp {
+        let _span = profile::span(Stage::Nfp);
+        // rings enclosing nothing never overlap anything
+        if a.len() < 3 || b.len() < 3 {
+            return Nfp::default();
+        }
+        if let Some(nfp) = no_fit_polygon_rectangles(a, b) {
+            return Nfp::from_ring(nfp);
+        }
+        let resolution = self.raster.filter(|_| a.len() * b.len() >= RASTER_MIN_QUADS);
+        if let Some(raster) = resolution.and_then(|r| RasterNfp::new(a, b, r)) {
+            return Nfp::from_raster(raster);
+        }
+        // the GPU grid is only accurate to a cell, so positions near its
+        // boundary are left to the exact test like those of a raster NFP
+        #[cfg(feature = "gpu")]
+        if let Some(raster) = self.gpu.as_ref().and_then(|g| g.raster_nfp(a, b)) {
+            return Nfp::from_raster(raster);
         }
-        let nfp = minkowski_difference_clip(a, b);
-        self.cache.insert(key, nfp.clone());
-        nfp
+        Nfp::from_shapes(minkowski_difference_shapes(a, b))
     }
 }
 
@@ -59,11 +405,33 @@ pub fn no_fit_polygon(a: &[Point], b: &[Point]) -> Vec<Point> {
 /// Generate inner fit polygons by offsetting the container and computing the
 /// outer no-fit polygon for each offset polygon.
 pub fn inner_fit_polygon(container: &[Point], part: &[Point], spacing: f64) -> Vec<Vec<Point>> {
+    // the shrunk container may have split into several regions
     let offsets = offset_polygon(container, -spacing.abs());
-    offsets
-        .into_iter()
-        .flat_map(|poly| minkowski_diff_erosion(&poly, part))
-        .collect()
+    minkowski_diff_erosion(&offsets, part)
+}
+
+/// Relative tolerance up to which rings count as axis-aligned rectangles,
+/// enough to absorb the rounding of rotations by multiples of 90°
+const RECTANGLE_TOLERANCE: f64 = 1e-9;
+
+/// Outer NFP of `b` around `a` when both are axis-aligned rectangles,
+/// computed from their bounds instead of a Minkowski sum. Like
+/// [`minkowski_difference_clip`] the result is relative to the first vertex
+/// of `b`. Returns `None` if either ring is not a rectangle.
+pub fn no_fit_polygon_rectangles(a: &[Point], b: &[Point]) -> Option<Vec<Point>> {
+    let ab = rectangle_bounds(a, RECTANGLE_TOLERANCE)?;
+    let bb = rectangle_bounds(b, RECTANGLE_TOLERANCE)?;
+    let b0 = b.first()?;
+    let x1 = ab.x - (bb.x + bb.width) + b0.x;
+    let y1 = ab.y - (bb.y + bb.height) + b0.y;
+    let x2 = ab.x + ab.width - bb.x + b0.x;
+    let y2 = ab.y + ab.height - bb.y + b0.y;
+    Some(vec![
+        Point { x: x1, y: y1 },
+        Point { x: x2, y: y1 },
+        Point { x: x2, y: y2 },
+        Point { x: x1, y: y2 },
+    ])
 }
 
 /// Interior NFP when the container is an axis-aligned rectangle.
@@ -71,15 +439,16 @@ pub fn inner_fit_polygon(container: &[Point], part: &[Point], spacing: f64) -> V
 pub fn no_fit_polygon_rectangle(container: &[Point], part: &[Point]) -> Option<Vec<Vec<Point>>> {
     let ab = get_polygon_bounds(container)?;
     let bb = get_polygon_bounds(part)?;
+    let p0 = part.first()?;
 
     if bb.width > ab.width || bb.height > ab.height {
         return None;
     }
 
-    let dx1 = ab.x - bb.x + part[0].x;
-    let dy1 = ab.y - bb.y + part[0].y;
-    let dx2 = ab.x + ab.width - (bb.x + bb.width) + part[0].x;
-    let dy2 = ab.y + ab.height - (bb.y + bb.height) + part[0].y;
+    let dx1 = ab.x - bb.x + p0.x;
+    let dy1 = ab.y - bb.y + p0.y;
+    let dx2 = ab.x + ab.width - (bb.x + bb.width) + p0.x;
+    let dy2 = ab.y + ab.height - (bb.y + bb.height) + p0.y;
 
     Some(vec![vec![
         Point { x: dx1, y: dy1 },
@@ -89,35 +458,19 @@ pub fn no_fit_polygon_rectangle(container: &[Point], part: &[Point]) -> Option<V
     ]])
 }
 
-fn to_geo_polygon(points: &[Point]) -> GeoPolygon<f64> {
-    let ls: LineString<f64> = points.iter().map(|p| (p.x, p.y)).collect::<Vec<_>>().into();
-    GeoPolygon::new(ls, vec![])
-}
-
-fn minkowski_diff_erosion(container: &[Point], part: &[Point]) -> Vec<Vec<Point>> {
-    if container.is_empty() || part.is_empty() {
+/// Translations of `part` that keep it inside the multi-contour `container`,
+/// relative to the origin of `part`. Holes of the container stay holes.
+fn minkowski_diff_erosion(container: &[Vec<Point>], part: &[Point]) -> Vec<Vec<Point>> {
+    let (Some((first, rest)), false) = (part.split_first(), container.is_empty()) else {
         return Vec::new();
-    }
-    let container_geo = to_geo_polygon(container);
-    let mut acc: Option<geo_types::MultiPolygon<f64>> = None;
-    for v in part {
+    };
+    let container_geo = to_geo_multipolygon(container);
+    let mut acc = container_geo.translate(-first.x, -first.y);
+    for v in rest {
         let shifted = container_geo.translate(-v.x, -v.y);
-        let mp = geo_types::MultiPolygon(vec![shifted]);
-        acc = Some(match acc {
-            Some(a) => Clipper::intersection(&a, &mp, CLIPPER_SCALE),
-            None => mp,
-        });
+        acc = backend::intersection(&acc, &shifted);
     }
-    let mp = acc.unwrap();
-    mp.0
-        .into_iter()
-        .map(|p| {
-            p.exterior()
-                .points()
-                .map(|c| Point { x: c.x(), y: c.y() })
-                .collect()
-        })
-        .collect()
+    multipolygon_to_polygons(acc)
 }
 
 /// General no-fit polygon. When `inside` is `true` this computes the interior
@@ -136,44 +489,51 @@ pub fn no_fit_polygon_general(
     }
 }
 
+/// Rings of `mp`, every outline followed by its holes.
 fn multipolygon_to_polygons(mp: geo_types::MultiPolygon<f64>) -> Vec<Vec<Point>> {
-    mp.0
-        .into_iter()
-        .map(|p| {
-            p.exterior()
-                .points()
-                .map(|c| Point { x: c.x(), y: c.y() })
-                .collect()
-        })
-        .collect()
+    ops::rings_of(ops::from_geo(mp))
 }
 
+/// Union of the shapes made of `polys`. The rings are grouped into outlines
+/// and holes first, so rings inside other rings cut holes instead of being
+/// covered by them.
 fn polygons_to_multipolygon(polys: &[Vec<Point>]) -> geo_types::MultiPolygon<f64> {
+    let rings: Vec<Vec<Point>> = polys.iter().filter(|p| p.len() >= 3).cloned().collect();
     let mut mp = geo_types::MultiPolygon(vec![]);
-    for poly in polys {
-        if poly.len() < 3 {
-            continue;
-        }
-        let g = to_geo_polygon(poly);
-        mp = if mp.0.is_empty() {
-            geo_types::MultiPolygon(vec![g])
-        } else {
-            Clipper::union(&mp, &g, CLIPPER_SCALE)
-        };
+    for shape in ops::shapes_from_rings(&rings) {
+        let g = ops::to_geo(std::slice::from_ref(&shape));
+        mp = if mp.0.is_empty() { g } else { backend::union(&mp, &g) };
     }
     mp
 }
 
-/// Union a list of polygons into a single MultiPolygon using geo_clipper.
+/// Union a list of polygons into a single MultiPolygon. Every outline of the
+/// result is followed by its holes.
 pub fn union_polygons(polys: &[Vec<Point>]) -> Vec<Vec<Point>> {
     let mp = polygons_to_multipolygon(polys);
     multipolygon_to_polygons(mp)
 }
 
-/// Difference of subject minus clip polygons using geo_clipper.
+/// Difference of subject minus clip polygons. Every outline of the result is
+/// followed by its holes.
 pub fn difference_polygons(subject: &[Vec<Point>], clip: &[Vec<Point>]) -> Vec<Vec<Point>> {
     let subj_mp = polygons_to_multipolygon(subject);
     let clip_mp = polygons_to_multipolygon(clip);
-    let diff = Clipper::difference(&subj_mp, &clip_mp, CLIPPER_SCALE);
+    let diff = backend::difference(&subj_mp, &clip_mp);
     multipolygon_to_polygons(diff)
 }
+
+/// Difference of subject minus clip polygons that keeps interior rings.
+/// Each entry holds the outer ring of a region followed by its holes.
+pub fn difference_polygons_with_holes(
+    subject: &[Vec<Point>],
+    clip: &[Vec<Point>],
+) -> Vec<Vec<Vec<Point>>> {
+    let subj_mp = polygons_to_multipolygon(subject);
+    let clip_mp = polygons_to_multipolygon(clip);
+    let diff = backend::difference(&subj_mp, &clip_mp);
+    ops::from_geo(diff)
+        .into_iter()
+        .map(|shape| std::iter::once(shape.outline).chain(shape.holes).collect())
+        .collect()
+}
@@ -0,0 +1,251 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::SheetMarks;
+use crate::geometry::Bounds;
+
+/// Temporary sibling of `path` used while the new contents are written.
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.tmp", name))
+}
+
+/// Write `data` to the temporary sibling of `path` and return that sibling.
+fn write_temp(path: &Path, data: &[u8]) -> std::io::Result<PathBuf> {
+    let tmp = temp_path(path);
+    let result = (|| {
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(data)?;
+        file.sync_all()
+    })();
+    match result {
+        Ok(()) => Ok(tmp),
+        Err(e) => {
+            let _ = fs::remove_file(&tmp);
+            Err(e)
+        }
+    }
+}
+
+/// Write `data` to `path` atomically: the contents go to a temporary file in
+/// the same directory which is then renamed over the destination, so readers
+/// never observe a partially written file.
+pub fn write_atomic(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let tmp = write_temp(path, data)?;
+    fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
+
+/// Output files of a run, written together once all of them are known.
+/// Every file goes to a temporary sibling first and only when all of them
+/// are written are they renamed over their destinations, so a run failing
+/// halfway leaves the results of the previous run as they were.
+#[derive(Debug, Default)]
+pub struct OutputSet {
+    files: Vec<(PathBuf, Vec<u8>)>,
+}
+
+impl OutputSet {
+    /// Queue `data` to be written to `path`.
+    pub fn add(&mut self, path: impl Into<PathBuf>, data: impl Into<Vec<u8>>) {
+        self.files.push((path.into(), data.into()));
+    }
+
+    /// Write all queued files.
+    pub fn commit(self) -> anyhow::Result<()> {
+        let mut written: Vec<(PathBuf, &Path)> = Vec::with_capacity(self.files.len());
+        for (path, data) in &self.files {
+            match write_temp(path, data) {
+                Ok(tmp) => written.push((tmp, path)),
+                Err(e) => {
+                    for (tmp, _) in &written {
+                        let _ = fs::remove_file(tmp);
+                    }
+                    return Err(anyhow::anyhow!("failed to write {}: {}", path.display(), e));
+                }
+            }
+        }
+        for (i, (tmp, path)) in written.iter().enumerate() {
+            if let Err(e) = fs::rename(tmp, path) {
+                for (tmp, _) in &written[i..] {
+                    let _ = fs::remove_file(tmp);
+                }
+                return Err(anyhow::anyhow!("failed to write {}: {}", path.display(), e));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Escape text for use inside an XML attribute value.
+pub fn escape_xml(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Render metadata as a string of XML attributes, each preceded by a space.
+pub fn metadata_attributes(metadata: &std::collections::BTreeMap<String, String>) -> String {
+    metadata
+        .iter()
+        .map(|(k, v)| format!(" {}=\"{}\"", k, escape_xml(v)))
+        .collect()
+}
+
+/// Current UTC date as `YYYY-MM-DD`.
+pub fn today() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    civil_date(secs / 86400)
+}
+
+/// Date `days` days after 1970-01-01 in the proleptic Gregorian calendar.
+fn civil_date(days: u64) -> String {
+    // shift the epoch to 0000-03-01 so leap days end the 400 year eras
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let doe = days % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = era * 400 + yoe + u64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Layer with the `marks` of `sheets`, stacked vertically in layout
+/// coordinates, dated `date`. Empty if no mark is enabled.
+pub fn sheet_marks_layer(marks: &SheetMarks, sheets: &[Bounds], date: &str) -> String {
+    if !marks.any() {
+        return String::new();
+    }
+    let s = marks.size;
+    let mut layer = String::from(
+        "<g id=\"marks\" inkscape:groupmode=\"layer\" inkscape:label=\"Marks\" fill=\"none\" stroke=\"red\">\n",
+    );
+    for (sheet, b) in sheets.iter().enumerate() {
+        let (top, width, height) = (b.y, b.width, b.height);
+        if marks.registration {
+            for (x, y) in [(s, top + s), (width - s, top + s), (s, top + height - s)] {
+                layer.push_str(&format!(
+                    "<circle cx=\"{}\" cy=\"{}\" r=\"{}\"/><path d=\"M{} {}H{}M{} {}V{}\"/>\n",
+                    x,
+                    y,
+                    s / 2.0,
+                    x - s / 2.0,
+                    y,
+                    x + s / 2.0,
+                    x,
+                    y - s / 2.0,
+                    y + s / 2.0
+                ));
+            }
+        }
+        if marks.origin {
+            layer.push_str(&format!(
+                "<path d=\"M{} {}H{}M0 {}V{}\"/>\n",
+                -s,
+                top,
+                s,
+                top - s,
+                top + s
+            ));
+        }
+        if marks.label {
+            layer.push_str(&format!(
+                "<text x=\"{}\" y=\"{}\" font-size=\"{}\" text-anchor=\"end\" fill=\"red\" stroke=\"none\">Sheet {} of {}, {}</text>\n",
+                width - s / 2.0,
+                top + height - s / 2.0,
+                s,
+                sheet + 1,
+                sheets.len(),
+                escape_xml(date)
+            ));
+        }
+    }
+    layer.push_str("</g>\n");
+    layer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_atomic_replaces_contents() {
+        let dir = std::env::temp_dir().join(format!("svgnest_output_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("out.svg");
+        write_atomic(&path, b"first").unwrap();
+        write_atomic(&path, b"second").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        assert!(!temp_path(&path).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn output_set_keeps_old_files_when_one_fails() {
+        let dir = std::env::temp_dir().join(format!("svgnest_output_set_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let good = dir.join("nested.svg");
+        fs::write(&good, "previous").unwrap();
+        let mut outputs = OutputSet::default();
+        outputs.add(&good, "new");
+        outputs.add(dir.join("missing").join("report.json"), "{}");
+        assert!(outputs.commit().is_err());
+        assert_eq!(fs::read_to_string(&good).unwrap(), "previous");
+        assert!(!temp_path(&good).exists());
+
+        let mut outputs = OutputSet::default();
+        outputs.add(&good, "new");
+        outputs.commit().unwrap();
+        assert_eq!(fs::read_to_string(&good).unwrap(), "new");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn dates_follow_the_calendar() {
+        assert_eq!(civil_date(0), "1970-01-01");
+        assert_eq!(civil_date(11_016), "2000-02-29");
+        assert_eq!(civil_date(20_742), "2026-10-16");
+    }
+
+    #[test]
+    fn marks_every_sheet() {
+        let bin = Bounds {
+            x: 0.0,
+            y: 0.0,
+            width: 100.0,
+            height: 50.0,
+        };
+        let stack = crate::layout::stack_sheets(bin, &[], 2);
+        assert!(sheet_marks_layer(&SheetMarks::default(), &stack, "").is_empty());
+        let marks = SheetMarks {
+            registration: true,
+            label: true,
+            ..Default::default()
+        };
+        let layer = sheet_marks_layer(&marks, &stack, "2026-10-16");
+        assert_eq!(layer.matches("<circle").count(), 6);
+        assert!(layer.contains(">Sheet 2 of 2, 2026-10-16</text>"));
+        assert!(layer.contains("inkscape:groupmode=\"layer\""));
+    }
+}
@@ -0,0 +1,105 @@
+//! Colors telling the parts of a nested SVG apart: by the file they were read
+//! from, the sheet they are on or the part they are a copy of. Colors are
+//! handed out from a fixed palette in the order the keys first appear, so a
+//! job is drawn the same way every time, and a legend layer names them.
+
+use serde::{Deserialize, Serialize};
+
+use crate::output::escape_xml;
+
+/// What the parts of the SVG output are colored by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorBy {
+    /// The input file a part was read from
+    Source,
+    /// The sheet a part is on
+    Sheet,
+    /// The part an instance is a copy of
+    Part,
+}
+
+const PALETTE: [&str; 10] = [
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b", "#e377c2", "#7f7f7f", "#bcbd22", "#17becf",
+];
+
+/// Color number `index`: those of the palette first, then hues spread by the
+/// golden angle.
+pub fn color(index: usize) -> String {
+    match PALETTE.get(index) {
+        Some(c) => c.to_string(),
+        None => format!("hsl({:.0}, 65%, 40%)", (index - PALETTE.len()) as f64 * 137.508 % 360.0),
+    }
+}
+
+/// Colors of keys, in the order they were first asked for.
+#[derive(Debug, Clone, Default)]
+pub struct Palette {
+    keys: Vec<String>,
+}
+
+impl Palette {
+    /// Color of `key`, giving new keys the next color.
+    pub fn color_of(&mut self, key: &str) -> String {
+        let index = match self.keys.iter().position(|k| k == key) {
+            Some(i) => i,
+            None => {
+                self.keys.push(key.to_string());
+                self.keys.len() - 1
+            }
+        };
+        color(index)
+    }
+
+    /// Legend layer with a swatch and the name of every key, in rows `size`
+    /// high below `x`, `y`, with its width and height. Empty if no color was
+    /// handed out.
+    pub fn legend(&self, x: f64, y: f64, size: f64) -> (String, f64, f64) {
+        if self.keys.is_empty() {
+            return (String::new(), 0.0, 0.0);
+        }
+        let mut layer =
+            String::from("<g id=\"legend\" inkscape:groupmode=\"layer\" inkscape:label=\"Legend\" stroke=\"none\">\n");
+        for (i, key) in self.keys.iter().enumerate() {
+            let top = y + 1.5 * size * i as f64;
+            layer.push_str(&format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n<text x=\"{}\" y=\"{}\" font-size=\"{}\" fill=\"black\">{}</text>\n",
+                x,
+                top,
+                size,
+                size,
+                color(i),
+                x + 1.5 * size,
+                top + size,
+                size,
+                escape_xml(key)
+            ));
+        }
+        layer.push_str("</g>\n");
+        // text runs about 0.6 of its size per character
+        let longest = self.keys.iter().map(|k| k.chars().count()).max().unwrap_or(0);
+        let width = 1.5 * size + 0.6 * size * longest as f64;
+        let height = 1.5 * size * self.keys.len() as f64;
+        (layer, width, height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_keep_their_colors() {
+        let mut palette = Palette::default();
+        let a = palette.color_of("a.svg");
+        let b = palette.color_of("b.svg");
+        assert_ne!(a, b);
+        assert_eq!(palette.color_of("a.svg"), a);
+        assert_eq!(a, color(0));
+        assert!(color(25).starts_with("hsl("));
+        let (legend, _, height) = palette.legend(0.0, 0.0, 2.0);
+        assert_eq!(legend.matches("<rect").count(), 2);
+        assert!(legend.contains(">b.svg</text>"));
+        assert_eq!(height, 6.0);
+    }
+}
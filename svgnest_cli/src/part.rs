@@ -1,20 +1,372 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
 use crate::{
     geometry::{
-        normalize_polygons, Bounds, get_polygons_bounds, rotate_polygon,
+        batch::{self, Affine},
+        normalize_polygons, offset_polyline, Bounds, LineCap, LineJoin, convex_hull, get_polygon_bounds,
+        get_polygons_bounds, get_rings_bounds, minimum_rotated_rect, point_in_polygon, polygon_area, polygon_centroid,
+        polygon_perimeter, rotate_polygon, rotate_polygons,
     },
-    svg_parser::Polygon,
+    svg_parser::{Point, Polygon},
+    warning::{Code, Warning},
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(from = "PartData")]
 pub struct Part {
+    /// Contours of the part, grouped by [`Shape`]: every outline is followed
+    /// by the holes directly inside it, the outline with the largest area
+    /// first. The grouping follows from which contour lies inside which, not
+    /// from their winding.
     pub polygons: Vec<Polygon>,
+    /// Identifying attributes of the part. Starts out as the merged metadata
+    /// of all contours, the first contour defining a key wins.
+    pub metadata: BTreeMap<String, String>,
+    /// Which part of the input this is, and which copy of it
+    pub id: InstanceId,
+    /// Geometry that is not cut, like engraving lines or fold marks, in the
+    /// frame of `polygons`. It moves with the part but never collides.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub marking: Vec<Polygon>,
+    /// Degrees the contours were turned by from the input, by
+    /// [`Part::aligned`]. Placement angles turn the part further.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub alignment: f64,
+    /// Derived from `polygons` when the part is built, so build a new part
+    /// rather than changing the contours of this one
+    #[serde(skip)]
+    geometry: Geometry,
+}
+
+/// Where a part comes from in the input. Stays meaningful however parts are
+/// reordered, filtered or copied, unlike their position in a list.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+pub struct PartId {
+    /// File the part was read from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    /// `id` attribute of the element the part was built from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub element: Option<String>,
+    /// Position of the part among those read from the file
+    #[serde(default)]
+    pub index: usize,
+}
+
+/// A single copy of a part.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+pub struct InstanceId {
+    #[serde(flatten)]
+    pub part: PartId,
+    /// Copies of the same part are numbered from 0
+    #[serde(default)]
+    pub copy: usize,
+}
+
+impl fmt::Display for PartId {
+    /// `file#element`, or `file[index]` for elements without an id.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}", file.display())?;
+        }
+        match &self.element {
+            Some(element) => write!(f, "#{}", element),
+            None => write!(f, "[{}]", self.index),
+        }
+    }
+}
+
+impl fmt::Display for InstanceId {
+    /// The part followed by the copy, e.g. `gears.svg#small/2`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.part, self.copy)
+    }
+}
+
+/// Attribute of the source element giving the weight of a part
+pub const WEIGHT_ATTRIBUTE: &str = "data-weight";
+
+/// Weight recorded in the part `metadata`, 0 if it has none.
+pub fn weight(metadata: &BTreeMap<String, String>) -> f64 {
+    metadata
+        .get(WEIGHT_ATTRIBUTE)
+        .and_then(|w| w.trim().parse::<f64>().ok())
+        .filter(|w| w.is_finite() && *w >= 0.0)
+        .unwrap_or(0.0)
+}
+
+/// Attribute of the source element weighting the rotations of a part, as
+/// `angle:weight` pairs such as `0:10 90:1`
+pub const ROTATION_WEIGHTS_ATTRIBUTE: &str = "data-rotation-weights";
+
+/// Rotation weights recorded in the part `metadata`. Pairs that are not two
+/// numbers or have a negative weight are left out.
+pub fn rotation_weights(metadata: &BTreeMap<String, String>) -> Vec<(f64, f64)> {
+    let Some(value) = metadata.get(ROTATION_WEIGHTS_ATTRIBUTE) else {
+        return Vec::new();
+    };
+    value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter_map(|pair| {
+            let (angle, weight) = pair.split_once(':')?;
+            let angle = angle.trim().parse::<f64>().ok()?;
+            let weight = weight.trim().parse::<f64>().ok()?;
+            (angle.is_finite() && weight.is_finite() && weight >= 0.0).then_some((angle, weight))
+        })
+        .collect()
+}
+
+/// Number the copies of every part: instances sharing a [`PartId`] get the
+/// copy indices 0, 1, … in the order of `parts`.
+pub fn number_copies(parts: &mut [Part]) {
+    let mut copies: HashMap<PartId, usize> = HashMap::new();
+    for part in parts {
+        let next = copies.entry(part.id.part.clone()).or_default();
+        part.id.copy = *next;
+        *next += 1;
+    }
+}
+
+/// An outline of a part with the holes directly inside it, as indices into
+/// [`Part::polygons`]. Islands inside a hole are shapes of their own.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Shape {
+    pub outline: usize,
+    pub holes: Vec<usize>,
+}
+
+/// Group `polygons` into shapes by containment: a contour inside an odd
+/// number of others is a hole of the smallest contour containing it. Shapes
+/// come largest first.
+fn shapes_of(polygons: &[Polygon]) -> Vec<Shape> {
+    let areas: Vec<f64> = polygons.iter().map(|p| polygon_area(&p.points).abs()).collect();
+    let containing: Vec<Vec<usize>> = polygons
+        .iter()
+        .enumerate()
+        .map(|(i, poly)| {
+            let Some(&pt) = poly.points.first() else {
+                return Vec::new();
+            };
+            (0..polygons.len())
+                .filter(|&j| j != i && areas[j] > areas[i] && point_in_polygon(&polygons[j].points, pt.x, pt.y))
+                .collect()
+        })
+        .collect();
+    let mut outlines: Vec<usize> = (0..polygons.len()).filter(|&i| containing[i].len().is_multiple_of(2)).collect();
+    outlines.sort_by(|&a, &b| areas[b].total_cmp(&areas[a]));
+    let mut shapes: Vec<Shape> = outlines
+        .iter()
+        .map(|&outline| Shape {
+            outline,
+            holes: Vec::new(),
+        })
+        .collect();
+    for (i, around) in containing.iter().enumerate() {
+        if around.len() % 2 == 0 {
+            continue;
+        }
+        // the innermost contour around a hole is its outline
+        let Some(&outline) = around.iter().min_by(|&&a, &&b| areas[a].total_cmp(&areas[b])) else {
+            continue;
+        };
+        if let Some(shape) = shapes.iter_mut().find(|s| s.outline == outline) {
+            shape.holes.push(i);
+        }
+    }
+    shapes
+}
+
+/// `polygons` in the order of their shapes, with the shapes renumbered to
+/// match.
+fn order_by_shape(mut polygons: Vec<Polygon>) -> (Vec<Polygon>, Vec<Shape>) {
+    let shapes = shapes_of(&polygons);
+    let mut taken: Vec<Option<Polygon>> = polygons.drain(..).map(Some).collect();
+    let mut ordered = Vec::with_capacity(taken.len());
+    let mut renumbered = Vec::with_capacity(shapes.len());
+    for shape in shapes {
+        let outline = ordered.len();
+        ordered.extend(taken[shape.outline].take());
+        let mut holes = Vec::with_capacity(shape.holes.len());
+        for hole in shape.holes {
+            holes.push(ordered.len());
+            ordered.extend(taken[hole].take());
+        }
+        renumbered.push(Shape { outline, holes });
+    }
+    // contours the containment test could not place, e.g. of crossing
+    // rings, go last
+    ordered.extend(taken.into_iter().flatten());
+    (ordered, renumbered)
+}
+
+/// Values of a part that the nester asks for over and over.
+#[derive(Debug, Clone, Default)]
+struct Geometry {
+    shapes: Vec<Shape>,
+    area: f64,
+    perimeter: f64,
+    hull: Vec<Point>,
+    bounds: Option<Bounds>,
+    /// Bounds at the angles given to [`Part::with_rotations`]
+    rotated_bounds: Vec<(f64, Option<Bounds>)>,
+}
+
+impl Geometry {
+    fn of(polygons: &[Polygon], shapes: Vec<Shape>) -> Self {
+        let ring_area = |i: usize| polygon_area(&polygons[i].points).abs();
+        let area = shapes
+            .iter()
+            .map(|s| ring_area(s.outline) - s.holes.iter().map(|&h| ring_area(h)).sum::<f64>())
+            .sum();
+        let perimeter = polygons.iter().map(|p| polygon_perimeter(&p.points, p.closed)).sum();
+        Self {
+            shapes,
+            area,
+            perimeter,
+            hull: convex_hull(polygons.iter().flat_map(|p| p.points.iter().copied())),
+            bounds: get_polygons_bounds(polygons),
+            rotated_bounds: Vec::new(),
+        }
+    }
+}
+
+/// Serialized form of a part, without the derived values.
+#[derive(serde::Deserialize)]
+struct PartData {
+    polygons: Vec<Polygon>,
+    metadata: BTreeMap<String, String>,
+    #[serde(default)]
+    id: InstanceId,
+    #[serde(default)]
+    marking: Vec<Polygon>,
+    #[serde(default)]
+    alignment: f64,
+}
+
+fn is_zero(angle: &f64) -> bool {
+    *angle == 0.0
+}
+
+impl From<PartData> for Part {
+    fn from(data: PartData) -> Self {
+        let mut part = Self::from_normalized(data.polygons, data.metadata).with_id(data.id);
+        part.marking = data.marking;
+        part.alignment = data.alignment;
+        part
+    }
+}
+
+/// Smallest x and y of the points of `polys`, the corner
+/// [`normalize_polygons`] moves to the origin.
+fn min_corner(polys: &[Polygon]) -> Point {
+    let mut corner = Point {
+        x: f64::INFINITY,
+        y: f64::INFINITY,
+    };
+    for p in polys.iter().flat_map(|poly| &poly.points) {
+        corner.x = corner.x.min(p.x);
+        corner.y = corner.y.min(p.y);
+    }
+    corner
+}
+
+fn translate_polygons(polys: &mut [Polygon], dx: f64, dy: f64) {
+    for poly in polys {
+        for p in &mut poly.points {
+            p.x += dx;
+            p.y += dy;
+        }
+        if let Some(contour) = &mut poly.contour {
+            *contour = contour.translate(dx, dy);
+        }
+    }
 }
 
 impl Part {
     pub fn new(polys: Vec<Polygon>) -> Self {
         let mut p = polys;
         normalize_polygons(&mut p);
-        Self { polygons: p }
+        let mut metadata = BTreeMap::new();
+        for poly in &p {
+            for (k, v) in &poly.metadata {
+                metadata.entry(k.clone()).or_insert_with(|| v.clone());
+            }
+        }
+        Self::from_normalized(p, metadata)
+    }
+
+    /// Part cut along `polys` with the non-cut `marking` on it, both in the
+    /// same frame.
+    pub fn new_with_marking(polys: Vec<Polygon>, mut marking: Vec<Polygon>) -> Self {
+        if !polys.is_empty() && !marking.is_empty() {
+            let corner = min_corner(&polys);
+            translate_polygons(&mut marking, -corner.x, -corner.y);
+        }
+        let mut part = Self::new(polys);
+        part.marking = marking;
+        part
+    }
+
+    fn from_normalized(polygons: Vec<Polygon>, metadata: BTreeMap<String, String>) -> Self {
+        let (polygons, shapes) = order_by_shape(polygons);
+        let geometry = Geometry::of(&polygons, shapes);
+        Self {
+            polygons,
+            metadata,
+            id: InstanceId::default(),
+            marking: Vec::new(),
+            alignment: 0.0,
+            geometry,
+        }
+    }
+
+    pub fn with_id(mut self, id: InstanceId) -> Self {
+        self.id = id;
+        self
+    }
+
+    /// Remember the bounds of the part at each of `angles`, typically the
+    /// configured rotations, so [`Part::bounds_rotated`] does not have to
+    /// rotate the part for them.
+    pub fn with_rotations(mut self, angles: &[f64]) -> Self {
+        let mut rings = Vec::new();
+        self.geometry.rotated_bounds = angles
+            .iter()
+            .map(|&angle| {
+                self.rotate_into(angle, &mut rings);
+                (angle, get_rings_bounds(&rings))
+            })
+            .collect();
+        self
+    }
+
+    /// Override metadata entries with attributes describing the part as a
+    /// whole, e.g. those of the `<g>` element it was built from.
+    pub fn with_metadata(mut self, metadata: BTreeMap<String, String>) -> Self {
+        self.metadata.extend(metadata);
+        self
+    }
+
+    /// The part turned so the smallest rectangle around it at any angle is
+    /// axis-aligned, its longer side along x. A few rotations then reach
+    /// the orientations of elongated parts whatever angle they were drawn
+    /// at. The turn is added to [`Part::alignment`].
+    pub fn aligned(self) -> Self {
+        let Some(rect) = minimum_rotated_rect(self.convex_hull()) else {
+            return self;
+        };
+        let angle = if rect.height > rect.width { 90.0 - rect.angle } else { -rect.angle };
+        if angle == 0.0 {
+            return self;
+        }
+        let marking = self.rotated_marking(angle);
+        let mut part = Self::from_normalized(self.rotated(angle), self.metadata).with_id(self.id);
+        part.marking = marking;
+        part.alignment = self.alignment + angle;
+        part
     }
 
     pub fn rotated(&self, angle: f64) -> Vec<Polygon> {
@@ -25,18 +377,561 @@ impl Part {
                 id: p.id,
                 points: rotate_polygon(&p.points, angle),
                 closed: p.closed,
+                metadata: p.metadata.clone(),
+                contour: p.contour.as_ref().map(|c| c.rotate(angle)),
             })
             .collect();
         normalize_polygons(&mut result);
         result
     }
 
+    /// Corner of the part rotated by `angle` about the origin that
+    /// [`Part::rotated`] moves to the origin.
+    pub fn rotated_corner(&self, angle: f64) -> Point {
+        min_corner(&rotate_polygons(&self.polygons, angle))
+    }
+
+    /// Marking of the part rotated by `angle`, lined up with
+    /// [`Part::rotated`].
+    pub fn rotated_marking(&self, angle: f64) -> Vec<Polygon> {
+        if self.marking.is_empty() {
+            return Vec::new();
+        }
+        let corner = self.rotated_corner(angle);
+        let mut marking = rotate_polygons(&self.marking, angle);
+        translate_polygons(&mut marking, -corner.x, -corner.y);
+        marking
+    }
+
+    /// Rotate only the points of the part into `out`, one ring per polygon,
+    /// reusing the buffers already there. The rings match the points of
+    /// [`Part::rotated`] without cloning metadata or contours.
+    pub fn rotate_into(&self, angle: f64, out: &mut Vec<Vec<Point>>) {
+        out.truncate(self.polygons.len());
+        out.resize_with(self.polygons.len(), Vec::new);
+        let m = Affine::rotation(angle);
+        let (mut min_x, mut min_y) = (f64::INFINITY, f64::INFINITY);
+        for (poly, ring) in self.polygons.iter().zip(out.iter_mut()) {
+            ring.clear();
+            batch::transform_into(&poly.points, &m, ring);
+            // same comparisons as `normalize_polygons` so signed zeros match
+            for p in ring.iter() {
+                if p.x < min_x {
+                    min_x = p.x;
+                }
+                if p.y < min_y {
+                    min_y = p.y;
+                }
+            }
+        }
+        if min_x == 0.0 && min_y == 0.0 {
+            return;
+        }
+        for p in out.iter_mut().flatten() {
+            p.x -= min_x;
+            p.y -= min_y;
+        }
+    }
+
+    /// Outlines of the part with their holes.
+    pub fn shapes(&self) -> &[Shape] {
+        &self.geometry.shapes
+    }
+
+    /// The outline with the largest area, first of [`Part::polygons`].
+    pub fn outline(&self) -> Option<&Polygon> {
+        self.geometry.shapes.first().map(|s| &self.polygons[s.outline])
+    }
+
+    /// Whether contour `i` of [`Part::polygons`] is a hole.
+    pub fn is_hole(&self, i: usize) -> bool {
+        self.geometry.shapes.iter().any(|s| s.holes.contains(&i))
+    }
+
+    /// The holes of the part.
+    pub fn holes(&self) -> impl Iterator<Item = &Polygon> {
+        self.geometry.shapes.iter().flat_map(|s| &s.holes).map(|&i| &self.polygons[i])
+    }
+
+    /// Net area of the part, the area of the outlines less that of their
+    /// holes.
+    pub fn area(&self) -> f64 {
+        self.geometry.area
+    }
+
+    /// Weight given by the `data-weight` attribute of the source element.
+    pub fn weight(&self) -> f64 {
+        weight(&self.metadata)
+    }
+
+    /// Weights of single rotations given by the `data-rotation-weights`
+    /// attribute of the source element, as angle and weight.
+    pub fn rotation_weights(&self) -> Vec<(f64, f64)> {
+        rotation_weights(&self.metadata)
+    }
+
+    /// Total length of all contours, closing edges included.
+    pub fn perimeter(&self) -> f64 {
+        self.geometry.perimeter
+    }
+
+    /// Centroid of the area of the part, its holes left out. `None` for
+    /// parts enclosing no area.
+    pub fn centroid(&self) -> Option<Point> {
+        let (mut x, mut y, mut area) = (0.0, 0.0, 0.0);
+        for shape in self.shapes() {
+            let rings = std::iter::once((shape.outline, 1.0)).chain(shape.holes.iter().map(|&h| (h, -1.0)));
+            for (i, sign) in rings {
+                let points = &self.polygons[i].points;
+                if let Some(c) = polygon_centroid(points) {
+                    let a = sign * polygon_area(points).abs();
+                    x += a * c.x;
+                    y += a * c.y;
+                    area += a;
+                }
+            }
+        }
+        (area > 0.0).then(|| Point { x: x / area, y: y / area })
+    }
+
+    /// Why the part cannot be nested, if it cannot: it has no outline, its
+    /// outline has fewer than 3 points, or some of its coordinates are not
+    /// finite. Such parts have no NFPs and are left unplaced.
+    pub fn degenerate(&self) -> Option<&'static str> {
+        let Some(outline) = self.outline() else {
+            return Some("it has no outline");
+        };
+        if outline.points.len() < 3 {
+            return Some("its outline has fewer than 3 points");
+        }
+        let finite = |p: &Point| p.x.is_finite() && p.y.is_finite();
+        if !self.polygons.iter().flat_map(|poly| &poly.points).all(finite) {
+            return Some("some of its coordinates are not finite");
+        }
+        None
+    }
+
+    /// Convex hull of the part, wound like an outline.
+    pub fn convex_hull(&self) -> &[Point] {
+        &self.geometry.hull
+    }
+
     pub fn bounds(&self) -> Option<Bounds> {
-        get_polygons_bounds(&self.polygons)
+        self.geometry.bounds
     }
 
+    /// Bounds of the part rotated by `angle`, remembered for the angles
+    /// given to [`Part::with_rotations`] and computed for any other.
     pub fn bounds_rotated(&self, angle: f64) -> Option<Bounds> {
-        let rot = self.rotated(angle);
-        get_polygons_bounds(&rot)
+        if let Some((_, bounds)) = self.geometry.rotated_bounds.iter().find(|(a, _)| *a == angle) {
+            return *bounds;
+        }
+        let mut rings = Vec::new();
+        self.rotate_into(angle, &mut rings);
+        get_rings_bounds(&rings)
+    }
+}
+
+/// Limits below which contours are taken for export artifacts or stray
+/// points rather than geometry. Zero disables a limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FeatureFilter {
+    /// Smallest area of a closed contour
+    pub min_area: f64,
+    /// Smallest width or height of the bounds of a contour
+    pub min_size: f64,
+}
+
+impl FeatureFilter {
+    /// Drop the contours of `parts` below the limits, and parts left without
+    /// contours, adding a warning for each dropped contour.
+    pub fn apply(&self, parts: Vec<Part>, file: Option<&Path>, warnings: &mut Vec<Warning>) -> Vec<Part> {
+        if self.min_area <= 0.0 && self.min_size <= 0.0 {
+            return parts;
+        }
+        parts
+            .into_iter()
+            .filter_map(|part| {
+                let count = part.polygons.len();
+                let mut kept = Vec::with_capacity(count);
+                for (i, poly) in part.polygons.into_iter().enumerate() {
+                    match self.reject(&poly) {
+                        Some(message) => {
+                            let mut warning = Warning::new(Code::FeatureDropped, contour_name(&poly, i), message);
+                            warning.file = file.map(Path::to_path_buf);
+                            warnings.push(warning);
+                        }
+                        None => kept.push(poly),
+                    }
+                }
+                if kept.len() == count {
+                    let mut same = Part::from_normalized(kept, part.metadata).with_id(part.id);
+                    same.marking = part.marking;
+                    return Some(same);
+                }
+                // without the dropped contours the part may start elsewhere
+                (!kept.is_empty()).then(|| {
+                    Part::new_with_marking(kept, part.marking)
+                        .with_metadata(part.metadata)
+                        .with_id(part.id)
+                })
+            })
+            .collect()
+    }
+
+    /// Why `poly` is too small to keep, if it is.
+    fn reject(&self, poly: &Polygon) -> Option<String> {
+        let size = get_polygon_bounds(&poly.points).map_or(0.0, |b| b.width.max(b.height));
+        if size < self.min_size {
+            return Some(format!("dropped, size {} is below the minimum feature size {}", size, self.min_size));
+        }
+        let area = polygon_area(&poly.points).abs();
+        if poly.closed && area < self.min_area {
+            return Some(format!("dropped, area {} is below the minimum area {}", area, self.min_area));
+        }
+        None
+    }
+}
+
+/// Names contour `i` of a part in warnings, by its id if it has one.
+fn contour_name(poly: &Polygon, i: usize) -> String {
+    match poly.metadata.get("id") {
+        Some(id) => format!("contour#{}", id),
+        None => format!("contour {}", i),
+    }
+}
+
+/// What happens to open contours, which have no inside that could be kept
+/// clear of other parts.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum OpenPaths {
+    /// Leave them open
+    #[default]
+    Keep,
+    /// Drop them
+    Ignore,
+    /// Join their ends with a straight segment
+    Close,
+    /// Replace them by a closed outline of the given total width around
+    /// them
+    Buffer(f64),
+}
+
+impl FromStr for OpenPaths {
+    type Err = String;
+
+    /// Parse `keep`, `ignore`, `close` or `buffer:WIDTH`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep" => Ok(Self::Keep),
+            "ignore" => Ok(Self::Ignore),
+            "close" => Ok(Self::Close),
+            _ => {
+                let width = s
+                    .strip_prefix("buffer:")
+                    .ok_or_else(|| format!("expected keep, ignore, close or buffer:WIDTH, got {}", s))?;
+                match width.parse::<f64>() {
+                    Ok(w) if w > 0.0 && w.is_finite() => Ok(Self::Buffer(w)),
+                    _ => Err(format!("buffer width must be a positive number, got {}", width)),
+                }
+            }
+        }
+    }
+}
+
+impl OpenPaths {
+    /// Apply the policy to the open contours of `parts`, dropping parts left
+    /// without contours and adding a note for each contour changed. `tol`
+    /// bounds the error of the round ends of buffered outlines.
+    pub fn apply(self, parts: Vec<Part>, tol: f64, file: Option<&Path>, warnings: &mut Vec<Warning>) -> Vec<Part> {
+        if self == Self::Keep {
+            return parts;
+        }
+        parts
+            .into_iter()
+            .filter_map(|part| {
+                if part.polygons.iter().all(|p| p.closed) {
+                    return Some(part);
+                }
+                let mut polygons = Vec::with_capacity(part.polygons.len());
+                for (i, mut poly) in part.polygons.into_iter().enumerate() {
+                    if poly.closed {
+                        polygons.push(poly);
+                        continue;
+                    }
+                    let note = match self {
+                        Self::Keep => None,
+                        Self::Ignore => Some((Code::OpenContourDropped, "open contour dropped".to_string())),
+                        Self::Close => Some((Code::OpenContourClosed, "open contour closed".to_string())),
+                        Self::Buffer(width) => Some((
+                            Code::OpenContourBuffered,
+                            format!("open contour replaced by an outline {} wide", width),
+                        )),
+                    };
+                    if let Some((code, message)) = note {
+                        let mut warning = Warning::new(code, contour_name(&poly, i), message);
+                        warning.file = file.map(Path::to_path_buf);
+                        warnings.push(warning);
+                    }
+                    match self {
+                        Self::Keep => polygons.push(poly),
+                        Self::Ignore => {}
+                        Self::Close => {
+                            poly.closed = true;
+                            if let Some(contour) = &mut poly.contour {
+                                contour.closed = true;
+                            }
+                            polygons.push(poly);
+                        }
+                        Self::Buffer(width) => {
+                            let rings = offset_polyline(&poly.points, width / 2.0, LineJoin::Round, LineCap::Round, tol);
+                            polygons.extend(rings.into_iter().map(|ring| Polygon {
+                                id: poly.id,
+                                contour: crate::contour::polyline(&ring, true),
+                                points: ring,
+                                closed: true,
+                                metadata: poly.metadata.clone(),
+                            }));
+                        }
+                    }
+                }
+                (!polygons.is_empty()).then(|| {
+                    Part::new_with_marking(polygons, part.marking)
+                        .with_metadata(part.metadata)
+                        .with_id(part.id)
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotate_into_matches_rotated() {
+        let ring = |pts: &[(f64, f64)]| Polygon {
+            points: pts.iter().map(|&(x, y)| Point { x, y }).collect(),
+            closed: true,
+            ..Default::default()
+        };
+        let part = Part::new(vec![
+            ring(&[(0.0, 0.0), (4.0, 0.0), (4.0, 3.0), (0.0, 3.0)]),
+            ring(&[(1.0, 1.0), (1.0, 2.0), (2.0, 2.0)]),
+        ]);
+        let mut out = vec![Vec::new(); 5];
+        for angle in [0.0, 90.0, 123.4] {
+            part.rotate_into(angle, &mut out);
+            let expected: Vec<Vec<Point>> = part.rotated(angle).into_iter().map(|p| p.points).collect();
+            assert_eq!(out, expected);
+        }
+    }
+
+    #[test]
+    fn derived_values_are_cached() {
+        let ring = |pts: &[(f64, f64)]| Polygon {
+            points: pts.iter().map(|&(x, y)| Point { x, y }).collect(),
+            closed: true,
+            ..Default::default()
+        };
+        // an L with a triangular hole
+        let part = Part::new(vec![
+            ring(&[(0.0, 0.0), (4.0, 0.0), (4.0, 1.0), (1.0, 1.0), (1.0, 3.0), (0.0, 3.0)]),
+            ring(&[(0.25, 0.25), (0.25, 0.75), (0.75, 0.25)]),
+        ])
+        .with_rotations(&[0.0, 90.0]);
+        assert_eq!(part.area(), 6.0 - 0.125);
+        assert!((part.perimeter() - (14.0 + 1.0 + 0.5f64.sqrt())).abs() < 1e-9);
+        assert_eq!(part.convex_hull().len(), 5);
+        assert!(polygon_area(part.convex_hull()) < 0.0);
+        // the L without the hole, whose centroid is at (5/12, 5/12)
+        let c = part.centroid().unwrap();
+        let hole = 0.125 * 5.0 / 12.0;
+        assert!((c.x - (9.0 - hole) / 5.875).abs() < 1e-9 && (c.y - (6.0 - hole) / 5.875).abs() < 1e-9);
+        for angle in [0.0, 90.0, 45.0] {
+            let b = part.bounds_rotated(angle).unwrap();
+            let expected = get_polygons_bounds(&part.rotated(angle)).unwrap();
+            assert!((b.width - expected.width).abs() < 1e-9);
+            assert!((b.height - expected.height).abs() < 1e-9);
+        }
+
+        let json = serde_json::to_string(&part).unwrap();
+        let back: Part = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.area(), part.area());
+    }
+
+    #[test]
+    fn holes_are_found_by_containment() {
+        let ring = |x: f64, size: f64| Polygon {
+            // all wound the same way, so winding tells nothing
+            points: vec![
+                Point { x, y: x },
+                Point { x: x + size, y: x },
+                Point { x: x + size, y: x + size },
+                Point { x, y: x + size },
+            ],
+            closed: true,
+            ..Default::default()
+        };
+        // an island in the hole of a frame, listed inside out
+        let part = Part::new(vec![ring(4.0, 2.0), ring(2.0, 6.0), ring(0.0, 10.0)]);
+        assert_eq!(part.outline().map(|p| p.points.len()), Some(4));
+        assert_eq!(part.polygons[0].points[2], Point { x: 10.0, y: 10.0 });
+        assert_eq!(
+            part.shapes(),
+            [
+                Shape {
+                    outline: 0,
+                    holes: vec![1]
+                },
+                Shape {
+                    outline: 2,
+                    holes: Vec::new()
+                }
+            ]
+        );
+        assert!(part.is_hole(1) && !part.is_hole(2));
+        assert_eq!(part.holes().count(), 1);
+        assert_eq!(part.area(), 100.0 - 36.0 + 4.0);
+    }
+
+    #[test]
+    fn marking_turns_with_the_part() {
+        let outline = Polygon {
+            points: vec![
+                Point { x: 10.0, y: 10.0 },
+                Point { x: 15.0, y: 10.0 },
+                Point { x: 15.0, y: 12.0 },
+                Point { x: 10.0, y: 12.0 },
+            ],
+            closed: true,
+            ..Default::default()
+        };
+        let line = Polygon {
+            points: vec![Point { x: 11.0, y: 11.0 }, Point { x: 14.0, y: 11.0 }],
+            ..Default::default()
+        };
+        let part = Part::new_with_marking(vec![outline], vec![line]);
+        assert_eq!(part.marking[0].points[0], Point { x: 1.0, y: 1.0 });
+        // the part is 2 wide after turning, so the line runs down at x = 1
+        let turned = part.rotated_marking(90.0);
+        for (p, (x, y)) in turned[0].points.iter().zip([(1.0, 1.0), (1.0, 4.0)]) {
+            assert!((p.x - x).abs() < 1e-9 && (p.y - y).abs() < 1e-9, "{:?}", p);
+        }
+    }
+
+    #[test]
+    fn aligned_parts_lie_along_x() {
+        // a 6 by 1 bar drawn at 30°
+        let bar: Vec<Point> = [(0.0, 0.0), (6.0, 0.0), (6.0, 1.0), (0.0, 1.0)]
+            .into_iter()
+            .map(|(x, y)| Point { x, y })
+            .collect();
+        let drawn = Polygon {
+            points: rotate_polygon(&bar, 30.0),
+            closed: true,
+            ..Default::default()
+        };
+        let part = Part::new(vec![drawn]).aligned();
+        let b = part.bounds().unwrap();
+        assert!((b.width - 6.0).abs() < 1e-9 && (b.height - 1.0).abs() < 1e-9, "{:?}", b);
+        assert!((part.alignment.rem_euclid(180.0) - 150.0).abs() < 1e-9, "{}", part.alignment);
+        assert!((part.area() - 6.0).abs() < 1e-9);
+        // standing upright, it is laid down
+        let upright = Part::new(vec![Polygon {
+            points: rotate_polygon(&bar, 90.0),
+            closed: true,
+            ..Default::default()
+        }])
+        .aligned();
+        assert!((upright.alignment.rem_euclid(180.0) - 90.0).abs() < 1e-9, "{}", upright.alignment);
+        let b = upright.bounds().unwrap();
+        assert!((b.width - 6.0).abs() < 1e-9 && (b.height - 1.0).abs() < 1e-9, "{:?}", b);
+        let flat = Part::new(vec![Polygon {
+            points: bar,
+            closed: true,
+            ..Default::default()
+        }])
+        .aligned();
+        assert_eq!(flat.alignment, 0.0);
+    }
+
+    #[test]
+    fn copies_are_numbered_per_part() {
+        let id = |element: Option<&str>, index: usize| InstanceId {
+            part: PartId {
+                file: Some(PathBuf::from("parts.svg")),
+                element: element.map(str::to_string),
+                index,
+            },
+            copy: 0,
+        };
+        let mut parts: Vec<Part> = [id(Some("gear"), 0), id(None, 1), id(Some("gear"), 0)]
+            .into_iter()
+            .map(|i| Part::new(Vec::new()).with_id(i))
+            .collect();
+        number_copies(&mut parts);
+        let names: Vec<String> = parts.iter().map(|p| p.id.to_string()).collect();
+        assert_eq!(names, ["parts.svg#gear/0", "parts.svg[1]/0", "parts.svg#gear/1"]);
+    }
+
+    #[test]
+    fn feature_filter_drops_tiny_contours() {
+        let ring = |pts: &[(f64, f64)]| Polygon {
+            points: pts.iter().map(|&(x, y)| Point { x, y }).collect(),
+            closed: true,
+            ..Default::default()
+        };
+        let parts = vec![
+            Part::new(vec![
+                ring(&[(-1.0, -1.0), (-0.99, -1.0), (-0.99, -0.99)]),
+                ring(&[(0.0, 0.0), (4.0, 0.0), (4.0, 3.0), (0.0, 3.0)]),
+            ]),
+            Part::new(vec![ring(&[(0.0, 0.0), (0.005, 0.0), (0.0, 0.005)])]),
+        ];
+        let filter = FeatureFilter {
+            min_area: 0.01,
+            min_size: 0.1,
+        };
+        let mut warnings = Vec::new();
+        let kept = filter.apply(parts.clone(), Some(Path::new("a.svg")), &mut warnings);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].polygons.len(), 1);
+        assert_eq!(kept[0].bounds().map(|b| (b.x, b.y)), Some((0.0, 0.0)));
+        assert_eq!(warnings.len(), 2);
+        // the outlines are sorted largest first, so the tiny one comes second
+        assert!(warnings[0].to_string().starts_with("a.svg: contour 1: dropped, size"));
+
+        let mut warnings = Vec::new();
+        let kept = FeatureFilter::default().apply(parts, None, &mut warnings);
+        assert_eq!(kept.len(), 2);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn open_path_policies() {
+        let line = Polygon {
+            points: vec![Point { x: 0.0, y: 0.0 }, Point { x: 10.0, y: 0.0 }, Point { x: 10.0, y: 5.0 }],
+            closed: false,
+            ..Default::default()
+        };
+        let parts = vec![Part::new(vec![line])];
+        assert_eq!("buffer:2".parse(), Ok(OpenPaths::Buffer(2.0)));
+        assert!("buffer:-1".parse::<OpenPaths>().is_err());
+        assert!("fill".parse::<OpenPaths>().is_err());
+
+        let mut warnings = Vec::new();
+        assert!(OpenPaths::Ignore.apply(parts.clone(), 0.1, None, &mut warnings).is_empty());
+        let closed = OpenPaths::Close.apply(parts.clone(), 0.1, None, &mut warnings);
+        assert!(closed[0].polygons[0].closed);
+        assert_eq!(closed[0].area(), 25.0);
+        let codes: Vec<Code> = warnings.iter().map(|w| w.code).collect();
+        assert_eq!(codes, [Code::OpenContourDropped, Code::OpenContourClosed]);
+        let buffered = OpenPaths::Buffer(2.0).apply(parts, 0.1, None, &mut warnings);
+        let b = buffered[0].bounds().unwrap();
+        assert!(buffered[0].polygons.iter().all(|p| p.closed));
+        // round ends are approximated to within the tolerance
+        assert!((b.width - 12.0).abs() <= 0.1 && (b.height - 7.0).abs() <= 0.1);
     }
 }
@@ -0,0 +1,165 @@
+//! What an optimization run will cost, worked out before starting it: how
+//! many NFPs it may generate, how much memory they take in the cache and a
+//! rough run time. The figures are projected from one layout of the parts,
+//! largest first, timed with an empty and with a full NFP cache, so rotations
+//! and population size can be adjusted before committing to a long run.
+
+use std::fmt;
+use std::time::Instant;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::config::NestingConfig;
+use crate::estimate::largest_first;
+use crate::ga::GeneticAlgorithm;
+use crate::part::Part;
+use crate::svg_parser::Polygon;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Plan {
+    pub parts: usize,
+    pub rotations: usize,
+    /// NFPs the run may need at most, one for every ordered pair of parts
+    /// at every pair of rotations
+    pub nfps: usize,
+    /// NFPs generated for the sampled layout
+    pub sampled_nfps: usize,
+    /// Mean seconds taken to generate one of them
+    pub nfp_seconds: f64,
+    /// Bytes the cache takes once it holds all `nfps`
+    pub cache_bytes: f64,
+    /// Layouts evaluated over all generations and restarts
+    pub evaluations: usize,
+    /// Seconds taken to lay the parts out with every NFP cached
+    pub layout_seconds: f64,
+    /// Threads the evaluations are spread over
+    pub threads: usize,
+    /// Projected seconds of the run if it generates every NFP
+    pub seconds: f64,
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} part(s) at {} rotation(s): up to {} NFPs, {} for the first layout at {} each",
+            self.parts,
+            self.rotations,
+            self.nfps,
+            self.sampled_nfps,
+            duration(self.nfp_seconds)
+        )?;
+        writeln!(f, "NFP cache: up to {}", bytes(self.cache_bytes))?;
+        writeln!(
+            f,
+            "{} layouts at {} each on {} thread(s)",
+            self.evaluations,
+            duration(self.layout_seconds),
+            self.threads
+        )?;
+        write!(f, "Run time: about {}", duration(self.seconds))
+    }
+}
+
+/// `seconds` in the largest unit keeping it above 1.
+fn duration(seconds: f64) -> String {
+    match seconds {
+        s if s >= 3600.0 => format!("{:.1} h", s / 3600.0),
+        s if s >= 60.0 => format!("{:.1} min", s / 60.0),
+        s if s >= 1.0 => format!("{:.1} s", s),
+        s => format!("{:.2} ms", s * 1e3),
+    }
+}
+
+/// `n` bytes in the largest unit keeping it above 1.
+fn bytes(n: f64) -> String {
+    match n {
+        n if n >= 1e9 => format!("{:.1} GB", n / 1e9),
+        n if n >= 1e6 => format!("{:.1} MB", n / 1e6),
+        n if n >= 1e3 => format!("{:.1} kB", n / 1e3),
+        n => format!("{:.0} B", n),
+    }
+}
+
+/// Plan a run nesting `parts` into `bin` with the options of `config` over
+/// `generations` generations, `restarts` times.
+pub fn plan(parts: &[Part], bin: &Polygon, config: &NestingConfig, generations: usize, restarts: usize) -> Result<Plan> {
+    let sample = NestingConfig {
+        population_size: 1,
+        elitism: 1,
+        local_search_iters: 0,
+        ..config.clone()
+    };
+    let mut ga = GeneticAlgorithm::new(parts, bin, sample)?;
+    let ind = largest_first(parts, ga.bin_bounds(), &config.rotations);
+    let start = Instant::now();
+    ga.fitness(&ind);
+    let first = start.elapsed().as_secs_f64();
+    let start = Instant::now();
+    ga.fitness(&ind);
+    let layout_seconds = start.elapsed().as_secs_f64();
+    let cache = ga.into_nfp_cache();
+    let (_, sampled_nfps) = cache.lookups();
+
+    let rotations = config.rotations.len().max(1);
+    let n = parts.len();
+    let nfps = n * n.saturating_sub(1) * rotations * rotations;
+    let (nfp_seconds, nfp_bytes) = if sampled_nfps > 0 {
+        let generating = (first - layout_seconds).max(0.0);
+        (generating / sampled_nfps as f64, cache.bytes() as f64 / sampled_nfps as f64)
+    } else {
+        (0.0, 0.0)
+    };
+    // the initial population and the offspring of every generation are laid
+    // out in parallel, the local search of the offspring one after another
+    let size = config.population_size;
+    let parallel = size * (generations + 1);
+    let searched = match config.local_search_iters {
+        0 => 0,
+        iters => generations * size.saturating_sub(config.elitism) * (iters + 1),
+    };
+    let threads = rayon::current_num_threads().max(1);
+    let restarts = restarts.max(1);
+    let seconds = restarts as f64
+        * ((nfps as f64 * nfp_seconds + parallel as f64 * layout_seconds) / threads as f64
+            + searched as f64 * layout_seconds);
+    Ok(Plan {
+        parts: n,
+        rotations,
+        nfps,
+        sampled_nfps,
+        nfp_seconds,
+        cache_bytes: nfps as f64 * nfp_bytes,
+        evaluations: restarts * (parallel + searched),
+        layout_seconds,
+        threads,
+        seconds,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::rect;
+
+    #[test]
+    fn counts_nfps_and_evaluations() {
+        let parts: Vec<Part> = (0..3).map(|i| Part::new(vec![rect(2.0 + i as f64, 2.0)])).collect();
+        let bin = rect(20.0, 20.0);
+        let config = NestingConfig::builder()
+            .rotations(&[0.0, 90.0])
+            .population_size(4)
+            .elitism(1)
+            .local_search_iters(2)
+            .build()
+            .unwrap();
+        let plan = plan(&parts, &bin, &config, 10, 2).unwrap();
+        assert_eq!(plan.nfps, 3 * 2 * 2 * 2);
+        // the three parts in a row touch the ones before them
+        assert!(plan.sampled_nfps >= 1 && plan.sampled_nfps <= plan.nfps, "{:?}", plan);
+        assert_eq!(plan.evaluations, 2 * (4 * 11 + 10 * 3 * 3));
+        assert!(plan.cache_bytes > 0.0 && plan.seconds >= 0.0);
+        assert!(plan.to_string().contains("up to 24 NFPs"), "{}", plan);
+    }
+}
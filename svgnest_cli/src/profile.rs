@@ -0,0 +1,228 @@
+//! Opt-in timing of the stages of a nesting run.
+//!
+//! Instrumented code opens a [`span`] for its stage. Spans nest: the time a
+//! span spends in inner spans is attributed to the inner stage, so the
+//! breakdown adds up to the wall time of the instrumented code. While
+//! profiling is disabled a span costs one atomic load.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+    Parse,
+    Nfp,
+    Layout,
+    Boolean,
+    Output,
+}
+
+impl Stage {
+    const ALL: [Stage; 5] = [Stage::Parse, Stage::Nfp, Stage::Layout, Stage::Boolean, Stage::Output];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::Parse => "parse",
+            Stage::Nfp => "nfp",
+            Stage::Layout => "layout",
+            Stage::Boolean => "boolean",
+            Stage::Output => "output",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Event {
+    stage: Stage,
+    /// Generation the span ran in, `None` before the first generation
+    generation: Option<usize>,
+    start: Duration,
+    duration: Duration,
+    /// `duration` minus the time spent in nested spans
+    self_time: Duration,
+    thread: usize,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+/// Current generation plus one, zero before the first generation
+static GENERATION: AtomicUsize = AtomicUsize::new(0);
+static NEXT_THREAD: AtomicUsize = AtomicUsize::new(0);
+static START: OnceLock<Instant> = OnceLock::new();
+static EVENTS: Mutex<Vec<Event>> = Mutex::new(Vec::new());
+
+thread_local! {
+    static THREAD: usize = NEXT_THREAD.fetch_add(1, Ordering::Relaxed);
+    /// Time spent in finished child spans of every open span
+    static OPEN: RefCell<Vec<Duration>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Start recording spans.
+pub fn enable() {
+    START.get_or_init(Instant::now);
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Attribute the following spans to generation `generation`.
+pub fn set_generation(generation: usize) {
+    GENERATION.store(generation + 1, Ordering::Relaxed);
+}
+
+/// Time the rest of the scope as `stage`.
+pub fn span(stage: Stage) -> Span {
+    if !is_enabled() {
+        return Span(None);
+    }
+    OPEN.with(|open| open.borrow_mut().push(Duration::ZERO));
+    Span(Some((stage, Instant::now())))
+}
+
+/// Guard returned by [`span`], records the span when dropped.
+pub struct Span(Option<(Stage, Instant)>);
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        let Some((stage, start)) = self.0 else {
+            return;
+        };
+        let duration = start.elapsed();
+        let children = OPEN.with(|open| {
+            let mut open = open.borrow_mut();
+            let children = open.pop().unwrap_or_default();
+            if let Some(parent) = open.last_mut() {
+                *parent += duration;
+            }
+            children
+        });
+        let origin = *START.get_or_init(Instant::now);
+        let event = Event {
+            stage,
+            generation: GENERATION.load(Ordering::Relaxed).checked_sub(1),
+            start: start.saturating_duration_since(origin),
+            duration,
+            self_time: duration.saturating_sub(children),
+            thread: THREAD.with(|t| *t),
+        };
+        EVENTS.lock().unwrap().push(event);
+    }
+}
+
+fn events() -> Vec<Event> {
+    EVENTS.lock().unwrap().clone()
+}
+
+fn millis(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+/// Table of the time spent per stage, in total and for every generation.
+pub fn summary() -> String {
+    let events = events();
+    let mut totals: BTreeMap<Stage, Duration> = BTreeMap::new();
+    let mut per_generation: BTreeMap<usize, BTreeMap<Stage, Duration>> = BTreeMap::new();
+    for e in &events {
+        *totals.entry(e.stage).or_default() += e.self_time;
+        if let Some(g) = e.generation {
+            *per_generation.entry(g).or_default().entry(e.stage).or_default() += e.self_time;
+        }
+    }
+    let total: Duration = totals.values().sum();
+
+    let mut out = String::from("Profile (ms):\n");
+    for stage in Stage::ALL {
+        let t = totals.get(&stage).copied().unwrap_or_default();
+        let share = if total.is_zero() {
+            0.0
+        } else {
+            t.as_secs_f64() / total.as_secs_f64() * 100.0
+        };
+        let _ = writeln!(out, "  {:<8} {:>10.1} {:>5.1}%", stage.name(), millis(t), share);
+    }
+    let _ = writeln!(out, "  {:<8} {:>10.1}", "total", millis(total));
+    if !per_generation.is_empty() {
+        out.push_str("Per generation (ms):\n  gen");
+        for stage in Stage::ALL {
+            let _ = write!(out, " {:>9}", stage.name());
+        }
+        out.push('\n');
+        for (g, stages) in &per_generation {
+            let _ = write!(out, "  {:>3}", g);
+            for stage in Stage::ALL {
+                let t = stages.get(&stage).copied().unwrap_or_default();
+                let _ = write!(out, " {:>9.1}", millis(t));
+            }
+            out.push('\n');
+        }
+    }
+    out
+}
+
+#[derive(Serialize)]
+struct TraceEvent {
+    name: &'static str,
+    cat: &'static str,
+    ph: &'static str,
+    /// Microseconds since profiling was enabled
+    ts: f64,
+    dur: f64,
+    pid: u32,
+    tid: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    args: Option<TraceArgs>,
+}
+
+#[derive(Serialize)]
+struct TraceArgs {
+    generation: usize,
+}
+
+/// Recorded spans in the Chrome trace event format, viewable in
+/// `chrome://tracing` or Perfetto.
+pub fn chrome_trace() -> serde_json::Result<String> {
+    let events: Vec<TraceEvent> = events()
+        .iter()
+        .map(|e| TraceEvent {
+            name: e.stage.name(),
+            cat: "svgnest",
+            ph: "X",
+            ts: e.start.as_secs_f64() * 1e6,
+            dur: e.duration.as_secs_f64() * 1e6,
+            pid: std::process::id(),
+            tid: e.thread,
+            args: e.generation.map(|generation| TraceArgs { generation }),
+        })
+        .collect();
+    serde_json::to_string(&serde_json::json!({ "traceEvents": events }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_spans_split_self_time() {
+        enable();
+        {
+            let _outer = span(Stage::Layout);
+            let _inner = span(Stage::Nfp);
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        let thread = THREAD.with(|t| *t);
+        let mine: Vec<Event> = events().into_iter().filter(|e| e.thread == thread).collect();
+        let nfp = mine.iter().find(|e| e.stage == Stage::Nfp).unwrap();
+        let layout = mine.iter().find(|e| e.stage == Stage::Layout).unwrap();
+        assert!(nfp.self_time >= Duration::from_millis(5));
+        assert!(layout.duration >= nfp.duration);
+        assert!(layout.self_time < nfp.self_time);
+        assert!(chrome_trace().unwrap().contains("\"ph\":\"X\""));
+    }
+}
@@ -0,0 +1,111 @@
+//! Progress of a running optimization, readable from other threads.
+//!
+//! The genetic algorithm publishes a [`Progress`] after every evaluated
+//! population through a [`ProgressHandle`]. Clones of the handle can be
+//! moved to a GUI or server thread and polled while `evolve` runs.
+
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Progress {
+    /// Generations evaluated so far, not counting the initial population
+    pub generations_done: usize,
+    /// Generations the run is going to evaluate, 0 while unknown
+    pub generations_total: usize,
+    /// Seconds since the run started
+    pub elapsed_secs: f64,
+    /// Fitness of the best individual found so far
+    pub best_fitness: Option<f64>,
+    /// Utilization of the sheets by the best layout found so far
+    pub best_utilization: Option<f64>,
+    /// No-fit polygons generated so far
+    pub nfps_computed: usize,
+    /// No-fit polygons of part pairs and rotations not generated yet. Not all
+    /// of them are necessarily needed.
+    pub nfps_remaining: usize,
+}
+
+impl Progress {
+    /// Share of the generations done, between 0 and 1. 0 while the total is
+    /// unknown.
+    pub fn fraction(&self) -> f64 {
+        if self.generations_total == 0 {
+            return 0.0;
+        }
+        (self.generations_done as f64 / self.generations_total as f64).min(1.0)
+    }
+
+    /// Seconds until the run is expected to end, judged by the time the
+    /// generations done took.
+    pub fn eta_secs(&self) -> Option<f64> {
+        if self.generations_done == 0 || self.generations_total == 0 {
+            return None;
+        }
+        let left = self.generations_total.saturating_sub(self.generations_done);
+        Some(self.elapsed_secs / self.generations_done as f64 * left as f64)
+    }
+}
+
+/// Shared view of the [`Progress`] of one run. Cloning is cheap and every
+/// clone sees the same run.
+#[derive(Debug, Clone)]
+pub struct ProgressHandle {
+    inner: Arc<Mutex<Progress>>,
+    started: Arc<Mutex<Option<Instant>>>,
+}
+
+impl Default for ProgressHandle {
+    fn default() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Progress::default())),
+            started: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+impl ProgressHandle {
+    /// Snapshot of the progress.
+    pub fn get(&self) -> Progress {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Change the progress and refresh the elapsed time, starting the clock
+    /// on the first update.
+    pub(crate) fn update(&self, f: impl FnOnce(&mut Progress)) {
+        let elapsed = self
+            .started
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get_or_insert_with(Instant::now)
+            .elapsed();
+        let mut progress = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        f(&mut progress);
+        progress.elapsed_secs = elapsed.as_secs_f64();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimates_remaining_time() {
+        let progress = Progress {
+            generations_done: 5,
+            generations_total: 20,
+            elapsed_secs: 10.0,
+            ..Default::default()
+        };
+        assert_eq!(progress.fraction(), 0.25);
+        assert_eq!(progress.eta_secs(), Some(30.0));
+        assert_eq!(Progress::default().eta_secs(), None);
+
+        let handle = ProgressHandle::default();
+        let reader = handle.clone();
+        handle.update(|p| p.generations_done = 3);
+        assert_eq!(reader.get().generations_done, 3);
+    }
+}
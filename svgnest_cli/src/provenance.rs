@@ -0,0 +1,178 @@
+//! Where an output file comes from: the tool version, the options and seed
+//! of the run and hashes of the input files. Embedded into the nested SVG
+//! and written next to it, so a cut file can be traced back to the run that
+//! produced it and the run repeated.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::config::NestingConfig;
+use crate::output::{escape_xml, write_atomic};
+use crate::schema::{self, Format, Versioned};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputFile {
+    pub path: PathBuf,
+    /// SHA-256 of the file contents in hex
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunInfo {
+    /// Version of svgnest_cli
+    pub version: String,
+    /// Options of the run, including the seed it used
+    pub config: NestingConfig,
+    /// Bin first, then the part files
+    pub inputs: Vec<InputFile>,
+}
+
+impl RunInfo {
+    /// Record a run of `config` on `inputs`, hashing the files.
+    pub fn new(config: &NestingConfig, inputs: &[PathBuf]) -> Result<Self> {
+        let inputs = inputs
+            .iter()
+            .map(|path| {
+                let data = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+                Ok(InputFile {
+                    path: path.clone(),
+                    sha256: hex(&sha256(&data)),
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            config: config.clone(),
+            inputs,
+        })
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        schema::to_json(Format::Run, self)
+    }
+
+    /// `svg` with the run described in a `<metadata>` element right after
+    /// the opening `<svg>` tag. Returned unchanged if it has no such tag.
+    pub fn embed_in_svg(&self, svg: &str) -> Result<String> {
+        let Some(start) = svg.find("<svg") else {
+            return Ok(svg.to_string());
+        };
+        let Some(end) = svg[start..].find('>').map(|i| start + i + 1) else {
+            return Ok(svg.to_string());
+        };
+        let json = serde_json::to_string(&Versioned::new(Format::Run, self))?;
+        Ok(format!(
+            "{}<metadata id=\"svgnest-run\">{}</metadata>{}",
+            &svg[..end],
+            escape_xml(&json),
+            &svg[end..]
+        ))
+    }
+
+    /// Write the run as JSON to `path`.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        write_atomic(path, self.to_json()?.as_bytes()).with_context(|| format!("failed to write {}", path.display()))
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5, 0xd807aa98,
+    0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786,
+    0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da, 0x983e5152, 0xa831c66d, 0xb00327c8,
+    0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13,
+    0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819,
+    0xd6990624, 0xf40e3585, 0x106aa070, 0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a,
+    0x5b9cca4f, 0x682e6ff3, 0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7,
+    0xc67178f2,
+];
+
+/// SHA-256 digest of `data` (FIPS 180-4).
+fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut h: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+    ];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64).wrapping_mul(8)).to_be_bytes());
+    for block in message.chunks_exact(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = h;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = hh.wrapping_add(s1).wrapping_add(ch).wrapping_add(K[i]).wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            hh = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (state, v) in h.iter_mut().zip([a, b, c, d, e, f, g, hh]) {
+            *state = state.wrapping_add(v);
+        }
+    }
+    let mut digest = [0u8; 32];
+    for (chunk, word) in digest.chunks_exact_mut(4).zip(h) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_match_sha256() {
+        assert_eq!(
+            hex(&sha256(b"")),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hex(&sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        // two blocks
+        assert_eq!(
+            hex(&sha256(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq")),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+    }
+
+    #[test]
+    fn embeds_after_the_svg_tag() {
+        let info = RunInfo {
+            version: "1.0".to_string(),
+            config: NestingConfig::default(),
+            inputs: Vec::new(),
+        };
+        let svg = info.embed_in_svg("<svg width=\"1\"><rect/></svg>").unwrap();
+        let head = "<svg width=\"1\"><metadata id=\"svgnest-run\">{&quot;schema_version&quot;:1,&quot;version&quot;:&quot;1.0&quot;";
+        assert!(svg.starts_with(head));
+        assert!(svg.ends_with("</metadata><rect/></svg>"));
+    }
+}
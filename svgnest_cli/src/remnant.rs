@@ -0,0 +1,214 @@
+use crate::{
+    ga::Placement,
+    geometry::{
+        Bounds, get_polygons_bounds, ring_winding,
+        ops::{self, Shape},
+    },
+    part::Part,
+    svg_parser::Point,
+};
+
+/// Region of a sheet that is left free after nesting.
+#[derive(Debug, Clone)]
+pub struct Remnant {
+    /// Index of the sheet the region belongs to
+    pub sheet: usize,
+    /// Outer ring in sheet-local coordinates
+    pub outline: Vec<Point>,
+    /// Interior rings, e.g. around parts placed in the middle of the region
+    pub holes: Vec<Vec<Point>>,
+    /// Usable area (outline minus holes)
+    pub area: f64,
+}
+
+/// Compute the leftover area of every sheet as sheet minus placed parts and
+/// the holes of the bin. Regions smaller than `min_area` are dropped.
+/// `sheets` are the sheets of the layout in layout coordinates, `bin_holes`
+/// the holes every sheet has, relative to its top left corner.
+pub fn compute_remnants(
+    parts: &[Part],
+    placement: &[Placement],
+    sheets: &[Bounds],
+    bin_holes: &[Vec<Point>],
+    wrap_x: bool,
+    min_area: f64,
+) -> Vec<Remnant> {
+    let mut used: Vec<Vec<Vec<Point>>> = vec![Vec::new(); sheets.len()];
+    for p in placement {
+        let Some(clip) = used.get_mut(p.sheet) else {
+            continue;
+        };
+        let rotated = parts[p.idx].rotated(p.angle);
+        let Some(first) = rotated.first() else {
+            continue;
+        };
+        let orient = ring_winding(&first.points);
+        let mut offsets = vec![0.0];
+        if wrap_x {
+            if let Some(b) = get_polygons_bounds(&rotated) {
+                if p.x + b.width > sheets[p.sheet].width {
+                    offsets.push(-sheets[p.sheet].width);
+                }
+            }
+        }
+        let sheet_y = sheets[p.sheet].y;
+        for poly in &rotated {
+            // holes of a part are scrap, only its outer contours are removed
+            if poly.points.len() < 3 || ring_winding(&poly.points).opposes(orient) {
+                continue;
+            }
+            for &dx in &offsets {
+                clip.push(
+                    poly.points
+                        .iter()
+                        .map(|pt| Point {
+                            x: pt.x + p.x + dx,
+                            y: pt.y + p.y - sheet_y,
+                        })
+                        .collect(),
+                );
+            }
+        }
+    }
+
+    let mut result = Vec::new();
+    for (sheet, clip) in used.iter().enumerate() {
+        let (width, height) = (sheets[sheet].width, sheets[sheet].height);
+        let sheet_rect = vec![
+            Point { x: 0.0, y: 0.0 },
+            Point { x: width, y: 0.0 },
+            Point { x: width, y: height },
+            Point { x: 0.0, y: height },
+        ];
+        // outlines are removed as solids, also where one lies in the hole
+        // of another
+        let mut removed = Vec::new();
+        for ring in bin_holes.iter().chain(clip) {
+            removed = ops::union(&removed, &[Shape::new(ring.clone())]);
+        }
+        for shape in ops::difference(&[Shape::new(sheet_rect)], &removed) {
+            let area = shape.area();
+            if area < min_area {
+                continue;
+            }
+            result.push(Remnant {
+                sheet,
+                outline: shape.outline,
+                holes: shape.holes,
+                area,
+            });
+        }
+    }
+    result
+}
+
+fn ring_path(points: &[Point]) -> String {
+    let coords: Vec<String> = points.iter().map(|p| format!("{},{}", p.x, p.y)).collect();
+    format!("M{} Z", coords.join(" L"))
+}
+
+/// Render the remnants of one sheet, of the size of `bin_bounds`, as an SVG
+/// document.
+pub fn remnant_svg(remnants: &[Remnant], bin_bounds: Bounds) -> String {
+    let mut body = String::new();
+    for r in remnants {
+        let mut d = ring_path(&r.outline);
+        for h in &r.holes {
+            d.push(' ');
+            d.push_str(&ring_path(h));
+        }
+        body.push_str(&format!(
+            "<path d=\"{}\" fill=\"none\" fill-rule=\"evenodd\" stroke=\"green\"/>\n",
+            d
+        ));
+    }
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">{}<rect x=\"0\" y=\"0\" width=\"{}\" height=\"{}\" fill=\"none\" stroke=\"blue\"/></svg>",
+        bin_bounds.width, bin_bounds.height, body, bin_bounds.width, bin_bounds.height
+    )
+}
+
+/// Overlay layer shading the free space of every one of `sheets`, for a
+/// layout whose first sheet has its top left corner at `ox`, `oy`. The
+/// larger a region's share of its sheet, the stronger it is shaded, so wasted
+/// space stands out. The layer carries `data-overlay` so it is not read back
+/// as parts.
+pub fn free_space_layer(remnants: &[Remnant], sheets: &[Bounds], ox: f64, oy: f64) -> String {
+    let mut layer = String::from(
+        "<g id=\"free-space\" data-overlay=\"free-space\" inkscape:groupmode=\"layer\" inkscape:label=\"Free space\" fill=\"red\" stroke=\"none\">\n",
+    );
+    for r in remnants {
+        let sheet = sheets[r.sheet];
+        let dy = oy + sheet.y;
+        let sheet_area = sheet.width * sheet.height;
+        let moved = |ring: &[Point]| -> Vec<Point> { ring.iter().map(|p| Point { x: p.x + ox, y: p.y + dy }).collect() };
+        let mut d = ring_path(&moved(&r.outline));
+        for h in &r.holes {
+            d.push(' ');
+            d.push_str(&ring_path(&moved(h)));
+        }
+        let share = if sheet_area > 0.0 { (r.area / sheet_area).min(1.0) } else { 0.0 };
+        layer.push_str(&format!(
+            "<path d=\"{}\" fill-rule=\"evenodd\" fill-opacity=\"{:.3}\"><title>{:.1}% of sheet {} free</title></path>\n",
+            d,
+            0.1 + 0.5 * share,
+            100.0 * share,
+            r.sheet + 1
+        ));
+    }
+    layer.push_str("</g>\n");
+    layer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remnant_of_half_filled_sheet() {
+        let part = Part::new(vec![crate::svg_parser::Polygon {
+            id: 0,
+            points: vec![
+                Point { x: 0.0, y: 0.0 },
+                Point { x: 5.0, y: 0.0 },
+                Point { x: 5.0, y: 10.0 },
+                Point { x: 0.0, y: 10.0 },
+            ],
+            closed: true,
+            ..Default::default()
+        }]);
+        let bounds = Bounds {
+            x: 0.0,
+            y: 0.0,
+            width: 10.0,
+            height: 10.0,
+        };
+        let placement = [Placement {
+            idx: 0,
+            sheet: 0,
+            angle: 0.0,
+            x: 0.0,
+            y: 0.0,
+        }];
+        let rem = compute_remnants(&[part.clone()], &placement, &[bounds], &[], false, 1.0);
+        assert_eq!(rem.len(), 1);
+        assert!((rem[0].area - 50.0).abs() < 1e-6);
+        assert!(compute_remnants(&[], &[], &[bounds], &[], false, 200.0).is_empty());
+
+        let layer = free_space_layer(&rem, &[bounds], 100.0, 0.0);
+        assert!(layer.contains("fill-opacity=\"0.350\""));
+        assert!(layer.contains("<title>50.0% of sheet 1 free</title>"));
+
+        // a hole of the bin in the free half is no remnant
+        let hole = vec![
+            Point { x: 6.0, y: 2.0 },
+            Point { x: 8.0, y: 2.0 },
+            Point { x: 8.0, y: 4.0 },
+            Point { x: 6.0, y: 4.0 },
+        ];
+        let rem = compute_remnants(&[part], &placement, &[bounds], &[hole], false, 1.0);
+        assert_eq!(rem.len(), 1);
+        assert!((rem[0].area - 46.0).abs() < 1e-6);
+        assert_eq!(rem[0].holes.len(), 1);
+    }
+}
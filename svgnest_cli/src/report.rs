@@ -0,0 +1,448 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::{
+    ga::Placement,
+    geometry::{Bounds, polygon_area},
+    part::{self, InstanceId, Part},
+    schema::{self, Format},
+    svg_parser::Point,
+    warning::Warning,
+};
+
+/// A part as placed in the final layout.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlacedPart {
+    /// Index of the part in the input order (bin excluded)
+    pub part: usize,
+    /// Which part of the input this is, missing in reports of older versions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<InstanceId>,
+    /// Sheet the part was placed on
+    pub sheet: usize,
+    pub x: f64,
+    pub y: f64,
+    /// Rotation in degrees
+    pub angle: f64,
+    /// Degrees the part was turned by before nesting, with `--align-parts`.
+    /// The contours of the input are turned by this and `angle` together.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub alignment: f64,
+    /// Placed contours in layout coordinates
+    pub contours: Vec<Vec<Point>>,
+    /// Position in the frames of the sheet, missing in reports of older
+    /// versions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frames: Option<Frames>,
+    /// Identifying attributes carried over from the source design
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+}
+
+fn is_zero(angle: &f64) -> bool {
+    *angle == 0.0
+}
+
+/// Position of a placed part in one coordinate frame: its contours, given
+/// with the axes of the frame, are turned by `angle` degrees about their
+/// origin and then moved by (`x`, `y`).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FramePosition {
+    pub x: f64,
+    pub y: f64,
+    pub angle: f64,
+}
+
+/// Where a placed part is on its sheet, in each coordinate frame consumers
+/// of the report work in. Angles turn clockwise in the SVG frame, with y
+/// pointing down, and counter-clockwise in the others, with y pointing up,
+/// so the same physical turn never mirrors the part.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Frames {
+    /// Origin in the top-left corner of the sheet, y down, as in SVG
+    pub svg: FramePosition,
+    /// Origin in the bottom-left corner of the sheet, y up
+    pub sheet: FramePosition,
+    /// The sheet frame moved to the machine origin
+    pub machine: FramePosition,
+}
+
+impl Frames {
+    /// Frames of a part at (`x`, `y`) in SVG coordinates of a sheet `height`
+    /// high, turned by `angle`, with the machine origin at the sheet origin.
+    pub fn new(x: f64, y: f64, angle: f64, height: f64) -> Self {
+        let sheet = FramePosition {
+            x,
+            y: height - y,
+            angle: (360.0 - angle).rem_euclid(360.0),
+        };
+        Self {
+            svg: FramePosition { x, y, angle },
+            sheet,
+            machine: sheet,
+        }
+    }
+
+    /// These frames with the machine origin at `origin` in the sheet frame.
+    pub fn with_machine_origin(self, origin: Point) -> Self {
+        Self {
+            machine: FramePosition {
+                x: self.sheet.x - origin.x,
+                y: self.sheet.y - origin.y,
+                ..self.sheet
+            },
+            ..self
+        }
+    }
+}
+
+impl PlacedPart {
+    /// Net area of the placed contours, holes subtracted.
+    pub fn area(&self) -> f64 {
+        self.contours.iter().map(|c| polygon_area(c)).sum::<f64>().abs()
+    }
+}
+
+/// What the parts on one sheet add up to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct SheetTotals {
+    pub parts: usize,
+    /// Total weight, from the `data-weight` attribute of the parts
+    pub weight: f64,
+    pub area: f64,
+}
+
+impl SheetTotals {
+    /// Count in a part of `weight` and `area`.
+    pub fn add(&mut self, weight: f64, area: f64) {
+        self.parts += 1;
+        self.weight += weight;
+        self.area += area;
+    }
+}
+
+/// Totals of each of `sheets` sheets holding `placements`.
+fn sheet_totals(placements: &[PlacedPart], sheets: usize) -> Vec<SheetTotals> {
+    let mut totals = vec![SheetTotals::default(); sheets];
+    for p in placements {
+        if let Some(t) = totals.get_mut(p.sheet) {
+            t.add(part::weight(&p.metadata), p.area());
+        }
+    }
+    totals
+}
+
+/// Machine readable summary of a nesting run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Report {
+    pub bin_width: f64,
+    pub bin_height: f64,
+    /// Sheets that are the bin turned by 90°, `bin_width` wide and
+    /// `bin_height` high are the others
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub turned_sheets: Vec<usize>,
+    pub sheets: usize,
+    /// Placed part area divided by the area of all used sheets
+    pub utilization: f64,
+    pub placements: Vec<PlacedPart>,
+    /// Indices of parts that could not be placed
+    pub unplaced: Vec<usize>,
+    /// Ids of the parts that could not be placed, in the order of `unplaced`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unplaced_ids: Vec<InstanceId>,
+    /// Parts, weight and area on every sheet, missing in reports of older
+    /// versions
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sheet_totals: Vec<SheetTotals>,
+    /// Warnings raised while reading the inputs and placing the parts
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<Warning>,
+}
+
+impl Report {
+    /// Report of `placement`, whose sheets cut from `bin_bounds` are
+    /// `sheets` in layout coordinates.
+    pub fn new(parts: &[Part], placement: &[Placement], bin_bounds: Bounds, sheets: &[Bounds]) -> Self {
+        let mut placed_area = 0.0;
+        let placements: Vec<PlacedPart> = placement
+            .iter()
+            .map(|p| {
+                let part = &parts[p.idx];
+                placed_area += part.area();
+                let contours = part
+                    .rotated(p.angle)
+                    .into_iter()
+                    .map(|poly| {
+                        poly.points
+                            .into_iter()
+                            .map(|pt| Point {
+                                x: pt.x + p.x,
+                                y: pt.y + p.y,
+                            })
+                            .collect()
+                    })
+                    .collect();
+                let sheet = sheets.get(p.sheet).copied().unwrap_or(Bounds {
+                    y: p.sheet as f64 * bin_bounds.height,
+                    ..bin_bounds
+                });
+                PlacedPart {
+                    part: p.idx,
+                    id: Some(part.id.clone()),
+                    sheet: p.sheet,
+                    x: p.x,
+                    y: p.y,
+                    angle: p.angle,
+                    alignment: part.alignment,
+                    contours,
+                    frames: Some(Frames::new(p.x, p.y - sheet.y, p.angle + part.alignment, sheet.height)),
+                    metadata: part.metadata.clone(),
+                }
+            })
+            .collect();
+        let unplaced: Vec<usize> = (0..parts.len())
+            .filter(|i| !placement.iter().any(|p| p.idx == *i))
+            .collect();
+        let unplaced_ids = unplaced.iter().map(|&i| parts[i].id.clone()).collect();
+        let sheet_area: f64 = sheets.iter().map(|s| s.width * s.height).sum();
+        let turned_sheets = (0..sheets.len())
+            .filter(|&k| (sheets[k].width - bin_bounds.width).abs() > 1e-9)
+            .collect();
+        let sheets = sheets.len().max(1);
+        Self {
+            sheet_totals: sheet_totals(&placements, sheets),
+            bin_width: bin_bounds.width,
+            bin_height: bin_bounds.height,
+            turned_sheets,
+            sheets,
+            utilization: if sheet_area > 0.0 {
+                placed_area / sheet_area
+            } else {
+                0.0
+            },
+            placements,
+            unplaced,
+            unplaced_ids,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Bounds of every sheet in layout coordinates.
+    pub fn sheet_bounds(&self) -> Vec<Bounds> {
+        let bin = Bounds {
+            x: 0.0,
+            y: 0.0,
+            width: self.bin_width,
+            height: self.bin_height,
+        };
+        crate::layout::stack_sheets(bin, &self.turned_sheets, self.sheets)
+    }
+
+    /// Load a report previously written with [`Report::to_json`].
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let data = std::fs::read_to_string(path)?;
+        schema::from_str(Format::Report, &data)
+    }
+
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        schema::to_json(Format::Report, self)
+    }
+
+    /// This report with the machine frame of every placement starting at
+    /// `origin` in the sheet frame.
+    pub fn with_machine_origin(mut self, origin: Point) -> Self {
+        for p in &mut self.placements {
+            p.frames = p.frames.map(|f| f.with_machine_origin(origin));
+        }
+        self
+    }
+
+    /// This layout with `sheets` nested again. Their parts are replaced by
+    /// `renested`, the report of nesting them and any parts added since, and
+    /// every other sheet stays as it is. The sheets of `renested` take the
+    /// places of the re-nested sheets in order, more of them go after the
+    /// last sheet. Both reports must number the parts the same way.
+    pub fn with_renested(&self, sheets: &[usize], renested: &Report) -> anyhow::Result<Report> {
+        if (self.bin_width - renested.bin_width).abs() > 1e-9
+            || (self.bin_height - renested.bin_height).abs() > 1e-9
+        {
+            anyhow::bail!(
+                "sheets of {} x {} cannot be re-nested into sheets of {} x {}",
+                self.bin_width,
+                self.bin_height,
+                renested.bin_width,
+                renested.bin_height
+            );
+        }
+        let mut slots: Vec<usize> = sheets.iter().copied().filter(|&s| s < self.sheets).collect();
+        slots.sort_unstable();
+        slots.dedup();
+        let slot = |k: usize| slots.get(k).copied().unwrap_or(self.sheets + k - slots.len());
+
+        let mut placements: Vec<PlacedPart> = self
+            .placements
+            .iter()
+            .filter(|p| !slots.contains(&p.sheet))
+            .cloned()
+            .collect();
+        let kept = placements.len();
+        placements.extend(renested.placements.iter().map(|p| PlacedPart {
+            sheet: slot(p.sheet),
+            ..p.clone()
+        }));
+
+        // re-nested sheets left empty at the end are dropped, those between
+        // kept sheets stay empty so the kept ones keep their numbers
+        let sheets = placements.iter().map(|p| p.sheet + 1).max().unwrap_or(1);
+        // each sheet keeps the orientation of the sheet it came from, which
+        // may move the sheets below it
+        let turned_sheets: Vec<usize> = (0..sheets)
+            .filter(|&k| match slots.iter().position(|&s| s == k) {
+                Some(from) => renested.turned_sheets.contains(&from),
+                None if k >= self.sheets => renested.turned_sheets.contains(&(k + slots.len() - self.sheets)),
+                None => self.turned_sheets.contains(&k),
+            })
+            .collect();
+        let bin = Bounds {
+            x: 0.0,
+            y: 0.0,
+            width: self.bin_width,
+            height: self.bin_height,
+        };
+        let tops = crate::layout::stack_sheets(bin, &turned_sheets, sheets);
+        let (old_tops, renested_tops) = (self.sheet_bounds(), renested.sheet_bounds());
+        let top = |stack: &[Bounds], k: usize| stack.get(k).map_or(k as f64 * self.bin_height, |s| s.y);
+        for (i, p) in placements.iter_mut().enumerate() {
+            let from = if i < kept {
+                top(&old_tops, p.sheet)
+            } else {
+                let k = renested.placements[i - kept].sheet;
+                top(&renested_tops, k)
+            };
+            let dy = top(&tops, p.sheet) - from;
+            p.y += dy;
+            for pt in p.contours.iter_mut().flatten() {
+                pt.y += dy;
+            }
+        }
+
+        let placed_area: f64 = placements.iter().map(PlacedPart::area).sum();
+        let sheet_area: f64 = tops.iter().map(|s| s.width * s.height).sum();
+        Ok(Report {
+            sheet_totals: sheet_totals(&placements, sheets),
+            sheets,
+            utilization: if sheet_area > 0.0 {
+                placed_area / sheet_area
+            } else {
+                0.0
+            },
+            turned_sheets,
+            placements,
+            unplaced: renested.unplaced.clone(),
+            unplaced_ids: renested.unplaced_ids.clone(),
+            warnings: renested.warnings.clone(),
+            ..self.clone()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placed(part: usize, sheet: usize) -> PlacedPart {
+        let y = sheet as f64 * 10.0;
+        PlacedPart {
+            part,
+            id: None,
+            sheet,
+            x: 0.0,
+            y,
+            angle: 0.0,
+            alignment: 0.0,
+            contours: vec![vec![
+                Point { x: 0.0, y },
+                Point { x: 0.0, y: y + 5.0 },
+                Point { x: 5.0, y: y + 5.0 },
+                Point { x: 5.0, y },
+            ]],
+            frames: None,
+            metadata: Default::default(),
+        }
+    }
+
+    fn report(placements: Vec<PlacedPart>, sheets: usize) -> Report {
+        Report {
+            bin_width: 10.0,
+            bin_height: 10.0,
+            turned_sheets: Vec::new(),
+            sheets,
+            utilization: 0.0,
+            placements,
+            unplaced: Vec::new(),
+            unplaced_ids: Vec::new(),
+            sheet_totals: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn renested_sheets_take_the_places_of_the_old_ones() {
+        let old = report(vec![placed(0, 0), placed(1, 1), placed(2, 2)], 3);
+        // sheet 1 nested again with a new part, needing two sheets
+        let new = report(vec![placed(1, 0), placed(3, 1)], 2);
+        let merged = old.with_renested(&[1], &new).unwrap();
+        assert_eq!(merged.sheets, 4);
+        let sheet_of = |part: usize| merged.placements.iter().find(|p| p.part == part).unwrap().sheet;
+        assert_eq!([sheet_of(0), sheet_of(1), sheet_of(2), sheet_of(3)], [0, 1, 2, 3]);
+        let moved = merged.placements.iter().find(|p| p.part == 3).unwrap();
+        assert_eq!((moved.y, moved.contours[0][0].y), (30.0, 30.0));
+        assert!((merged.utilization - 0.25).abs() < 1e-9);
+        assert_eq!(merged.sheet_totals.len(), 4);
+        assert_eq!(merged.sheet_totals[3].parts, 1);
+        assert_eq!(merged.sheet_totals[3].area, 25.0);
+
+        // the last sheet nested onto nothing is dropped
+        let merged = old.with_renested(&[2], &report(Vec::new(), 1)).unwrap();
+        assert_eq!(merged.sheets, 2);
+
+        let mut other = report(Vec::new(), 1);
+        other.bin_width = 20.0;
+        assert!(old.with_renested(&[2], &other).is_err());
+    }
+
+    #[test]
+    fn placements_are_given_in_every_frame() {
+        let square = crate::svg_parser::Polygon {
+            points: vec![
+                Point { x: 0.0, y: 0.0 },
+                Point { x: 2.0, y: 0.0 },
+                Point { x: 2.0, y: 2.0 },
+                Point { x: 0.0, y: 2.0 },
+            ],
+            ..Default::default()
+        };
+        let parts = vec![Part::new(vec![square])];
+        let bin = Bounds {
+            x: 0.0,
+            y: 0.0,
+            width: 10.0,
+            height: 10.0,
+        };
+        let placement = [Placement {
+            idx: 0,
+            sheet: 1,
+            angle: 90.0,
+            x: 3.0,
+            y: 14.0,
+        }];
+        let sheets = crate::layout::stack_sheets(bin, &[], 2);
+        let report = Report::new(&parts, &placement, bin, &sheets).with_machine_origin(Point { x: 1.0, y: 2.0 });
+        let frames = report.placements[0].frames.unwrap();
+        let at = |f: FramePosition| (f.x, f.y, f.angle);
+        assert_eq!(at(frames.svg), (3.0, 4.0, 90.0));
+        assert_eq!(at(frames.sheet), (3.0, 6.0, 270.0));
+        assert_eq!(at(frames.machine), (2.0, 4.0, 270.0));
+    }
+}
@@ -0,0 +1,112 @@
+//! Versions of the JSON files the crate writes and reads back, so tools
+//! built on them notice a change of format instead of misreading it. Every
+//! file carries a `schema_version` next to its other fields; files written
+//! before versions were added count as version 1. Readers take every
+//! version up to their own, ignoring fields they do not know where the
+//! format allows it, and refuse newer ones saying so.
+
+use anyhow::{Result, anyhow, bail};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+/// Name of the version field in every versioned file
+pub const FIELD: &str = "schema_version";
+
+/// A versioned JSON format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// `config.json` of a batch job
+    JobConfig,
+    /// Report of a nesting run, written with `--report`
+    Report,
+    /// Saved layout, reloaded to write outputs or compare runs
+    Layout,
+    /// `run.json`, the record of a run
+    Run,
+}
+
+impl Format {
+    /// Version written by this build, the newest it reads.
+    pub const fn version(self) -> u64 {
+        match self {
+            Format::JobConfig | Format::Report | Format::Layout | Format::Run => 1,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Format::JobConfig => "job config",
+            Format::Report => "report",
+            Format::Layout => "layout",
+            Format::Run => "run record",
+        }
+    }
+}
+
+/// `value` with the version of `format` in front of its fields.
+#[derive(Serialize)]
+pub struct Versioned<'a, T> {
+    schema_version: u64,
+    #[serde(flatten)]
+    value: &'a T,
+}
+
+impl<'a, T: Serialize> Versioned<'a, T> {
+    pub fn new(format: Format, value: &'a T) -> Self {
+        Self {
+            schema_version: format.version(),
+            value,
+        }
+    }
+}
+
+/// `value` as pretty-printed JSON of `format`.
+pub fn to_json<T: Serialize>(format: Format, value: &T) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&Versioned::new(format, value))?)
+}
+
+/// Read `data` as JSON of `format`, failing with a clear error if it was
+/// written in a newer version of it.
+pub fn from_str<T: DeserializeOwned>(format: Format, data: &str) -> Result<T> {
+    let mut value: serde_json::Value = serde_json::from_str(data)?;
+    let version = match value.as_object_mut().and_then(|fields| fields.remove(FIELD)) {
+        None => 1,
+        Some(v) => v
+            .as_u64()
+            .filter(|&v| v >= 1)
+            .ok_or_else(|| anyhow!("{} {} must be a positive integer, got {}", format.name(), FIELD, v))?,
+    };
+    if version > format.version() {
+        bail!(
+            "{} has schema version {}, but this version of svgnest_cli reads up to {}; upgrade it to read the file",
+            format.name(),
+            version,
+            format.version()
+        );
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Sample {
+        size: f64,
+    }
+
+    #[test]
+    fn versions_are_written_and_checked() {
+        let json = to_json(Format::Report, &Sample { size: 2.0 }).unwrap();
+        assert!(json.starts_with("{\n  \"schema_version\": 1,"));
+        assert_eq!(from_str::<Sample>(Format::Report, &json).unwrap(), Sample { size: 2.0 });
+        // files from before versioning
+        assert_eq!(from_str::<Sample>(Format::Report, r#"{"size": 3}"#).unwrap(), Sample { size: 3.0 });
+        let newer = from_str::<Sample>(Format::Layout, r#"{"schema_version": 2, "size": 3}"#).unwrap_err();
+        assert!(newer.to_string().contains("layout has schema version 2"), "{}", newer);
+        assert!(from_str::<Sample>(Format::Layout, r#"{"schema_version": "1", "size": 3}"#).is_err());
+    }
+}
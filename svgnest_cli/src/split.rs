@@ -0,0 +1,120 @@
+//! Splitting parts larger than any sheet into pieces that do fit, as is done
+//! for signage. A part is cut along vertical and horizontal lines into a grid
+//! of tiles the size of the sheet, neighbouring pieces overlapping by a joint
+//! allowance so they can be joined again, and the pieces are nested like any
+//! other part.
+
+use anyhow::{Result, bail};
+
+use crate::config::{AngleFallback, NestingConfig};
+use crate::ga;
+use crate::geometry::{get_polygon_bounds, get_polygons_bounds};
+use crate::geometry::ops::{self, Shape};
+use crate::part::Part;
+use crate::svg_parser::{Point, Polygon};
+
+/// Start of every tile along an edge `length` long, for tiles `size` long
+/// overlapping by `joint`.
+fn tile_starts(length: f64, size: f64, joint: f64) -> Vec<f64> {
+    if length <= size {
+        return vec![0.0];
+    }
+    let step = size - joint;
+    let n = ((length - size) / step).ceil() as usize + 1;
+    (0..n).map(|i| i as f64 * step).collect()
+}
+
+/// Pieces of `part` fitting a `width` x `height` sheet unrotated, neighbours
+/// overlapping by `joint`. Pieces are numbered row by row in the element of
+/// their id and keep the metadata of the part, not its marking.
+pub fn split_part(part: &Part, width: f64, height: f64, joint: f64) -> Result<Vec<Part>> {
+    if joint >= width.min(height) {
+        bail!("joint allowance {} leaves nothing of a {} x {} sheet", joint, width, height);
+    }
+    let Some(bounds) = get_polygons_bounds(&part.polygons) else {
+        return Ok(Vec::new());
+    };
+    let rings: Vec<Vec<Point>> = part.polygons.iter().map(|p| p.points.clone()).collect();
+    let shapes = ops::shapes_from_rings(&rings);
+    let base = part.id.part.element.clone().unwrap_or_else(|| format!("part{}", part.id.part.index));
+    let mut pieces = Vec::new();
+    for y in tile_starts(bounds.height, height, joint) {
+        for x in tile_starts(bounds.width, width, joint) {
+            let (x0, y0) = (bounds.x + x, bounds.y + y);
+            let tile = Shape::new(vec![
+                Point { x: x0, y: y0 },
+                Point { x: x0 + width, y: y0 },
+                Point {
+                    x: x0 + width,
+                    y: y0 + height,
+                },
+                Point { x: x0, y: y0 + height },
+            ]);
+            for piece in ops::intersection(&shapes, &[tile]) {
+                if piece.area() <= 0.0 {
+                    continue;
+                }
+                let polygons = ops::rings_of(vec![piece])
+                    .into_iter()
+                    .map(|points| Polygon {
+                        points,
+                        ..Default::default()
+                    })
+                    .collect();
+                let mut id = part.id.clone();
+                id.part.element = Some(format!("{}.piece{}", base, pieces.len() + 1));
+                pieces.push(Part::new(polygons).with_metadata(part.metadata.clone()).with_id(id));
+            }
+        }
+    }
+    Ok(pieces)
+}
+
+/// `parts` with those fitting `bin` at none of the allowed rotations split
+/// into pieces, if `config` asks for it with [`AngleFallback::Split`].
+/// Other parts are passed on as they are.
+pub fn split_oversize(parts: Vec<Part>, bin: &Polygon, config: &NestingConfig) -> Result<Vec<Part>> {
+    if config.angle_fallback != AngleFallback::Split {
+        return Ok(parts);
+    }
+    let Some(sheet) = get_polygon_bounds(&bin.points) else {
+        bail!("failed to compute bin bounds");
+    };
+    let oversize: Vec<bool> = ga::feasibility(&parts, bin, config)?
+        .into_iter()
+        .map(|f| f.reason.is_some())
+        .collect();
+    let mut result = Vec::with_capacity(parts.len());
+    for (part, oversize) in parts.into_iter().zip(oversize) {
+        if oversize {
+            result.extend(split_part(&part, sheet.width, sheet.height, config.split_joint)?);
+        } else {
+            result.push(part);
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::rect;
+
+    #[test]
+    fn pieces_fit_the_sheet_and_overlap_by_the_joint() {
+        let part = Part::new(vec![rect(25.0, 4.0)]);
+        let pieces = split_part(&part, 10.0, 10.0, 1.0).unwrap();
+        let widths: Vec<f64> = pieces.iter().map(|p| p.bounds_rotated(0.0).unwrap().width).collect();
+        assert_eq!(widths, [10.0, 10.0, 7.0]);
+        assert_eq!(pieces[2].id.part.element.as_deref(), Some("part0.piece3"));
+        assert!(split_part(&part, 10.0, 10.0, 10.0).is_err());
+
+        let config = NestingConfig::builder()
+            .angle_fallback(AngleFallback::Split)
+            .split_joint(1.0)
+            .build()
+            .unwrap();
+        let parts = vec![part, Part::new(vec![rect(3.0, 3.0)])];
+        assert_eq!(split_oversize(parts, &rect(10.0, 10.0), &config).unwrap().len(), 4);
+    }
+}
@@ -0,0 +1,145 @@
+//! Per-generation statistics of the genetic algorithm.
+//!
+//! Recorded for every evaluated population so population size and mutation
+//! rate can be tuned from how fitness and diversity develop over a run.
+
+use std::fmt::Write as _;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::ga::Individual;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GenerationStats {
+    /// Generation the population belongs to, 0 for the initial one
+    pub generation: usize,
+    pub best: f64,
+    pub median: f64,
+    pub worst: f64,
+    /// Mean fraction of genes in which an individual differs from the best
+    /// one, 0 once the population has converged
+    pub diversity: f64,
+    /// Share of NFP lookups answered from the cache during the evaluation
+    pub cache_hit_rate: f64,
+    /// Time spent evaluating the population in milliseconds
+    pub eval_ms: f64,
+}
+
+impl GenerationStats {
+    /// Statistics of an evaluated `population`. `hits` and `misses` are the
+    /// NFP cache lookups made while evaluating it.
+    pub fn new(generation: usize, population: &[Individual], hits: usize, misses: usize, eval: Duration) -> Self {
+        let mut fitness: Vec<f64> = population.iter().map(|ind| ind.fitness).collect();
+        fitness.sort_by(f64::total_cmp);
+        let median = match fitness.len() {
+            0 => f64::NAN,
+            n if n % 2 == 1 => fitness[n / 2],
+            n => (fitness[n / 2 - 1] + fitness[n / 2]) / 2.0,
+        };
+        let lookups = hits + misses;
+        Self {
+            generation,
+            best: fitness.first().copied().unwrap_or(f64::NAN),
+            median,
+            worst: fitness.last().copied().unwrap_or(f64::NAN),
+            diversity: diversity(population),
+            cache_hit_rate: if lookups == 0 { 0.0 } else { hits as f64 / lookups as f64 },
+            eval_ms: eval.as_secs_f64() * 1000.0,
+        }
+    }
+}
+
+fn diversity(population: &[Individual]) -> f64 {
+    let Some(best) = population.iter().min_by(|a, b| a.fitness.total_cmp(&b.fitness)) else {
+        return 0.0;
+    };
+    let genes = best.placement.len();
+    if genes == 0 {
+        return 0.0;
+    }
+    let differing: usize = population
+        .iter()
+        .map(|ind| {
+            (0..ind.placement.len().min(genes))
+                .filter(|&i| {
+                    ind.placement[i] != best.placement[i]
+                        || ind.rotation.get(i) != best.rotation.get(i)
+                        || ind.sheet_type.get(i) != best.sheet_type.get(i)
+                })
+                .count()
+        })
+        .sum();
+    differing as f64 / (genes * population.len()) as f64
+}
+
+pub fn to_csv(stats: &[GenerationStats]) -> String {
+    let mut out = String::from("generation,best,median,worst,diversity,cache_hit_rate,eval_ms\n");
+    for s in stats {
+        let _ = writeln!(
+            out,
+            "{},{},{},{},{},{},{}",
+            s.generation, s.best, s.median, s.worst, s.diversity, s.cache_hit_rate, s.eval_ms
+        );
+    }
+    out
+}
+
+/// Write `stats` to `path`, as JSON if it ends in `.json` and as CSV
+/// otherwise.
+pub fn write(path: &Path, stats: &[GenerationStats]) -> Result<()> {
+    crate::output::write_atomic(path, render(path, stats)?.as_bytes())?;
+    Ok(())
+}
+
+/// Contents [`write`] writes `stats` to `path` with.
+pub fn render(path: &Path, stats: &[GenerationStats]) -> Result<String> {
+    let json = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("json"));
+    Ok(if json {
+        serde_json::to_string_pretty(stats)?
+    } else {
+        to_csv(stats)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn individual(placement: &[usize], fitness: f64) -> Individual {
+        Individual {
+            placement: placement.to_vec(),
+            rotation: vec![0.0; placement.len()],
+            sheet_type: vec![0; placement.len()],
+            fitness,
+        }
+    }
+
+    #[test]
+    fn summarizes_population() {
+        let population = [
+            individual(&[0, 1, 2, 3], 3.0),
+            individual(&[0, 1, 2, 3], 1.0),
+            individual(&[1, 0, 2, 3], 4.0),
+            individual(&[3, 2, 1, 0], 2.0),
+        ];
+        let stats = GenerationStats::new(5, &population, 3, 1, Duration::from_millis(2));
+        assert_eq!(stats.generation, 5);
+        assert_eq!(stats.best, 1.0);
+        assert_eq!(stats.median, 2.5);
+        assert_eq!(stats.worst, 4.0);
+        assert_eq!(stats.diversity, 6.0 / 16.0);
+        assert_eq!(stats.cache_hit_rate, 0.75);
+        assert_eq!(stats.eval_ms, 2.0);
+
+        let csv = to_csv(&[stats]);
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("generation,best,median,worst,diversity,cache_hit_rate,eval_ms"));
+        assert_eq!(lines.next(), Some("5,1,2.5,4,0.375,0.75,2"));
+    }
+}
@@ -1,9 +1,18 @@
 use roxmltree::{Document, Node};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::Path;
 use lyon_path::{iterator::PathIterator, Path as LyonPath, PathEvent};
 use lyon_svg::path_utils::build_path;
 
+use crate::contour::{self, Contour, Segment};
+use crate::geometry::{
+    CURVE_TOLERANCE, LineCap, LineJoin, PointLocation, Tolerances, Winding, locate_point, offset_polyline, polygon_area, ring_winding,
+};
+use crate::part::{InstanceId, Part, PartId};
+use crate::warning::{Code, Warning};
+
 /// Simple 2D transformation matrix represented as [a,b,c,d,e,f].
 #[derive(Clone, Copy, Debug)]
 struct Transform([f64; 6]);
@@ -26,6 +35,10 @@ impl Transform {
         ])
     }
 
+    fn translate(x: f64, y: f64) -> Self {
+        Self([1.0, 0.0, 0.0, 1.0, x, y])
+    }
+
     fn apply(&self, x: f64, y: f64) -> (f64, f64) {
         let m = self.0;
         (x * m[0] + y * m[2] + m[4], x * m[1] + y * m[3] + m[5])
@@ -42,14 +55,14 @@ fn parse_transform(value: &str) -> Transform {
 }
 
 /// Single point.
-#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct Point {
     pub x: f64,
     pub y: f64,
 }
 
 /// Polygon composed of points.
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
 pub struct Polygon {
     /// Unique identifier assigned during parsing
     pub id: usize,
@@ -57,10 +70,35 @@ pub struct Polygon {
     pub points: Vec<Point>,
     /// Whether the polygon forms a closed path
     pub closed: bool,
+    /// Identifying attributes of the source element (`id`, `inkscape:label`, `data-*`)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+    /// Exact lines, arcs and curves the points were flattened from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contour: Option<Contour>,
+}
+
+const INKSCAPE_NS: &str = "http://www.inkscape.org/namespaces/inkscape";
+
+/// Collect the attributes of `node` that identify it in the source design.
+fn node_metadata(node: Node) -> BTreeMap<String, String> {
+    let mut metadata = BTreeMap::new();
+    for attr in node.attributes() {
+        let name = attr.name();
+        let key = match attr.namespace() {
+            None if name == "id" || name.starts_with("data-") => name.to_string(),
+            Some(INKSCAPE_NS) if name == "label" => "inkscape:label".to_string(),
+            _ => continue,
+        };
+        metadata.insert(key, attr.value().to_string());
+    }
+    metadata
 }
 
 /// Approximate a SVG path into points using recursive subdivision with the given tolerance.
+/// Tolerances that are not positive fall back to [`CURVE_TOLERANCE`].
 pub fn approximate_path(d: &str, tol: f64) -> Vec<(bool, Vec<(f64, f64)>)> {
+    let tol = if tol > 0.0 && tol.is_finite() { tol } else { CURVE_TOLERANCE };
     let builder = LyonPath::builder().with_svg();
     let path = match build_path(builder, d) {
         Ok(p) => p,
@@ -94,6 +132,124 @@ pub fn approximate_path(d: &str, tol: f64) -> Vec<(bool, Vec<(f64, f64)>)> {
     result
 }
 
+/// Parse path data into exact contours, one per subpath, without flattening.
+fn path_contours(d: &str) -> Vec<Contour> {
+    use svgtypes::PathSegment;
+
+    let resolve = |pos: Point, abs: bool, x: f64, y: f64| {
+        if abs {
+            Point { x, y }
+        } else {
+            Point {
+                x: pos.x + x,
+                y: pos.y + y,
+            }
+        }
+    };
+    let reflect = |p: Point, about: Point| Point {
+        x: 2.0 * about.x - p.x,
+        y: 2.0 * about.y - p.y,
+    };
+
+    let mut contours: Vec<Contour> = Vec::new();
+    let mut pos = Point::default();
+    // control points of the previous cubic/quadratic for smooth curves
+    let mut last_cubic: Option<Point> = None;
+    let mut last_quad: Option<Point> = None;
+    for segment in svgtypes::PathParser::from(d) {
+        let Ok(segment) = segment else {
+            break;
+        };
+        if let PathSegment::MoveTo { abs, x, y } = segment {
+            pos = resolve(pos, abs, x, y);
+            contours.push(Contour::new(pos));
+            last_cubic = None;
+            last_quad = None;
+            continue;
+        }
+        // drawing after a close starts a new subpath at the same point
+        if contours.last().is_none_or(|c| c.closed) {
+            contours.push(Contour::new(pos));
+        }
+        let Some(current) = contours.last_mut() else {
+            break;
+        };
+        let mut cubic_ctrl = None;
+        let mut quad_ctrl = None;
+        match segment {
+            PathSegment::MoveTo { .. } => {}
+            PathSegment::LineTo { abs, x, y } => {
+                pos = resolve(pos, abs, x, y);
+                current.segments.push(Segment::Line { to: pos });
+            }
+            PathSegment::HorizontalLineTo { abs, x } => {
+                pos.x = if abs { x } else { pos.x + x };
+                current.segments.push(Segment::Line { to: pos });
+            }
+            PathSegment::VerticalLineTo { abs, y } => {
+                pos.y = if abs { y } else { pos.y + y };
+                current.segments.push(Segment::Line { to: pos });
+            }
+            PathSegment::CurveTo {
+                abs,
+                x1,
+                y1,
+                x2,
+                y2,
+                x,
+                y,
+            } => {
+                let ctrl1 = resolve(pos, abs, x1, y1);
+                let ctrl2 = resolve(pos, abs, x2, y2);
+                pos = resolve(pos, abs, x, y);
+                current.segments.push(Segment::Cubic { ctrl1, ctrl2, to: pos });
+                cubic_ctrl = Some(ctrl2);
+            }
+            PathSegment::SmoothCurveTo { abs, x2, y2, x, y } => {
+                let ctrl1 = last_cubic.map_or(pos, |c| reflect(c, pos));
+                let ctrl2 = resolve(pos, abs, x2, y2);
+                pos = resolve(pos, abs, x, y);
+                current.segments.push(Segment::Cubic { ctrl1, ctrl2, to: pos });
+                cubic_ctrl = Some(ctrl2);
+            }
+            PathSegment::Quadratic { abs, x1, y1, x, y } => {
+                let ctrl = resolve(pos, abs, x1, y1);
+                pos = resolve(pos, abs, x, y);
+                current.segments.push(Segment::Quadratic { ctrl, to: pos });
+                quad_ctrl = Some(ctrl);
+            }
+            PathSegment::SmoothQuadratic { abs, x, y } => {
+                let ctrl = last_quad.map_or(pos, |c| reflect(c, pos));
+                pos = resolve(pos, abs, x, y);
+                current.segments.push(Segment::Quadratic { ctrl, to: pos });
+                quad_ctrl = Some(ctrl);
+            }
+            PathSegment::EllipticalArc {
+                abs,
+                rx,
+                ry,
+                x_axis_rotation,
+                large_arc,
+                sweep,
+                x,
+                y,
+            } => {
+                let to = resolve(pos, abs, x, y);
+                let arc = contour::svg_arc(pos, (rx, ry), x_axis_rotation, large_arc, sweep, to);
+                current.segments.extend(arc);
+                pos = to;
+            }
+            PathSegment::ClosePath { .. } => {
+                current.closed = true;
+                pos = current.start;
+            }
+        }
+        last_cubic = cubic_ctrl;
+        last_quad = quad_ctrl;
+    }
+    contours
+}
+
 /// Parse an SVG file and return all polygons.
 pub fn polygons_from_file(path: &Path, merge: bool, tol: f64) -> anyhow::Result<Vec<Polygon>> {
     let data = fs::read_to_string(path)?;
@@ -102,36 +258,689 @@ pub fn polygons_from_file(path: &Path, merge: bool, tol: f64) -> anyhow::Result<
 
 /// Parse an SVG string and return all polygons.
 pub fn polygons_from_str(data: &str, merge: bool, tol: f64) -> anyhow::Result<Vec<Polygon>> {
+    let opts = ParseOptions {
+        merge_lines: merge,
+        tolerances: Tolerances {
+            curve: tol,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    Ok(parse_polygons(data, &opts, &mut Vec::new())?.0)
+}
+
+/// Cut and marking polygons of a whole document.
+fn parse_polygons(
+    data: &str,
+    opts: &ParseOptions,
+    warnings: &mut Vec<Warning>,
+) -> anyhow::Result<(Vec<Polygon>, Vec<Polygon>)> {
     let doc = Document::parse(data)?;
     let root = doc.root_element();
+    let ctx = Context::new(&doc, opts);
     let mut polys = Vec::new();
-    extract_node_polygons(root, Transform::identity(), tol, &mut polys)?;
+    extract_node_polygons(root, Transform::identity(), &ctx, &mut polys)?;
+    ctx.finish(warnings)?;
+    let marking = ctx.marked.take();
     for (i, p) in polys.iter_mut().enumerate() {
         p.id = i;
     }
-    if merge {
-        Ok(crate::line_merge::merge_lines(&polys))
+    if opts.merge_lines {
+        Ok((crate::line_merge::merge_lines(&polys, opts.tolerances.merge), marking))
     } else {
-        Ok(polys)
+        Ok((polys, marking))
+    }
+}
+
+/// How the shapes of an SVG file are grouped into parts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum PartMode {
+    /// Every top-level `<g>` is one part; shapes outside groups stand alone
+    Group,
+    /// Every shape element is its own part
+    Element,
+    /// The whole file is a single part
+    #[default]
+    File,
+}
+
+/// Which shapes are marking geometry, like engraving lines or fold marks,
+/// rather than cuts. Marking travels with its part into the output but is
+/// ignored when placing parts. In [`PartMode::Element`], where every shape
+/// is a part of its own, marking has no part to travel with and is dropped.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MarkingSelector {
+    /// Groups, by `inkscape:label` or `id`, whose shapes are marking
+    pub layers: Vec<String>,
+    /// Stroke colors of marking shapes, compared as written but ignoring
+    /// case, so `#f00` does not match `red`
+    pub colors: Vec<String>,
+}
+
+impl MarkingSelector {
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty() && self.colors.is_empty()
+    }
+}
+
+/// Options controlling how an SVG document is turned into parts.
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    /// Merge overlapping line segments and chain them into polylines and
+    /// closed shapes
+    pub merge_lines: bool,
+    /// Curve and merge tolerances
+    pub tolerances: Tolerances,
+    /// How shapes are grouped into parts
+    pub part_per: PartMode,
+    /// Replace stroked open paths by the outline of their stroke
+    pub stroke_to_path: bool,
+    /// Fail on the first malformed or unsupported element instead of
+    /// skipping it with a warning
+    pub strict: bool,
+    /// Shapes read as marking geometry instead of cuts
+    pub marking: MarkingSelector,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            merge_lines: false,
+            tolerances: Tolerances::default(),
+            part_per: PartMode::File,
+            stroke_to_path: false,
+            strict: false,
+            marking: MarkingSelector::default(),
+        }
+    }
+}
+
+/// Parse an SVG file into parts according to `opts.part_per`. Skipped
+/// elements are appended to `warnings`.
+pub fn parts_from_file(
+    path: &Path,
+    opts: &ParseOptions,
+    warnings: &mut Vec<Warning>,
+) -> anyhow::Result<Vec<Part>> {
+    let data = fs::read_to_string(path)?;
+    let start = warnings.len();
+    let mut parts = parts_from_str(&data, opts, warnings);
+    for w in &mut warnings[start..] {
+        w.file = Some(path.to_path_buf());
+    }
+    for part in parts.iter_mut().flatten() {
+        part.id.part.file = Some(path.to_path_buf());
+    }
+    parts
+}
+
+/// Metadata, cut contours and marking geometry of a part being read
+type PartSource = (BTreeMap<String, String>, Vec<Polygon>, Vec<Polygon>);
+
+/// Parse an SVG string into parts according to `opts.part_per`. In `File`
+/// mode exactly one part is returned, even if it has no geometry. Skipped
+/// elements are appended to `warnings`, or fail the parse in strict mode.
+pub fn parts_from_str(
+    data: &str,
+    opts: &ParseOptions,
+    warnings: &mut Vec<Warning>,
+) -> anyhow::Result<Vec<Part>> {
+    let mode = opts.part_per;
+    if mode == PartMode::File {
+        let (polys, marking) = parse_polygons(data, opts, warnings)?;
+        return Ok(vec![Part::new_with_marking(polys, marking)]);
+    }
+    let doc = Document::parse(data)?;
+    let root = doc.root_element();
+    let ctx = Context::new(&doc, opts);
+    let transform = node_transform(root);
+    let mut groups: Vec<PartSource> = Vec::new();
+    for child in root.children().filter(|n| n.is_element()) {
+        if mode == PartMode::Group && child.tag_name().name() == "g" {
+            let mut polys = Vec::new();
+            extract_node_polygons(child, transform, &ctx, &mut polys)?;
+            groups.push((node_metadata(child), polys, ctx.marked.take()));
+        } else {
+            let mut elements = Vec::new();
+            extract_element_parts(child, transform, &ctx, &mut elements);
+            groups.extend(elements.into_iter().map(|polys| (BTreeMap::new(), polys, Vec::new())));
+            ctx.marked.take();
+        }
+    }
+    ctx.finish(warnings)?;
+
+    let mut parts = Vec::new();
+    for (metadata, mut polys, marking) in groups {
+        if polys.is_empty() {
+            continue;
+        }
+        for (i, p) in polys.iter_mut().enumerate() {
+            p.id = i;
+        }
+        if opts.merge_lines {
+            polys = crate::line_merge::merge_lines(&polys, opts.tolerances.merge);
+        }
+        let part = Part::new_with_marking(polys, marking).with_metadata(metadata);
+        let id = InstanceId {
+            part: PartId {
+                file: None,
+                element: part.metadata.get("id").cloned(),
+                index: parts.len(),
+            },
+            copy: 0,
+        };
+        parts.push(part.with_id(id));
+    }
+    Ok(parts)
+}
+
+/// Parse CSS declarations (`name: value; ...`) into key/value pairs.
+fn parse_declarations(body: &str) -> Vec<(String, String)> {
+    body.split(';')
+        .filter_map(|decl| decl.split_once(':'))
+        .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+        .collect()
+}
+
+/// Remove `/* ... */` comments from a CSS string.
+fn strip_css_comments(css: &str) -> String {
+    let mut out = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(start) = rest.find("/*") {
+        out.push_str(&rest[..start]);
+        rest = match rest[start + 2..].find("*/") {
+            Some(end) => &rest[start + 2 + end + 2..],
+            None => "",
+        };
     }
+    out.push_str(rest);
+    out
+}
+
+/// Class rules collected from the `<style>` elements of a document. Only
+/// plain `.class` selectors are understood.
+#[derive(Debug, Default)]
+struct Stylesheet {
+    classes: HashMap<String, Vec<(String, String)>>,
+}
+
+impl Stylesheet {
+    fn from_document(doc: &Document) -> Self {
+        let mut sheet = Self::default();
+        for node in doc.descendants().filter(|n| n.has_tag_name("style")) {
+            let css: String = node.children().filter_map(|c| c.text()).collect();
+            sheet.add_rules(&strip_css_comments(&css));
+        }
+        sheet
+    }
+
+    fn add_rules(&mut self, css: &str) {
+        for rule in css.split('}') {
+            let Some((selectors, body)) = rule.split_once('{') else {
+                continue;
+            };
+            let decls = parse_declarations(body);
+            for selector in selectors.split(',') {
+                let Some(class) = selector.trim().strip_prefix('.') else {
+                    continue;
+                };
+                if class.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_') {
+                    self.classes
+                        .entry(class.to_string())
+                        .or_default()
+                        .extend(decls.iter().cloned());
+                }
+            }
+        }
+    }
+
+    /// Value of `name` set for `node` through its classes; later rules win.
+    fn lookup<'a>(&'a self, node: Node, name: &str) -> Option<&'a str> {
+        let mut found = None;
+        for class in node.attribute("class")?.split_whitespace() {
+            for (key, value) in self.classes.get(class).into_iter().flatten() {
+                if key == name {
+                    found = Some(value.as_str());
+                }
+            }
+        }
+        found
+    }
+}
+
+/// Elements whose content is never rendered directly.
+const NON_RENDERED: &[&str] = &["defs", "clipPath", "mask", "marker", "pattern", "symbol"];
+
+/// Settings and document-wide state used while extracting geometry.
+struct Context {
+    tol: f64,
+    stroke_to_path: bool,
+    stylesheet: Stylesheet,
+    /// Current depth of nested `<use>` instantiations
+    use_depth: Cell<usize>,
+    strict: bool,
+    /// Elements skipped so far
+    warnings: RefCell<Vec<Warning>>,
+    marking: MarkingSelector,
+    /// Marking polygons extracted since they were last taken
+    marked: RefCell<Vec<Polygon>>,
+}
+
+impl Context {
+    fn new(doc: &Document, opts: &ParseOptions) -> Self {
+        Self {
+            tol: opts.tolerances.curve,
+            stroke_to_path: opts.stroke_to_path,
+            stylesheet: Stylesheet::from_document(doc),
+            use_depth: Cell::new(0),
+            strict: opts.strict,
+            warnings: RefCell::new(Vec::new()),
+            marking: opts.marking.clone(),
+            marked: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Record that `node` was skipped or only partly read.
+    fn warn(&self, node: Node, code: Code, message: impl Into<String>) {
+        let mut element = node.tag_name().name().to_string();
+        if let Some(id) = node.attribute("id") {
+            element.push('#');
+            element.push_str(id);
+        }
+        let mut warning = Warning::new(code, element, message);
+        warning.line = Some(node.document().text_pos_at(node.range().start).row);
+        self.warnings.borrow_mut().push(warning);
+    }
+
+    /// Hand the collected warnings to `out`. In strict mode the first one
+    /// is returned as an error instead.
+    fn finish(&self, out: &mut Vec<Warning>) -> anyhow::Result<()> {
+        let mut warnings = self.warnings.take();
+        if self.strict {
+            if let Some(first) = warnings.first() {
+                anyhow::bail!("{}", first);
+            }
+        }
+        out.append(&mut warnings);
+        Ok(())
+    }
+
+    /// Numeric attribute `name` of `node`, 0 if missing. Values that are not
+    /// numbers are reported and read as 0.
+    fn number(&self, node: Node, name: &str) -> f64 {
+        let Some(value) = node.attribute(name) else {
+            return 0.0;
+        };
+        value.parse().unwrap_or_else(|_| {
+            self.warn(node, Code::InvalidAttribute, format!("invalid {} `{}`, using 0", name, value));
+            0.0
+        })
+    }
+
+    /// Look up a presentation property of `node`. The `style` attribute
+    /// takes precedence over class rules, which take precedence over a plain
+    /// presentation attribute.
+    fn property<'a>(&'a self, node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+        if let Some(style) = node.attribute("style") {
+            for decl in style.split(';') {
+                if let Some((key, value)) = decl.split_once(':') {
+                    if key.trim() == name {
+                        return Some(value.trim());
+                    }
+                }
+            }
+        }
+        self.stylesheet.lookup(node, name).or_else(|| node.attribute(name))
+    }
+
+    /// Value of an inherited property, taken from the nearest element that
+    /// sets it explicitly.
+    fn inherited_property<'a>(&'a self, node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+        node.ancestors()
+            .filter(|n| n.is_element())
+            .filter_map(|n| self.property(n, name))
+            .find(|v| *v != "inherit")
+    }
+
+    /// Effective `fill-rule` of `node`, which is inherited from its ancestors.
+    fn fill_rule(&self, node: Node) -> FillRule {
+        for n in node.ancestors().filter(|n| n.is_element()) {
+            match self.property(n, "fill-rule") {
+                Some("evenodd") => return FillRule::EvenOdd,
+                Some("nonzero") => return FillRule::NonZero,
+                _ => {}
+            }
+        }
+        FillRule::NonZero
+    }
+
+    /// False if nothing below `node` can be rendered: `display:none`, zero
+    /// opacity, or containers like `<defs>`.
+    fn renders_subtree(&self, node: Node) -> bool {
+        if NON_RENDERED.contains(&node.tag_name().name()) {
+            return false;
+        }
+        if self.property(node, "display") == Some("none") {
+            return false;
+        }
+        match self.property(node, "opacity") {
+            Some(v) => !is_zero_opacity(v),
+            None => true,
+        }
+    }
+
+    /// Effective visibility of `node`. `visibility` is inherited but can be
+    /// overridden by descendants.
+    /// Whether `node` is marking geometry, by the layer it is in or its
+    /// stroke color.
+    fn is_marking(&self, node: Node) -> bool {
+        if self.marking.is_empty() {
+            return false;
+        }
+        let in_layer = node.ancestors().filter(|n| n.has_tag_name("g")).any(|g| {
+            [g.attribute((INKSCAPE_NS, "label")), g.attribute("id")]
+                .into_iter()
+                .flatten()
+                .any(|name| self.marking.layers.iter().any(|l| l == name))
+        });
+        in_layer
+            || self
+                .inherited_property(node, "stroke")
+                .is_some_and(|stroke| self.marking.colors.iter().any(|c| c.eq_ignore_ascii_case(stroke)))
+    }
+
+    fn is_visible(&self, node: Node) -> bool {
+        for n in node.ancestors().filter(|n| n.is_element()) {
+            match self.property(n, "visibility") {
+                Some("hidden") | Some("collapse") => return false,
+                Some("visible") => return true,
+                _ => {}
+            }
+        }
+        true
+    }
+}
+
+fn is_zero_opacity(value: &str) -> bool {
+    let (num, scale) = match value.strip_suffix('%') {
+        Some(v) => (v, 100.0),
+        None => (value, 1.0),
+    };
+    num.trim().parse::<f64>().map(|v| v / scale <= 0.0).unwrap_or(false)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FillRule {
+    NonZero,
+    EvenOdd,
+}
+
+/// Classify the closed subpaths of a single path into outlines and holes
+/// according to `rule`, using containment between the subpaths. Outlines are
+/// moved to the front and wound with negative area, holes with positive area,
+/// which is the convention the placer relies on.
+fn assign_holes(subpaths: &mut Vec<Polygon>, rule: FillRule) {
+    let closed: Vec<usize> = (0..subpaths.len())
+        .filter(|&i| subpaths[i].closed && subpaths[i].points.len() >= 3)
+        .collect();
+    if closed.len() < 2 {
+        return;
+    }
+
+    let winding = |i: usize| match ring_winding(&subpaths[i].points) {
+        Winding::CounterClockwise => 1,
+        _ => -1,
+    };
+    let mut depth = vec![0usize; subpaths.len()];
+    let mut hole = vec![false; subpaths.len()];
+    for &i in &closed {
+        // probe with the first vertex that is not on the other ring, rings
+        // touching at a vertex would otherwise be ambiguous
+        let inside = |j: usize| {
+            subpaths[i]
+                .points
+                .iter()
+                .map(|p| locate_point(&subpaths[j].points, p.x, p.y))
+                .find(|&l| l != PointLocation::Boundary)
+                == Some(PointLocation::Inside)
+        };
+        let parents: Vec<usize> = closed.iter().copied().filter(|&j| j != i && inside(j)).collect();
+        depth[i] = parents.len();
+        hole[i] = match rule {
+            FillRule::EvenOdd => parents.len() % 2 == 1,
+            FillRule::NonZero => {
+                let outside: i32 = parents.iter().map(|&j| winding(j)).sum();
+                outside != 0 && outside + winding(i) == 0
+            }
+        };
+    }
+
+    for &i in &closed {
+        let area = polygon_area(&subpaths[i].points);
+        if (hole[i] && area < 0.0) || (!hole[i] && area > 0.0) {
+            subpaths[i].points.reverse();
+            subpaths[i].contour = subpaths[i].contour.as_ref().map(Contour::reversed);
+        }
+    }
+
+    let mut order = closed.clone();
+    order.sort_by_key(|&i| (hole[i], depth[i]));
+    order.extend((0..subpaths.len()).filter(|i| !closed.contains(i)));
+    let mut taken: Vec<Option<Polygon>> = subpaths.drain(..).map(Some).collect();
+    subpaths.extend(order.into_iter().filter_map(|i| taken[i].take()));
+}
+
+fn node_transform(node: Node) -> Transform {
+    let transform = node
+        .attribute("transform")
+        .map(parse_transform)
+        .unwrap_or(Transform::identity());
+    // inner <svg> elements establish a new viewport
+    if node.has_tag_name("svg") && node.parent_element().is_some() {
+        let x = length_attribute(node, "x").unwrap_or(0.0);
+        let y = length_attribute(node, "y").unwrap_or(0.0);
+        let viewport = transform.multiply(&Transform::translate(x, y));
+        return viewport.multiply(&viewbox_transform(node, None, None));
+    }
+    transform
+}
+
+/// Map from the coordinates of `node` to those of the document, through the
+/// transforms of the node and all its ancestors. Used to read back drawn
+/// output, where the viewBox of the document already is in drawing units.
+pub fn document_transform(node: Node) -> impl Fn(f64, f64) -> (f64, f64) {
+    let ancestors: Vec<Node> = node.ancestors().filter(|n| n.is_element()).collect();
+    let transform = ancestors
+        .iter()
+        .rev()
+        .fold(Transform::identity(), |t, &a| t.multiply(&node_transform(a)));
+    move |x, y| transform.apply(x, y)
+}
+
+/// Parse a length attribute in user units. Percentages are not resolved.
+fn length_attribute(node: Node, name: &str) -> Option<f64> {
+    parse_length(node.attribute(name)?)
+}
+
+fn parse_length(value: &str) -> Option<f64> {
+    let value = value.trim();
+    if value.ends_with('%') {
+        return None;
+    }
+    let number = value.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    number.parse::<f64>().ok()
+}
+
+/// Transform mapping the `viewBox` of `node` onto a viewport of the given
+/// size, honoring `preserveAspectRatio`. Missing viewport dimensions fall
+/// back to the element's width/height and then to the viewBox size.
+fn viewbox_transform(node: Node, width: Option<f64>, height: Option<f64>) -> Transform {
+    let Some(view_box) = node.attribute("viewBox") else {
+        return Transform::identity();
+    };
+    let nums: Vec<f64> = view_box
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .filter_map(|s| s.parse::<f64>().ok())
+        .collect();
+    let [vx, vy, vw, vh] = nums[..] else {
+        return Transform::identity();
+    };
+    if vw <= 0.0 || vh <= 0.0 {
+        return Transform::identity();
+    }
+    let width = width.or_else(|| length_attribute(node, "width")).unwrap_or(vw);
+    let height = height.or_else(|| length_attribute(node, "height")).unwrap_or(vh);
+    let sx = width / vw;
+    let sy = height / vh;
+
+    let par = node.attribute("preserveAspectRatio").unwrap_or("xMidYMid meet");
+    let mut tokens = par.split_whitespace();
+    let align = tokens.next().unwrap_or("xMidYMid");
+    if align == "none" {
+        return Transform([sx, 0.0, 0.0, sy, -vx * sx, -vy * sy]);
+    }
+    let scale = if tokens.next() == Some("slice") {
+        sx.max(sy)
+    } else {
+        sx.min(sy)
+    };
+    let offset = |key: &str, available: f64, used: f64| {
+        if align.contains(&format!("{}Min", key)) {
+            0.0
+        } else if align.contains(&format!("{}Max", key)) {
+            available - used
+        } else {
+            (available - used) / 2.0
+        }
+    };
+    let tx = offset("x", width, vw * scale);
+    let ty = offset("Y", height, vh * scale);
+    Transform([scale, 0.0, 0.0, scale, tx - vx * scale, ty - vy * scale])
+}
+
+const XLINK_NS: &str = "http://www.w3.org/1999/xlink";
+
+/// Maximum nesting of `<use>` references, guarding against cycles.
+const MAX_USE_DEPTH: usize = 16;
+
+/// Element referenced by a `<use>` element.
+fn use_target<'a, 'input>(node: Node<'a, 'input>) -> Option<Node<'a, 'input>> {
+    let href = node
+        .attribute("href")
+        .or_else(|| node.attribute((XLINK_NS, "href")))?;
+    let id = href.strip_prefix('#')?;
+    node.document()
+        .descendants()
+        .find(|n| n.attribute("id") == Some(id))
+}
+
+/// Instantiate the element referenced by `node` (a `<use>`). `transform`
+/// already includes the transform of the `<use>` element itself.
+fn extract_use(
+    node: Node,
+    transform: Transform,
+    ctx: &Context,
+    output: &mut Vec<Polygon>,
+) -> anyhow::Result<()> {
+    let Some(target) = use_target(node) else {
+        return Ok(());
+    };
+    if node.ancestors().any(|a| a == target) || ctx.use_depth.get() >= MAX_USE_DEPTH {
+        return Ok(());
+    }
+    let x = length_attribute(node, "x").unwrap_or(0.0);
+    let y = length_attribute(node, "y").unwrap_or(0.0);
+    let transform = transform.multiply(&Transform::translate(x, y));
+
+    ctx.use_depth.set(ctx.use_depth.get() + 1);
+    let result = if target.has_tag_name("symbol") {
+        let viewport = viewbox_transform(
+            target,
+            length_attribute(node, "width"),
+            length_attribute(node, "height"),
+        );
+        let transform = transform.multiply(&viewport);
+        target
+            .children()
+            .filter(|n| n.is_element())
+            .try_for_each(|child| extract_node_polygons(child, transform, ctx, output))
+    } else {
+        extract_node_polygons(target, transform, ctx, output)
+    };
+    ctx.use_depth.set(ctx.use_depth.get() - 1);
+    result
 }
 
 fn extract_node_polygons(
     node: Node,
     transform: Transform,
-    tol: f64,
+    ctx: &Context,
     output: &mut Vec<Polygon>,
 ) -> anyhow::Result<()> {
-    let node_transform = node
-        .attribute("transform")
-        .map(parse_transform)
-        .unwrap_or(Transform::identity());
-    let transform = transform.multiply(&node_transform);
+    if !ctx.renders_subtree(node) {
+        return Ok(());
+    }
+    let transform = transform.multiply(&node_transform(node));
+    if node.has_tag_name("use") {
+        return extract_use(node, transform, ctx, output);
+    }
+    extract_shape(node, transform, ctx, output);
+    for child in node.children().filter(|n| n.is_element()) {
+        extract_node_polygons(child, transform, ctx, output)?;
+    }
+    Ok(())
+}
+
+/// Walk the tree below `node` and turn every shape element into its own part.
+fn extract_element_parts(node: Node, transform: Transform, ctx: &Context, parts: &mut Vec<Vec<Polygon>>) {
+    if !ctx.renders_subtree(node) {
+        return;
+    }
+    let transform = transform.multiply(&node_transform(node));
+    let mut polys = Vec::new();
+    if node.has_tag_name("use") {
+        // a referenced symbol or group forms a single part
+        let _ = extract_use(node, transform, ctx, &mut polys);
+        if !polys.is_empty() {
+            parts.push(polys);
+        }
+        return;
+    }
+    extract_shape(node, transform, ctx, &mut polys);
+    if !polys.is_empty() {
+        parts.push(polys);
+    }
+    for child in node.children().filter(|n| n.is_element()) {
+        extract_element_parts(child, transform, ctx, parts);
+    }
+}
+
+/// Append the polygons of a single shape element, ignoring its children.
+/// `transform` must already include the element's own transform.
+fn extract_shape(node: Node, transform: Transform, ctx: &Context, output: &mut Vec<Polygon>) {
+    if !ctx.is_visible(node) {
+        return;
+    }
+    let metadata = node_metadata(node);
+    let start = output.len();
 
     match node.tag_name().name() {
         "path" => {
             if let Some(d) = node.attribute("d") {
-                for (closed, pts) in approximate_path(d, tol) {
+                if let Some(Err(e)) = svgtypes::PathParser::from(d).find(|s| s.is_err()) {
+                    ctx.warn(node, Code::InvalidGeometry, format!("invalid path data, skipped: {}", e));
+                    return;
+                }
+                let flattened = approximate_path(d, ctx.tol);
+                let mut contours = path_contours(d);
+                // only keep the curves if both parsers agree on the subpaths
+                if contours.len() != flattened.len() {
+                    contours.clear();
+                }
+                let mut contours = contours.into_iter().map(|c| c.transform(transform.0));
+                let mut subpaths = Vec::new();
+                for (closed, pts) in flattened {
                     let mapped = pts
                         .into_iter()
                         .map(|(x, y)| {
@@ -139,54 +948,51 @@ fn extract_node_polygons(
                             Point { x, y }
                         })
                         .collect();
-                    output.push(Polygon {
+                    subpaths.push(Polygon {
                         id: 0,
                         points: mapped,
                         closed,
+                        metadata: metadata.clone(),
+                        contour: contours.next(),
                     });
                 }
+                assign_holes(&mut subpaths, ctx.fill_rule(node));
+                output.append(&mut subpaths);
             }
         }
         "polygon" | "polyline" => {
             if let Some(points_str) = node.attribute("points") {
                 let mut pts = Vec::new();
+                let mut invalid = 0;
                 for pair in points_str.split_whitespace() {
                     let mut nums = pair.split(',');
                     if let (Some(x), Some(y)) = (nums.next(), nums.next()) {
                         if let (Ok(x), Ok(y)) = (x.parse::<f64>(), y.parse::<f64>()) {
                             let (x, y) = transform.apply(x, y);
                             pts.push(Point { x, y });
+                            continue;
                         }
                     }
+                    invalid += 1;
+                }
+                if invalid > 0 {
+                    ctx.warn(node, Code::InvalidGeometry, format!("ignored {} invalid point(s)", invalid));
                 }
+                let closed = node.tag_name().name() == "polygon";
                 output.push(Polygon {
                     id: 0,
+                    contour: contour::polyline(&pts, closed),
                     points: pts,
-                    closed: node.tag_name().name() == "polygon",
+                    closed,
+                    metadata: metadata.clone(),
                 });
             }
         }
         "rect" => {
-            let x = node
-                .attribute("x")
-                .unwrap_or("0")
-                .parse::<f64>()
-                .unwrap_or(0.0);
-            let y = node
-                .attribute("y")
-                .unwrap_or("0")
-                .parse::<f64>()
-                .unwrap_or(0.0);
-            let w = node
-                .attribute("width")
-                .unwrap_or("0")
-                .parse::<f64>()
-                .unwrap_or(0.0);
-            let h = node
-                .attribute("height")
-                .unwrap_or("0")
-                .parse::<f64>()
-                .unwrap_or(0.0);
+            let x = ctx.number(node, "x");
+            let y = ctx.number(node, "y");
+            let w = ctx.number(node, "width");
+            let h = ctx.number(node, "height");
             let pts = vec![
                 Point { x, y },
                 Point { x: x + w, y },
@@ -202,26 +1008,16 @@ fn extract_node_polygons(
                 .collect();
             output.push(Polygon {
                 id: 0,
+                contour: contour::polyline(&pts, true),
                 points: pts,
                 closed: true,
+                metadata: metadata.clone(),
             });
         }
         "circle" => {
-            let cx = node
-                .attribute("cx")
-                .unwrap_or("0")
-                .parse::<f64>()
-                .unwrap_or(0.0);
-            let cy = node
-                .attribute("cy")
-                .unwrap_or("0")
-                .parse::<f64>()
-                .unwrap_or(0.0);
-            let r = node
-                .attribute("r")
-                .unwrap_or("0")
-                .parse::<f64>()
-                .unwrap_or(0.0);
+            let cx = ctx.number(node, "cx");
+            let cy = ctx.number(node, "cy");
+            let r = ctx.number(node, "r");
             let segments = 32;
             let mut pts = Vec::new();
             for i in 0..segments {
@@ -230,33 +1026,20 @@ fn extract_node_polygons(
                 let (x, y) = transform.apply(x, y);
                 pts.push(Point { x, y });
             }
+            let center = Point { x: cx, y: cy };
             output.push(Polygon {
                 id: 0,
                 points: pts,
                 closed: true,
+                metadata: metadata.clone(),
+                contour: Some(contour::circle(center, r).transform(transform.0)),
             });
         }
         "ellipse" => {
-            let cx = node
-                .attribute("cx")
-                .unwrap_or("0")
-                .parse::<f64>()
-                .unwrap_or(0.0);
-            let cy = node
-                .attribute("cy")
-                .unwrap_or("0")
-                .parse::<f64>()
-                .unwrap_or(0.0);
-            let rx = node
-                .attribute("rx")
-                .unwrap_or("0")
-                .parse::<f64>()
-                .unwrap_or(0.0);
-            let ry = node
-                .attribute("ry")
-                .unwrap_or("0")
-                .parse::<f64>()
-                .unwrap_or(0.0);
+            let cx = ctx.number(node, "cx");
+            let cy = ctx.number(node, "cy");
+            let rx = ctx.number(node, "rx");
+            let ry = ctx.number(node, "ry");
             let segments = 32;
             let mut pts = Vec::new();
             for i in 0..segments {
@@ -265,42 +1048,98 @@ fn extract_node_polygons(
                 let (x, y) = transform.apply(x, y);
                 pts.push(Point { x, y });
             }
+            let center = Point { x: cx, y: cy };
             output.push(Polygon {
                 id: 0,
                 points: pts,
                 closed: true,
+                metadata: metadata.clone(),
+                contour: Some(contour::ellipse(center, rx, ry).transform(transform.0)),
             });
         }
         "line" => {
-            if let (Some(x1), Some(y1), Some(x2), Some(y2)) = (
-                node.attribute("x1"),
-                node.attribute("y1"),
-                node.attribute("x2"),
-                node.attribute("y2"),
-            ) {
-                if let (Ok(x1), Ok(y1), Ok(x2), Ok(y2)) = (
-                    x1.parse::<f64>(),
-                    y1.parse::<f64>(),
-                    x2.parse::<f64>(),
-                    y2.parse::<f64>(),
-                ) {
+            let coord = |name: &str| node.attribute(name).and_then(|v| v.parse::<f64>().ok());
+            match (coord("x1"), coord("y1"), coord("x2"), coord("y2")) {
+                (Some(x1), Some(y1), Some(x2), Some(y2)) => {
                     let (x1, y1) = transform.apply(x1, y1);
                     let (x2, y2) = transform.apply(x2, y2);
+                    let pts = vec![Point { x: x1, y: y1 }, Point { x: x2, y: y2 }];
                     output.push(Polygon {
                         id: 0,
-                        points: vec![Point { x: x1, y: y1 }, Point { x: x2, y: y2 }],
+                        contour: contour::polyline(&pts, false),
+                        points: pts,
                         closed: false,
+                        metadata: metadata.clone(),
                     });
                 }
+                _ => ctx.warn(node, Code::InvalidGeometry, "missing or invalid coordinates, skipped"),
             }
         }
+        "text" | "image" => ctx.warn(node, Code::UnsupportedElement, "unsupported element, skipped"),
         _ => {}
     }
 
-    for child in node.children().filter(|n| n.is_element()) {
-        extract_node_polygons(child, transform, tol, output)?;
+    if ctx.is_marking(node) {
+        let shape = output.split_off(start);
+        ctx.marked.borrow_mut().extend(shape);
+        return;
+    }
+    if ctx.stroke_to_path {
+        let mut shape = output.split_off(start);
+        expand_strokes(node, transform, ctx, &mut shape);
+        output.append(&mut shape);
     }
-    Ok(())
+}
+
+/// Replace the open polygons of a shape by the outline of their stroke, so
+/// artwork drawn with strokes nests by its actual footprint.
+fn expand_strokes(node: Node, transform: Transform, ctx: &Context, polys: &mut Vec<Polygon>) {
+    match ctx.inherited_property(node, "stroke") {
+        None | Some("none") | Some("transparent") => return,
+        Some(_) => {}
+    }
+    let width = ctx
+        .inherited_property(node, "stroke-width")
+        .and_then(parse_length)
+        .unwrap_or(1.0);
+    // strokes scale with the element
+    let m = transform.0;
+    let width = width * (m[0] * m[3] - m[1] * m[2]).abs().sqrt();
+    if width <= 0.0 {
+        return;
+    }
+    let join = match ctx.inherited_property(node, "stroke-linejoin") {
+        Some("round") => LineJoin::Round,
+        Some("bevel") => LineJoin::Bevel,
+        _ => LineJoin::Miter(
+            ctx.inherited_property(node, "stroke-miterlimit")
+                .and_then(|v| v.parse::<f64>().ok())
+                .unwrap_or(4.0),
+        ),
+    };
+    let cap = match ctx.inherited_property(node, "stroke-linecap") {
+        Some("round") => LineCap::Round,
+        Some("square") => LineCap::Square,
+        _ => LineCap::Butt,
+    };
+
+    let mut result = Vec::with_capacity(polys.len());
+    for poly in polys.drain(..) {
+        if poly.closed {
+            result.push(poly);
+            continue;
+        }
+        for ring in offset_polyline(&poly.points, width / 2.0, join, cap, ctx.tol) {
+            result.push(Polygon {
+                id: 0,
+                contour: contour::polyline(&ring, true),
+                points: ring,
+                closed: true,
+                metadata: poly.metadata.clone(),
+            });
+        }
+    }
+    *polys = result;
 }
 
 #[cfg(test)]
@@ -315,6 +1154,156 @@ mod tests {
         assert_eq!(polys[0].points.len(), 4);
     }
 
+    #[test]
+    fn parse_element_metadata() {
+        let svg = r##"<svg xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape"><rect id="r1" inkscape:label="Bracket" data-order="42" class="x" width="1" height="1"/></svg>"##;
+        let polys = polygons_from_str(svg, false, crate::geometry::CURVE_TOLERANCE).unwrap();
+        let meta = &polys[0].metadata;
+        assert_eq!(meta.len(), 3);
+        assert_eq!(meta["id"], "r1");
+        assert_eq!(meta["inkscape:label"], "Bracket");
+        assert_eq!(meta["data-order"], "42");
+    }
+
+    #[test]
+    fn parts_per_group_and_element() {
+        let svg = r#"<svg>
+            <g id="a"><rect width="1" height="1"/><rect x="2" width="1" height="1"/></g>
+            <g id="b" transform="translate(10,0)"><circle r="1"/></g>
+            <rect width="3" height="3"/>
+        </svg>"#;
+        let opts = |part_per: PartMode| ParseOptions {
+            part_per,
+            ..Default::default()
+        };
+        let groups = parts_from_str(svg, &opts(PartMode::Group), &mut Vec::new()).unwrap();
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups[0].polygons.len(), 2);
+        assert_eq!(groups[0].metadata["id"], "a");
+        assert_eq!(groups[1].metadata["id"], "b");
+        let elements = parts_from_str(svg, &opts(PartMode::Element), &mut Vec::new()).unwrap();
+        assert_eq!(elements.len(), 4);
+        let file = parts_from_str(svg, &opts(PartMode::File), &mut Vec::new()).unwrap();
+        assert_eq!(file.len(), 1);
+        assert_eq!(file[0].polygons.len(), 4);
+    }
+
+    #[test]
+    fn evenodd_path_gets_hole() {
+        // both subpaths wound the same way
+        let d = "M0,0 L5,0 L5,5 L0,5 Z M1,1 L4,1 L4,4 L1,4 Z";
+        let tol = crate::geometry::CURVE_TOLERANCE;
+        let svg = format!(r#"<svg><path fill-rule="evenodd" d="{}"/></svg>"#, d);
+        let polys = polygons_from_str(&svg, false, tol).unwrap();
+        assert!(polygon_area(&polys[0].points) < 0.0);
+        assert!(polygon_area(&polys[1].points) > 0.0);
+
+        let svg = format!(r#"<svg><path d="{}"/></svg>"#, d);
+        let polys = polygons_from_str(&svg, false, tol).unwrap();
+        assert!(polygon_area(&polys[1].points) < 0.0);
+    }
+
+    #[test]
+    fn hole_listed_first_is_moved_behind_outline() {
+        let svg = r#"<svg><g style="fill-rule: evenodd"><path d="M1,1 L4,1 L4,4 L1,4 Z M0,0 L5,0 L5,5 L0,5 Z"/></g></svg>"#;
+        let polys = polygons_from_str(svg, false, crate::geometry::CURVE_TOLERANCE).unwrap();
+        assert!((polygon_area(&polys[0].points) + 25.0).abs() < 1e-6);
+        assert!((polygon_area(&polys[1].points) - 9.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn skips_invisible_elements() {
+        let svg = r#"<svg>
+            <style>/* guides */ .guide, .other { display: none } .ghost { opacity: 0 }</style>
+            <defs><rect width="9" height="9"/></defs>
+            <rect width="1" height="1"/>
+            <rect class="guide" width="2" height="2"/>
+            <rect class="ghost" width="2" height="2"/>
+            <rect style="visibility:hidden" width="3" height="3"/>
+            <g visibility="hidden"><rect visibility="visible" width="4" height="4"/><rect width="5" height="5"/></g>
+            <g style="display:none"><rect width="6" height="6"/></g>
+            <rect opacity="0%" width="7" height="7"/>
+        </svg>"#;
+        let polys = polygons_from_str(svg, false, crate::geometry::CURVE_TOLERANCE).unwrap();
+        assert_eq!(polys.len(), 2);
+        assert!((polys[1].points[2].x - 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn nested_svg_viewport() {
+        let svg = r#"<svg><svg x="10" y="20" width="20" height="20" viewBox="0 0 10 10"><rect width="10" height="10"/></svg></svg>"#;
+        let polys = polygons_from_str(svg, false, crate::geometry::CURVE_TOLERANCE).unwrap();
+        let p = polys[0].points[2];
+        assert!((p.x - 30.0).abs() < 1e-9 && (p.y - 40.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn symbol_instantiated_by_use() {
+        let svg = r##"<svg xmlns:xlink="http://www.w3.org/1999/xlink">
+            <defs><symbol id="s" viewBox="0 0 1 1"><rect width="1" height="1"/></symbol></defs>
+            <use xlink:href="#s" x="5" y="5" width="2" height="2"/>
+            <use href="#r"/>
+            <rect id="r" x="1" width="1" height="1"/>
+        </svg>"##;
+        let polys = polygons_from_str(svg, false, crate::geometry::CURVE_TOLERANCE).unwrap();
+        assert_eq!(polys.len(), 3);
+        let p = polys[0].points[2];
+        assert!((p.x - 7.0).abs() < 1e-9 && (p.y - 7.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn stroke_to_path_outlines_open_paths() {
+        let svg = r#"<svg><g stroke="black"><line x1="0" y1="0" x2="10" y2="0" stroke-width="2"/></g><rect width="1" height="1" stroke="black"/></svg>"#;
+        let opts = ParseOptions {
+            stroke_to_path: true,
+            ..Default::default()
+        };
+        let (polys, _) = parse_polygons(svg, &opts, &mut Vec::new()).unwrap();
+        assert_eq!(polys.len(), 2);
+        assert!(polys[0].closed);
+        let b = crate::geometry::get_polygon_bounds(&polys[0].points).unwrap();
+        assert!((b.width - 10.0).abs() < 1e-6 && (b.height - 2.0).abs() < 1e-6);
+        assert!(polygon_area(&polys[0].points) < 0.0);
+        assert_eq!(polys[1].points.len(), 4);
+    }
+
+    #[test]
+    fn marking_is_kept_apart_from_cuts() {
+        let svg = r##"<svg xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape">
+            <g id="a"><rect x="10" y="10" width="5" height="5"/><line x1="11" y1="12" x2="14" y2="12" stroke="#F00"/></g>
+            <g id="b"><rect width="2" height="2"/><g inkscape:label="Engrave"><circle cx="1" cy="1" r="0.5"/></g></g>
+        </svg>"##;
+        let opts = ParseOptions {
+            part_per: PartMode::Group,
+            marking: MarkingSelector {
+                layers: vec!["Engrave".to_string()],
+                colors: vec!["#f00".to_string()],
+            },
+            ..Default::default()
+        };
+        let parts = parts_from_str(svg, &opts, &mut Vec::new()).unwrap();
+        assert_eq!(parts.len(), 2);
+        assert!(parts.iter().all(|p| p.polygons.len() == 1 && p.marking.len() == 1));
+        // marking moves with the part to the origin
+        assert_eq!(parts[0].marking[0].points[0], Point { x: 1.0, y: 2.0 });
+    }
+
+    #[test]
+    fn path_keeps_exact_arcs() {
+        let svg = r#"<svg><path d="M 0 0 L 10 0 A 5 5 0 0 1 0 0 Z" transform="translate(1 1)"/><circle cx="5" cy="5" r="2"/></svg>"#;
+        let polys = polygons_from_str(svg, false, crate::geometry::CURVE_TOLERANCE).unwrap();
+        let path = polys[0].contour.as_ref().unwrap();
+        assert!(path.closed);
+        assert_eq!(path.start, Point { x: 1.0, y: 1.0 });
+        let Segment::Arc { center, radius, .. } = path.segments[1] else {
+            panic!("expected an arc");
+        };
+        assert_eq!(center, Point { x: 6.0, y: 1.0 });
+        assert!((radius - 5.0).abs() < 1e-9);
+        let circle = polys[1].contour.as_ref().unwrap();
+        assert!(matches!(circle.segments[0], Segment::Arc { radius, .. } if radius == 2.0));
+    }
+
     #[test]
     fn merge_lines_option() {
         let svg = "<svg><line x1='0' y1='0' x2='1' y2='0'/><line x1='1' y1='0' x2='0' y2='0'/></svg>";
@@ -397,4 +1386,28 @@ mod tests {
         let (x, y) = t.apply(2.0, 0.0);
         assert!((x - 1.0).abs() < 1e-6 && (y - 1.0).abs() < 1e-6);
     }
+
+    #[test]
+    fn malformed_elements_are_skipped_with_warnings() {
+        let svg = r#"<svg>
+            <rect width="2" height="2"/>
+            <path id="bad" d="M0,0 L5,0 Q"/>
+            <circle r="abc"/>
+        </svg>"#;
+        let mut warnings = Vec::new();
+        let parts = parts_from_str(svg, &ParseOptions::default(), &mut warnings).unwrap();
+        // the circle is kept with radius 0, the path is dropped
+        assert_eq!(parts[0].polygons.len(), 2);
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0].element, "path#bad");
+        assert_eq!(warnings[0].line, Some(3));
+        assert!(warnings[1].message.contains("invalid r"));
+
+        let strict = ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let err = parts_from_str(svg, &strict, &mut Vec::new()).unwrap_err();
+        assert!(err.to_string().contains("path#bad"));
+    }
 }
@@ -0,0 +1,100 @@
+//! Tabs, short pieces of a part's outline that are left uncut so the part
+//! stays attached to the sheet until the job is finished.
+//!
+//! Tabs only change the emitted cut geometry: the outline is split into open
+//! pieces with gaps between them, spaced evenly along its length.
+
+use serde::{Deserialize, Serialize};
+
+use crate::svg_parser::Point;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Tabs {
+    /// Tabs per outline
+    pub count: usize,
+    /// Length of outline every tab leaves uncut
+    pub width: f64,
+}
+
+impl Tabs {
+    /// Open pieces to cut of the closed `ring`, leaving the tabs out. The
+    /// first tab is centered half a spacing after the first vertex. Returns
+    /// `None` if the tabs would take up the whole ring.
+    pub fn split(&self, ring: &[Point]) -> Option<Vec<Vec<Point>>> {
+        let n = ring.len();
+        if self.count == 0 || n < 2 {
+            return None;
+        }
+        // distance along the ring at every vertex
+        let mut along = Vec::with_capacity(n);
+        let mut length = 0.0;
+        for i in 0..n {
+            along.push(length);
+            let (a, b) = (ring[i], ring[(i + 1) % n]);
+            length += (b.x - a.x).hypot(b.y - a.y);
+        }
+        let spacing = length / self.count as f64;
+        if self.width >= spacing {
+            return None;
+        }
+        let at = |d: f64| {
+            let d = d.rem_euclid(length);
+            let i = along.partition_point(|&s| s <= d).saturating_sub(1);
+            let (a, b) = (ring[i], ring[(i + 1) % n]);
+            let edge = (b.x - a.x).hypot(b.y - a.y);
+            let t = if edge > 0.0 { (d - along[i]) / edge } else { 0.0 };
+            Point {
+                x: a.x + (b.x - a.x) * t,
+                y: a.y + (b.y - a.y) * t,
+            }
+        };
+        let pieces = (0..self.count)
+            .map(|k| {
+                let start = (k as f64 + 0.5) * spacing + self.width / 2.0;
+                let end = start + spacing - self.width;
+                let mut piece = vec![at(start)];
+                // vertices passed on the way, the ring taken twice to cover
+                // pieces running past the first vertex
+                for lap in 0..2 {
+                    for (i, &s) in along.iter().enumerate() {
+                        let d = s + lap as f64 * length;
+                        if d > start && d < end {
+                            piece.push(ring[i]);
+                        }
+                    }
+                }
+                piece.push(at(end));
+                piece
+            })
+            .collect();
+        Some(pieces)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leaves_evenly_spaced_gaps() {
+        let square = [
+            Point { x: 0.0, y: 0.0 },
+            Point { x: 10.0, y: 0.0 },
+            Point { x: 10.0, y: 10.0 },
+            Point { x: 0.0, y: 10.0 },
+        ];
+        let pieces = Tabs { count: 4, width: 2.0 }.split(&square).unwrap();
+        assert_eq!(pieces.len(), 4);
+        // a tab in the middle of every edge, the last piece running past the
+        // first vertex
+        let expected = [[(6.0, 0.0), (10.0, 0.0), (10.0, 4.0)], [(0.0, 4.0), (0.0, 0.0), (4.0, 0.0)]];
+        for (piece, points) in [&pieces[0], &pieces[3]].into_iter().zip(expected) {
+            assert_eq!(piece.len(), 3);
+            for (p, (x, y)) in piece.iter().zip(points) {
+                assert!((p.x - x).abs() < 1e-9 && (p.y - y).abs() < 1e-9, "{:?}", piece);
+            }
+        }
+
+        assert!(Tabs { count: 4, width: 10.0 }.split(&square).is_none());
+    }
+}
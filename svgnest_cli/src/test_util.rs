@@ -0,0 +1,16 @@
+//! Fixtures shared by the unit tests.
+
+use crate::svg_parser::{Point, Polygon};
+
+/// Open `w` by `h` rectangle with its top left corner at the origin.
+pub fn rect(w: f64, h: f64) -> Polygon {
+    Polygon {
+        points: vec![
+            Point { x: 0.0, y: 0.0 },
+            Point { x: w, y: 0.0 },
+            Point { x: w, y: h },
+            Point { x: 0.0, y: h },
+        ],
+        ..Default::default()
+    }
+}
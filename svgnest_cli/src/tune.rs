@@ -0,0 +1,137 @@
+//! Choosing the settings of the genetic algorithm for a job by trying a few
+//! on it. Every setting gets a short pilot run on the actual parts and bin,
+//! and the one improving the layout fastest is kept for the full run.
+
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+
+use crate::config::NestingConfig;
+use crate::ga::GeneticAlgorithm;
+use crate::part::Part;
+use crate::svg_parser::Polygon;
+
+/// Generations of every pilot run
+pub const PILOT_GENERATIONS: usize = 5;
+
+const POPULATION_SIZES: [usize; 3] = [10, 20, 40];
+const MUTATION_RATES: [usize; 2] = [5, 15];
+
+/// Outcome of a pilot run.
+#[derive(Debug, Clone)]
+pub struct Trial {
+    /// Settings of the run
+    pub config: NestingConfig,
+    /// Fitness of the best individual of the initial population
+    pub initial: f64,
+    /// Fitness of the best individual at the end of the run
+    pub fitness: f64,
+    pub elapsed: Duration,
+}
+
+impl Trial {
+    /// Fitness gained per second of the run, 0 unless both fitnesses are
+    /// finite.
+    pub fn gain_per_second(&self) -> f64 {
+        if !(self.initial.is_finite() && self.fitness.is_finite()) {
+            return 0.0;
+        }
+        (self.initial - self.fitness) / self.elapsed.as_secs_f64().max(1e-6)
+    }
+}
+
+/// Settings tried on top of `base`: every combination of a few population
+/// sizes and mutation rates with either the configured rotations or, if
+/// there are more than two, every other one of them. Rotations not in the
+/// configuration are never tried, as they may be ruled out by the material.
+pub fn candidates(base: &NestingConfig) -> Vec<NestingConfig> {
+    let mut rotation_sets = vec![(base.rotations.clone(), base.rotation_weights.clone())];
+    if base.rotations.len() > 2 {
+        let every_other = |v: &[f64]| v.iter().step_by(2).copied().collect::<Vec<f64>>();
+        let weights = every_other(&base.rotation_weights);
+        // weights of the kept angles may all be 0
+        let weights = if weights.iter().any(|&w| w > 0.0) { weights } else { Vec::new() };
+        rotation_sets.push((every_other(&base.rotations), weights));
+    }
+    let mut configs = Vec::new();
+    for (rotations, rotation_weights) in &rotation_sets {
+        for population_size in POPULATION_SIZES {
+            for mutation_rate in MUTATION_RATES {
+                configs.push(NestingConfig {
+                    population_size,
+                    elitism: base.elitism.min(population_size),
+                    mutation_rate,
+                    rotations: rotations.clone(),
+                    rotation_weights: rotation_weights.clone(),
+                    ..base.clone()
+                });
+            }
+        }
+    }
+    configs
+}
+
+/// Run every candidate setting for `generations` generations on `parts` and
+/// return the settings with the best fitness gained per second, ties going to
+/// the lower fitness, together with all trials in the order they ran.
+pub fn auto_tune(
+    parts: &[Part],
+    bin: &Polygon,
+    base: &NestingConfig,
+    generations: usize,
+) -> Result<(NestingConfig, Vec<Trial>)> {
+    let mut trials = Vec::new();
+    for config in candidates(base) {
+        let start = Instant::now();
+        let mut ga = GeneticAlgorithm::new(parts, bin, config.clone())?;
+        ga.evolve(generations);
+        let elapsed = start.elapsed();
+        let initial = ga.stats().first().map_or(f64::INFINITY, |s| s.best);
+        let fitness = ga.best().map_or(f64::INFINITY, |b| b.fitness);
+        trials.push(Trial {
+            config,
+            initial,
+            fitness,
+            elapsed,
+        });
+    }
+    let best = trials
+        .iter()
+        .max_by(|a, b| {
+            a.gain_per_second()
+                .total_cmp(&b.gain_per_second())
+                .then(b.fitness.total_cmp(&a.fitness))
+        })
+        .map(|t| t.config.clone())
+        .ok_or_else(|| anyhow::anyhow!("no settings to try"))?;
+    Ok((best, trials))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::rect;
+
+    #[test]
+    fn tries_only_configured_rotations() {
+        let base = NestingConfig::builder().rotations(&[0.0, 90.0, 180.0, 270.0]).build().unwrap();
+        let configs = candidates(&base);
+        assert_eq!(configs.len(), 12);
+        assert!(configs.iter().any(|c| c.rotations == [0.0, 180.0]));
+        assert!(configs.iter().all(|c| c.rotations.iter().all(|a| base.rotations.contains(a))));
+
+        let fixed = NestingConfig::builder().rotations(&[0.0]).build().unwrap();
+        assert_eq!(candidates(&fixed).len(), 6);
+    }
+
+    #[test]
+    fn picks_one_of_the_trials() {
+        let parts = vec![Part::new(vec![rect(3.0, 2.0)]), Part::new(vec![rect(2.0, 2.0)])];
+        let bin = rect(10.0, 10.0);
+        let base = NestingConfig::builder().rotations(&[0.0, 90.0]).seed(Some(1)).build().unwrap();
+        let (best, trials) = auto_tune(&parts, &bin, &base, 1).unwrap();
+        assert_eq!(trials.len(), 6);
+        assert!(trials.iter().any(|t| t.config == best));
+        assert_eq!(best.seed, Some(1));
+    }
+}
@@ -0,0 +1,207 @@
+//! Conditions worth telling the user about that do not stop a run: input
+//! that was skipped or changed, parts that cannot be placed, features that
+//! are unavailable. Every warning carries a stable code, so CI pipelines can
+//! fail on the conditions they care about with `--fail-on`.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// What a warning is about. Codes are kept across releases; they are
+/// written in `SCREAMING_SNAKE_CASE`, e.g. `OPEN_CONTOUR_CLOSED`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Code {
+    /// An element or entity the parser does not read, skipped
+    UnsupportedElement,
+    /// Geometry that could not be read, e.g. path data that does not parse,
+    /// skipped in whole or in part
+    InvalidGeometry,
+    /// A numeric attribute that is not a number, read as 0
+    InvalidAttribute,
+    /// A contour below the minimum feature size or area, dropped
+    FeatureDropped,
+    /// An open contour closed with a straight segment
+    OpenContourClosed,
+    /// An open contour dropped
+    OpenContourDropped,
+    /// An open contour replaced by an outline around it
+    OpenContourBuffered,
+    /// A part that fits the bin at none of the rotations
+    PartUnplaceable,
+    /// A part without a usable outline, e.g. one of fewer than 3 points or
+    /// with coordinates that are not finite, left unplaced
+    DegeneratePart,
+    /// NFPs asked to be generated on the GPU generated on the CPU
+    GpuUnavailable,
+    /// A renamed flag given by its old name
+    DeprecatedFlag,
+    /// Anything else, including warnings written before codes were added
+    #[default]
+    Other,
+}
+
+impl Code {
+    pub const ALL: [Code; 12] = [
+        Code::UnsupportedElement,
+        Code::InvalidGeometry,
+        Code::InvalidAttribute,
+        Code::FeatureDropped,
+        Code::OpenContourClosed,
+        Code::OpenContourDropped,
+        Code::OpenContourBuffered,
+        Code::PartUnplaceable,
+        Code::DegeneratePart,
+        Code::GpuUnavailable,
+        Code::DeprecatedFlag,
+        Code::Other,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Code::UnsupportedElement => "UNSUPPORTED_ELEMENT",
+            Code::InvalidGeometry => "INVALID_GEOMETRY",
+            Code::InvalidAttribute => "INVALID_ATTRIBUTE",
+            Code::FeatureDropped => "FEATURE_DROPPED",
+            Code::OpenContourClosed => "OPEN_CONTOUR_CLOSED",
+            Code::OpenContourDropped => "OPEN_CONTOUR_DROPPED",
+            Code::OpenContourBuffered => "OPEN_CONTOUR_BUFFERED",
+            Code::PartUnplaceable => "PART_UNPLACEABLE",
+            Code::DegeneratePart => "DEGENERATE_PART",
+            Code::GpuUnavailable => "GPU_UNAVAILABLE",
+            Code::DeprecatedFlag => "DEPRECATED_FLAG",
+            Code::Other => "OTHER",
+        }
+    }
+
+    /// How serious the condition is. Input changed as the options asked for
+    /// is only a note.
+    pub fn severity(self) -> Severity {
+        match self {
+            Code::FeatureDropped | Code::OpenContourClosed | Code::OpenContourDropped | Code::OpenContourBuffered => {
+                Severity::Note
+            }
+            _ => Severity::Warning,
+        }
+    }
+}
+
+impl fmt::Display for Code {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Code {
+    type Err = String;
+
+    /// Parse a code, ignoring case and reading `-` as `_`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.to_ascii_uppercase().replace('-', "_");
+        Code::ALL
+            .into_iter()
+            .find(|c| c.as_str() == name)
+            .ok_or_else(|| format!("unknown warning code {}", s))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Something the options asked for
+    Note,
+    /// Something the user likely wants to fix
+    #[default]
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Note => "Note",
+            Severity::Warning => "Warning",
+        })
+    }
+}
+
+/// Something skipped, changed or unavailable, and where.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Warning {
+    #[serde(default)]
+    pub code: Code,
+    #[serde(default)]
+    pub severity: Severity,
+    /// File the element is in, unset when parsing from a string
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    /// The element or part, e.g. `path#outline` or `SPLINE (handle 2F,
+    /// layer CUT)`, empty for warnings about the run as a whole
+    pub element: String,
+    /// Line of the element in the file, when the format has lines
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+    pub message: String,
+}
+
+impl Warning {
+    pub fn new(code: Code, element: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            severity: code.severity(),
+            file: None,
+            element: element.into(),
+            line: None,
+            message: message.into(),
+        }
+    }
+
+    /// Print the warning to stderr, its severity in front.
+    pub fn print(&self) {
+        eprintln!("{}: {}", self.severity, self);
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file.display())?;
+            if let Some(line) = self.line {
+                write!(f, "{}:", line)?;
+            }
+            write!(f, " ")?;
+        } else if let Some(line) = self.line {
+            write!(f, "line {}: ", line)?;
+        }
+        if !self.element.is_empty() {
+            write!(f, "{}: ", self.element)?;
+        }
+        write!(f, "{} [{}]", self.message, self.code)
+    }
+}
+
+/// The first of `warnings` with one of the `codes`.
+pub fn first_of<'a>(warnings: &'a [Warning], codes: &[Code]) -> Option<&'a Warning> {
+    warnings.iter().find(|w| codes.contains(&w.code))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_round_trip() {
+        for code in Code::ALL {
+            assert_eq!(code.as_str().parse(), Ok(code));
+            assert_eq!(serde_json::to_value(code).unwrap(), code.as_str());
+        }
+        assert_eq!("open-contour-closed".parse(), Ok(Code::OpenContourClosed));
+        assert!("OPEN".parse::<Code>().is_err());
+        // warnings written before codes were added
+        let old: Warning = serde_json::from_str(r#"{"element": "text", "message": "skipped"}"#).unwrap();
+        assert_eq!((old.code, old.severity), (Code::Other, Severity::Warning));
+        let closed = Warning::new(Code::OpenContourClosed, "path#a", "closed");
+        assert_eq!(closed.to_string(), "path#a: closed [OPEN_CONTOUR_CLOSED]");
+        assert_eq!(first_of(&[old, closed], &[Code::OpenContourClosed]).map(|w| w.code), Some(Code::OpenContourClosed));
+    }
+}
@@ -4,6 +4,8 @@ use predicates::prelude::*;
 use std::fs;
 use std::path::PathBuf;
 
+mod snapshot;
+
 #[test]
 fn cli_processes_sample_svgs() -> Result<(), Box<dyn std::error::Error>> {
     let bin = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/bin.svg");
@@ -30,8 +32,7 @@ fn cli_processes_sample_svgs() -> Result<(), Box<dyn std::error::Error>> {
         .stdout(predicate::str::contains("Nested result written"));
 
     let output = fs::read_to_string(tmp.path().join("nested.svg"))?;
-    let expected = fs::read_to_string("tests/fixtures/expected.svg")?;
-    assert_eq!(output.trim(), expected.trim());
+    snapshot::assert_snapshot("sample_svgs", &output);
     tmp.close()?;
     Ok(())
 }
@@ -62,8 +63,7 @@ fn cli_processes_dxf() -> Result<(), Box<dyn std::error::Error>> {
         .stdout(predicate::str::contains("Nested result written"));
 
     let output = fs::read_to_string(tmp.path().join("nested.svg"))?;
-    let expected = fs::read_to_string("tests/fixtures/expected.svg")?;
-    assert_eq!(output.trim(), expected.trim());
+    snapshot::assert_snapshot("sample_svgs", &output);
     tmp.close()?;
     Ok(())
 }
@@ -98,6 +98,66 @@ fn cli_handles_line_input() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[test]
+fn cli_open_paths_policy() -> Result<(), Box<dyn std::error::Error>> {
+    let bin = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/bin.svg");
+    let line = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/line.svg");
+    let tmp = TempDir::new()?;
+    let run = |policy: &str| -> Result<assert_cmd::assert::Assert, Box<dyn std::error::Error>> {
+        Ok(Command::cargo_bin("svgnest_cli")?
+            .current_dir(&tmp)
+            .args([
+                "--inputs", bin.to_str().unwrap(),
+                "--inputs", line.to_str().unwrap(),
+                "--population-size", "1",
+                "--rotations", "0",
+                "--open-paths", policy,
+            ])
+            .assert())
+    };
+    run("ignore")?.stderr(predicate::str::contains("No polygons found in input"));
+    run("buffer:1")?.stdout(predicate::str::contains("Nested result written"));
+    let output = fs::read_to_string(tmp.path().join("nested.svg"))?;
+    assert!(output.contains("<polygon"));
+    run("buffer:0")?.failure();
+    tmp.close()?;
+    Ok(())
+}
+
+#[test]
+fn cli_fails_on_chosen_warning_codes() -> Result<(), Box<dyn std::error::Error>> {
+    let bin = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/bin.svg");
+    let line = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/line.svg");
+    let tmp = TempDir::new()?;
+    let report = tmp.path().join("report.json");
+    let run = |fail_on: &str| -> Result<assert_cmd::assert::Assert, Box<dyn std::error::Error>> {
+        Ok(Command::cargo_bin("svgnest_cli")?
+            .current_dir(&tmp)
+            .args([
+                "--inputs", bin.to_str().unwrap(),
+                "--inputs", line.to_str().unwrap(),
+                "--population-size", "1",
+                "--rotations", "0",
+                "--open-paths", "buffer:1",
+                "--report", report.to_str().unwrap(),
+                "--fail-on", fail_on,
+            ])
+            .assert())
+    };
+    run("PART_UNPLACEABLE")?.success().stderr(predicate::str::contains("Note:"));
+    run("PART_UNPLACEABLE,open-contour-buffered")?
+        .code(1)
+        .stderr(predicate::str::contains("Failing on").and(predicate::str::contains("[OPEN_CONTOUR_BUFFERED]")));
+    // the outputs are written all the same
+    assert!(tmp.path().join("nested.svg").exists());
+    let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(&report)?)?;
+    assert_eq!(json["warnings"][0]["code"], "OPEN_CONTOUR_BUFFERED");
+    assert_eq!(json["warnings"][0]["severity"], "note");
+    run("NO_SUCH_CODE")?.failure();
+    tmp.close()?;
+    Ok(())
+}
+
 #[test]
 fn cli_processes_arc_dxf() -> Result<(), Box<dyn std::error::Error>> {
     let bin = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/bin.svg");
@@ -124,8 +184,7 @@ fn cli_processes_arc_dxf() -> Result<(), Box<dyn std::error::Error>> {
         .stdout(predicate::str::contains("Nested result written"));
 
     let output = fs::read_to_string(tmp.path().join("nested.svg"))?;
-    let expected = fs::read_to_string("tests/fixtures/expected_arc.svg")?;
-    assert_eq!(output.trim(), expected.trim());
+    snapshot::assert_snapshot("arc_dxf", &output);
     tmp.close()?;
     Ok(())
 }
@@ -182,8 +241,7 @@ fn cli_use_holes_allows_nested_parts() -> Result<(), Box<dyn std::error::Error>>
         .success();
 
     let output = fs::read_to_string(tmp.path().join("nested.svg"))?;
-    let expected = fs::read_to_string("tests/fixtures/expected_holes.svg")?;
-    assert_eq!(output.trim(), expected.trim());
+    snapshot::assert_snapshot("use_holes", &output);
     tmp.close()?;
     Ok(())
 }
@@ -227,8 +285,7 @@ fn cli_explore_concave_packs_tighter() -> Result<(), Box<dyn std::error::Error>>
         .assert()
         .success();
     let output2 = fs::read_to_string(tmp2.path().join("nested.svg"))?;
-    let expected = fs::read_to_string("tests/fixtures/expected_explore.svg")?;
-    assert_eq!(output2.trim(), expected.trim());
+    snapshot::assert_snapshot("explore_concave", &output2);
     tmp2.close()?;
     Ok(())
 }
@@ -253,8 +310,7 @@ fn cli_unplaceable_rotated_parts() -> Result<(), Box<dyn std::error::Error>> {
         .stdout(predicate::str::contains("Nested result written"));
 
     let output = fs::read_to_string(tmp.path().join("nested.svg"))?;
-    let expected = fs::read_to_string("tests/fixtures/expected_smallbin.svg")?;
-    assert_eq!(output.trim(), expected.trim());
+    snapshot::assert_snapshot("unplaceable_rotated", &output);
     tmp.close()?;
     Ok(())
 }
@@ -290,8 +346,698 @@ fn cli_concave_overlap_shapes() -> Result<(), Box<dyn std::error::Error>> {
         .assert()
         .success();
     let output = fs::read_to_string(tmp.path().join("nested.svg"))?;
-    let expected = fs::read_to_string("tests/fixtures/expected_concave.svg")?;
-    assert_eq!(output.trim(), expected.trim());
+    snapshot::assert_snapshot("concave_overlap", &output);
+    tmp.close()?;
+    Ok(())
+}
+
+#[test]
+fn cli_wrap_x_clips_to_sheet() -> Result<(), Box<dyn std::error::Error>> {
+    let bin = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/bin.svg");
+    let part = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/part.svg");
+    let tmp = TempDir::new()?;
+    Command::cargo_bin("svgnest_cli")?
+        .current_dir(&tmp)
+        .args([
+            "--inputs", bin.to_str().unwrap(),
+            "--inputs", part.to_str().unwrap(),
+            "--population-size", "1",
+            "--mutation-rate", "0",
+            "--rotations", "0",
+            "--wrap-x",
+        ])
+        .assert()
+        .success();
+
+    let output = fs::read_to_string(tmp.path().join("nested.svg"))?;
+    assert!(output.contains("clip-path=\"url(#sheet)\""));
+    assert!(output.contains("<polygon points=\"0,0 5,0 5,5 0,5\""));
+    tmp.close()?;
+    Ok(())
+}
+
+#[test]
+fn cli_exports_remnants() -> Result<(), Box<dyn std::error::Error>> {
+    let bin = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/bin.svg");
+    let part = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/part.svg");
+    let tmp = TempDir::new()?;
+    Command::cargo_bin("svgnest_cli")?
+        .current_dir(&tmp)
+        .args([
+            "--inputs", bin.to_str().unwrap(),
+            "--inputs", part.to_str().unwrap(),
+            "--population-size", "1",
+            "--mutation-rate", "0",
+            "--rotations", "0",
+            "--export-remnants",
+            "--remnant-min-area", "10",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Remnants written for 1 sheet(s)"));
+
+    let remnant = fs::read_to_string(tmp.path().join("remnant_0.svg"))?;
+    assert_eq!(remnant.matches("<path").count(), 1);
+    tmp.close()?;
+    Ok(())
+}
+
+#[test]
+fn cli_live_output_matches_final_result() -> Result<(), Box<dyn std::error::Error>> {
+    let bin = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/bin.svg");
+    let part = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/part.svg");
+    let tmp = TempDir::new()?;
+    Command::cargo_bin("svgnest_cli")?
+        .current_dir(&tmp)
+        .args([
+            "--inputs", bin.to_str().unwrap(),
+            "--inputs", part.to_str().unwrap(),
+            "--population-size", "1",
+            "--mutation-rate", "0",
+            "--rotations", "0",
+            "--live-output",
+        ])
+        .assert()
+        .success();
+
+    let output = fs::read_to_string(tmp.path().join("nested.svg"))?;
+    snapshot::assert_snapshot("sample_svgs", &output);
+    assert_eq!(fs::read_to_string(tmp.path().join("nested.live.svg"))?, output);
+    assert!(!tmp.path().join(".nested.svg.tmp").exists());
+    assert!(!tmp.path().join(".nested.live.svg.tmp").exists());
+    tmp.close()?;
+    Ok(())
+}
+
+#[test]
+fn cli_diff_compares_reports() -> Result<(), Box<dyn std::error::Error>> {
+    let bin = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/bin.svg");
+    let p1 = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/rect6x4.svg");
+    let p2 = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/rect4x6.svg");
+    let tmp = TempDir::new()?;
+    for (report, explore) in [("old.json", false), ("new.json", true)] {
+        let mut cmd = Command::cargo_bin("svgnest_cli")?;
+        cmd.current_dir(&tmp).args([
+            "--inputs", bin.to_str().unwrap(),
+            "--inputs", p1.to_str().unwrap(),
+            "--inputs", p2.to_str().unwrap(),
+            "--population-size", "1",
+            "--mutation-rate", "0",
+            "--rotations", "0",
+            "--report", report,
+        ]);
+        if explore {
+            cmd.arg("--explore-concave");
+        }
+        cmd.assert().success();
+    }
+
+    Command::cargo_bin("svgnest_cli")?
+        .current_dir(&tmp)
+        .args(["diff", "old.json", "new.json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Sheets: 2 -> 1"))
+        .stdout(predicate::str::contains(format!("part {}[0]/0:", p2.display())));
+    assert!(tmp.path().join("diff.svg").exists());
+    tmp.close()?;
+    Ok(())
+}
+
+#[test]
+fn cli_fit_arcs_emits_arc_paths() -> Result<(), Box<dyn std::error::Error>> {
+    let bin = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/bin.svg");
+    let arc = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/arc.dxf");
+    let tmp = TempDir::new()?;
+    Command::cargo_bin("svgnest_cli")?
+        .current_dir(&tmp)
+        .args([
+            "--inputs",
+            bin.to_str().unwrap(),
+            "--inputs",
+            arc.to_str().unwrap(),
+            "--population-size",
+            "1",
+            "--mutation-rate",
+            "0",
+            "--rotations",
+            "0",
+            "--fit-arcs",
+            "0.001",
+        ])
+        .assert()
+        .success();
+
+    let output = fs::read_to_string(tmp.path().join("nested.svg"))?;
+    // only the sheet outline is a polygon
+    let polygons: Vec<&str> = output.split("<polygon").skip(1).collect();
+    assert!(polygons.iter().all(|e| e.split("/>").next().unwrap().contains("data-sheet")));
+    let start = output.find("<path").unwrap();
+    let path = output[start..].lines().next().unwrap();
+    assert!(path.starts_with("<path d=\"M 5 0 A "));
+    assert_eq!(path.matches(" A ").count(), 1);
     tmp.close()?;
     Ok(())
 }
+
+#[test]
+fn cli_profile_writes_chrome_trace() -> Result<(), Box<dyn std::error::Error>> {
+    let bin = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/bin.svg");
+    let part = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/part.svg");
+    let tmp = TempDir::new()?;
+    Command::cargo_bin("svgnest_cli")?
+        .current_dir(&tmp)
+        .args([
+            "--inputs", bin.to_str().unwrap(),
+            "--inputs", part.to_str().unwrap(),
+            "--population-size", "1",
+            "--mutation-rate", "0",
+            "--rotations", "0",
+            "--profile-trace", "trace.json",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Profile (ms):"))
+        .stdout(predicate::str::contains("Per generation (ms):"));
+
+    let trace: serde_json::Value = serde_json::from_str(&fs::read_to_string(tmp.path().join("trace.json"))?)?;
+    let events = trace["traceEvents"].as_array().unwrap();
+    assert!(events.iter().any(|e| e["name"] == "parse"));
+    assert!(events.iter().any(|e| e["name"] == "layout"));
+    tmp.close()?;
+    Ok(())
+}
+
+#[test]
+fn cli_stats_out_writes_every_generation() -> Result<(), Box<dyn std::error::Error>> {
+    let bin = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/bin.svg");
+    let part = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/part.svg");
+    let tmp = TempDir::new()?;
+    for name in ["stats.csv", "stats.json"] {
+        Command::cargo_bin("svgnest_cli")?
+            .current_dir(&tmp)
+            .args([
+                "--inputs", bin.to_str().unwrap(),
+                "--inputs", part.to_str().unwrap(),
+                "--inputs", part.to_str().unwrap(),
+                "--population-size", "4",
+                "--rotations", "2",
+                "--stats-out", name,
+            ])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(format!("Statistics written to {}", name)));
+    }
+
+    let csv = fs::read_to_string(tmp.path().join("stats.csv"))?;
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some("generation,best,median,worst,diversity,cache_hit_rate,eval_ms"));
+    assert_eq!(lines.count(), 101);
+
+    let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(tmp.path().join("stats.json"))?)?;
+    let generations = json.as_array().unwrap();
+    assert_eq!(generations.len(), 101);
+    for (i, g) in generations.iter().enumerate() {
+        assert_eq!(g["generation"], i);
+        assert!(g["best"].as_f64().unwrap() <= g["median"].as_f64().unwrap());
+        assert!(g["median"].as_f64().unwrap() <= g["worst"].as_f64().unwrap());
+        assert!((0.0..=1.0).contains(&g["cache_hit_rate"].as_f64().unwrap()));
+    }
+    tmp.close()?;
+    Ok(())
+}
+
+#[test]
+fn cli_crossover_operators_place_every_part() -> Result<(), Box<dyn std::error::Error>> {
+    let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let tmp = TempDir::new()?;
+    for op in ["one-point", "ox", "pmx", "edge"] {
+        let mut cmd = Command::cargo_bin("svgnest_cli")?;
+        cmd.current_dir(&tmp).args(["--inputs", fixtures.join("bin.svg").to_str().unwrap()]);
+        for i in 0..6 {
+            cmd.args(["--inputs", fixtures.join(if i % 2 == 0 { "rect6x4.svg" } else { "rect4x6.svg" }).to_str().unwrap()]);
+        }
+        cmd.args(["--population-size", "6", "--crossover", op, "--report", "report.json"])
+            .assert()
+            .success();
+        let report: serde_json::Value = serde_json::from_str(&fs::read_to_string(tmp.path().join("report.json"))?)?;
+        assert_eq!(report["placements"].as_array().unwrap().len(), 6, "{}", op);
+    }
+    Command::cargo_bin("svgnest_cli")?
+        .args(["--inputs", "a.svg", "--crossover", "cycle"])
+        .assert()
+        .failure();
+    tmp.close()?;
+    Ok(())
+}
+
+#[test]
+fn cli_restarts_keep_the_best_run() -> Result<(), Box<dyn std::error::Error>> {
+    let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let bin = fixtures.join("bin.svg");
+    let p1 = fixtures.join("rect6x4.svg");
+    let p2 = fixtures.join("rect4x6.svg");
+    let tmp = TempDir::new()?;
+    Command::cargo_bin("svgnest_cli")?
+        .current_dir(&tmp)
+        .args([
+            "--inputs", bin.to_str().unwrap(),
+            "--inputs", p1.to_str().unwrap(),
+            "--inputs", p2.to_str().unwrap(),
+            "--population-size", "4",
+            "--restarts", "3",
+            "--explore-concave",
+            "--live-output",
+            "--stats-out", "stats.json",
+            "--report", "report.json",
+        ])
+        .assert()
+        .success();
+    let report: serde_json::Value = serde_json::from_str(&fs::read_to_string(tmp.path().join("report.json"))?)?;
+    assert_eq!(report["placements"].as_array().unwrap().len(), 2);
+    let stats: serde_json::Value = serde_json::from_str(&fs::read_to_string(tmp.path().join("stats.json"))?)?;
+    assert_eq!(stats.as_array().unwrap().len(), 101);
+
+    Command::cargo_bin("svgnest_cli")?
+        .current_dir(&tmp)
+        .args([
+            "--inputs", bin.to_str().unwrap(),
+            "--inputs", p1.to_str().unwrap(),
+            "--restarts", "2",
+            "--listen", "127.0.0.1:0",
+        ])
+        .assert()
+        .stderr(predicate::str::contains("--restarts cannot be combined with --listen"));
+    tmp.close()?;
+    Ok(())
+}
+
+#[test]
+fn cli_top_k_writes_distinct_layouts() -> Result<(), Box<dyn std::error::Error>> {
+    let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let tmp = TempDir::new()?;
+    let mut cmd = Command::cargo_bin("svgnest_cli")?;
+    cmd.current_dir(&tmp).args(["--inputs", fixtures.join("bin.svg").to_str().unwrap()]);
+    for name in ["rect6x4.svg", "rect4x6.svg", "part.svg"] {
+        cmd.args(["--inputs", fixtures.join(name).to_str().unwrap()]);
+    }
+    cmd.args(["--population-size", "6", "--top-k", "3", "--output-dir", "out"])
+        .assert()
+        .success();
+
+    let out = tmp.path().join("out");
+    assert!(out.join("nested.svg").exists());
+    assert!(out.join("top_1.svg").exists());
+    assert!(!out.join("top_4.svg").exists());
+    tmp.close()?;
+    Ok(())
+}
+
+#[test]
+fn cli_batch_nests_each_job() -> Result<(), Box<dyn std::error::Error>> {
+    let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let tmp = TempDir::new()?;
+    for job in ["a", "b"] {
+        let dir = tmp.path().join("jobs").join(job);
+        fs::create_dir_all(&dir)?;
+        fs::copy(fixtures.join("bin.svg"), dir.join("bin.svg"))?;
+        fs::copy(fixtures.join("part.svg"), dir.join("part.svg"))?;
+    }
+    fs::write(tmp.path().join("jobs/b/config.json"), r#"{"rotations": 0}"#)?;
+    fs::create_dir_all(tmp.path().join("jobs/empty"))?;
+    Command::cargo_bin("svgnest_cli")?
+        .current_dir(&tmp)
+        .args([
+            "batch", "jobs",
+            "--population-size", "1",
+            "--mutation-rate", "0",
+            "--parallel",
+        ])
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("3 job(s), 1 failed"));
+
+    assert!(tmp.path().join("batch-output/a/nested.svg").exists());
+    assert!(tmp.path().join("batch-output/b/report.json").exists());
+    let summary: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(tmp.path().join("batch-output/summary.json"))?)?;
+    let jobs = summary["jobs"].as_array().unwrap();
+    assert_eq!(jobs.len(), 3);
+    assert_eq!(jobs[0]["name"], "a");
+    assert_eq!(jobs[0]["ok"], true);
+    assert_eq!(jobs[2]["ok"], false);
+
+    // without the failing job the batch succeeds
+    fs::remove_dir(tmp.path().join("jobs/empty"))?;
+    Command::cargo_bin("svgnest_cli")?
+        .current_dir(&tmp)
+        .args(["batch", "jobs", "--population-size", "1", "--mutation-rate", "0"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("2 job(s), 0 failed"));
+    tmp.close()?;
+    Ok(())
+}
+
+#[test]
+fn cli_daemon_once_nests_dropped_jobs() -> Result<(), Box<dyn std::error::Error>> {
+    let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let tmp = TempDir::new()?;
+    let job = tmp.path().join("drop/job1");
+    fs::create_dir_all(&job)?;
+    fs::copy(fixtures.join("bin.svg"), job.join("bin.svg"))?;
+    fs::copy(fixtures.join("part.svg"), job.join("part.svg"))?;
+    let run = || -> Result<(), Box<dyn std::error::Error>> {
+        Command::cargo_bin("svgnest_cli")?
+            .current_dir(&tmp)
+            .args(["daemon", "drop", "--once", "--population-size", "1", "--mutation-rate", "0"])
+            .assert()
+            .success();
+        Ok(())
+    };
+    run()?;
+
+    let status: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(tmp.path().join("daemon-output/job1/status.json"))?)?;
+    assert_eq!(status["state"], "done");
+    assert!(tmp.path().join("daemon-output/job1/nested.svg").exists());
+
+    // finished jobs are not nested again
+    fs::remove_file(tmp.path().join("daemon-output/job1/nested.svg"))?;
+    run()?;
+    assert!(!tmp.path().join("daemon-output/job1/nested.svg").exists());
+    tmp.close()?;
+    Ok(())
+}
+
+#[test]
+fn cli_rejects_invalid_configuration() -> Result<(), Box<dyn std::error::Error>> {
+    let bin = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/bin.svg");
+    let part = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/part.svg");
+    let tmp = TempDir::new()?;
+    Command::cargo_bin("svgnest_cli")?
+        .current_dir(&tmp)
+        .args([
+            "--inputs", bin.to_str().unwrap(),
+            "--inputs", part.to_str().unwrap(),
+            "--spacing=-1",
+        ])
+        .assert()
+        .stderr(predicate::str::contains("Invalid configuration: spacing must be a non-negative number"));
+    assert!(!tmp.path().join("nested.svg").exists());
+    tmp.close()?;
+    Ok(())
+}
+
+#[test]
+fn cli_skips_malformed_elements_unless_strict() -> Result<(), Box<dyn std::error::Error>> {
+    let bin = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/bin.svg");
+    let tmp = TempDir::new()?;
+    let part = tmp.path().join("part.svg");
+    fs::write(
+        &part,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\">\n\
+         <rect width=\"10\" height=\"10\"/>\n\
+         <path id=\"bad\" d=\"M0,0 L5,0 Q\"/>\n\
+         </svg>",
+    )?;
+    let report = tmp.path().join("report.json");
+    Command::cargo_bin("svgnest_cli")?
+        .current_dir(&tmp)
+        .args([
+            "--inputs", bin.to_str().unwrap(),
+            "--inputs", part.to_str().unwrap(),
+            "--report", report.to_str().unwrap(),
+            "--population-size", "1",
+        ])
+        .assert()
+        .stderr(predicate::str::contains("Warning:").and(predicate::str::contains("path#bad")));
+    let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(&report)?)?;
+    assert_eq!(json["warnings"][0]["element"], "path#bad");
+    assert_eq!(json["warnings"][0]["line"], 3);
+
+    fs::remove_file(tmp.path().join("nested.svg"))?;
+    Command::cargo_bin("svgnest_cli")?
+        .current_dir(&tmp)
+        .args([
+            "--inputs", bin.to_str().unwrap(),
+            "--inputs", part.to_str().unwrap(),
+            "--strict",
+        ])
+        .assert()
+        .stderr(predicate::str::contains("Failed to parse").and(predicate::str::contains("path#bad")));
+    assert!(!tmp.path().join("nested.svg").exists());
+    tmp.close()?;
+    Ok(())
+}
+
+#[test]
+fn cli_generate_and_nest_large_instance() -> Result<(), Box<dyn std::error::Error>> {
+    let tmp = TempDir::new()?;
+    let dir = tmp.path().join("instance");
+    Command::cargo_bin("svgnest_cli")?
+        .args(["generate", "--parts", "100", "--seed", "3", "--out", dir.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Generated 100 part(s)"));
+
+    let mut inputs = vec![dir.join("bin.svg")];
+    let mut parts: Vec<PathBuf> = fs::read_dir(&dir)?
+        .map(|e| e.map(|e| e.path()))
+        .collect::<Result<_, _>>()?;
+    parts.retain(|p| p.file_name().is_some_and(|n| n != "bin.svg"));
+    parts.sort();
+    assert_eq!(parts.len(), 100);
+    inputs.extend(parts);
+
+    let mut args = Vec::new();
+    for input in &inputs {
+        args.push("--inputs".to_string());
+        args.push(input.to_str().unwrap().to_string());
+    }
+    args.extend(["--population-size", "1", "--mutation-rate", "0", "--rotations", "1", "--report", "report.json"].map(String::from));
+    Command::cargo_bin("svgnest_cli")?
+        .current_dir(&tmp)
+        .args(&args)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Nested result written"));
+
+    let report: serde_json::Value = serde_json::from_str(&fs::read_to_string(tmp.path().join("report.json"))?)?;
+    let placed = report["placements"].as_array().unwrap().len();
+    let unplaced = report["unplaced"].as_array().unwrap().len();
+    assert_eq!(placed + unplaced, 100);
+    assert!(placed > 0);
+
+    // a file where the directory should go fails the command
+    let blocked = tmp.path().join("blocked");
+    fs::write(&blocked, "")?;
+    Command::cargo_bin("svgnest_cli")?
+        .args(["generate", "--parts", "1", "--out", blocked.to_str().unwrap()])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("Failed to generate instance"));
+    tmp.close()?;
+    Ok(())
+}
+
+#[test]
+fn cli_worker_fails_without_a_coordinator() -> Result<(), Box<dyn std::error::Error>> {
+    // nothing listens on port 1, so the connection is refused
+    Command::cargo_bin("svgnest_cli")?
+        .args(["worker", "--connect", "127.0.0.1:1"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("Worker failed"));
+    Ok(())
+}
+
+#[test]
+fn cli_check_verifies_layouts() -> Result<(), Box<dyn std::error::Error>> {
+    let bin = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/bin.svg");
+    let p1 = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/rect6x4.svg");
+    let p2 = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/rect4x6.svg");
+    let tmp = TempDir::new()?;
+    Command::cargo_bin("svgnest_cli")?
+        .current_dir(&tmp)
+        .args([
+            "--inputs", bin.to_str().unwrap(),
+            "--inputs", p1.to_str().unwrap(),
+            "--inputs", p2.to_str().unwrap(),
+            "--population-size", "1",
+            "--explore-concave",
+        ])
+        .assert()
+        .success();
+    Command::cargo_bin("svgnest_cli")?
+        .current_dir(&tmp)
+        .args(["check", "nested.svg", "--bin", bin.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("2 part(s) checked, 0 violation(s)"));
+
+    fs::write(
+        tmp.path().join("bad.svg"),
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"10\" height=\"10\">\
+         <polygon points=\"0,0 6,0 6,4 0,4\"/><polygon points=\"5,3 9,3 9,9 5,9\"/>\
+         <rect x=\"0\" y=\"0\" width=\"10\" height=\"10\"/></svg>",
+    )?;
+    Command::cargo_bin("svgnest_cli")?
+        .current_dir(&tmp)
+        .args(["check", "bad.svg", "--bin", bin.to_str().unwrap()])
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("parts 0 and 1 overlap"));
+    tmp.close()?;
+    Ok(())
+}
+
+#[test]
+fn cli_records_the_run_with_its_outputs() -> Result<(), Box<dyn std::error::Error>> {
+    let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let tmp = TempDir::new()?;
+    Command::cargo_bin("svgnest_cli")?
+        .current_dir(&tmp)
+        .args([
+            "--inputs",
+            fixtures.join("bin.svg").to_str().unwrap(),
+            "--inputs",
+            fixtures.join("part.svg").to_str().unwrap(),
+            "--population-size",
+            "1",
+            "--seed",
+            "7",
+        ])
+        .assert()
+        .success();
+
+    let run: serde_json::Value = serde_json::from_str(&fs::read_to_string(tmp.path().join("run.json"))?)?;
+    assert_eq!(run["config"]["seed"], 7);
+    assert_eq!(run["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(run["inputs"].as_array().map(Vec::len), Some(2));
+    assert_eq!(run["inputs"][0]["sha256"].as_str().map(str::len), Some(64));
+    let svg = fs::read_to_string(tmp.path().join("nested.svg"))?;
+    assert!(svg.contains("<metadata id=\"svgnest-run\">"));
+    tmp.close()?;
+    Ok(())
+}
+
+#[test]
+fn cli_renest_keeps_the_other_sheets() -> Result<(), Box<dyn std::error::Error>> {
+    let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let tmp = TempDir::new()?;
+    let run = |extra: &[&str]| -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("svgnest_cli")?;
+        cmd.current_dir(&tmp).args(["--inputs", fixtures.join("bin.svg").to_str().unwrap()]);
+        for name in ["rect6x4.svg", "rect4x6.svg", "part.svg"] {
+            cmd.args(["--inputs", fixtures.join(name).to_str().unwrap()]);
+        }
+        cmd.args(["--population-size", "1"]).args(extra).assert().success();
+        let report = fs::read_to_string(tmp.path().join(extra[extra.len() - 1]))?;
+        Ok(serde_json::from_str(&report)?)
+    };
+    let first = run(&["--report", "first.json"])?;
+    let last = first["sheets"].as_u64().unwrap() - 1;
+    let second = run(&["--renest", "first.json", "--report", "second.json"])?;
+    assert_eq!(second["placements"].as_array().map(Vec::len), first["placements"].as_array().map(Vec::len));
+    // the parts of the last sheet may be spread over more sheets
+    let on_kept = |report: &serde_json::Value| -> Vec<serde_json::Value> {
+        report["placements"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|p| p["sheet"].as_u64().unwrap() < last)
+            .cloned()
+            .collect()
+    };
+    assert_eq!(on_kept(&second), on_kept(&first));
+    tmp.close()?;
+    Ok(())
+}
+
+#[test]
+fn cli_estimate_prints_json_without_writing_files() -> Result<(), Box<dyn std::error::Error>> {
+    let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let tmp = TempDir::new()?;
+    let output = Command::cargo_bin("svgnest_cli")?
+        .current_dir(&tmp)
+        .args([
+            "--inputs",
+            fixtures.join("bin.svg").to_str().unwrap(),
+            "--inputs",
+            fixtures.join("part.svg").to_str().unwrap(),
+            "--estimate",
+        ])
+        .output()?;
+    assert!(output.status.success());
+    let estimate: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(estimate["sheets"], 1);
+    assert_eq!(estimate["placed"], 1);
+    assert!(estimate["cut_length"].as_f64().unwrap() > 0.0);
+    assert!(!tmp.path().join("nested.svg").exists());
+    tmp.close()?;
+    Ok(())
+}
+
+#[test]
+fn cli_plan_prints_costs_without_writing_files() -> Result<(), Box<dyn std::error::Error>> {
+    let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let tmp = TempDir::new()?;
+    let output = Command::cargo_bin("svgnest_cli")?
+        .current_dir(&tmp)
+        .args([
+            "--inputs",
+            fixtures.join("bin.svg").to_str().unwrap(),
+            "--inputs",
+            fixtures.join("rect6x4.svg").to_str().unwrap(),
+            "--inputs",
+            fixtures.join("rect4x6.svg").to_str().unwrap(),
+            "--plan",
+        ])
+        .output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("2 part(s) at 4 rotation(s): up to 32 NFPs"), "{}", stdout);
+    assert!(stdout.contains("Run time: about"), "{}", stdout);
+    assert!(!tmp.path().join("nested.svg").exists());
+    tmp.close()?;
+    Ok(())
+}
+
+#[test]
+fn cli_validate_reports_parts_fitting_nowhere() -> Result<(), Box<dyn std::error::Error>> {
+    let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let tmp = TempDir::new()?;
+    let output = Command::cargo_bin("svgnest_cli")?
+        .current_dir(&tmp)
+        .args([
+            "--inputs",
+            fixtures.join("smallbin.svg").to_str().unwrap(),
+            "--inputs",
+            fixtures.join("part.svg").to_str().unwrap(),
+            "--inputs",
+            fixtures.join("rect4x6.svg").to_str().unwrap(),
+            "--validate",
+        ])
+        .output()?;
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("fits at 0°, 90°, 180°, 270°"), "{}", stdout);
+    assert!(stdout.contains("rect4x6.svg"), "{}", stdout);
+    assert!(stdout.contains("2 part(s) checked, 1 cannot be placed"), "{}", stdout);
+    assert!(!tmp.path().join("nested.svg").exists());
+    tmp.close()?;
+    Ok(())
+}
+
+#[test]
+fn cli_completions_cover_flags_and_their_values() -> Result<(), Box<dyn std::error::Error>> {
+    let output = Command::cargo_bin("svgnest_cli")?.args(["completions", "bash"]).output()?;
+    assert!(output.status.success());
+    let script = String::from_utf8(output.stdout)?;
+    assert!(script.contains("--position-score"), "{}", script);
+    assert!(script.contains("lowest-y"), "{}", script);
+    assert!(script.contains("completions"), "{}", script);
+    Command::cargo_bin("svgnest_cli")?.args(["completions", "tcsh"]).assert().failure();
+    Ok(())
+}
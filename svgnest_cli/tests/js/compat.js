@@ -0,0 +1,159 @@
+#!/usr/bin/env node
+/*
+ * Nest a part set with the geometry and placement code of the original
+ * JavaScript SVGnest, for comparison with the Rust port.
+ *
+ * Reads {config, bin, parts} as JSON from stdin, with points as [x, y]
+ * pairs and parts as {outline, holes}. Parts are placed once in order of
+ * decreasing area at their original orientation, like the first individual
+ * of the genetic algorithm. Prints {placed, sheets, utilization}.
+ */
+'use strict';
+
+const fs = require('fs');
+const path = require('path');
+const vm = require('vm');
+
+const util = path.resolve(__dirname, '../../../util');
+const context = vm.createContext({
+	console: console,
+	navigator: { userAgent: 'node', appName: 'node' },
+	global: { env: {} }
+});
+vm.runInContext('var self = this;', context);
+for (const file of ['clipper.js', 'matrix.js', 'geometryutil.js', 'placementworker.js']) {
+	vm.runInContext(fs.readFileSync(path.join(util, file), 'utf8'), context, { filename: file });
+}
+const GeometryUtil = context.GeometryUtil;
+const ClipperLib = context.ClipperLib;
+
+const input = JSON.parse(fs.readFileSync(0, 'utf8'));
+const config = Object.assign({ clipperScale: 10000000, exploreConcave: false, useHoles: false }, input.config);
+const points = ring => ring.map(p => ({ x: p[0], y: p[1] }));
+
+// the bin and parts are wound like svgnest.js leaves them before nesting
+const bin = points(input.bin);
+if (GeometryUtil.polygonArea(bin) > 0) {
+	bin.reverse();
+}
+bin.id = -1;
+
+const parts = input.parts.map((part, i) => {
+	const outline = points(part.outline);
+	if (GeometryUtil.polygonArea(outline) > 0) {
+		outline.reverse();
+	}
+	outline.id = i;
+	outline.rotation = 0;
+	outline.childNodes = (part.holes || []).map(points);
+	return outline;
+});
+const area = part => Math.abs(GeometryUtil.polygonArea(part)) -
+	part.childNodes.reduce((sum, hole) => sum + Math.abs(GeometryUtil.polygonArea(hole)), 0);
+
+const placelist = parts.slice(0).sort((a, b) => Math.abs(GeometryUtil.polygonArea(b)) - Math.abs(GeometryUtil.polygonArea(a)));
+
+// the NFP generation of the worker in svgnest.js launchWorkers
+function minkowskiDifference(A, B) {
+	const Ac = A.map(p => ({ X: p.x, Y: p.y }));
+	ClipperLib.JS.ScaleUpPath(Ac, config.clipperScale);
+	const Bc = B.map(p => ({ X: -p.x, Y: -p.y }));
+	ClipperLib.JS.ScaleUpPath(Bc, config.clipperScale);
+	const solution = ClipperLib.Clipper.MinkowskiSum(Ac, Bc, true);
+	let nfp = null;
+	let largestArea = null;
+	for (const s of solution) {
+		const n = s.map(p => ({ x: p.X / config.clipperScale, y: p.Y / config.clipperScale }));
+		const sarea = GeometryUtil.polygonArea(n);
+		if (largestArea === null || largestArea > sarea) {
+			nfp = n;
+			largestArea = sarea;
+		}
+	}
+	for (const p of nfp) {
+		p.x += B[0].x;
+		p.y += B[0].y;
+	}
+	return [nfp];
+}
+
+function generateNfp(A, B, inside) {
+	let nfp;
+	if (inside) {
+		nfp = GeometryUtil.isRectangle(A, 0.001)
+			? GeometryUtil.noFitPolygonRectangle(A, B)
+			: GeometryUtil.noFitPolygon(A, B, true, config.exploreConcave);
+		for (const n of nfp || []) {
+			if (GeometryUtil.polygonArea(n) > 0) {
+				n.reverse();
+			}
+		}
+		return nfp;
+	}
+	nfp = config.exploreConcave ? GeometryUtil.noFitPolygon(A, B, false, true) : minkowskiDifference(A, B);
+	if (!nfp || nfp.length === 0 || Math.abs(GeometryUtil.polygonArea(nfp[0])) < Math.abs(GeometryUtil.polygonArea(A))) {
+		return null;
+	}
+	for (let i = 0; i < nfp.length; i++) {
+		if (GeometryUtil.polygonArea(nfp[i]) > 0) {
+			nfp[i].reverse();
+		}
+		if (i > 0 && GeometryUtil.pointInPolygon(nfp[i][0], nfp[0]) && GeometryUtil.polygonArea(nfp[i]) < 0) {
+			nfp[i].reverse();
+		}
+	}
+	if (config.useHoles) {
+		const Bbounds = GeometryUtil.getPolygonBounds(B);
+		for (const hole of A.childNodes || []) {
+			const Abounds = GeometryUtil.getPolygonBounds(hole);
+			if (Abounds.width > Bbounds.width && Abounds.height > Bbounds.height) {
+				for (const c of GeometryUtil.noFitPolygon(hole, B, true, config.exploreConcave) || []) {
+					if (GeometryUtil.polygonArea(c) < 0) {
+						c.reverse();
+					}
+					nfp.push(c);
+				}
+			}
+		}
+	}
+	return nfp;
+}
+
+const nfpCache = {};
+for (let i = 0; i < placelist.length; i++) {
+	const part = placelist[i];
+	const key = { A: -1, B: part.id, inside: true, Arotation: 0, Brotation: 0 };
+	const nfp = generateNfp(bin, part, true);
+	if (nfp) {
+		nfpCache[JSON.stringify(key)] = nfp;
+	}
+	for (let j = 0; j < i; j++) {
+		const placed = placelist[j];
+		const key = { A: placed.id, B: part.id, inside: false, Arotation: 0, Brotation: 0 };
+		const nfp = generateNfp(placed, part, false);
+		if (nfp) {
+			nfpCache[JSON.stringify(key)] = nfp;
+		}
+	}
+}
+
+const worker = new context.PlacementWorker(bin, placelist, placelist.map(p => p.id), placelist.map(() => 0), config, nfpCache);
+context.global.env.self = worker;
+const result = worker.placePaths(placelist.slice(0));
+
+const binBounds = GeometryUtil.getPolygonBounds(bin);
+const sheets = Math.max(result.placements.length, 1);
+const placed = [];
+let placedArea = 0;
+for (const sheet of result.placements) {
+	for (const p of sheet) {
+		placed.push(p.id);
+		placedArea += area(parts[p.id]);
+	}
+}
+const sheetArea = sheets * binBounds.width * binBounds.height;
+process.stdout.write(JSON.stringify({
+	placed: placed,
+	sheets: sheets,
+	utilization: sheetArea > 0 ? placedArea / sheetArea : 0
+}) + '\n');
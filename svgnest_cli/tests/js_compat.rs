@@ -0,0 +1,142 @@
+//! Placement quality compared with the original JavaScript SVGnest.
+//!
+//! Every case is nested once with the first individual of the genetic
+//! algorithm, here and with the placement code of `svgnest.js` run through
+//! node by `tests/js/compat.js`. The port must not use the sheets worse than
+//! the original. Needs node, so the cases only run when `SVGNEST_JS_COMPAT`
+//! is set:
+//!
+//! ```text
+//! SVGNEST_JS_COMPAT=1 cargo test --test js_compat -- --nocapture
+//! ```
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use serde_json::json;
+use svgnest_cli::config::NestingConfig;
+use svgnest_cli::generate::{self, ShapeKind};
+use svgnest_cli::geometry::polygon_area;
+use svgnest_cli::nester::Nester;
+use svgnest_cli::part::Part;
+use svgnest_cli::svg_parser::{self, ParseOptions, Polygon};
+
+/// Utilization the port may lose to the original before the test fails
+const MAX_DRIFT: f64 = 0.01;
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+}
+
+fn load(path: &Path) -> Part {
+    let mut parts = svg_parser::parts_from_file(path, &ParseOptions::default(), &mut Vec::new()).unwrap();
+    parts.remove(0)
+}
+
+fn points(poly: &Polygon) -> Vec<[f64; 2]> {
+    poly.points.iter().map(|p| [p.x, p.y]).collect()
+}
+
+/// Utilization reached by the original for `parts`, which must already be in
+/// placement order.
+fn js_utilization(bin: &Polygon, parts: &[Part], config: &NestingConfig) -> f64 {
+    let parts: Vec<_> = parts
+        .iter()
+        .map(|part| {
+            let (outlines, holes): (Vec<&Polygon>, Vec<&Polygon>) =
+                part.polygons.iter().partition(|p| polygon_area(&p.points) <= 0.0);
+            json!({
+                "outline": points(outlines[0]),
+                "holes": holes.into_iter().map(points).collect::<Vec<_>>(),
+            })
+        })
+        .collect();
+    let input = json!({
+        "config": {
+            "exploreConcave": config.explore_concave,
+            "useHoles": config.use_holes,
+        },
+        "bin": points(bin),
+        "parts": parts,
+    });
+    let script = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/js/compat.js");
+    let mut node = Command::new("node")
+        .arg(script)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to run node");
+    node.stdin.take().unwrap().write_all(input.to_string().as_bytes()).unwrap();
+    let output = node.wait_with_output().unwrap();
+    assert!(output.status.success(), "compat.js failed");
+    let result: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    result["utilization"].as_f64().unwrap()
+}
+
+fn compare(name: &str, bin: &Polygon, mut parts: Vec<Part>, config: NestingConfig) {
+    // both nest in order of decreasing area, like the seed of svgnest.js
+    parts.sort_by(|a, b| b.area().total_cmp(&a.area()));
+    let nester = Nester::new(parts.clone(), bin.clone(), config.clone()).generations(0);
+    let layout = nester.run().unwrap().expect("no layout");
+    let ours = layout.to_report(&parts).utilization;
+    let theirs = js_utilization(bin, &parts, &config);
+    println!("{}: {:.4} (svgnest.js {:.4})", name, ours, theirs);
+    assert!(
+        ours >= theirs - MAX_DRIFT,
+        "{}: utilization {:.4} is below the {:.4} of svgnest.js",
+        name,
+        ours,
+        theirs
+    );
+}
+
+fn config(explore_concave: bool, use_holes: bool) -> NestingConfig {
+    NestingConfig::builder()
+        .population_size(1)
+        .mutation_rate(0)
+        .rotations(&[0.0])
+        .explore_concave(explore_concave)
+        .use_holes(use_holes)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn utilization_matches_svgnest_js() {
+    if std::env::var_os("SVGNEST_JS_COMPAT").is_none() {
+        eprintln!("SVGNEST_JS_COMPAT not set, skipping");
+        return;
+    }
+    let bin = |name: &str| load(&fixture(name)).polygons[0].clone();
+    let parts = |names: &[&str]| names.iter().map(|n| load(&fixture(n))).collect::<Vec<_>>();
+
+    compare("square", &bin("bin.svg"), parts(&["part.svg"]), config(false, false));
+    compare(
+        "rectangles",
+        &bin("bin.svg"),
+        parts(&["rect6x4.svg", "rect4x6.svg"]),
+        config(false, false),
+    );
+    compare(
+        "rectangles_concave",
+        &bin("bin.svg"),
+        parts(&["rect6x4.svg", "rect4x6.svg"]),
+        config(true, false),
+    );
+    compare(
+        "concave",
+        &bin("narrowbin.svg"),
+        parts(&["concave1.svg", "concave2.svg"]),
+        config(true, false),
+    );
+    compare(
+        "holes",
+        &bin("bin.svg"),
+        parts(&["frame.svg", "small.svg"]),
+        config(true, true),
+    );
+
+    let instance = generate::generate(30, &[ShapeKind::Convex, ShapeKind::Concave, ShapeKind::Ring], 1);
+    compare("generated", &instance.bin, instance.parts, config(false, false));
+}
@@ -1,5 +1,9 @@
-use svgnest_cli::geometry::{minkowski_difference_clip, polygon_area};
-use svgnest_cli::nfp::{inner_fit_polygon, no_fit_polygon_rectangle};
+use svgnest_cli::geometry::{
+    get_polygon_bounds, minkowski_difference_clip, minkowski_difference_shapes, polygon_area, rotate_polygon,
+};
+use svgnest_cli::nfp::{
+    Nfp, NfpCache, Orientation, RasterNfp, inner_fit_polygon, no_fit_polygon_rectangle, no_fit_polygon_rectangles,
+};
 use svgnest_cli::svg_parser::Point;
 
 #[test]
@@ -25,6 +29,61 @@ fn concave_minkowski_handles_l_shape() {
     assert!((area - 8.0).abs() < 0.1);
 }
 
+#[test]
+fn nfp_keeps_pockets_of_concave_parts() {
+    let pts = |coords: &[(f64, f64)]| coords.iter().map(|&(x, y)| Point { x, y }).collect::<Vec<_>>();
+    // a 6x6 bay whose mouth is too narrow for the square to leave it
+    let a = pts(&[
+        (0.0, 0.0),
+        (10.0, 0.0),
+        (10.0, 10.0),
+        (6.0, 10.0),
+        (6.0, 8.0),
+        (8.0, 8.0),
+        (8.0, 2.0),
+        (2.0, 2.0),
+        (2.0, 8.0),
+        (4.0, 8.0),
+        (4.0, 10.0),
+        (0.0, 10.0),
+    ]);
+    let b = pts(&[(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0)]);
+    let shapes = minkowski_difference_shapes(&a, &b);
+    assert_eq!(shapes.iter().map(|s| s.holes.len()).sum::<usize>(), 1);
+    let nfp = Nfp::from_shapes(shapes);
+    assert!(!nfp.contains_by(3.0, 3.0, 1e-9));
+    assert!(nfp.contains_by(0.0, 0.0, 1e-9));
+    // touching the wall of the pocket is no overlap
+    assert!(!nfp.contains_by(2.0, 3.0, 1e-9));
+
+    // the translations at which one square lies inside the other overlap
+    let small = pts(&[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]);
+    let big = pts(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]);
+    let shapes = minkowski_difference_shapes(&big, &small);
+    assert!(shapes.iter().all(|s| s.holes.is_empty()));
+    assert!(Nfp::from_shapes(shapes).contains_by(5.0, 5.0, 1e-9));
+
+    // the grid finds the pocket as well
+    let raster = RasterNfp::new(&a, &b, 200).unwrap();
+    assert!(!raster.contains_by(3.0, 3.0, 1e-9));
+    assert!(raster.contains_by(0.0, 0.0, 1e-9));
+}
+
+#[test]
+fn raster_nfp_approximates_the_exact_one() {
+    let pts = |coords: &[(f64, f64)]| coords.iter().map(|&(x, y)| Point { x, y }).collect::<Vec<_>>();
+    let a = pts(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]);
+    let b = pts(&[(2.0, 2.0), (6.0, 2.0), (6.0, 6.0), (2.0, 6.0)]);
+    // relative to b[0] like the exact NFP, which spans -4..10 on both axes
+    let nfp = Nfp::from_raster(RasterNfp::new(&a, &b, 28).unwrap());
+    assert!(nfp.contains_by(3.0, 3.0, 1e-9));
+    assert!(nfp.contains_by(-3.0, 8.0, 1e-9));
+    assert!(!nfp.contains_by(12.0, 12.0, 1e-9));
+    assert!(!nfp.contains_by(-6.0, 0.0, 1e-9));
+    assert!(!nfp.is_exact());
+    assert!(RasterNfp::new(&a, &b[..1], 28).is_none());
+}
+
 #[test]
 fn inner_nfp_rectangle_simple() {
     let container = vec![
@@ -66,3 +125,130 @@ fn inner_nfp_concave_splits_regions() {
     let nfps = inner_fit_polygon(&container, &part, 0.0);
     assert!(nfps.is_empty() || nfps.len() >= 1);
 }
+
+#[test]
+fn rectangle_nfp_matches_minkowski() {
+    let rect = |x: f64, y: f64, w: f64, h: f64| {
+        vec![
+            Point { x, y },
+            Point { x: x + w, y },
+            Point { x: x + w, y: y + h },
+            Point { x, y: y + h },
+        ]
+    };
+    let a = rect(1.0, 2.0, 6.0, 4.0);
+    let turned = rotate_polygon(&rect(0.0, 0.0, 3.0, 2.0), 90.0);
+    for b in [rect(0.0, 0.0, 2.0, 5.0), rect(3.0, -1.0, 1.0, 1.0), turned] {
+        let fast = no_fit_polygon_rectangles(&a, &b).expect("rectangles");
+        let slow = get_polygon_bounds(&minkowski_difference_clip(&a, &b)).unwrap();
+        let fast = get_polygon_bounds(&fast).unwrap();
+        for (f, s) in [(fast.x, slow.x), (fast.y, slow.y), (fast.width, slow.width), (fast.height, slow.height)] {
+            assert!((f - s).abs() < 1e-6, "{:?} != {:?}", fast, slow);
+        }
+    }
+
+    let l_shape = vec![
+        Point { x: 0.0, y: 0.0 },
+        Point { x: 2.0, y: 0.0 },
+        Point { x: 2.0, y: 1.0 },
+        Point { x: 1.0, y: 1.0 },
+        Point { x: 1.0, y: 2.0 },
+        Point { x: 0.0, y: 2.0 },
+    ];
+    assert!(no_fit_polygon_rectangles(&a, &l_shape).is_none());
+}
+
+#[test]
+fn cache_keeps_mirrored_and_grown_nfps_apart() {
+    let square = vec![
+        Point { x: 0.0, y: 0.0 },
+        Point { x: 1.0, y: 0.0 },
+        Point { x: 1.0, y: 1.0 },
+        Point { x: 0.0, y: 1.0 },
+    ];
+    let at = |part: usize, flipped: bool| Orientation {
+        part,
+        angle: 90.0,
+        flipped,
+    };
+    let cache = NfpCache::default();
+    cache.get_or_generate(at(0, false), at(1, false), 0.0, &square, &square);
+    cache.get_or_generate(at(0, false), at(1, false), -0.0, &square, &square);
+    assert_eq!(cache.lookups(), (1, 1));
+    cache.get_or_generate(at(0, false), at(1, true), 0.0, &square, &square);
+    cache.get_or_generate(at(0, false), at(1, false), 0.5, &square, &square);
+    assert_eq!(cache.lookups(), (1, 3));
+}
+
+#[test]
+fn cache_generates_each_nfp_once_across_threads() {
+    let square: Vec<Point> = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]
+        .iter()
+        .map(|&(x, y)| Point { x, y })
+        .collect();
+    let at = |part: usize| Orientation {
+        part,
+        angle: 0.0,
+        flipped: false,
+    };
+    let cache = NfpCache::default();
+    std::thread::scope(|scope| {
+        for _ in 0..8 {
+            scope.spawn(|| {
+                for b in 0..4 {
+                    let nfp = cache.get_or_generate(at(0), at(b), 0.0, &square, &square);
+                    assert_eq!(nfp.shapes[0].outline.len(), 4);
+                }
+            });
+        }
+    });
+    assert_eq!(cache.lookups(), (28, 4));
+}
+
+#[test]
+fn rayon_tasks_waiting_for_an_nfp_do_not_generate_it_again() {
+    use rayon::prelude::*;
+    // concave, so the NFPs take long enough for tasks to run into each other
+    let l_shape: Vec<Point> = [(0.0, 0.0), (3.0, 0.0), (3.0, 1.0), (1.0, 1.0), (1.0, 3.0), (0.0, 3.0)]
+        .iter()
+        .map(|&(x, y)| Point { x, y })
+        .collect();
+    let at = |part: usize| Orientation {
+        part,
+        angle: 0.0,
+        flipped: false,
+    };
+    let cache = NfpCache::default();
+    (0..64).into_par_iter().for_each(|i| {
+        let nfp = cache.get_or_generate(at(0), at(i % 3), 0.0, &l_shape, &l_shape);
+        assert!(!nfp.shapes.is_empty());
+    });
+    assert_eq!(cache.lookups(), (61, 3));
+}
+
+#[test]
+fn degenerate_rings_give_no_nfp() {
+    let square: Vec<Point> = [(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)]
+        .iter()
+        .map(|&(x, y)| Point { x, y })
+        .collect();
+    let segment = [Point { x: 0.0, y: 0.0 }, Point { x: 1.0, y: 0.0 }];
+    let at = |part: usize| Orientation {
+        part,
+        angle: 0.0,
+        flipped: false,
+    };
+    for ring in [&[][..], &segment[..1], &segment[..]] {
+        assert!(no_fit_polygon_rectangles(&square, ring).is_none());
+        assert!(no_fit_polygon_rectangle(&square, ring).is_none());
+        assert!(RasterNfp::new(&square, ring, 8).is_none());
+    }
+    assert!(inner_fit_polygon(&square, &[], 0.0).is_empty());
+    let cache = NfpCache::default();
+    for (i, ring) in [&[][..], &segment[..1], &segment[..]].into_iter().enumerate() {
+        let nfp = cache.get_or_generate(at(0), at(i + 1), 0.0, &square, ring);
+        assert!(nfp.shapes.is_empty() && !nfp.contains_by(0.0, 0.0, 0.0));
+        let nfp = cache.get_or_generate(at(i + 1), at(0), 0.0, ring, &square);
+        assert!(nfp.shapes.is_empty());
+    }
+}
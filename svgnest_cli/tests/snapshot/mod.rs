@@ -0,0 +1,162 @@
+//! Golden layouts for the CLI tests.
+//!
+//! Nested SVGs are parsed back into their shapes and compared with the
+//! snapshot in `tests/snapshots` within a tolerance, so changes to number
+//! formatting, element order, the element used for a shape or where a ring
+//! starts do not break the tests. Run the tests with `UPDATE_SNAPSHOTS=1` to
+//! rewrite the snapshots from the current output.
+
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use svgnest_cli::svg_parser::approximate_path;
+
+/// Largest coordinate difference still considered equal
+const TOLERANCE: f64 = 1e-6;
+
+/// Flattening tolerance for paths in the output
+const CURVE_TOLERANCE: f64 = 0.01;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Shape {
+    pub stroke: String,
+    pub closed: bool,
+    pub points: Vec<[f64; 2]>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub width: f64,
+    pub height: f64,
+    pub shapes: Vec<Shape>,
+}
+
+fn numbers(s: &str) -> Vec<f64> {
+    s.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|t| !t.is_empty())
+        .filter_map(|t| t.parse().ok())
+        .collect()
+}
+
+impl Snapshot {
+    /// Shapes drawn by a nested SVG. Clip paths are not shapes.
+    pub fn from_svg(svg: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let doc = roxmltree::Document::parse(svg)?;
+        let root = doc.root_element();
+        let attr = |node: roxmltree::Node, name: &str| node.attribute(name).and_then(|v| v.parse().ok()).unwrap_or(0.0);
+        let mut shapes = Vec::new();
+        for node in root.descendants().filter(|n| n.is_element()) {
+            if node.ancestors().any(|a| a.has_tag_name("clipPath")) {
+                continue;
+            }
+            let stroke = node.attribute("stroke").unwrap_or_default().to_string();
+            let mut push = |closed: bool, points: Vec<[f64; 2]>| {
+                shapes.push(Shape {
+                    stroke: stroke.clone(),
+                    closed,
+                    points,
+                })
+            };
+            match node.tag_name().name() {
+                tag @ ("polygon" | "polyline") => {
+                    let values = numbers(node.attribute("points").unwrap_or_default());
+                    let points = values.chunks_exact(2).map(|c| [c[0], c[1]]).collect();
+                    push(tag == "polygon", points);
+                }
+                "rect" => {
+                    let (x, y) = (attr(node, "x"), attr(node, "y"));
+                    let (w, h) = (attr(node, "width"), attr(node, "height"));
+                    push(true, vec![[x, y], [x + w, y], [x + w, y + h], [x, y + h]]);
+                }
+                "path" => {
+                    for (closed, pts) in approximate_path(node.attribute("d").unwrap_or_default(), CURVE_TOLERANCE) {
+                        push(closed, pts.into_iter().map(|(x, y)| [x, y]).collect());
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(Self {
+            width: attr(root, "width"),
+            height: attr(root, "height"),
+            shapes,
+        })
+    }
+}
+
+fn close(a: [f64; 2], b: [f64; 2]) -> bool {
+    (a[0] - b[0]).abs() <= TOLERANCE && (a[1] - b[1]).abs() <= TOLERANCE
+}
+
+/// Points of a shape without a repeated closing point.
+fn ring(shape: &Shape) -> &[[f64; 2]] {
+    let pts = &shape.points[..];
+    match (pts.first(), pts.last()) {
+        (Some(&first), Some(&last)) if shape.closed && pts.len() > 1 && close(first, last) => &pts[..pts.len() - 1],
+        _ => pts,
+    }
+}
+
+/// True if both shapes trace the same points, in either direction and, for
+/// closed shapes, starting anywhere.
+fn same_shape(a: &Shape, b: &Shape) -> bool {
+    let (pa, pb) = (ring(a), ring(b));
+    if a.stroke != b.stroke || a.closed != b.closed || pa.len() != pb.len() {
+        return false;
+    }
+    let n = pa.len();
+    let starts = if a.closed { n.max(1) } else { 1 };
+    (0..starts).any(|s| {
+        let forward = (0..n).all(|i| close(pa[i], pb[(s + i) % n]));
+        let backward = (0..n).all(|i| close(pa[i], pb[(s + n - i) % n]));
+        let reversed = !a.closed && (0..n).all(|i| close(pa[i], pb[n - 1 - i]));
+        forward || (a.closed && backward) || reversed
+    })
+}
+
+/// Differences between the expected and the actual layout, empty if they match.
+pub fn compare(expected: &Snapshot, actual: &Snapshot) -> Vec<String> {
+    let mut problems = Vec::new();
+    if (expected.width - actual.width).abs() > TOLERANCE || (expected.height - actual.height).abs() > TOLERANCE {
+        problems.push(format!(
+            "size {}x{} instead of {}x{}",
+            actual.width, actual.height, expected.width, expected.height
+        ));
+    }
+    let mut unmatched: Vec<&Shape> = actual.shapes.iter().collect();
+    for shape in &expected.shapes {
+        match unmatched.iter().position(|s| same_shape(shape, s)) {
+            Some(i) => {
+                unmatched.remove(i);
+            }
+            None => problems.push(format!("missing {:?}", shape)),
+        }
+    }
+    problems.extend(unmatched.into_iter().map(|s| format!("unexpected {:?}", s)));
+    problems
+}
+
+/// Compare the nested SVG `svg` with the snapshot `name`, or record it when
+/// `UPDATE_SNAPSHOTS` is set.
+pub fn assert_snapshot(name: &str, svg: &str) {
+    let actual = Snapshot::from_svg(svg).expect("output is not valid SVG");
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/snapshots")
+        .join(format!("{}.json", name));
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some_and(|v| !v.is_empty()) {
+        let json = serde_json::to_string_pretty(&actual).unwrap();
+        fs::write(&path, json + "\n").unwrap();
+        return;
+    }
+    let data = fs::read_to_string(&path)
+        .unwrap_or_else(|_| panic!("no snapshot {}, run with UPDATE_SNAPSHOTS=1 to record it", path.display()));
+    let expected: Snapshot = serde_json::from_str(&data).unwrap();
+    let problems = compare(&expected, &actual);
+    assert!(
+        problems.is_empty(),
+        "layout differs from snapshot {}:\n{}",
+        path.display(),
+        problems.join("\n")
+    );
+}